@@ -0,0 +1,211 @@
+use crate::{
+    handle::{
+        handle_deposit, handle_flash_deposit, handle_flash_withdraw, handle_hedge_otc,
+        handle_open_contract, handle_pause, handle_redeem_short_shutdown, handle_transfer_vault,
+        handle_unpause, handle_update_config, handle_withdraw_shutdown, handle_withdrawal,
+    },
+    query::{
+        query_check_price_hedge, query_check_time_hedge, query_config, query_owner,
+        query_power_from_crab_amount, query_state, query_vault_details,
+    },
+    reply::{
+        handle_flash_deposit_reply, handle_flash_withdraw_reply, handle_instantiate_reply,
+        reply_kind,
+    },
+    state::{
+        Config, State, CONFIG, DEFAULT_HEDGE_PRICE_THRESHOLD, DEFAULT_HEDGE_TIME_THRESHOLD,
+        DEFAULT_HEDGING_TWAP_PERIOD, DEFAULT_STRATEGY_CAP, OWNER, OWNERSHIP_PROPOSAL, STATE,
+        STRATEGY_SUBDENOM,
+    },
+};
+
+use cosmwasm_std::{
+    entry_point, to_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Reply, ReplyOn,
+    Response, StdError, StdResult, SubMsg,
+};
+use cw2::set_contract_version;
+use margined_common::{
+    common::check_denom_metadata,
+    errors::ContractError,
+    ownership::{
+        get_ownership_proposal, handle_claim_ownership, handle_ownership_proposal,
+        handle_ownership_proposal_rejection,
+    },
+};
+use margined_protocol::{
+    crab::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg},
+    power::Pool,
+};
+use osmosis_std::types::osmosis::tokenfactory::v1beta1::MsgCreateDenom;
+use std::str::FromStr;
+
+pub const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// reply ids encode the kind of reply in the low bits and the context nonce above them
+pub const REPLY_KIND_BITS: u32 = 8;
+
+pub const INSTANTIATE_REPLY_ID: u64 = 1u64;
+pub const FLASH_DEPOSIT_REPLY_ID: u64 = 2u64;
+pub const FLASH_WITHDRAW_REPLY_ID: u64 = 3u64;
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(
+        deps.storage,
+        format!("crates.io:{CONTRACT_NAME}"),
+        CONTRACT_VERSION,
+    )?;
+
+    let config = Config {
+        power_contract: deps.api.addr_validate(&msg.power_contract)?,
+        query_contract: deps.api.addr_validate(&msg.query_contract)?,
+        fee_pool_contract: deps.api.addr_validate(&msg.fee_pool_contract)?,
+        fee_rate: Decimal::from_str(&msg.fee_rate)?,
+        power_denom: msg.power_denom,
+        base_denom: msg.base_denom,
+        base_pool: Pool {
+            id: msg.base_pool_id,
+            quote_denom: msg.base_pool_quote,
+        },
+        power_pool: Pool {
+            id: msg.power_pool_id,
+            quote_denom: msg.power_pool_quote,
+        },
+        base_decimals: msg.base_decimals,
+        power_decimals: msg.power_decimals,
+        hedge_price_threshold: DEFAULT_HEDGE_PRICE_THRESHOLD,
+        hedge_time_threshold: DEFAULT_HEDGE_TIME_THRESHOLD,
+        hedging_twap_period: DEFAULT_HEDGING_TWAP_PERIOD,
+        strategy_cap: DEFAULT_STRATEGY_CAP,
+        strategy_denom: String::new(),
+        hedger: info.sender.clone(),
+    };
+
+    config.validate()?;
+
+    CONFIG.save(deps.storage, &config)?;
+
+    // validate denoms exist
+    check_denom_metadata(deps.as_ref(), &config.base_denom)
+        .map_err(|_| ContractError::InvalidDenom(config.base_denom.clone()))?;
+    check_denom_metadata(deps.as_ref(), &config.power_denom)
+        .map_err(|_| ContractError::InvalidDenom(config.power_denom.clone()))?;
+
+    STATE.save(
+        deps.storage,
+        &State {
+            is_open: false,
+            is_paused: true,
+            last_pause: env.block.time,
+            time_at_last_hedge: env.block.time,
+            price_at_last_hedge: Decimal::zero(),
+            strategy_vault_id: 0u64,
+            has_redeemed_in_shutdown: false,
+        },
+    )?;
+
+    // create the strategy share token, the denom is stored in the reply
+    let msg_create_denom = SubMsg {
+        id: INSTANTIATE_REPLY_ID,
+        msg: MsgCreateDenom {
+            sender: env.contract.address.to_string(),
+            subdenom: STRATEGY_SUBDENOM.to_string(),
+        }
+        .into(),
+        gas_limit: None,
+        reply_on: ReplyOn::Success,
+    };
+
+    OWNER.set(deps, Some(info.sender))?;
+
+    Ok(Response::new()
+        .add_submessage(msg_create_denom)
+        .add_attribute("action", "instantiate"))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match reply_kind(msg.id) {
+        INSTANTIATE_REPLY_ID => handle_instantiate_reply(deps, env, msg),
+        FLASH_DEPOSIT_REPLY_ID => handle_flash_deposit_reply(deps, msg),
+        FLASH_WITHDRAW_REPLY_ID => handle_flash_withdraw_reply(deps, env, msg),
+
+        _ => Err(ContractError::UnknownReplyId(msg.id)),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::SetOpen {} => handle_open_contract(deps, env, info),
+        ExecuteMsg::Deposit {} => handle_deposit(deps, env, info),
+        ExecuteMsg::FlashDeposit { min_base_out } => {
+            handle_flash_deposit(deps, env, info, min_base_out)
+        }
+        ExecuteMsg::Withdraw {} => handle_withdrawal(deps, env, info),
+        ExecuteMsg::FlashWithdraw { max_base_in } => {
+            handle_flash_withdraw(deps, env, info, max_base_in)
+        }
+        ExecuteMsg::HedgeOTC {} => handle_hedge_otc(deps, env, info),
+        ExecuteMsg::UpdateConfig { new_config } => handle_update_config(deps, info, new_config),
+        ExecuteMsg::Pause {} => handle_pause(deps, env, info),
+        ExecuteMsg::UnPause {} => handle_unpause(deps, info),
+        ExecuteMsg::TransferVault { recipient } => handle_transfer_vault(deps, info, recipient),
+        ExecuteMsg::RedeemShortShutdown {} => handle_redeem_short_shutdown(deps, info),
+        ExecuteMsg::WithdrawShutdown {} => handle_withdraw_shutdown(deps, env, info),
+        ExecuteMsg::ProposeNewOwner {
+            new_owner,
+            duration,
+        } => handle_ownership_proposal(
+            deps,
+            info,
+            env,
+            new_owner,
+            duration,
+            OWNER,
+            OWNERSHIP_PROPOSAL,
+        ),
+        ExecuteMsg::RejectOwner {} => {
+            handle_ownership_proposal_rejection(deps, info, OWNER, OWNERSHIP_PROPOSAL)
+        }
+        ExecuteMsg::ClaimOwnership {} => {
+            handle_claim_ownership(deps, info, env, OWNER, OWNERSHIP_PROPOSAL)
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&query_config(deps)?),
+        QueryMsg::State {} => to_binary(&query_state(deps)?),
+        QueryMsg::Owner {} => {
+            to_binary(&query_owner(deps).map_err(|err| StdError::generic_err(err.to_string()))?)
+        }
+        QueryMsg::CheckPriceHedge {} => to_binary(&query_check_price_hedge(deps, env)?),
+        QueryMsg::CheckTimeHedge {} => to_binary(&query_check_time_hedge(deps, env)?),
+        QueryMsg::GetVaultDetails {} => to_binary(&query_vault_details(deps)?),
+        QueryMsg::GetPowerFromCrabAmount { crab_amount } => {
+            to_binary(&query_power_from_crab_amount(deps, crab_amount)?)
+        }
+        QueryMsg::GetOwnershipProposal {} => {
+            to_binary(&get_ownership_proposal(deps, OWNERSHIP_PROPOSAL)?)
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    Ok(Response::new())
+}
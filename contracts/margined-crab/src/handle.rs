@@ -0,0 +1,690 @@
+use crate::{
+    contract::{FLASH_DEPOSIT_REPLY_ID, FLASH_WITHDRAW_REPLY_ID},
+    helpers::{
+        calculate_hedge, calculate_withdrawal, check_price_hedge, check_time_hedge,
+        create_power_burn_message, create_power_mint_message, create_share_burn_message,
+        create_share_mint_message, get_base_price, get_power_price, get_power_to_mint,
+    },
+    queries::{get_balance, get_next_vault_id, get_power_state, get_power_vault, get_total_supply},
+    reply::save_reply_context,
+    state::{Config, ReplyContext, State, CONFIG, OWNER, STATE},
+};
+
+use cosmwasm_std::{
+    coins, ensure, ensure_eq, to_binary, BankMsg, CosmosMsg, Decimal, DepsMut, Env, Event,
+    MessageInfo, ReplyOn, Response, StdResult, SubMsg, Uint128, WasmMsg,
+};
+use cw_utils::{must_pay, nonpayable};
+use margined_common::{
+    common::{decimal_to_fixed, must_pay_two_denoms},
+    errors::ContractError,
+    messages::{create_swap_exact_amount_in_message, create_swap_exact_amount_out_message},
+};
+use margined_protocol::{crab::UpdateConfig, power::ExecuteMsg as PowerExecuteMsg};
+
+pub fn handle_open_contract(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    ensure!(
+        OWNER.is_admin(deps.as_ref(), &info.sender)?,
+        ContractError::Unauthorized {}
+    );
+
+    let state: State = STATE.load(deps.storage)?;
+    ensure!(!state.is_open, ContractError::IsOpen {});
+
+    let config: Config = CONFIG.load(deps.storage)?;
+    ensure!(
+        !config.strategy_denom.is_empty(),
+        ContractError::DenomNotInitialized {}
+    );
+
+    // the initial collateral is sent to the contract on instantiation
+    let collateral = get_balance(
+        deps.as_ref(),
+        env.contract.address.to_string(),
+        config.base_denom.clone(),
+    )?;
+
+    ensure!(!collateral.is_zero(), ContractError::InvalidFunds {});
+    ensure!(
+        collateral <= config.strategy_cap,
+        ContractError::StrategyCapExceeded {}
+    );
+
+    let base_price = get_base_price(deps.as_ref(), &env)?;
+    let vault_id = get_next_vault_id(deps.as_ref())?;
+
+    let power_to_mint = get_power_to_mint(
+        deps.as_ref(),
+        &env,
+        collateral,
+        Uint128::zero(),
+        Uint128::zero(),
+    )?;
+
+    ensure!(!power_to_mint.is_zero(), ContractError::ZeroMint {});
+
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.is_open = true;
+        state.is_paused = false;
+        state.strategy_vault_id = vault_id;
+        state.time_at_last_hedge = env.block.time;
+        state.price_at_last_hedge = base_price;
+        Ok(state)
+    })?;
+
+    let msg_mint_power = create_power_mint_message(&config, None, power_to_mint, collateral)?;
+
+    let msg_mint_shares = create_share_mint_message(
+        &config,
+        env.contract.address.to_string(),
+        collateral,
+        info.sender.to_string(),
+    );
+
+    let msg_transfer: CosmosMsg = CosmosMsg::Bank(BankMsg::Send {
+        to_address: info.sender.to_string(),
+        amount: coins(power_to_mint.u128(), config.power_denom),
+    });
+
+    let open_event = Event::new("open_contract").add_attributes([
+        ("vault_id", &vault_id.to_string()),
+        ("collateral_deposited", &collateral.to_string()),
+        ("power_minted", &power_to_mint.to_string()),
+        ("shares_minted", &collateral.to_string()),
+    ]);
+
+    Ok(Response::new()
+        .add_messages(vec![msg_mint_power, msg_mint_shares, msg_transfer])
+        .add_event(open_event))
+}
+
+pub fn handle_update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    new_config: UpdateConfig,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    ensure!(
+        OWNER.is_admin(deps.as_ref(), &info.sender)?,
+        ContractError::Unauthorized {}
+    );
+
+    let mut event = Event::new("update_config");
+    if let Some(fee_pool_contract) = new_config.fee_pool_contract {
+        config.fee_pool_contract = deps.api.addr_validate(&fee_pool_contract)?;
+        event = event.add_attribute("fee_pool_contract", fee_pool_contract);
+    }
+
+    if let Some(fee_rate) = new_config.fee_rate {
+        config.fee_rate = fee_rate;
+        event = event.add_attribute("fee_rate", fee_rate.to_string());
+    }
+
+    if let Some(hedge_price_threshold) = new_config.hedge_price_threshold {
+        config.hedge_price_threshold = hedge_price_threshold;
+        event = event.add_attribute("hedge_price_threshold", hedge_price_threshold.to_string());
+    }
+
+    if let Some(hedge_time_threshold) = new_config.hedge_time_threshold {
+        config.hedge_time_threshold = hedge_time_threshold;
+        event = event.add_attribute("hedge_time_threshold", hedge_time_threshold.to_string());
+    }
+
+    if let Some(hedging_twap_period) = new_config.hedging_twap_period {
+        config.hedging_twap_period = hedging_twap_period;
+        event = event.add_attribute("hedging_twap_period", hedging_twap_period.to_string());
+    }
+
+    if let Some(strategy_cap) = new_config.strategy_cap {
+        config.strategy_cap = strategy_cap;
+        event = event.add_attribute("strategy_cap", strategy_cap.to_string());
+    }
+
+    if let Some(hedger) = new_config.hedger {
+        config.hedger = deps.api.addr_validate(&hedger)?;
+        event = event.add_attribute("hedger", hedger);
+    }
+
+    config.validate()?;
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::default().add_event(event))
+}
+
+pub fn handle_pause(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    ensure!(
+        OWNER.is_admin(deps.as_ref(), &info.sender)?,
+        ContractError::Unauthorized {}
+    );
+
+    let mut state = STATE.load(deps.storage)?;
+
+    state.is_open_and_unpaused()?;
+
+    state.is_paused = true;
+    state.last_pause = env.block.time;
+
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::default().add_event(
+        Event::new("pause")
+            .add_attribute("is_paused", state.is_paused.to_string())
+            .add_attribute("last_pause", state.last_pause.to_string()),
+    ))
+}
+
+pub fn handle_unpause(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    ensure!(
+        OWNER.is_admin(deps.as_ref(), &info.sender)?,
+        ContractError::Unauthorized {}
+    );
+
+    let mut state = STATE.load(deps.storage)?;
+
+    ensure!(state.is_open, ContractError::NotOpen {});
+    ensure!(state.is_paused, ContractError::NotPaused {});
+
+    state.is_paused = false;
+
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::default().add_event(
+        Event::new("unpause")
+            .add_attribute("is_paused", state.is_paused.to_string())
+            .add_attribute("last_pause", state.last_pause.to_string()),
+    ))
+}
+
+/// Hands the strategy vault to a new operator, e.g. a migrated strategy. The
+/// strategy must be paused first so that no deposits or withdrawals reference
+/// a vault it no longer controls.
+pub fn handle_transfer_vault(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    ensure!(
+        OWNER.is_admin(deps.as_ref(), &info.sender)?,
+        ContractError::Unauthorized {}
+    );
+
+    let state = STATE.load(deps.storage)?;
+    ensure!(state.is_open, ContractError::NotOpen {});
+    ensure!(state.is_paused, ContractError::NotPaused {});
+
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    let recipient = deps.api.addr_validate(&recipient)?;
+
+    let msg_transfer_vault = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: config.power_contract.to_string(),
+        msg: to_binary(&PowerExecuteMsg::TransferVault {
+            vault_id: state.strategy_vault_id,
+            recipient: recipient.to_string(),
+        })?,
+        funds: vec![],
+    });
+
+    let transfer_event = Event::new("transfer_vault").add_attributes([
+        ("vault_id", &state.strategy_vault_id.to_string()),
+        ("recipient", &recipient.to_string()),
+    ]);
+
+    Ok(Response::new()
+        .add_message(msg_transfer_vault)
+        .add_event(transfer_event))
+}
+
+/// Settles the strategy vault once the power controller has been shutdown, the
+/// remaining collateral is returned to the strategy to be claimed by share holders.
+pub fn handle_redeem_short_shutdown(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    let state = STATE.load(deps.storage)?;
+    ensure!(state.is_open, ContractError::NotOpen {});
+    ensure!(
+        !state.has_redeemed_in_shutdown,
+        ContractError::generic_err("Strategy vault already redeemed")
+    );
+
+    let power_state = get_power_state(deps.as_ref())?;
+    ensure!(power_state.is_shutdown, ContractError::NotShutdown {});
+
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.has_redeemed_in_shutdown = true;
+        Ok(state)
+    })?;
+
+    let msg_withdraw_shutdown = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: config.power_contract.to_string(),
+        msg: to_binary(&PowerExecuteMsg::WithdrawShutdown {
+            vault_id: state.strategy_vault_id,
+        })?,
+        funds: vec![],
+    });
+
+    Ok(Response::new()
+        .add_message(msg_withdraw_shutdown)
+        .add_event(
+            Event::new("redeem_short_shutdown")
+                .add_attribute("vault_id", state.strategy_vault_id.to_string()),
+        ))
+}
+
+pub fn handle_withdraw_shutdown(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    ensure!(
+        state.has_redeemed_in_shutdown,
+        ContractError::generic_err("Strategy vault not yet redeemed")
+    );
+
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    let shares =
+        must_pay(&info, &config.strategy_denom).map_err(|_| ContractError::InvalidFunds {})?;
+
+    let total_shares = get_total_supply(deps.as_ref(), config.strategy_denom.clone())?;
+    let base_balance = get_balance(
+        deps.as_ref(),
+        env.contract.address.to_string(),
+        config.base_denom.clone(),
+    )?;
+
+    let amount_to_withdraw = base_balance.multiply_ratio(shares, total_shares);
+
+    let mut messages = vec![create_share_burn_message(
+        &config,
+        env.contract.address.to_string(),
+        shares,
+    )];
+
+    if !amount_to_withdraw.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: coins(amount_to_withdraw.u128(), config.base_denom),
+        }));
+    }
+
+    let withdraw_event = Event::new("withdraw_shutdown").add_attributes([
+        ("shares_burnt", &shares.to_string()),
+        ("collateral_withdrawn", &amount_to_withdraw.to_string()),
+    ]);
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_event(withdraw_event))
+}
+
+pub fn handle_deposit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    deposit(deps, env, info, false, None)
+}
+
+pub fn handle_flash_deposit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    min_base_out: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    deposit(deps, env, info, true, min_base_out)
+}
+
+pub fn handle_withdrawal(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    state.is_open_and_unpaused()?;
+
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    let (shares, power_sent) =
+        must_pay_two_denoms(&info, &config.strategy_denom, &config.power_denom)
+            .map_err(|_| ContractError::InvalidFunds {})?;
+
+    let vault = get_power_vault(deps.as_ref(), state.strategy_vault_id)?;
+    let total_shares = get_total_supply(deps.as_ref(), config.strategy_denom.clone())?;
+
+    let (amount_to_burn, amount_to_withdraw) =
+        calculate_withdrawal(shares, total_shares, vault.collateral, vault.short_amount);
+
+    ensure!(!amount_to_burn.is_zero(), ContractError::InvalidFunds {});
+    ensure!(
+        power_sent >= amount_to_burn,
+        ContractError::InsufficientPower(config.power_denom, amount_to_burn)
+    );
+
+    let mut messages = vec![
+        create_power_burn_message(
+            &config,
+            state.strategy_vault_id,
+            amount_to_burn,
+            amount_to_withdraw,
+        )?,
+        create_share_burn_message(&config, env.contract.address.to_string(), shares),
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: coins(amount_to_withdraw.u128(), config.base_denom.clone()),
+        }),
+    ];
+
+    // refund any excess power sent
+    let refund = power_sent.checked_sub(amount_to_burn).unwrap();
+    if !refund.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: coins(refund.u128(), config.power_denom.clone()),
+        }));
+    }
+
+    let withdraw_event = Event::new("withdraw").add_attributes([
+        ("shares_burnt", &shares.to_string()),
+        ("power_burnt", &amount_to_burn.to_string()),
+        ("collateral_withdrawn", &amount_to_withdraw.to_string()),
+    ]);
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_event(withdraw_event))
+}
+
+pub fn handle_flash_withdraw(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    max_base_in: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    state.is_open_and_unpaused()?;
+
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    // base sent is used to buy back the power debt, the excess is refunded
+    let (shares, amount_to_swap) =
+        must_pay_two_denoms(&info, &config.strategy_denom, &config.base_denom)
+            .map_err(|_| ContractError::InvalidFunds {})?;
+
+    let vault = get_power_vault(deps.as_ref(), state.strategy_vault_id)?;
+    let total_shares = get_total_supply(deps.as_ref(), config.strategy_denom.clone())?;
+
+    let (amount_to_burn, amount_to_withdraw) =
+        calculate_withdrawal(shares, total_shares, vault.collateral, vault.short_amount);
+
+    ensure!(!amount_to_burn.is_zero(), ContractError::InvalidFunds {});
+
+    // the maximum input cannot exceed the funds sent, any unused funds are refunded
+    let max_base_in = max_base_in.unwrap_or(amount_to_swap);
+    ensure!(
+        max_base_in <= amount_to_swap,
+        ContractError::InvalidFunds {}
+    );
+
+    let swap_msg = create_swap_exact_amount_out_message(
+        env.contract.address.to_string(),
+        config.power_pool.id,
+        config.base_denom,
+        config.power_denom,
+        amount_to_burn.to_string(),
+        max_base_in.to_string(),
+    );
+
+    let reply_id = save_reply_context(
+        deps.storage,
+        FLASH_WITHDRAW_REPLY_ID,
+        &ReplyContext {
+            sender: info.sender,
+            shares,
+            amount_to_burn,
+            amount_to_withdraw,
+            amount_to_swap,
+        },
+    )?;
+
+    let swap_submsg: SubMsg = SubMsg {
+        id: reply_id,
+        msg: swap_msg.into(),
+        gas_limit: None,
+        reply_on: ReplyOn::Success,
+    };
+
+    Ok(Response::new().add_submessage(swap_submsg))
+}
+
+/// Trades the hedge that returns the strategy to delta neutral at the TWAP. As the
+/// TWAP can lag the spot price only the hedger approved by the owner can take it.
+pub fn handle_hedge_otc(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    state.is_open_and_unpaused()?;
+
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    ensure_eq!(info.sender, config.hedger, ContractError::Unauthorized {});
+
+    let can_hedge =
+        check_time_hedge(deps.as_ref(), &env)? || check_price_hedge(deps.as_ref(), &env)?;
+    ensure!(can_hedge, ContractError::HedgeNotAllowed {});
+
+    let power_price = get_power_price(deps.as_ref(), &env)?;
+    let base_price = get_base_price(deps.as_ref(), &env)?;
+
+    let vault = get_power_vault(deps.as_ref(), state.strategy_vault_id)?;
+
+    let (hedge_amount, is_selling) = calculate_hedge(
+        Decimal::from_atomics(vault.collateral, config.base_decimals).unwrap(),
+        Decimal::from_atomics(vault.short_amount, config.power_decimals).unwrap(),
+        power_price,
+    );
+
+    let power_amount = decimal_to_fixed(hedge_amount, config.power_decimals);
+    let base_amount = decimal_to_fixed(
+        hedge_amount.checked_mul(power_price).unwrap(),
+        config.base_decimals,
+    );
+
+    ensure!(
+        !power_amount.is_zero() && !base_amount.is_zero(),
+        ContractError::HedgeNotAllowed {}
+    );
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if is_selling {
+        // the hedger buys power from the strategy, the base paid is added as collateral
+        let base_sent =
+            must_pay(&info, &config.base_denom).map_err(|_| ContractError::InvalidFunds {})?;
+        ensure!(base_sent >= base_amount, ContractError::InvalidFunds {});
+
+        messages.push(create_power_mint_message(
+            &config,
+            Some(state.strategy_vault_id),
+            power_amount,
+            base_amount,
+        )?);
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: coins(power_amount.u128(), config.power_denom.clone()),
+        }));
+
+        let refund = base_sent.checked_sub(base_amount).unwrap();
+        if !refund.is_zero() {
+            messages.push(CosmosMsg::Bank(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: coins(refund.u128(), config.base_denom.clone()),
+            }));
+        }
+    } else {
+        // the hedger sells power to the strategy, the strategy burns it and pays in base
+        let power_sent =
+            must_pay(&info, &config.power_denom).map_err(|_| ContractError::InvalidFunds {})?;
+        ensure!(
+            power_sent >= power_amount,
+            ContractError::InsufficientPower(config.power_denom.clone(), power_amount)
+        );
+
+        messages.push(create_power_burn_message(
+            &config,
+            state.strategy_vault_id,
+            power_amount,
+            base_amount,
+        )?);
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: coins(base_amount.u128(), config.base_denom.clone()),
+        }));
+
+        let refund = power_sent.checked_sub(power_amount).unwrap();
+        if !refund.is_zero() {
+            messages.push(CosmosMsg::Bank(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: coins(refund.u128(), config.power_denom.clone()),
+            }));
+        }
+    }
+
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.time_at_last_hedge = env.block.time;
+        state.price_at_last_hedge = base_price;
+        Ok(state)
+    })?;
+
+    let hedge_event = Event::new("hedge_otc").add_attributes([
+        ("is_selling", &is_selling.to_string()),
+        ("power_amount", &power_amount.to_string()),
+        ("base_amount", &base_amount.to_string()),
+        ("power_price", &power_price.to_string()),
+    ]);
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_event(hedge_event))
+}
+
+fn deposit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    should_sell: bool,
+    min_base_out: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    state.is_open_and_unpaused()?;
+
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    let amount_sent =
+        must_pay(&info, &config.base_denom).map_err(|_| ContractError::InvalidFunds {})?;
+
+    let fee_amount = amount_sent * config.fee_rate;
+    let deposit_amount = amount_sent.checked_sub(fee_amount).unwrap();
+
+    let vault = get_power_vault(deps.as_ref(), state.strategy_vault_id)?;
+    ensure!(
+        !vault.short_amount.is_zero(),
+        ContractError::generic_err("Strategy vault is empty")
+    );
+    ensure!(
+        vault.collateral + deposit_amount <= config.strategy_cap,
+        ContractError::StrategyCapExceeded {}
+    );
+
+    let power_to_mint = get_power_to_mint(
+        deps.as_ref(),
+        &env,
+        deposit_amount,
+        vault.collateral,
+        vault.short_amount,
+    )?;
+
+    ensure!(!power_to_mint.is_zero(), ContractError::ZeroMint {});
+
+    // shares are issued pro-rata to the debt minted as it is not affected by fees
+    let total_shares = get_total_supply(deps.as_ref(), config.strategy_denom.clone())?;
+    let shares_to_mint = power_to_mint.multiply_ratio(total_shares, vault.short_amount);
+
+    let mut response = Response::new()
+        .add_message(create_power_mint_message(
+            &config,
+            Some(state.strategy_vault_id),
+            power_to_mint,
+            deposit_amount,
+        )?)
+        .add_message(create_share_mint_message(
+            &config,
+            env.contract.address.to_string(),
+            shares_to_mint,
+            info.sender.to_string(),
+        ));
+
+    if !fee_amount.is_zero() {
+        response = response.add_message(BankMsg::Send {
+            to_address: config.fee_pool_contract.to_string(),
+            amount: coins(fee_amount.u128(), config.base_denom.clone()),
+        });
+    }
+
+    if should_sell {
+        let swap_msg = create_swap_exact_amount_in_message(
+            env.contract.address.to_string(),
+            config.power_pool.id,
+            config.power_denom,
+            config.base_denom,
+            power_to_mint.to_string(),
+            min_base_out.unwrap_or(Uint128::one()).to_string(),
+        );
+
+        let reply_id = save_reply_context(
+            deps.storage,
+            FLASH_DEPOSIT_REPLY_ID,
+            &ReplyContext {
+                sender: info.sender.clone(),
+                shares: Uint128::zero(),
+                amount_to_burn: Uint128::zero(),
+                amount_to_withdraw: Uint128::zero(),
+                amount_to_swap: Uint128::zero(),
+            },
+        )?;
+
+        response = response.add_submessage(SubMsg {
+            id: reply_id,
+            msg: swap_msg.into(),
+            gas_limit: None,
+            reply_on: ReplyOn::Success,
+        });
+    } else {
+        response = response.add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: coins(power_to_mint.u128(), config.power_denom),
+        });
+    }
+
+    let deposit_event = Event::new("deposit").add_attributes([
+        ("collateral_deposited", &deposit_amount.to_string()),
+        ("power_minted", &power_to_mint.to_string()),
+        ("shares_minted", &shares_to_mint.to_string()),
+        ("fee_amount", &fee_amount.to_string()),
+    ]);
+
+    Ok(response.add_event(deposit_event))
+}
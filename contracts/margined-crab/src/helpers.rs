@@ -0,0 +1,262 @@
+use crate::{
+    queries::{get_fee_preview, get_pool_twap},
+    state::{Config, CONFIG, STATE},
+};
+
+use cosmwasm_std::{coins, to_binary, CosmosMsg, Decimal, Deps, Env, StdResult, Uint128, WasmMsg};
+use margined_common::common::decimal_to_fixed;
+use margined_protocol::power::ExecuteMsg as PowerExecuteMsg;
+use osmosis_std::types::{
+    cosmos::base::v1beta1::Coin,
+    osmosis::tokenfactory::v1beta1::{MsgBurn, MsgMint},
+};
+
+/// Returns the price of one power token denominated in the base token.
+pub fn get_power_price(deps: Deps, env: &Env) -> StdResult<Decimal> {
+    let config = CONFIG.load(deps.storage)?;
+
+    let start_time = env.block.time.minus_seconds(config.hedging_twap_period);
+
+    get_pool_twap(
+        deps,
+        config.power_pool.id,
+        config.power_denom,
+        config.base_denom,
+        start_time,
+    )
+}
+
+/// Returns the price of one base token denominated in the base pool quote token.
+pub fn get_base_price(deps: Deps, env: &Env) -> StdResult<Decimal> {
+    let config = CONFIG.load(deps.storage)?;
+
+    let start_time = env.block.time.minus_seconds(config.hedging_twap_period);
+
+    get_pool_twap(
+        deps,
+        config.base_pool.id,
+        config.base_denom,
+        config.base_pool.quote_denom,
+        start_time,
+    )
+}
+
+pub fn check_time_hedge(deps: Deps, env: &Env) -> StdResult<bool> {
+    let config = CONFIG.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
+
+    let hedge_time = state.time_at_last_hedge.seconds() + config.hedge_time_threshold;
+
+    Ok(env.block.time.seconds() >= hedge_time)
+}
+
+pub fn check_price_hedge(deps: Deps, env: &Env) -> StdResult<bool> {
+    let config = CONFIG.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
+
+    if state.price_at_last_hedge.is_zero() {
+        return Ok(false);
+    }
+
+    let current_price = get_base_price(deps, env)?;
+
+    let price_ratio = current_price
+        .checked_div(state.price_at_last_hedge)
+        .unwrap();
+
+    let upper_bound = Decimal::one() + config.hedge_price_threshold;
+    let lower_bound = Decimal::one() - config.hedge_price_threshold;
+
+    Ok(price_ratio >= upper_bound || price_ratio <= lower_bound)
+}
+
+/// Returns the power to mint for a deposit so that the strategy keeps its collateral
+/// ratio once the power controller has taken its fee from the deposit. The fee rate
+/// depends on the size of the mint and the stake of the strategy, so the fee is
+/// previewed for the size minted without a fee and again for the size found in case
+/// it falls into another fee tier.
+pub fn get_power_to_mint(
+    deps: Deps,
+    env: &Env,
+    deposit: Uint128,
+    collateral: Uint128,
+    debt: Uint128,
+) -> StdResult<Uint128> {
+    let config = CONFIG.load(deps.storage)?;
+
+    let power_price = get_power_price(deps, env)?;
+
+    let deposit = Decimal::from_atomics(deposit, config.base_decimals).unwrap();
+    let collateral = Decimal::from_atomics(collateral, config.base_decimals).unwrap();
+    let debt = Decimal::from_atomics(debt, config.power_decimals).unwrap();
+
+    let mut fee_per_power = Decimal::zero();
+    let mut power_to_mint = decimal_to_fixed(
+        calculate_power_to_mint(deposit, collateral, debt, power_price, fee_per_power),
+        config.power_decimals,
+    );
+
+    for _ in 0..2 {
+        if power_to_mint.is_zero() {
+            break;
+        }
+
+        let preview = get_fee_preview(deps, env.contract.address.to_string(), power_to_mint)?;
+
+        let previewed_fee_per_power =
+            Decimal::from_atomics(preview.fee_amount, config.base_decimals)
+                .unwrap()
+                .checked_div(Decimal::from_atomics(power_to_mint, config.power_decimals).unwrap())
+                .unwrap();
+        if previewed_fee_per_power == fee_per_power {
+            break;
+        }
+
+        fee_per_power = previewed_fee_per_power;
+        power_to_mint = decimal_to_fixed(
+            calculate_power_to_mint(deposit, collateral, debt, power_price, fee_per_power),
+            config.power_decimals,
+        );
+    }
+
+    Ok(power_to_mint)
+}
+
+/// Calculates the power to mint for a deposit so that the strategy keeps its
+/// current debt to collateral ratio after the power controller fee, charged in
+/// base per power minted, is paid. If the strategy holds no debt the mint targets
+/// a collateral ratio of 200%, at which the vault is delta neutral.
+pub fn calculate_power_to_mint(
+    deposit: Decimal,
+    collateral: Decimal,
+    debt: Decimal,
+    power_price: Decimal,
+    fee_per_power: Decimal,
+) -> Decimal {
+    if debt.is_zero() {
+        let denominator = power_price
+            .checked_mul(Decimal::percent(200))
+            .unwrap()
+            .checked_add(fee_per_power)
+            .unwrap();
+
+        return deposit.checked_div(denominator).unwrap();
+    }
+
+    let fee_adjustment = debt.checked_mul(fee_per_power).unwrap();
+
+    deposit
+        .checked_mul(debt)
+        .unwrap()
+        .checked_div(collateral + fee_adjustment)
+        .unwrap()
+}
+
+/// Calculates the amount of power that has to be traded to return the vault to
+/// delta neutral, i.e. collateral == 2 * debt * power_price. Returns the amount
+/// and whether the strategy has to sell (mint) power or buy (burn) power.
+pub fn calculate_hedge(
+    collateral: Decimal,
+    debt: Decimal,
+    power_price: Decimal,
+) -> (Decimal, bool) {
+    let debt_delta = debt
+        .checked_mul(power_price)
+        .unwrap()
+        .checked_mul(Decimal::percent(200))
+        .unwrap();
+
+    if collateral > debt_delta {
+        let amount = (collateral - debt_delta).checked_div(power_price).unwrap();
+        (amount, true)
+    } else {
+        let amount = (debt_delta - collateral).checked_div(power_price).unwrap();
+        (amount, false)
+    }
+}
+
+/// Returns the debt to burn and collateral to withdraw for the shares redeemed.
+pub fn calculate_withdrawal(
+    shares: Uint128,
+    total_shares: Uint128,
+    collateral: Uint128,
+    debt: Uint128,
+) -> (Uint128, Uint128) {
+    let debt_to_burn = debt.multiply_ratio(shares, total_shares);
+    let collateral_to_withdraw = collateral.multiply_ratio(shares, total_shares);
+
+    (debt_to_burn, collateral_to_withdraw)
+}
+
+pub fn create_power_mint_message(
+    config: &Config,
+    vault_id: Option<u64>,
+    amount: Uint128,
+    collateral: Uint128,
+) -> StdResult<CosmosMsg> {
+    let funds = if collateral.is_zero() {
+        vec![]
+    } else {
+        coins(collateral.u128(), config.base_denom.clone())
+    };
+
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: config.power_contract.to_string(),
+        msg: to_binary(&PowerExecuteMsg::MintPowerPerp {
+            amount,
+            vault_id,
+            rebase: false,
+            market_id: None,
+        })?,
+        funds,
+    }))
+}
+
+pub fn create_power_burn_message(
+    config: &Config,
+    vault_id: u64,
+    amount_to_burn: Uint128,
+    amount_to_withdraw: Uint128,
+) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: config.power_contract.to_string(),
+        msg: to_binary(&PowerExecuteMsg::BurnPowerPerp {
+            amount_to_withdraw: Some(amount_to_withdraw),
+            vault_id,
+        })?,
+        funds: coins(amount_to_burn.u128(), config.power_denom.clone()),
+    }))
+}
+
+pub fn create_share_mint_message(
+    config: &Config,
+    contract_address: String,
+    amount: Uint128,
+    recipient: String,
+) -> CosmosMsg {
+    MsgMint {
+        sender: contract_address,
+        amount: Some(Coin {
+            denom: config.strategy_denom.clone(),
+            amount: amount.to_string(),
+        }),
+        mint_to_address: recipient,
+    }
+    .into()
+}
+
+pub fn create_share_burn_message(
+    config: &Config,
+    contract_address: String,
+    amount: Uint128,
+) -> CosmosMsg {
+    MsgBurn {
+        sender: contract_address.clone(),
+        amount: Some(Coin {
+            denom: config.strategy_denom.clone(),
+            amount: amount.to_string(),
+        }),
+        burn_from_address: contract_address,
+    }
+    .into()
+}
@@ -0,0 +1,10 @@
+pub mod contract;
+pub mod handle;
+pub mod helpers;
+pub mod queries;
+pub mod query;
+pub mod reply;
+pub mod state;
+
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,103 @@
+use crate::state::CONFIG;
+
+use cosmwasm_std::{
+    to_binary, Decimal, Deps, QueryRequest, StdError, StdResult, Timestamp, Uint128, WasmQuery,
+};
+use margined_protocol::{
+    power::{
+        FeePreviewResponse, QueryMsg as PowerQueryMsg, StateResponse as PowerStateResponse,
+        VaultResponse,
+    },
+    query::QueryMsg,
+};
+use osmosis_std::types::cosmos::bank::v1beta1::BankQuerier;
+use std::str::FromStr;
+
+pub fn get_pool_twap(
+    deps: Deps,
+    pool_id: u64,
+    base_asset: String,
+    quote_asset: String,
+    start_time: Timestamp,
+) -> StdResult<Decimal> {
+    let config = CONFIG.load(deps.storage)?;
+
+    let price: Decimal = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: config.query_contract.to_string(),
+        msg: to_binary(&QueryMsg::GetArithmeticTwapToNow {
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+        })?,
+    }))?;
+
+    Ok(price)
+}
+
+/// Returns the fee the power controller charges the user to mint the amount of power.
+pub fn get_fee_preview(deps: Deps, user: String, amount: Uint128) -> StdResult<FeePreviewResponse> {
+    let config = CONFIG.load(deps.storage)?;
+
+    deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: config.power_contract.to_string(),
+        msg: to_binary(&PowerQueryMsg::GetFeePreview {
+            user,
+            amount,
+            market_id: None,
+        })?,
+    }))
+}
+
+pub fn get_power_state(deps: Deps) -> StdResult<PowerStateResponse> {
+    let config = CONFIG.load(deps.storage)?;
+
+    deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: config.power_contract.to_string(),
+        msg: to_binary(&PowerQueryMsg::State { market_id: None })?,
+    }))
+}
+
+pub fn get_power_vault(deps: Deps, vault_id: u64) -> StdResult<VaultResponse> {
+    let config = CONFIG.load(deps.storage)?;
+
+    deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: config.power_contract.to_string(),
+        msg: to_binary(&PowerQueryMsg::GetVault { vault_id })?,
+    }))
+}
+
+pub fn get_next_vault_id(deps: Deps) -> StdResult<u64> {
+    let config = CONFIG.load(deps.storage)?;
+
+    deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: config.power_contract.to_string(),
+        msg: to_binary(&PowerQueryMsg::GetNextVaultId {})?,
+    }))
+}
+
+pub fn get_total_supply(deps: Deps, denom: String) -> StdResult<Uint128> {
+    let bank = BankQuerier::new(&deps.querier);
+
+    let res = bank.supply_of(denom)?;
+
+    let amount = match res.amount {
+        Some(amount) => Uint128::from_str(&amount.amount)?,
+        None => return Err(StdError::generic_err("No supply found")),
+    };
+
+    Ok(amount)
+}
+
+pub fn get_balance(deps: Deps, address: String, denom: String) -> StdResult<Uint128> {
+    let bank = BankQuerier::new(&deps.querier);
+
+    let res = bank.balance(address, denom)?;
+
+    let amount = match res.balance {
+        Some(amount) => Uint128::from_str(&amount.amount)?,
+        None => Uint128::zero(),
+    };
+
+    Ok(amount)
+}
@@ -0,0 +1,85 @@
+use crate::{
+    helpers::{check_price_hedge, check_time_hedge},
+    queries::{get_power_vault, get_total_supply},
+    state::{CONFIG, OWNER, STATE},
+};
+
+use cosmwasm_std::{Addr, Deps, Env, StdResult, Uint128};
+use margined_common::errors::ContractError;
+use margined_protocol::{
+    crab::{ConfigResponse, StateResponse},
+    power::VaultResponse,
+};
+
+pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+
+    Ok(ConfigResponse {
+        power_contract: config.power_contract,
+        query_contract: config.query_contract,
+        fee_pool_contract: config.fee_pool_contract,
+        power_denom: config.power_denom,
+        base_denom: config.base_denom,
+        base_pool: config.base_pool,
+        power_pool: config.power_pool,
+        base_decimals: config.base_decimals,
+        power_decimals: config.power_decimals,
+        fee_rate: config.fee_rate,
+        hedge_price_threshold: config.hedge_price_threshold,
+        hedge_time_threshold: config.hedge_time_threshold,
+        hedging_twap_period: config.hedging_twap_period,
+        strategy_cap: config.strategy_cap,
+        strategy_denom: config.strategy_denom,
+        hedger: config.hedger,
+    })
+}
+
+pub fn query_state(deps: Deps) -> StdResult<StateResponse> {
+    let state = STATE.load(deps.storage)?;
+
+    Ok(StateResponse {
+        is_open: state.is_open,
+        is_paused: state.is_paused,
+        last_pause: state.last_pause,
+        time_at_last_hedge: state.time_at_last_hedge,
+        price_at_last_hedge: state.price_at_last_hedge,
+        strategy_vault_id: state.strategy_vault_id,
+        has_redeemed_in_shutdown: state.has_redeemed_in_shutdown,
+    })
+}
+
+pub fn query_owner(deps: Deps) -> Result<Addr, ContractError> {
+    if let Some(owner) = OWNER.get(deps)? {
+        Ok(owner)
+    } else {
+        Err(ContractError::NoOwner {})
+    }
+}
+
+pub fn query_check_price_hedge(deps: Deps, env: Env) -> StdResult<bool> {
+    check_price_hedge(deps, &env)
+}
+
+pub fn query_check_time_hedge(deps: Deps, env: Env) -> StdResult<bool> {
+    check_time_hedge(deps, &env)
+}
+
+pub fn query_vault_details(deps: Deps) -> StdResult<VaultResponse> {
+    let state = STATE.load(deps.storage)?;
+
+    get_power_vault(deps, state.strategy_vault_id)
+}
+
+pub fn query_power_from_crab_amount(deps: Deps, crab_amount: Uint128) -> StdResult<Uint128> {
+    let config = CONFIG.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
+
+    let total_shares = get_total_supply(deps, config.strategy_denom)?;
+    if total_shares.is_zero() {
+        return Ok(Uint128::zero());
+    }
+
+    let vault = get_power_vault(deps, state.strategy_vault_id)?;
+
+    Ok(vault.short_amount.multiply_ratio(crab_amount, total_shares))
+}
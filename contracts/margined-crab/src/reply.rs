@@ -0,0 +1,146 @@
+use crate::{
+    contract::REPLY_KIND_BITS,
+    helpers::{create_power_burn_message, create_share_burn_message},
+    state::{Config, ReplyContext, CONFIG, NEXT_REPLY_NONCE, REPLY_CONTEXTS, STATE},
+};
+
+use cosmwasm_std::{
+    coins, BankMsg, CosmosMsg, DepsMut, Env, Event, Reply, Response, StdError, StdResult, Storage,
+    Uint128,
+};
+use margined_common::{common::parse_response_result_data, errors::ContractError};
+use osmosis_std::types::osmosis::{
+    poolmanager::v1beta1::{MsgSwapExactAmountInResponse, MsgSwapExactAmountOutResponse},
+    tokenfactory::v1beta1::MsgCreateDenomResponse,
+};
+use std::str::FromStr;
+
+/// Saves the context of a call awaiting a reply under a new nonce and returns
+/// the reply id encoding the nonce and the kind of reply.
+pub fn save_reply_context(
+    storage: &mut dyn Storage,
+    kind: u64,
+    context: &ReplyContext,
+) -> StdResult<u64> {
+    let nonce = NEXT_REPLY_NONCE.may_load(storage)?.unwrap_or(1);
+    NEXT_REPLY_NONCE.save(storage, &(nonce + 1))?;
+
+    REPLY_CONTEXTS.save(storage, nonce, context)?;
+
+    Ok(encode_reply_id(kind, nonce))
+}
+
+pub fn encode_reply_id(kind: u64, nonce: u64) -> u64 {
+    (nonce << REPLY_KIND_BITS) | kind
+}
+
+pub fn reply_kind(id: u64) -> u64 {
+    id & ((1 << REPLY_KIND_BITS) - 1)
+}
+
+pub fn reply_nonce(id: u64) -> u64 {
+    id >> REPLY_KIND_BITS
+}
+
+pub fn handle_instantiate_reply(
+    deps: DepsMut,
+    _env: Env,
+    msg: Reply,
+) -> Result<Response, ContractError> {
+    let data = parse_response_result_data(msg.result)?;
+
+    let response: MsgCreateDenomResponse = data.try_into().map_err(ContractError::Std)?;
+
+    CONFIG.update(deps.storage, |mut config| -> StdResult<_> {
+        config.strategy_denom = response.new_token_denom.clone();
+        Ok(config)
+    })?;
+
+    Ok(Response::new().add_event(
+        Event::new("create_strategy_denom")
+            .add_attribute("strategy_denom", response.new_token_denom),
+    ))
+}
+
+pub fn handle_flash_deposit_reply(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let nonce = reply_nonce(msg.id);
+    let context = REPLY_CONTEXTS.load(deps.storage, nonce)?;
+    REPLY_CONTEXTS.remove(deps.storage, nonce);
+
+    let data = parse_response_result_data(msg.result)?;
+
+    let response: MsgSwapExactAmountInResponse = data.try_into().map_err(ContractError::Std)?;
+
+    let base_received = Uint128::from_str(&response.token_out_amount)?;
+
+    let config: Config = CONFIG.load(deps.storage)?;
+
+    let send_msg = CosmosMsg::Bank(BankMsg::Send {
+        to_address: context.sender.to_string(),
+        amount: coins(base_received.u128(), config.base_denom),
+    });
+
+    Ok(Response::new().add_message(send_msg).add_event(
+        Event::new("flash_deposit").add_attribute("base_received", base_received.to_string()),
+    ))
+}
+
+pub fn handle_flash_withdraw_reply(
+    deps: DepsMut,
+    env: Env,
+    msg: Reply,
+) -> Result<Response, ContractError> {
+    let nonce = reply_nonce(msg.id);
+    let context = REPLY_CONTEXTS.load(deps.storage, nonce)?;
+    REPLY_CONTEXTS.remove(deps.storage, nonce);
+
+    let data = parse_response_result_data(msg.result)?;
+
+    let response: MsgSwapExactAmountOutResponse = data.try_into().map_err(ContractError::Std)?;
+
+    let token_in_amount = Uint128::from_str(&response.token_in_amount)?;
+
+    let config: Config = CONFIG.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
+
+    // collateral withdrawn plus any base that was not required for the swap
+    let unused_base = context
+        .amount_to_swap
+        .checked_sub(token_in_amount)
+        .map_err(|_| ContractError::ExcessiveInput(token_in_amount, context.amount_to_swap))?;
+    let amount_to_send = context
+        .amount_to_withdraw
+        .checked_add(unused_base)
+        .map_err(StdError::from)?;
+
+    let mut messages = vec![
+        create_power_burn_message(
+            &config,
+            state.strategy_vault_id,
+            context.amount_to_burn,
+            context.amount_to_withdraw,
+        )?,
+        create_share_burn_message(&config, env.contract.address.to_string(), context.shares),
+    ];
+
+    if !amount_to_send.is_zero() {
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: context.sender.to_string(),
+            amount: coins(amount_to_send.u128(), config.base_denom),
+        }));
+    }
+
+    let withdraw_event = Event::new("flash_withdraw").add_attributes([
+        ("shares_burnt", &context.shares.to_string()),
+        ("power_burnt", &context.amount_to_burn.to_string()),
+        (
+            "collateral_withdrawn",
+            &context.amount_to_withdraw.to_string(),
+        ),
+        ("base_swapped", &token_in_amount.to_string()),
+    ]);
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_event(withdraw_event))
+}
@@ -0,0 +1,137 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{ensure, ensure_ne, Addr, Decimal, StdError, StdResult, Timestamp, Uint128};
+use cw_controllers::Admin;
+use cw_storage_plus::{Item, Map};
+use margined_common::ownership::OwnerProposal;
+use margined_protocol::power::Pool;
+
+pub const OWNER: Admin = Admin::new("owner");
+pub const OWNERSHIP_PROPOSAL: Item<OwnerProposal> = Item::new("ownership_proposals");
+
+pub const CONFIG: Item<Config> = Item::new("config");
+pub const STATE: Item<State> = Item::new("state");
+
+// context of flash deposits and withdrawals awaiting their swap reply, keyed by nonce
+pub const REPLY_CONTEXTS: Map<u64, ReplyContext> = Map::new("reply_contexts");
+pub const NEXT_REPLY_NONCE: Item<u64> = Item::new("next_reply_nonce");
+
+pub const STRATEGY_SUBDENOM: &str = "crab";
+
+pub const DEFAULT_HEDGE_PRICE_THRESHOLD: Decimal = Decimal::percent(20); // 20%
+pub const DEFAULT_HEDGE_TIME_THRESHOLD: u64 = 24 * 60 * 60; // 24 hours
+pub const DEFAULT_HEDGING_TWAP_PERIOD: u64 = 420; // 420 seconds (7 minutes)
+pub const DEFAULT_STRATEGY_CAP: Uint128 = Uint128::new(1_000_000_000_000u128); // 1,000,000.0@6dp
+
+pub const MAX_HEDGING_TWAP_PERIOD: u64 = 48 * 60 * 60; // 48 hours
+
+#[cw_serde]
+pub struct Config {
+    pub power_contract: Addr, // The power controller that owns the strategy vault
+    pub query_contract: Addr, // The contract that wraps the querier interface
+    pub fee_pool_contract: Addr, // The address where fees are sent
+    pub fee_rate: Decimal,    // The fee rate charged on deposits
+    pub power_denom: String,  // Denom of the power perp native token, e.g. atom^2
+    pub base_denom: String,   // Denom of the underlying native token, e.g. atom
+    pub base_pool: Pool,      // Pool of the underlying to quote, e.g. atom:usdc
+    pub power_pool: Pool,     // Pool of the underlying to power, e.g. atom:atom^2
+    pub base_decimals: u32,   // Decimals of the underlying token
+    pub power_decimals: u32,  // Decimals of the power perp token
+    pub hedge_price_threshold: Decimal, // Price move required before a hedge is allowed
+    pub hedge_time_threshold: u64, // Seconds elapsed before a hedge is allowed
+    pub hedging_twap_period: u64, // TWAP period used to price hedges
+    pub strategy_cap: Uint128, // Maximum collateral the strategy vault can hold
+    pub strategy_denom: String, // Denom of the strategy share token, set on instantiation
+    pub hedger: Addr,         // The only address allowed to trade the hedge, set by the owner
+}
+
+impl Config {
+    pub fn validate(&self) -> StdResult<()> {
+        ensure!(
+            self.base_decimals > 0 && self.base_decimals <= 18,
+            StdError::generic_err("Invalid base decimals")
+        );
+
+        ensure!(
+            self.power_decimals > 0 && self.power_decimals <= 18,
+            StdError::generic_err("Invalid power decimals")
+        );
+
+        ensure!(
+            self.fee_rate < Decimal::one(),
+            StdError::generic_err("Invalid fee rate")
+        );
+
+        ensure!(
+            !self.hedge_price_threshold.is_zero() && self.hedge_price_threshold < Decimal::one(),
+            StdError::generic_err("Invalid hedge price threshold")
+        );
+
+        ensure!(
+            self.hedge_time_threshold > 0,
+            StdError::generic_err("Invalid hedge time threshold")
+        );
+
+        ensure!(
+            self.hedging_twap_period > 0 && self.hedging_twap_period <= MAX_HEDGING_TWAP_PERIOD,
+            StdError::generic_err(format!(
+                "Invalid hedging twap period, must be between 0 and {} seconds",
+                MAX_HEDGING_TWAP_PERIOD
+            ))
+        );
+
+        ensure!(
+            !self.strategy_cap.is_zero(),
+            StdError::generic_err("Invalid strategy cap")
+        );
+
+        ensure_ne!(
+            self.power_denom,
+            self.base_denom,
+            StdError::generic_err("Invalid base and power denom must be different")
+        );
+
+        ensure_ne!(
+            self.power_pool.id,
+            self.base_pool.id,
+            StdError::generic_err("Invalid base and power pool id must be different")
+        );
+
+        Ok(())
+    }
+}
+
+#[cw_serde]
+pub struct State {
+    pub is_open: bool,                  // Whether the strategy is open
+    pub is_paused: bool,                // Whether the strategy is paused
+    pub last_pause: Timestamp,          // Last time the strategy was paused
+    pub time_at_last_hedge: Timestamp,  // Timestamp of the last hedge
+    pub price_at_last_hedge: Decimal,   // Base price at the last hedge
+    pub strategy_vault_id: u64,         // Id of the vault owned by the strategy
+    pub has_redeemed_in_shutdown: bool, // Whether the vault has been settled after power shutdown
+}
+
+impl State {
+    pub fn is_open_and_unpaused(&self) -> StdResult<()> {
+        ensure!(
+            self.is_open,
+            StdError::generic_err("Cannot perform action as contract is not open")
+        );
+
+        ensure!(
+            !self.is_paused,
+            StdError::generic_err("Cannot perform action as contract is paused")
+        );
+
+        Ok(())
+    }
+}
+
+#[cw_serde]
+pub struct ReplyContext {
+    pub sender: Addr,
+    pub shares: Uint128,             // shares burnt by a flash withdrawal
+    pub amount_to_burn: Uint128,     // power bought back to burn
+    pub amount_to_withdraw: Uint128, // collateral withdrawn from the vault
+    pub amount_to_swap: Uint128,     // base sent to buy back the power
+}
@@ -0,0 +1,703 @@
+use crate::state::DEFAULT_HEDGE_TIME_THRESHOLD;
+
+use cosmwasm_std::{coin, Addr, Decimal, Uint128};
+use margined_protocol::{
+    crab::{ConfigResponse, ExecuteMsg, QueryMsg, StateResponse, UpdateConfig},
+    power::{
+        ExecuteMsg as PowerExecuteMsg, FeeSchedule, FeeTier, UpdateConfig as PowerUpdateConfig,
+        VaultResponse,
+    },
+};
+use margined_testing::{
+    helpers::{is_similar, parse_event_attribute},
+    power_env::PowerEnv,
+};
+use osmosis_test_tube::{Account, Module, Wasm};
+use std::str::FromStr;
+
+#[test]
+fn test_open_contract() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+
+    let (_, _, crab_address) = env.setup_crab(&wasm, true, "0.0".to_string());
+
+    let state: StateResponse = wasm.query(&crab_address, &QueryMsg::State {}).unwrap();
+    assert!(state.is_open);
+    assert!(!state.is_paused);
+
+    let vault: VaultResponse = wasm
+        .query(&crab_address, &QueryMsg::GetVaultDetails {})
+        .unwrap();
+    assert_eq!(vault.operator, Addr::unchecked(&crab_address));
+    assert_eq!(vault.collateral, Uint128::from(300_000_000u128));
+    assert!(!vault.short_amount.is_zero());
+
+    let config: ConfigResponse = wasm.query(&crab_address, &QueryMsg::Config {}).unwrap();
+    let shares = env.get_balance(env.signer.address(), config.strategy_denom);
+    assert_eq!(shares, Uint128::from(300_000_000u128));
+
+    wasm.execute(&crab_address, &ExecuteMsg::SetOpen {}, &[], &env.signer)
+        .unwrap_err();
+}
+
+#[test]
+fn test_deposit_and_withdraw() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+
+    let (_, _, crab_address) = env.setup_crab(&wasm, true, "0.0".to_string());
+
+    let config: ConfigResponse = wasm.query(&crab_address, &QueryMsg::Config {}).unwrap();
+
+    let vault_before: VaultResponse = wasm
+        .query(&crab_address, &QueryMsg::GetVaultDetails {})
+        .unwrap();
+
+    wasm.execute(
+        &crab_address,
+        &ExecuteMsg::Deposit {},
+        &[coin(30_000_000u128, config.base_denom.clone())],
+        &env.traders[0],
+    )
+    .unwrap();
+
+    let vault_after: VaultResponse = wasm
+        .query(&crab_address, &QueryMsg::GetVaultDetails {})
+        .unwrap();
+    assert_eq!(
+        vault_after.collateral,
+        vault_before.collateral + Uint128::from(30_000_000u128)
+    );
+
+    // with no fees the debt grows in proportion to the collateral
+    let power_minted = vault_after.short_amount - vault_before.short_amount;
+    assert_eq!(
+        power_minted,
+        vault_before.short_amount.multiply_ratio(1u128, 10u128)
+    );
+
+    let shares = env.get_balance(env.traders[0].address(), config.strategy_denom.clone());
+    assert_eq!(shares, Uint128::from(30_000_000u128));
+
+    let power_balance = env.get_balance(env.traders[0].address(), config.power_denom.clone());
+    assert_eq!(power_balance, power_minted);
+
+    let power_to_burn: Uint128 = wasm
+        .query(
+            &crab_address,
+            &QueryMsg::GetPowerFromCrabAmount {
+                crab_amount: shares,
+            },
+        )
+        .unwrap();
+    assert_eq!(power_to_burn, power_minted);
+
+    let base_before = env.get_balance(env.traders[0].address(), config.base_denom.clone());
+
+    let mut funds = vec![
+        coin(shares.u128(), config.strategy_denom.clone()),
+        coin(power_to_burn.u128(), config.power_denom.clone()),
+    ];
+    funds.sort_by(|a, b| a.denom.cmp(&b.denom));
+
+    wasm.execute(
+        &crab_address,
+        &ExecuteMsg::Withdraw {},
+        &funds,
+        &env.traders[0],
+    )
+    .unwrap();
+
+    let base_after = env.get_balance(env.traders[0].address(), config.base_denom.clone());
+    assert_eq!(base_after - base_before, Uint128::from(30_000_000u128));
+    assert!(env
+        .get_balance(env.traders[0].address(), config.strategy_denom)
+        .is_zero());
+
+    let vault_end: VaultResponse = wasm
+        .query(&crab_address, &QueryMsg::GetVaultDetails {})
+        .unwrap();
+    assert_eq!(vault_end.collateral, vault_before.collateral);
+    assert_eq!(vault_end.short_amount, vault_before.short_amount);
+}
+
+#[test]
+fn test_deposit_keeps_collateral_ratio_with_fee_tiers() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+
+    let (power_address, _, crab_address) = env.setup_crab(&wasm, true, "0.01".to_string());
+
+    // larger mints are charged a higher rate than the flat fee rate
+    wasm.execute(
+        &power_address,
+        &PowerExecuteMsg::UpdateConfig {
+            market_id: None,
+            new_config: PowerUpdateConfig {
+                fee_schedule: Some(FeeSchedule {
+                    size_tiers: vec![FeeTier {
+                        min_value: Uint128::from(1_000_000u128),
+                        fee_rate: Decimal::percent(2),
+                    }],
+                    staking_contract: None,
+                    staking_discounts: vec![],
+                }),
+                ..Default::default()
+            },
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    let config: ConfigResponse = wasm.query(&crab_address, &QueryMsg::Config {}).unwrap();
+
+    let vault_before: VaultResponse = wasm
+        .query(&crab_address, &QueryMsg::GetVaultDetails {})
+        .unwrap();
+
+    wasm.execute(
+        &crab_address,
+        &ExecuteMsg::Deposit {},
+        &[coin(30_000_000u128, config.base_denom)],
+        &env.traders[0],
+    )
+    .unwrap();
+
+    let vault_after: VaultResponse = wasm
+        .query(&crab_address, &QueryMsg::GetVaultDetails {})
+        .unwrap();
+
+    // the collateral left once the fee is taken matches the debt minted
+    let collateral_added = vault_after.collateral - vault_before.collateral;
+    let power_minted = vault_after.short_amount - vault_before.short_amount;
+    assert!(collateral_added < Uint128::from(30_000_000u128));
+    assert!(is_similar(
+        collateral_added,
+        power_minted.multiply_ratio(vault_before.collateral, vault_before.short_amount),
+        Uint128::from(100u128),
+    ));
+}
+
+#[test]
+fn test_pause_and_unpause() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+
+    let (_, _, crab_address) = env.setup_crab(&wasm, true, "0.0".to_string());
+
+    wasm.execute(&crab_address, &ExecuteMsg::Pause {}, &[], &env.traders[0])
+        .unwrap_err();
+
+    wasm.execute(&crab_address, &ExecuteMsg::Pause {}, &[], &env.signer)
+        .unwrap();
+
+    let config: ConfigResponse = wasm.query(&crab_address, &QueryMsg::Config {}).unwrap();
+    wasm.execute(
+        &crab_address,
+        &ExecuteMsg::Deposit {},
+        &[coin(30_000_000u128, config.base_denom)],
+        &env.traders[0],
+    )
+    .unwrap_err();
+
+    wasm.execute(&crab_address, &ExecuteMsg::UnPause {}, &[], &env.signer)
+        .unwrap();
+
+    let state: StateResponse = wasm.query(&crab_address, &QueryMsg::State {}).unwrap();
+    assert!(!state.is_paused);
+}
+
+#[test]
+fn test_hedge_not_allowed_before_threshold() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+
+    let (_, _, crab_address) = env.setup_crab(&wasm, true, "0.0".to_string());
+
+    let time_hedge: bool = wasm
+        .query(&crab_address, &QueryMsg::CheckTimeHedge {})
+        .unwrap();
+    assert!(!time_hedge);
+
+    let price_hedge: bool = wasm
+        .query(&crab_address, &QueryMsg::CheckPriceHedge {})
+        .unwrap();
+    assert!(!price_hedge);
+
+    let config: ConfigResponse = wasm.query(&crab_address, &QueryMsg::Config {}).unwrap();
+    wasm.execute(
+        &crab_address,
+        &ExecuteMsg::HedgeOTC {},
+        &[coin(1_000_000u128, config.base_denom)],
+        &env.traders[0],
+    )
+    .unwrap_err();
+}
+
+#[test]
+fn test_transfer_vault() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+
+    let (_, _, crab_address) = env.setup_crab(&wasm, true, "0.0".to_string());
+
+    let transfer_msg = ExecuteMsg::TransferVault {
+        recipient: env.traders[1].address(),
+    };
+
+    // should fail if strategy is not paused
+    wasm.execute(&crab_address, &transfer_msg, &[], &env.signer)
+        .unwrap_err();
+
+    wasm.execute(&crab_address, &ExecuteMsg::Pause {}, &[], &env.signer)
+        .unwrap();
+
+    // should fail if sender is not the owner
+    wasm.execute(&crab_address, &transfer_msg, &[], &env.traders[0])
+        .unwrap_err();
+
+    wasm.execute(&crab_address, &transfer_msg, &[], &env.signer)
+        .unwrap();
+
+    let vault: VaultResponse = wasm
+        .query(&crab_address, &QueryMsg::GetVaultDetails {})
+        .unwrap();
+    assert_eq!(vault.operator, Addr::unchecked(env.traders[1].address()));
+}
+
+#[test]
+fn test_shutdown_withdrawal() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+
+    let (power_address, _, crab_address) = env.setup_crab(&wasm, true, "0.0".to_string());
+
+    let config: ConfigResponse = wasm.query(&crab_address, &QueryMsg::Config {}).unwrap();
+
+    // should fail if the power controller is not shutdown
+    wasm.execute(
+        &crab_address,
+        &ExecuteMsg::RedeemShortShutdown {},
+        &[],
+        &env.traders[0],
+    )
+    .unwrap_err();
+
+    wasm.execute(
+        &power_address,
+        &PowerExecuteMsg::Shutdown { market_id: None },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    let shares = env.get_balance(env.signer.address(), config.strategy_denom.clone());
+
+    // should fail if the strategy vault has not been redeemed
+    wasm.execute(
+        &crab_address,
+        &ExecuteMsg::WithdrawShutdown {},
+        &[coin(shares.u128(), config.strategy_denom.clone())],
+        &env.signer,
+    )
+    .unwrap_err();
+
+    wasm.execute(
+        &crab_address,
+        &ExecuteMsg::RedeemShortShutdown {},
+        &[],
+        &env.traders[0],
+    )
+    .unwrap();
+
+    let state: StateResponse = wasm.query(&crab_address, &QueryMsg::State {}).unwrap();
+    assert!(state.has_redeemed_in_shutdown);
+
+    let vault: VaultResponse = wasm
+        .query(&crab_address, &QueryMsg::GetVaultDetails {})
+        .unwrap();
+    assert!(vault.collateral.is_zero());
+
+    let strategy_balance = env.get_balance(crab_address.clone(), config.base_denom.clone());
+    assert!(!strategy_balance.is_zero());
+
+    let balance_before = env.get_balance(env.signer.address(), config.base_denom.clone());
+
+    wasm.execute(
+        &crab_address,
+        &ExecuteMsg::WithdrawShutdown {},
+        &[coin(shares.u128(), config.strategy_denom.clone())],
+        &env.signer,
+    )
+    .unwrap();
+
+    let balance_after = env.get_balance(env.signer.address(), config.base_denom.clone());
+    assert_eq!(balance_after - balance_before, strategy_balance);
+    assert!(env
+        .get_balance(env.signer.address(), config.strategy_denom)
+        .is_zero());
+}
+
+#[test]
+fn test_hedge_otc_selling() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+
+    let (_, query_address, crab_address) = env.setup_crab(&wasm, true, "0.0".to_string());
+
+    let config: ConfigResponse = wasm.query(&crab_address, &QueryMsg::Config {}).unwrap();
+
+    // the power price falls so the strategy holds more collateral than it needs
+    env.app.increase_time(DEFAULT_HEDGE_TIME_THRESHOLD);
+    env.set_oracle_price(
+        &wasm,
+        query_address,
+        env.power_pool_id,
+        Decimal::from_str("0.25").unwrap(),
+    );
+
+    let time_hedge: bool = wasm
+        .query(&crab_address, &QueryMsg::CheckTimeHedge {})
+        .unwrap();
+    assert!(time_hedge);
+
+    let vault_before: VaultResponse = wasm
+        .query(&crab_address, &QueryMsg::GetVaultDetails {})
+        .unwrap();
+    let shares_before = env.get_total_supply(config.strategy_denom.clone());
+    let state_before: StateResponse = wasm.query(&crab_address, &QueryMsg::State {}).unwrap();
+
+    // the power needed to bring the debt back to half the collateral, (c - 2 * d * p) / p
+    let expected_power = (vault_before.collateral
+        - vault_before.short_amount.multiply_ratio(1u128, 2u128))
+        * Uint128::from(4u128);
+    let expected_base = expected_power.multiply_ratio(1u128, 4u128);
+
+    // only the hedger approved by the owner can take the hedge
+    wasm.execute(
+        &crab_address,
+        &ExecuteMsg::UpdateConfig {
+            new_config: UpdateConfig {
+                hedger: Some(env.traders[0].address()),
+                ..Default::default()
+            },
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    wasm.execute(
+        &crab_address,
+        &ExecuteMsg::HedgeOTC {},
+        &[coin(
+            expected_base.u128() + 1_000_000u128,
+            config.base_denom.clone(),
+        )],
+        &env.traders[1],
+    )
+    .unwrap_err();
+
+    let base_balance_before = env.get_balance(env.traders[0].address(), config.base_denom.clone());
+    let power_balance_before =
+        env.get_balance(env.traders[0].address(), config.power_denom.clone());
+
+    let response = wasm
+        .execute(
+            &crab_address,
+            &ExecuteMsg::HedgeOTC {},
+            &[coin(
+                expected_base.u128() + 1_000_000u128,
+                config.base_denom.clone(),
+            )],
+            &env.traders[0],
+        )
+        .unwrap();
+
+    assert_eq!(
+        parse_event_attribute(response.events.clone(), "wasm-hedge_otc", "is_selling"),
+        "true"
+    );
+    assert_eq!(
+        parse_event_attribute(response.events.clone(), "wasm-hedge_otc", "power_amount"),
+        expected_power.to_string()
+    );
+    assert_eq!(
+        parse_event_attribute(response.events, "wasm-hedge_otc", "base_amount"),
+        expected_base.to_string()
+    );
+
+    // the hedger pays base for the power minted, the excess is refunded
+    let base_balance_after = env.get_balance(env.traders[0].address(), config.base_denom.clone());
+    let power_balance_after = env.get_balance(env.traders[0].address(), config.power_denom.clone());
+    assert_eq!(base_balance_before - base_balance_after, expected_base);
+    assert_eq!(power_balance_after - power_balance_before, expected_power);
+
+    let vault_after: VaultResponse = wasm
+        .query(&crab_address, &QueryMsg::GetVaultDetails {})
+        .unwrap();
+    assert_eq!(
+        vault_after.collateral,
+        vault_before.collateral + expected_base
+    );
+    assert_eq!(
+        vault_after.short_amount,
+        vault_before.short_amount + expected_power
+    );
+    assert_eq!(
+        env.get_total_supply(config.strategy_denom.clone()),
+        shares_before
+    );
+
+    // the strategy cannot be hedged again until the threshold has passed
+    let state: StateResponse = wasm.query(&crab_address, &QueryMsg::State {}).unwrap();
+    assert!(state.time_at_last_hedge > state_before.time_at_last_hedge);
+
+    wasm.execute(
+        &crab_address,
+        &ExecuteMsg::HedgeOTC {},
+        &[coin(1_000_000u128, config.base_denom)],
+        &env.traders[0],
+    )
+    .unwrap_err();
+}
+
+#[test]
+fn test_flash_deposit_and_flash_withdraw() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+
+    let (_, _, crab_address) = env.setup_crab(&wasm, true, "0.0".to_string());
+
+    let config: ConfigResponse = wasm.query(&crab_address, &QueryMsg::Config {}).unwrap();
+
+    let deposit_amount = Uint128::from(30_000_000u128);
+
+    let vault_before: VaultResponse = wasm
+        .query(&crab_address, &QueryMsg::GetVaultDetails {})
+        .unwrap();
+    let total_shares_before = env.get_total_supply(config.strategy_denom.clone());
+
+    let crab_base_before = env.get_balance(crab_address.clone(), config.base_denom.clone());
+    let crab_power_before = env.get_balance(crab_address.clone(), config.power_denom.clone());
+
+    // should revert if the power sells for less than the minimum
+    wasm.execute(
+        &crab_address,
+        &ExecuteMsg::FlashDeposit {
+            min_base_out: Some(deposit_amount),
+        },
+        &[coin(deposit_amount.u128(), config.base_denom.clone())],
+        &env.traders[0],
+    )
+    .unwrap_err();
+
+    // should mint against the deposit and sell the power for the sender
+    let base_received: Uint128;
+    let shares: Uint128;
+    {
+        let base_balance_before =
+            env.get_balance(env.traders[0].address(), config.base_denom.clone());
+
+        let response = wasm
+            .execute(
+                &crab_address,
+                &ExecuteMsg::FlashDeposit {
+                    min_base_out: Some(Uint128::one()),
+                },
+                &[coin(deposit_amount.u128(), config.base_denom.clone())],
+                &env.traders[0],
+            )
+            .unwrap();
+
+        base_received = Uint128::from_str(&parse_event_attribute(
+            response.events,
+            "wasm-flash_deposit",
+            "base_received",
+        ))
+        .unwrap();
+        assert!(!base_received.is_zero());
+
+        // the power minted keeps the collateral ratio of the vault
+        let vault_after: VaultResponse = wasm
+            .query(&crab_address, &QueryMsg::GetVaultDetails {})
+            .unwrap();
+        let power_minted = vault_after.short_amount - vault_before.short_amount;
+        assert_eq!(
+            vault_after.collateral,
+            vault_before.collateral + deposit_amount
+        );
+        assert_eq!(
+            power_minted,
+            deposit_amount.multiply_ratio(vault_before.short_amount, vault_before.collateral)
+        );
+
+        shares = env.get_balance(env.traders[0].address(), config.strategy_denom.clone());
+        assert_eq!(
+            shares,
+            power_minted.multiply_ratio(total_shares_before, vault_before.short_amount)
+        );
+        assert_eq!(
+            env.get_total_supply(config.strategy_denom.clone()),
+            total_shares_before + shares
+        );
+
+        // the sender receives the base from the sale and the strategy keeps nothing
+        let base_balance_after =
+            env.get_balance(env.traders[0].address(), config.base_denom.clone());
+        assert_eq!(
+            base_balance_after + deposit_amount,
+            base_balance_before + base_received
+        );
+        assert!(env
+            .get_balance(env.traders[0].address(), config.power_denom.clone())
+            .is_zero());
+        assert_eq!(
+            env.get_balance(crab_address.clone(), config.power_denom.clone()),
+            crab_power_before
+        );
+        assert_eq!(
+            env.get_balance(crab_address.clone(), config.base_denom.clone()),
+            crab_base_before
+        );
+    }
+
+    // should buy back the debt of the shares and withdraw the collateral to the sender
+    {
+        let vault_before: VaultResponse = wasm
+            .query(&crab_address, &QueryMsg::GetVaultDetails {})
+            .unwrap();
+        let total_shares_before = env.get_total_supply(config.strategy_denom.clone());
+
+        let expected_burn = vault_before
+            .short_amount
+            .multiply_ratio(shares, total_shares_before);
+        let expected_withdraw = vault_before
+            .collateral
+            .multiply_ratio(shares, total_shares_before);
+
+        // more base than the power is worth is sent for the swap
+        let amount_to_swap = base_received * Uint128::from(2u128);
+
+        let base_balance_before =
+            env.get_balance(env.traders[0].address(), config.base_denom.clone());
+
+        let mut funds = vec![
+            coin(shares.u128(), config.strategy_denom.clone()),
+            coin(amount_to_swap.u128(), config.base_denom.clone()),
+        ];
+        funds.sort_by(|a, b| a.denom.cmp(&b.denom));
+
+        // should revert if the maximum input is more than the base sent
+        wasm.execute(
+            &crab_address,
+            &ExecuteMsg::FlashWithdraw {
+                max_base_in: Some(amount_to_swap + Uint128::one()),
+            },
+            &funds,
+            &env.traders[0],
+        )
+        .unwrap_err();
+
+        // should revert if buying back the power costs more than the maximum
+        wasm.execute(
+            &crab_address,
+            &ExecuteMsg::FlashWithdraw {
+                max_base_in: Some(Uint128::one()),
+            },
+            &funds,
+            &env.traders[0],
+        )
+        .unwrap_err();
+
+        let response = wasm
+            .execute(
+                &crab_address,
+                &ExecuteMsg::FlashWithdraw {
+                    max_base_in: Some(amount_to_swap),
+                },
+                &funds,
+                &env.traders[0],
+            )
+            .unwrap();
+
+        assert_eq!(
+            parse_event_attribute(
+                response.events.clone(),
+                "wasm-flash_withdraw",
+                "shares_burnt"
+            ),
+            shares.to_string()
+        );
+        assert_eq!(
+            parse_event_attribute(
+                response.events.clone(),
+                "wasm-flash_withdraw",
+                "power_burnt"
+            ),
+            expected_burn.to_string()
+        );
+        assert_eq!(
+            parse_event_attribute(
+                response.events.clone(),
+                "wasm-flash_withdraw",
+                "collateral_withdrawn"
+            ),
+            expected_withdraw.to_string()
+        );
+        let base_swapped = Uint128::from_str(&parse_event_attribute(
+            response.events,
+            "wasm-flash_withdraw",
+            "base_swapped",
+        ))
+        .unwrap();
+        assert!(base_swapped <= amount_to_swap);
+
+        let vault_after: VaultResponse = wasm
+            .query(&crab_address, &QueryMsg::GetVaultDetails {})
+            .unwrap();
+        assert_eq!(
+            vault_after.collateral,
+            vault_before.collateral - expected_withdraw
+        );
+        assert_eq!(
+            vault_after.short_amount,
+            vault_before.short_amount - expected_burn
+        );
+
+        assert!(env
+            .get_balance(env.traders[0].address(), config.strategy_denom.clone())
+            .is_zero());
+        assert_eq!(
+            env.get_total_supply(config.strategy_denom.clone()),
+            total_shares_before - shares
+        );
+
+        // the collateral and the base not needed for the swap are returned
+        let base_balance_after =
+            env.get_balance(env.traders[0].address(), config.base_denom.clone());
+        assert_eq!(
+            base_balance_after + base_swapped,
+            base_balance_before + expected_withdraw
+        );
+        assert_eq!(
+            env.get_balance(crab_address.clone(), config.power_denom.clone()),
+            crab_power_before
+        );
+        assert_eq!(
+            env.get_balance(crab_address, config.base_denom),
+            crab_base_before
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use crate::state::{
+    DEFAULT_HEDGE_PRICE_THRESHOLD, DEFAULT_HEDGE_TIME_THRESHOLD, DEFAULT_HEDGING_TWAP_PERIOD,
+    DEFAULT_STRATEGY_CAP, STRATEGY_SUBDENOM,
+};
+
+use cosmwasm_std::{Addr, Decimal};
+use margined_protocol::crab::{ConfigResponse, QueryMsg, StateResponse};
+use margined_testing::power_env::PowerEnv;
+use osmosis_test_tube::{Account, Module, Wasm};
+
+#[test]
+fn test_instantiation() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+
+    let (power_address, query_address) =
+        env.deploy_power(&wasm, "margined-power".to_string(), false);
+    let crab_address = env.deploy_crab(&wasm, power_address.clone(), query_address.clone());
+
+    let config: ConfigResponse = wasm.query(&crab_address, &QueryMsg::Config {}).unwrap();
+    assert_eq!(config.power_contract, Addr::unchecked(power_address));
+    assert_eq!(config.query_contract, Addr::unchecked(query_address));
+    assert_eq!(
+        config.fee_pool_contract,
+        Addr::unchecked(env.fee_pool.address())
+    );
+    assert_eq!(config.fee_rate, Decimal::zero());
+    assert_eq!(config.hedge_price_threshold, DEFAULT_HEDGE_PRICE_THRESHOLD);
+    assert_eq!(config.hedge_time_threshold, DEFAULT_HEDGE_TIME_THRESHOLD);
+    assert_eq!(config.hedging_twap_period, DEFAULT_HEDGING_TWAP_PERIOD);
+    assert_eq!(config.strategy_cap, DEFAULT_STRATEGY_CAP);
+    assert_eq!(
+        config.strategy_denom,
+        format!("factory/{crab_address}/{STRATEGY_SUBDENOM}")
+    );
+    assert_eq!(config.hedger, Addr::unchecked(env.signer.address()));
+
+    let state: StateResponse = wasm.query(&crab_address, &QueryMsg::State {}).unwrap();
+    assert!(!state.is_open);
+    assert!(state.is_paused);
+
+    let owner: Addr = wasm.query(&crab_address, &QueryMsg::Owner {}).unwrap();
+    assert_eq!(owner, env.signer.address());
+}
@@ -0,0 +1,2 @@
+mod execute_test;
+mod instantiation_test;
@@ -0,0 +1,150 @@
+use crate::{
+    market::get_market_id,
+    queries::{get_pool_spot_price, get_pool_twap},
+    state::{CONFIG, STATE},
+};
+
+use cosmwasm_std::{ensure, Deps, DepsMut, Env, Event, MessageInfo, Response, StdError, StdResult};
+use cw_utils::nonpayable;
+use margined_common::errors::ContractError;
+
+// the recent TWAP checked before tripping covers this fraction of the TWAP period
+const RECENT_TWAP_FRACTION: u64 = 4;
+
+/// Blocks the operation when the spot and TWAP prices of the market pools
+/// cannot be trusted, without pausing the market.
+pub fn check_circuit_breaker(deps: Deps, env: &Env, market_id: u64) -> Result<(), ContractError> {
+    match get_price_deviation(deps, env, market_id)? {
+        Some(reason) => Err(ContractError::CircuitBreaker(reason)),
+        None => Ok(()),
+    }
+}
+
+/// Pauses the market when the recent TWAP of one of its pools deviates from
+/// the TWAP over the whole period, so moving the spot price in one block
+/// cannot pause it. Anyone can trip it and the owner unpauses as usual.
+pub fn handle_trip_circuit_breaker(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    market_id: Option<u64>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    let mut state = STATE.load(deps.storage, market_id)?;
+    state.is_open_and_unpaused()?;
+
+    let reason = get_sustained_deviation(deps.as_ref(), &env, market_id)?
+        .ok_or_else(|| StdError::generic_err("Pool prices are within the max spot deviation"))?;
+
+    state.is_paused = true;
+    state.last_pause = env.block.time;
+
+    STATE.save(deps.storage, market_id, &state)?;
+
+    Ok(Response::new().add_event(
+        Event::new("circuit_breaker")
+            .add_attributes([("market_id", market_id.to_string()), ("reason", reason)]),
+    ))
+}
+
+/// Returns why the spot and TWAP prices of the market pools cannot be trusted,
+/// either as they diverge past the max spot deviation or cannot be queried.
+pub fn get_price_deviation(deps: Deps, env: &Env, market_id: u64) -> StdResult<Option<String>> {
+    find_deviation(deps, env, market_id, None)
+}
+
+/// Returns why the recent TWAP of the market pools cannot be trusted, either as
+/// it diverges from the TWAP over the period or cannot be queried.
+pub fn get_sustained_deviation(deps: Deps, env: &Env, market_id: u64) -> StdResult<Option<String>> {
+    let config = CONFIG.load(deps.storage, market_id)?;
+
+    let recent_period = config.twap_period / RECENT_TWAP_FRACTION;
+    ensure!(
+        recent_period > 0,
+        StdError::generic_err("TWAP period is too short to trip the circuit breaker")
+    );
+
+    find_deviation(deps, env, market_id, Some(recent_period))
+}
+
+/// Compares the TWAP of each market pool over the period with its spot price,
+/// or with its TWAP over the recent period when one is given.
+fn find_deviation(
+    deps: Deps,
+    env: &Env,
+    market_id: u64,
+    recent_period: Option<u64>,
+) -> StdResult<Option<String>> {
+    let config = CONFIG.load(deps.storage, market_id)?;
+
+    // circuit breaker is disabled
+    if config.max_spot_deviation.is_zero() {
+        return Ok(None);
+    }
+
+    let start_time = env.block.time.minus_seconds(config.twap_period);
+
+    let pools = [
+        (
+            config.base_pool.id,
+            config.base_denom.clone(),
+            config.base_pool.quote_denom.clone(),
+        ),
+        (
+            config.power_pool.id,
+            config.power_denom.clone(),
+            config.base_denom.clone(),
+        ),
+    ];
+
+    for (pool_id, base_asset, quote_asset) in pools {
+        let recent_price = match recent_period {
+            Some(recent_period) => get_pool_twap(
+                &deps,
+                market_id,
+                pool_id,
+                base_asset.clone(),
+                quote_asset.clone(),
+                env.block.time.minus_seconds(recent_period),
+            ),
+            None => get_pool_spot_price(
+                &deps,
+                market_id,
+                pool_id,
+                base_asset.clone(),
+                quote_asset.clone(),
+            ),
+        };
+        let twap = get_pool_twap(
+            &deps,
+            market_id,
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+        );
+
+        let (recent_price, twap) = match (recent_price, twap) {
+            (Ok(recent_price), Ok(twap)) if !twap.is_zero() => (recent_price, twap),
+            _ => return Ok(Some(format!("pool {pool_id} price unavailable"))),
+        };
+
+        let price_kind = if recent_period.is_some() {
+            "recent twap"
+        } else {
+            "spot price"
+        };
+
+        let deviation = recent_price.abs_diff(twap) / twap;
+        if deviation > config.max_spot_deviation {
+            return Ok(Some(format!(
+                "pool {pool_id} {price_kind} deviates from twap by {deviation}"
+            )));
+        }
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,295 @@
+use crate::{
+    helpers::decimal_to_fixed,
+    oracle::{get_base_price, query_price_feed},
+    queries::get_pool_twap,
+    state::{Config, COLLATERAL_ASSETS},
+    vault::{get_vault_collateral, Vault, VAULT_COLLATERAL},
+};
+
+use cosmwasm_std::{
+    coin, ensure, Coin, Decimal, Deps, StdError, StdResult, Storage, Timestamp, Uint128,
+};
+use margined_protocol::power::{CollateralAsset, CollateralPriceSource};
+
+/// Collateral of a vault other than the base denom with its price in base.
+pub struct PricedCollateral {
+    pub denom: String,
+    pub amount: Uint128,
+    pub decimals: u32,
+    pub price: Decimal,
+}
+
+impl PricedCollateral {
+    /// Returns the value in base of the collateral, without the haircut.
+    pub fn value(&self, base_decimals: u32) -> StdResult<Uint128> {
+        let unit = Uint128::from(10u128.pow(self.decimals));
+
+        Ok(decimal_to_fixed(
+            Decimal::from_ratio(self.amount, unit).checked_mul(self.price)?,
+            base_decimals,
+        ))
+    }
+}
+
+pub fn validate_collateral_asset(
+    deps: Deps,
+    config: &Config,
+    asset: &CollateralAsset,
+) -> StdResult<()> {
+    ensure!(
+        asset.denom != config.base_denom && asset.denom != config.power_denom,
+        StdError::generic_err("Invalid collateral denom, cannot be the base or power denom")
+    );
+
+    ensure!(
+        asset.decimals > 0 && asset.decimals <= 18,
+        StdError::generic_err("Invalid collateral decimals")
+    );
+
+    ensure!(
+        asset.haircut < Decimal::one(),
+        StdError::generic_err("Invalid collateral haircut, must be less than 100%")
+    );
+
+    match &asset.price_source {
+        CollateralPriceSource::Quote {} => ensure!(
+            asset.denom == config.base_pool.quote_denom,
+            StdError::generic_err("Invalid collateral price source, denom is not the quote denom")
+        ),
+        CollateralPriceSource::Pool { quote_denom, .. } => ensure!(
+            *quote_denom == config.base_denom || *quote_denom == config.base_pool.quote_denom,
+            StdError::generic_err(
+                "Invalid collateral price source, must be quoted in the base or quote denom"
+            )
+        ),
+        CollateralPriceSource::PriceFeed { contract, max_age } => {
+            deps.api.addr_validate(contract)?;
+
+            ensure!(
+                *max_age > 0,
+                StdError::generic_err("Invalid collateral price feed max age")
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the price in base of one whole token of the collateral asset,
+/// without the haircut.
+pub fn get_collateral_price(
+    deps: &Deps,
+    config: &Config,
+    market_id: u64,
+    asset: &CollateralAsset,
+    start_time: Timestamp,
+    block_time: Timestamp,
+) -> StdResult<Decimal> {
+    let price = match &asset.price_source {
+        CollateralPriceSource::Quote {} => {
+            let price = Decimal::one()
+                .checked_div(get_base_price(deps, market_id, start_time, block_time)?)
+                .map_err(|_| StdError::generic_err("Base price cannot be zero"))?;
+
+            scale_pool_price(price, asset.decimals, config.base_decimals)?
+        }
+        CollateralPriceSource::Pool {
+            pool_id,
+            quote_denom,
+        } => {
+            let price = get_pool_twap(
+                deps,
+                market_id,
+                *pool_id,
+                asset.denom.clone(),
+                quote_denom.clone(),
+                start_time,
+            )?;
+
+            let price = if *quote_denom == config.base_denom {
+                price
+            } else {
+                price
+                    .checked_div(get_base_price(deps, market_id, start_time, block_time)?)
+                    .map_err(|_| StdError::generic_err("Base price cannot be zero"))?
+            };
+
+            scale_pool_price(price, asset.decimals, config.base_decimals)?
+        }
+        CollateralPriceSource::PriceFeed { contract, max_age } => query_price_feed(
+            deps,
+            contract.clone(),
+            asset.denom.clone(),
+            config.base_denom.clone(),
+            *max_age,
+            block_time,
+        )?,
+    };
+
+    ensure!(
+        !price.is_zero(),
+        StdError::generic_err("Collateral price cannot be zero")
+    );
+
+    Ok(price)
+}
+
+/// Converts a pool price, a ratio of raw base units per raw unit of the asset,
+/// into the price in base of one whole token of the asset.
+fn scale_pool_price(price: Decimal, asset_decimals: u32, base_decimals: u32) -> StdResult<Decimal> {
+    if asset_decimals >= base_decimals {
+        let scale = Uint128::from(10u128.pow(asset_decimals - base_decimals));
+
+        Ok(price.checked_mul(Decimal::from_ratio(scale, 1u128))?)
+    } else {
+        let scale = Uint128::from(10u128.pow(base_decimals - asset_decimals));
+
+        price
+            .checked_div(Decimal::from_ratio(scale, 1u128))
+            .map_err(|_| StdError::generic_err("Invalid collateral price scale"))
+    }
+}
+
+/// Returns the collateral of the vault other than the base denom at current prices.
+pub fn get_priced_collateral(
+    deps: &Deps,
+    config: &Config,
+    vault_id: u64,
+    market_id: u64,
+    start_time: Timestamp,
+    block_time: Timestamp,
+) -> StdResult<Vec<PricedCollateral>> {
+    get_vault_collateral(deps.storage, vault_id)?
+        .into_iter()
+        .map(|(denom, amount)| {
+            let asset = COLLATERAL_ASSETS.load(deps.storage, (market_id, &denom))?;
+            let price =
+                get_collateral_price(deps, config, market_id, &asset, start_time, block_time)?;
+
+            Ok(PricedCollateral {
+                denom,
+                amount,
+                decimals: asset.decimals,
+                price,
+            })
+        })
+        .collect()
+}
+
+/// Returns the value in base of the collateral of the vault other than the
+/// base denom, after the haircut of each asset.
+pub fn get_collateral_value(
+    deps: &Deps,
+    config: &Config,
+    vault_id: u64,
+    market_id: u64,
+    start_time: Timestamp,
+    block_time: Timestamp,
+) -> StdResult<Uint128> {
+    let mut value = Decimal::zero();
+
+    for (denom, amount) in get_vault_collateral(deps.storage, vault_id)? {
+        let asset = COLLATERAL_ASSETS.load(deps.storage, (market_id, &denom))?;
+        let price = get_collateral_price(deps, config, market_id, &asset, start_time, block_time)?;
+
+        let decimal_amount = Decimal::from_ratio(amount, Uint128::from(10u128.pow(asset.decimals)));
+
+        value = value.checked_add(
+            decimal_amount
+                .checked_mul(price)?
+                .checked_mul(Decimal::one() - asset.haircut)?,
+        )?;
+    }
+
+    Ok(decimal_to_fixed(value, config.base_decimals))
+}
+
+/// Returns the vault with its collateral valued in base, including the
+/// collateral other than the base denom after haircuts.
+pub fn value_vault(
+    deps: &Deps,
+    config: &Config,
+    vault_id: u64,
+    vault: Vault,
+    start_time: Timestamp,
+    block_time: Timestamp,
+) -> StdResult<Vault> {
+    let collateral_value = get_collateral_value(
+        deps,
+        config,
+        vault_id,
+        vault.market_id,
+        start_time,
+        block_time,
+    )?;
+
+    Ok(Vault {
+        collateral: vault.collateral.checked_add(collateral_value)?,
+        ..vault
+    })
+}
+
+/// Takes collateral other than the base denom from the vault to cover the value
+/// in base owed, returning the coins taken and the value left uncovered.
+pub fn take_collateral(
+    storage: &mut dyn Storage,
+    vault_id: u64,
+    base_decimals: u32,
+    collateral: Vec<PricedCollateral>,
+    amount_owed: Uint128,
+) -> StdResult<(Vec<Coin>, Uint128)> {
+    let (taken, remaining) = select_collateral(base_decimals, &collateral, amount_owed)?;
+
+    for (asset, taken) in collateral.iter().zip(taken.iter()) {
+        let left = asset.amount.checked_sub(taken.amount)?;
+        if left.is_zero() {
+            VAULT_COLLATERAL.remove(storage, (vault_id, &asset.denom));
+        } else {
+            VAULT_COLLATERAL.save(storage, (vault_id, &asset.denom), &left)?;
+        }
+    }
+
+    Ok((taken, remaining))
+}
+
+/// Returns the coins of the collateral, in order, that cover the value in base owed
+/// and the value left uncovered. Amounts are rounded up so the value taken covers
+/// the amount owed.
+pub fn select_collateral(
+    base_decimals: u32,
+    collateral: &[PricedCollateral],
+    amount_owed: Uint128,
+) -> StdResult<(Vec<Coin>, Uint128)> {
+    let mut remaining = amount_owed;
+    let mut taken = vec![];
+
+    for asset in collateral {
+        if remaining.is_zero() {
+            break;
+        }
+
+        let value = asset.value(base_decimals)?;
+
+        let amount = if value <= remaining {
+            remaining = remaining.checked_sub(value)?;
+            asset.amount
+        } else {
+            let unit = Uint128::from(10u128.pow(asset.decimals));
+            let needed = Decimal::from_ratio(remaining, Uint128::from(10u128.pow(base_decimals)))
+                .checked_div(asset.price)
+                .unwrap();
+
+            let mut amount = decimal_to_fixed(needed, asset.decimals);
+            if Decimal::from_ratio(amount, unit) < needed {
+                amount += Uint128::one();
+            }
+
+            remaining = Uint128::zero();
+            amount.min(asset.amount)
+        };
+
+        taken.push(coin(amount.u128(), asset.denom.clone()));
+    }
+
+    Ok((taken, remaining))
+}
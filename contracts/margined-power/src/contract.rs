@@ -1,47 +1,75 @@
 use crate::{
+    circuit_breaker::handle_trip_circuit_breaker,
     handle::{
-        handle_apply_funding, handle_burn_power_perp, handle_close_short, handle_deposit,
-        handle_liquidation, handle_mint_power_perp, handle_open_contract, handle_open_short,
-        handle_pause, handle_unpause, handle_update_config, handle_withdrawal,
+        handle_add_market, handle_adjust_vault, handle_apply_funding, handle_approve_all_nft,
+        handle_approve_nft, handle_approve_operator, handle_burn_power_perp, handle_close_short,
+        handle_delist_collateral_asset, handle_deposit, handle_flash_mint, handle_fund_insurance,
+        handle_liquidation, handle_migrate_vaults, handle_mint_and_provide_liquidity,
+        handle_mint_power_perp, handle_open_contract, handle_open_long, handle_open_short,
+        handle_pause, handle_redeem_shutdown, handle_remove_liquidity, handle_revoke_all_nft,
+        handle_revoke_nft, handle_revoke_operator, handle_send_nft, handle_set_collateral_asset,
+        handle_shutdown, handle_start_liquidation_auction, handle_transfer_nft,
+        handle_transfer_vault, handle_unpause, handle_update_config, handle_withdraw_collateral,
+        handle_withdraw_shutdown, handle_withdrawal,
     },
+    market::create_market,
+    migration::{has_legacy_market, migrate_legacy_market, start_legacy_vault_migration},
+    nft::{save_vault_nft_info, vault_nft},
     query::{
-        get_check_vault, get_denormalised_mark, get_denormalised_mark_for_funding, get_index,
-        get_next_vault_id, get_normalisation_factor, get_unscaled_index, get_user_vaults,
-        get_vault, query_config, query_owner, query_state,
+        get_annualised_funding_rate, get_check_vault, get_collateral_assets, get_denormalised_mark,
+        get_denormalised_mark_for_funding, get_fee_preview, get_funding_history, get_index,
+        get_insurance_balance, get_liquidation_auction, get_next_vault_id,
+        get_normalisation_factor, get_total_bad_debt, get_unscaled_index,
+        get_user_liquidity_positions, get_user_vaults, get_vault, get_vault_collateral_balances,
+        get_vault_health, get_vault_operators, list_unsafe_vaults, list_vaults, query_config,
+        query_markets, query_owner, query_state,
     },
-    reply::{handle_close_short_reply, handle_open_short_reply, handle_open_short_swap_reply},
-    state::{Config, State, CONFIG, OWNER, OWNERSHIP_PROPOSAL, STATE},
+    reply::{
+        handle_close_short_reply, handle_collect_incentives_reply,
+        handle_collect_spread_rewards_reply, handle_flash_mint_reply,
+        handle_mint_and_provide_liquidity_reply, handle_open_short_reply,
+        handle_provide_liquidity_reply, handle_remove_liquidity_reply,
+        handle_swap_exact_amount_in_reply, reply_kind,
+    },
+    state::{OWNER, OWNERSHIP_PROPOSAL},
 };
 
 use cosmwasm_std::{
-    entry_point, to_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Reply, Response,
-    StdError, StdResult,
+    entry_point, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError,
+    StdResult,
 };
 use cw2::set_contract_version;
+use cw721::Cw721Query;
 use margined_common::{
-    common::{check_denom_exists_in_pool, check_denom_metadata},
     errors::ContractError,
     ownership::{
         get_ownership_proposal, handle_claim_ownership, handle_ownership_proposal,
         handle_ownership_proposal_rejection,
     },
 };
-use margined_protocol::power::{
-    ExecuteMsg, InstantiateMsg, MigrateMsg, Pool, QueryMsg, FUNDING_PERIOD,
-};
-use std::str::FromStr;
+use margined_protocol::power::{ExecuteMsg, InstantiateMsg, MigrateMsg, NewMarket, QueryMsg};
 
 pub const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// reply ids of keyed calls carry the nonce of their context above these bits
+pub const REPLY_KIND_BITS: u32 = 8;
+
 pub const OPEN_SHORT_REPLY_ID: u64 = 1u64;
 pub const OPEN_SHORT_SWAP_REPLY_ID: u64 = 2u64;
 pub const CLOSE_SHORT_REPLY_ID: u64 = 3u64;
 pub const CLOSE_SHORT_SWAP_REPLY_ID: u64 = 4u64;
+pub const FLASH_MINT_REPLY_ID: u64 = 5u64;
+pub const OPEN_LONG_SWAP_REPLY_ID: u64 = 6u64;
+pub const MINT_AND_PROVIDE_LIQUIDITY_REPLY_ID: u64 = 7u64;
+pub const PROVIDE_LIQUIDITY_REPLY_ID: u64 = 8u64;
+pub const REMOVE_LIQUIDITY_REPLY_ID: u64 = 9u64;
+pub const COLLECT_SPREAD_REWARDS_REPLY_ID: u64 = 10u64;
+pub const COLLECT_INCENTIVES_REPLY_ID: u64 = 11u64;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
@@ -52,54 +80,26 @@ pub fn instantiate(
         CONTRACT_VERSION,
     )?;
 
-    let config = Config {
-        fee_rate: Decimal::from_str(&msg.fee_rate)?,
-        fee_pool_contract: deps.api.addr_validate(&msg.fee_pool)?,
-        query_contract: deps.api.addr_validate(&msg.query_contract)?,
-        power_denom: msg.power_denom.clone(),
-        base_denom: msg.base_denom,
-        base_pool: Pool {
-            id: msg.base_pool_id,
-            quote_denom: msg.base_pool_quote,
-        },
-        power_pool: Pool {
-            id: msg.power_pool_id,
-            quote_denom: msg.power_denom,
-        },
-        funding_period: FUNDING_PERIOD,
-        base_decimals: msg.base_decimals,
-        power_decimals: msg.power_decimals,
-    };
-
-    config.validate()?;
-
-    CONFIG.save(deps.storage, &config)?;
-
-    // validate denoms exist
-    check_denom_metadata(deps.as_ref(), &config.base_denom)
-        .map_err(|_| ContractError::InvalidDenom(config.base_denom.clone()))?;
-    check_denom_metadata(deps.as_ref(), &config.power_denom)
-        .map_err(|_| ContractError::InvalidDenom(config.power_denom.clone()))?;
-
-    // validate denoms are present in pool
-    check_denom_exists_in_pool(deps.as_ref(), config.base_pool.id, &config.base_denom)
-        .map_err(ContractError::Std)?;
-    check_denom_exists_in_pool(deps.as_ref(), config.power_pool.id, &config.base_denom)
-        .map_err(ContractError::Std)?;
-    check_denom_exists_in_pool(deps.as_ref(), config.power_pool.id, &config.power_denom)
-        .map_err(ContractError::Std)?;
-
-    STATE.save(
-        deps.storage,
-        &State {
-            is_open: false,
-            is_paused: true,
-            last_pause: env.block.time,
-            normalisation_factor: Decimal::one(),
-            last_funding_update: env.block.time,
+    // the market created on instantiation is the default market
+    create_market(
+        deps.branch(),
+        &env,
+        NewMarket {
+            fee_rate: msg.fee_rate,
+            fee_pool: msg.fee_pool,
+            query_contract: msg.query_contract,
+            base_denom: msg.base_denom,
+            power_denom: msg.power_denom,
+            base_pool_id: msg.base_pool_id,
+            base_pool_quote: msg.base_pool_quote,
+            power_pool_id: msg.power_pool_id,
+            base_decimals: msg.base_decimals,
+            power_decimals: msg.power_decimals,
         },
     )?;
 
+    save_vault_nft_info(deps.storage)?;
+
     OWNER.set(deps, Some(info.sender))?;
 
     Ok(Response::new().add_attribute("action", "instantiate"))
@@ -107,10 +107,19 @@ pub fn instantiate(
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
-    match msg.id {
+    match reply_kind(msg.id) {
         OPEN_SHORT_REPLY_ID => handle_open_short_reply(deps, env, msg),
-        OPEN_SHORT_SWAP_REPLY_ID => handle_open_short_swap_reply(deps, env, msg),
+        OPEN_SHORT_SWAP_REPLY_ID => handle_swap_exact_amount_in_reply(deps, env, msg),
         CLOSE_SHORT_REPLY_ID => handle_close_short_reply(deps, env, msg),
+        FLASH_MINT_REPLY_ID => handle_flash_mint_reply(deps, env, msg),
+        OPEN_LONG_SWAP_REPLY_ID => handle_swap_exact_amount_in_reply(deps, env, msg),
+        MINT_AND_PROVIDE_LIQUIDITY_REPLY_ID => {
+            handle_mint_and_provide_liquidity_reply(deps, env, msg)
+        }
+        PROVIDE_LIQUIDITY_REPLY_ID => handle_provide_liquidity_reply(deps, msg),
+        REMOVE_LIQUIDITY_REPLY_ID => handle_remove_liquidity_reply(deps, msg),
+        COLLECT_SPREAD_REWARDS_REPLY_ID => handle_collect_spread_rewards_reply(deps, msg),
+        COLLECT_INCENTIVES_REPLY_ID => handle_collect_incentives_reply(deps, msg),
 
         _ => Err(ContractError::UnknownReplyId(msg.id)),
     }
@@ -124,23 +133,43 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::SetOpen {} => handle_open_contract(deps, env, info),
+        ExecuteMsg::SetOpen { market_id } => handle_open_contract(deps, env, info, market_id),
+        ExecuteMsg::AddMarket { new_market } => handle_add_market(deps, env, info, new_market),
         ExecuteMsg::MintPowerPerp {
             amount,
             vault_id,
             rebase,
-        } => handle_mint_power_perp(deps, env, info, amount, vault_id, rebase),
+            market_id,
+        } => handle_mint_power_perp(deps, env, info, amount, vault_id, rebase, market_id),
         ExecuteMsg::BurnPowerPerp {
             amount_to_withdraw,
             vault_id,
         } => handle_burn_power_perp(deps, env, info, amount_to_withdraw, vault_id),
-        ExecuteMsg::OpenShort { amount, vault_id } => {
-            handle_open_short(deps, env, info, amount, vault_id)
-        }
+        ExecuteMsg::OpenShort {
+            amount,
+            vault_id,
+            min_base_out,
+            deadline,
+            market_id,
+            route,
+        } => handle_open_short(
+            deps,
+            env,
+            info,
+            amount,
+            vault_id,
+            min_base_out,
+            deadline,
+            market_id,
+            route,
+        ),
         ExecuteMsg::CloseShort {
             amount_to_burn,
             amount_to_withdraw,
             vault_id,
+            max_base_in,
+            deadline,
+            route,
         } => handle_close_short(
             deps,
             env,
@@ -148,21 +177,123 @@ pub fn execute(
             amount_to_burn,
             amount_to_withdraw,
             vault_id,
+            max_base_in,
+            deadline,
+            route,
+        ),
+        ExecuteMsg::OpenLong {
+            min_power_out,
+            deadline,
+            market_id,
+        } => handle_open_long(deps, env, info, min_power_out, deadline, market_id),
+        ExecuteMsg::MintAndProvideLiquidity {
+            amount,
+            collateral_amount,
+            vault_id,
+            min_power_provided,
+            min_base_provided,
+            deadline,
+            market_id,
+        } => handle_mint_and_provide_liquidity(
+            deps,
+            env,
+            info,
+            amount,
+            collateral_amount,
+            vault_id,
+            min_power_provided,
+            min_base_provided,
+            deadline,
+            market_id,
         ),
+        ExecuteMsg::RemoveLiquidity { position_id } => {
+            handle_remove_liquidity(deps, env, info, position_id)
+        }
         ExecuteMsg::Deposit { vault_id } => handle_deposit(deps, env, info, vault_id),
         ExecuteMsg::Withdraw { amount, vault_id } => {
             handle_withdrawal(deps, env, info, amount, vault_id)
         }
+        ExecuteMsg::WithdrawCollateral {
+            denom,
+            amount,
+            vault_id,
+        } => handle_withdraw_collateral(deps, env, info, denom, amount, vault_id),
+        ExecuteMsg::FlashMint {
+            amount,
+            callback,
+            vault_id,
+            market_id,
+        } => handle_flash_mint(deps, env, info, amount, callback, vault_id, market_id),
+        ExecuteMsg::AdjustVault {
+            vault_id,
+            collateral_delta,
+            debt_delta,
+        } => handle_adjust_vault(deps, env, info, vault_id, collateral_delta, debt_delta),
         ExecuteMsg::Liquidate {
             vault_id,
             max_debt_amount,
         } => handle_liquidation(deps, env, info, max_debt_amount, vault_id),
-        ExecuteMsg::ApplyFunding { .. } => handle_apply_funding(deps, env, info),
-        ExecuteMsg::UpdateConfig { fee_rate, fee_pool } => {
-            handle_update_config(deps, info, fee_rate, fee_pool)
+        ExecuteMsg::StartLiquidationAuction { vault_id } => {
+            handle_start_liquidation_auction(deps, env, info, vault_id)
+        }
+        ExecuteMsg::FundInsurance { market_id } => handle_fund_insurance(deps, info, market_id),
+        ExecuteMsg::TransferVault {
+            vault_id,
+            recipient,
+        } => handle_transfer_vault(deps, info, vault_id, recipient),
+        ExecuteMsg::ApproveOperator {
+            vault_id,
+            spender,
+            expires,
+        } => handle_approve_operator(deps, env, info, vault_id, spender, expires),
+        ExecuteMsg::RevokeOperator { vault_id, spender } => {
+            handle_revoke_operator(deps, info, vault_id, spender)
+        }
+        ExecuteMsg::TransferNft {
+            recipient,
+            token_id,
+        } => handle_transfer_nft(deps, env, info, recipient, token_id),
+        ExecuteMsg::SendNft {
+            contract,
+            token_id,
+            msg,
+        } => handle_send_nft(deps, env, info, contract, token_id, msg),
+        ExecuteMsg::Approve {
+            spender,
+            token_id,
+            expires,
+        } => handle_approve_nft(deps, env, info, spender, token_id, expires),
+        ExecuteMsg::Revoke { spender, token_id } => {
+            handle_revoke_nft(deps, env, info, spender, token_id)
+        }
+        ExecuteMsg::ApproveAll { operator, expires } => {
+            handle_approve_all_nft(deps, env, info, operator, expires)
+        }
+        ExecuteMsg::RevokeAll { operator } => handle_revoke_all_nft(deps, env, info, operator),
+        ExecuteMsg::ApplyFunding { market_id } => handle_apply_funding(deps, env, info, market_id),
+        ExecuteMsg::UpdateConfig {
+            market_id,
+            new_config,
+        } => handle_update_config(deps, info, market_id, new_config),
+        ExecuteMsg::SetCollateralAsset { market_id, asset } => {
+            handle_set_collateral_asset(deps, info, market_id, asset)
+        }
+        ExecuteMsg::DelistCollateralAsset { market_id, denom } => {
+            handle_delist_collateral_asset(deps, info, market_id, denom)
+        }
+        ExecuteMsg::Pause { market_id } => handle_pause(deps, env, info, market_id),
+        ExecuteMsg::MigrateVaults { limit } => handle_migrate_vaults(deps, info, limit),
+        ExecuteMsg::TripCircuitBreaker { market_id } => {
+            handle_trip_circuit_breaker(deps, env, info, market_id)
+        }
+        ExecuteMsg::UnPause { market_id } => handle_unpause(deps, env, info, market_id),
+        ExecuteMsg::Shutdown { market_id } => handle_shutdown(deps, env, info, market_id),
+        ExecuteMsg::RedeemShutdown { market_id } => {
+            handle_redeem_shutdown(deps, env, info, market_id)
+        }
+        ExecuteMsg::WithdrawShutdown { vault_id } => {
+            handle_withdraw_shutdown(deps, env, info, vault_id)
         }
-        ExecuteMsg::Pause {} => handle_pause(deps, env, info),
-        ExecuteMsg::UnPause {} => handle_unpause(deps, env, info),
         ExecuteMsg::ProposeNewOwner {
             new_owner,
             duration,
@@ -187,35 +318,127 @@ pub fn execute(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
-        QueryMsg::Config {} => to_binary(&query_config(deps)?),
-        QueryMsg::State {} => to_binary(&query_state(deps)?),
+        QueryMsg::Config { market_id } => to_binary(&query_config(deps, market_id)?),
+        QueryMsg::State { market_id } => to_binary(&query_state(deps, market_id)?),
+        QueryMsg::GetMarkets { start_after, limit } => {
+            to_binary(&query_markets(deps, start_after, limit)?)
+        }
         QueryMsg::Owner {} => {
             to_binary(&query_owner(deps).map_err(|err| StdError::generic_err(err.to_string()))?)
         }
-        QueryMsg::GetNormalisationFactor {} => to_binary(&get_normalisation_factor(deps, env)?),
-        QueryMsg::GetIndex { period } => to_binary(&get_index(deps, env, period)?),
-        QueryMsg::GetUnscaledIndex { period } => to_binary(&get_unscaled_index(deps, env, period)?),
-        QueryMsg::GetDenormalisedMark { period } => {
-            to_binary(&get_denormalised_mark(deps, env, period)?)
+        QueryMsg::GetNormalisationFactor { market_id } => {
+            to_binary(&get_normalisation_factor(deps, env, market_id)?)
+        }
+        QueryMsg::GetIndex { period, market_id } => {
+            to_binary(&get_index(deps, env, period, market_id)?)
+        }
+        QueryMsg::GetUnscaledIndex { period, market_id } => {
+            to_binary(&get_unscaled_index(deps, env, period, market_id)?)
         }
-        QueryMsg::GetDenormalisedMarkFunding { period } => {
-            to_binary(&get_denormalised_mark_for_funding(deps, env, period)?)
+        QueryMsg::GetDenormalisedMark { period, market_id } => {
+            to_binary(&get_denormalised_mark(deps, env, period, market_id)?)
         }
+        QueryMsg::GetDenormalisedMarkFunding { period, market_id } => to_binary(
+            &get_denormalised_mark_for_funding(deps, env, period, market_id)?,
+        ),
         QueryMsg::GetVault { vault_id } => to_binary(&get_vault(deps, vault_id)?),
+        QueryMsg::GetVaultOperators { vault_id } => {
+            to_binary(&get_vault_operators(deps, vault_id)?)
+        }
         QueryMsg::GetNextVaultId {} => to_binary(&get_next_vault_id(deps)?),
         QueryMsg::GetUserVaults {
             user,
             start_after,
             limit,
         } => to_binary(&get_user_vaults(deps, user, start_after, limit)?),
+        QueryMsg::GetUserLiquidityPositions {
+            user,
+            start_after,
+            limit,
+        } => to_binary(&get_user_liquidity_positions(
+            deps,
+            user,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::ListVaults { start_after, limit } => {
+            to_binary(&list_vaults(deps, start_after, limit)?)
+        }
+        QueryMsg::ListUnsafeVaults { start_after, limit } => {
+            to_binary(&list_unsafe_vaults(deps, env, start_after, limit)?)
+        }
         QueryMsg::GetOwnershipProposal {} => {
             to_binary(&get_ownership_proposal(deps, OWNERSHIP_PROPOSAL)?)
         }
         QueryMsg::CheckVault { vault_id } => to_binary(&get_check_vault(deps, env, vault_id)?),
+        QueryMsg::GetLiquidationAuction { vault_id } => {
+            to_binary(&get_liquidation_auction(deps, env, vault_id)?)
+        }
+        QueryMsg::GetVaultHealth { vault_id } => to_binary(&get_vault_health(deps, env, vault_id)?),
+        QueryMsg::GetVaultCollateral { vault_id } => {
+            to_binary(&get_vault_collateral_balances(deps, env, vault_id)?)
+        }
+        QueryMsg::GetCollateralAssets { market_id } => {
+            to_binary(&get_collateral_assets(deps, market_id)?)
+        }
+        QueryMsg::GetFeePreview {
+            user,
+            amount,
+            market_id,
+        } => to_binary(&get_fee_preview(deps, env, user, amount, market_id)?),
+        QueryMsg::GetInsuranceBalance { market_id } => {
+            to_binary(&get_insurance_balance(deps, market_id)?)
+        }
+        QueryMsg::GetTotalBadDebt { market_id } => to_binary(&get_total_bad_debt(deps, market_id)?),
+        QueryMsg::GetFundingHistory {
+            market_id,
+            start_after,
+            limit,
+        } => to_binary(&get_funding_history(deps, market_id, start_after, limit)?),
+        QueryMsg::GetAnnualisedFundingRate { period, market_id } => {
+            to_binary(&get_annualised_funding_rate(deps, env, period, market_id)?)
+        }
+        QueryMsg::OwnerOf {
+            token_id,
+            include_expired,
+        } => to_binary(&vault_nft().owner_of(
+            deps,
+            env,
+            token_id,
+            include_expired.unwrap_or(false),
+        )?),
+        QueryMsg::NftInfo { token_id } => to_binary(&vault_nft().nft_info(deps, token_id)?),
+        QueryMsg::Tokens {
+            owner,
+            start_after,
+            limit,
+        } => to_binary(&vault_nft().tokens(deps, owner, start_after, limit)?),
+        QueryMsg::AllTokens { start_after, limit } => {
+            to_binary(&vault_nft().all_tokens(deps, start_after, limit)?)
+        }
+        QueryMsg::NumTokens {} => to_binary(&vault_nft().num_tokens(deps)?),
+        QueryMsg::ContractInfo {} => to_binary(&vault_nft().contract_info(deps)?),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
-    Ok(Response::new())
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let mut response = Response::new().add_attribute("action", "migrate");
+
+    // contracts deployed before markets were added hold a single market, its vaults
+    // are migrated with `MigrateVaults` while the market is paused
+    if has_legacy_market(deps.storage)? {
+        migrate_legacy_market(deps.storage)?;
+        let is_migrating = start_legacy_vault_migration(deps.storage, env.block.time)?;
+
+        response = response.add_attribute("migrating_vaults", is_migrating.to_string());
+    }
+
+    set_contract_version(
+        deps.storage,
+        format!("crates.io:{CONTRACT_NAME}"),
+        CONTRACT_VERSION,
+    )?;
+
+    Ok(response)
 }
@@ -1,76 +1,167 @@
 use crate::{
     helpers::{calculate_denormalized_mark, calculate_index, wrapped_pow},
-    state::STATE,
+    state::{Config, CONFIG, FUNDING_HISTORY, STATE, YEAR_IN_SECONDS},
 };
 
 use cosmwasm_std::{Decimal, Deps, DepsMut, Env, StdResult};
-use margined_protocol::power::FUNDING_PERIOD;
+use margined_protocol::power::{FundingRateResponse, FundingSnapshot, FUNDING_PERIOD};
 use num::Zero;
 
-pub const MAX_TWAP_PERIOD: u64 = 48 * 60 * 60; // TWAP from pool can be no longer than 48 hours
-
-pub fn apply_funding_rate(deps: DepsMut, env: Env) -> StdResult<Decimal> {
-    let state = STATE.load(deps.storage).unwrap();
+pub fn apply_funding_rate(deps: DepsMut, env: Env, market_id: u64) -> StdResult<Decimal> {
+    let mut state = STATE.load(deps.storage, market_id).unwrap();
 
     // normalisation factor is only updated onces per block
     if state.last_funding_update.seconds() == env.block.time.seconds() {
         return Ok(state.normalisation_factor);
     }
 
-    let normalisation_factor = calculate_normalisation_factor(deps.as_ref(), env.clone())?;
+    let snapshot = calculate_funding(deps.as_ref(), env.clone(), market_id)?;
+
+    let normalisation_factor = match snapshot {
+        Some(snapshot) => {
+            FUNDING_HISTORY.save(
+                deps.storage,
+                (market_id, env.block.time.seconds()),
+                &snapshot,
+            )?;
 
-    STATE.update(deps.storage, |mut state| -> StdResult<_> {
-        state.normalisation_factor = normalisation_factor;
-        state.last_funding_update = env.block.time;
+            snapshot.new_normalisation_factor
+        }
+        None => state.normalisation_factor,
+    };
 
-        Ok(state)
-    })?;
+    state.normalisation_factor = normalisation_factor;
+    state.last_funding_update = env.block.time;
+
+    STATE.save(deps.storage, market_id, &state)?;
 
     Ok(normalisation_factor)
 }
 
-pub fn calculate_normalisation_factor(deps: Deps, env: Env) -> StdResult<Decimal> {
-    let state = STATE.load(deps.storage).unwrap();
+pub fn calculate_normalisation_factor(deps: Deps, env: Env, market_id: u64) -> StdResult<Decimal> {
+    match calculate_funding(deps, env, market_id)? {
+        Some(snapshot) => Ok(snapshot.new_normalisation_factor),
+        None => Ok(STATE.load(deps.storage, market_id)?.normalisation_factor),
+    }
+}
+
+/// Returns the funding since the last update, or none if no funding is due.
+fn calculate_funding(deps: Deps, env: Env, market_id: u64) -> StdResult<Option<FundingSnapshot>> {
+    let state = STATE.load(deps.storage, market_id).unwrap();
+
+    // funding stops once the contract is shutdown
+    if state.is_shutdown {
+        return Ok(None);
+    }
+
+    let config = CONFIG.load(deps.storage, market_id)?;
 
     let funding_period = env
         .block
         .time
         .minus_seconds(state.last_funding_update.seconds());
 
-    let period = funding_period.seconds().min(MAX_TWAP_PERIOD);
+    let period = funding_period.seconds().min(config.max_twap_period);
 
-    // NOTE: pools must have a TWAP available for past 48 hours
-    let start_time = if period < MAX_TWAP_PERIOD {
+    // NOTE: pools must have a TWAP available for the max twap period
+    let start_time = if period < config.max_twap_period {
         state.last_funding_update
     } else {
-        env.block.time.minus_seconds(MAX_TWAP_PERIOD)
+        env.block.time.minus_seconds(config.max_twap_period)
     };
 
     if period.is_zero() {
-        return Ok(state.normalisation_factor);
+        return Ok(None);
     };
 
-    let mut mark =
-        calculate_denormalized_mark(deps, start_time, state.normalisation_factor).unwrap();
+    let mark = calculate_denormalized_mark(
+        deps,
+        market_id,
+        start_time,
+        env.block.time,
+        state.normalisation_factor,
+    )?;
 
-    let index = calculate_index(deps, start_time).unwrap();
+    let index = calculate_index(deps, market_id, start_time, env.block.time)?;
 
     let r_funding = Decimal::from_ratio(funding_period.seconds(), FUNDING_PERIOD);
 
-    // check that the mark price is between upper and lower bounds of 140% and 80% of the index price
-    let lower_bound = index * Decimal::percent(80);
-    let upper_bound = index * Decimal::percent(140);
-
-    if mark < lower_bound {
-        mark = lower_bound;
-    } else if mark > upper_bound {
-        mark = upper_bound;
-    };
+    let (mark, is_clamped) = clamp_mark(&config, mark, index);
 
     // normFactor(new) = multiplier * normFactor(old)
     // multiplier = (index/mark)^rFunding
     let base = index.checked_div(mark).unwrap();
     let multiplier = wrapped_pow(base, r_funding).unwrap();
 
-    Ok(multiplier * state.normalisation_factor)
+    Ok(Some(FundingSnapshot {
+        timestamp: env.block.time,
+        old_normalisation_factor: state.normalisation_factor,
+        new_normalisation_factor: multiplier * state.normalisation_factor,
+        mark,
+        index,
+        is_clamped,
+    }))
+}
+
+/// Returns the funding rate over a year at the mark and index of the period,
+/// the normalisation factor changes by index / mark every funding period.
+pub fn calculate_annualised_funding_rate(
+    deps: Deps,
+    env: Env,
+    market_id: u64,
+    period: u64,
+) -> StdResult<FundingRateResponse> {
+    let state = STATE.load(deps.storage, market_id)?;
+    let config = CONFIG.load(deps.storage, market_id)?;
+
+    if state.is_shutdown {
+        return Ok(FundingRateResponse {
+            annualised_rate: Decimal::zero(),
+            longs_pay: false,
+        });
+    }
+
+    let start_time = env
+        .block
+        .time
+        .minus_seconds(period.min(config.max_twap_period));
+
+    let mark = calculate_denormalized_mark(
+        deps,
+        market_id,
+        start_time,
+        env.block.time,
+        state.normalisation_factor,
+    )?;
+    let index = calculate_index(deps, market_id, start_time, env.block.time)?;
+
+    let (mark, _) = clamp_mark(&config, mark, index);
+
+    let ratio = index.checked_div(mark).unwrap();
+    let longs_pay = ratio <= Decimal::one();
+
+    let rate = if longs_pay {
+        Decimal::one() - ratio
+    } else {
+        ratio - Decimal::one()
+    };
+
+    Ok(FundingRateResponse {
+        annualised_rate: rate * Decimal::from_ratio(YEAR_IN_SECONDS, FUNDING_PERIOD),
+        longs_pay,
+    })
+}
+
+/// Clamps the mark price between the configured upper and lower bounds of the index price.
+fn clamp_mark(config: &Config, mark: Decimal, index: Decimal) -> (Decimal, bool) {
+    let lower_bound = index * config.funding_lower_bound;
+    let upper_bound = index * config.funding_upper_bound;
+
+    if mark < lower_bound {
+        (lower_bound, true)
+    } else if mark > upper_bound {
+        (upper_bound, true)
+    } else {
+        (mark, false)
+    }
 }
@@ -1,105 +1,390 @@
 use crate::{
-    contract::CLOSE_SHORT_REPLY_ID,
+    circuit_breaker::check_circuit_breaker,
+    collateral::{
+        get_collateral_price, get_priced_collateral, select_collateral, take_collateral,
+        validate_collateral_asset, PricedCollateral,
+    },
+    contract::{
+        CLOSE_SHORT_REPLY_ID, COLLECT_INCENTIVES_REPLY_ID, COLLECT_SPREAD_REWARDS_REPLY_ID,
+        FLASH_MINT_REPLY_ID, MINT_AND_PROVIDE_LIQUIDITY_REPLY_ID, OPEN_LONG_SWAP_REPLY_ID,
+        OPEN_SHORT_REPLY_ID, REMOVE_LIQUIDITY_REPLY_ID,
+    },
     funding::apply_funding_rate,
     helpers::{
-        create_apply_funding_event, create_swap_exact_amount_out_message, get_liquidation_results,
+        calculate_debt_in_base, calculate_liquidation_bonus, check_deadline,
+        create_apply_funding_event, create_mint_message, create_swap_exact_amount_in_message,
+        create_swap_exact_amount_out_message, decimal_to_fixed, format_coins, format_fee_splits,
+        get_liquidation_results, validate_swap_route,
+    },
+    market::{create_market, get_market_id, resolve_market_id, DEFAULT_MARKET_ID},
+    migration::{is_migrating_vaults, migrate_legacy_vaults},
+    nft::{parse_vault_token_id, vault_nft},
+    operations::{adjust_vault, burn, distribute_fee, mint},
+    oracle::{describe_price_type, describe_source, get_scaled_base_price},
+    queries::{get_balance, get_denom_authority},
+    reply::{encode_reply_id, reply_nonce, save_reply_context},
+    state::{
+        Config, FlashMint, ReplyContext, State, COLLATERAL_ASSETS, COLLATERAL_SETTLEMENT_PRICES,
+        CONFIG, DELISTED_COLLATERAL_ASSETS, FLASH_MINT, LIQUIDITY_POSITIONS, OWNER,
+        SETTLED_COLLATERAL, STATE, WEEK_IN_SECONDS,
+    },
+    vault::{
+        add_collateral, add_vault_collateral, approve_vault_operator, burn_vault, check_can_burn,
+        check_vault, check_vault_owner, clear_liquidation_auction, get_mint_recipient,
+        get_vault_collateral, get_vault_market_id, get_vault_owner_for_sender,
+        revoke_vault_operator, settle_vault, start_liquidation_auction, subtract_collateral,
+        subtract_vault_collateral, sync_vault_operator, transfer_vault, VAULTS, VAULT_COLLATERAL,
     },
-    operations::{burn, mint},
-    queries::{get_balance, get_denom_authority, get_total_supply},
-    state::{Config, State, TmpCacheValues, CONFIG, OWNER, STATE, TMP_CACHE, WEEK_IN_SECONDS},
-    vault::{add_collateral, burn_vault, check_can_burn, check_vault, subtract_collateral, VAULTS},
 };
 
 use cosmwasm_std::{
-    coin, ensure, ensure_eq, BankMsg, CosmosMsg, Decimal, DepsMut, Env, Event, MessageInfo,
-    ReplyOn, Response, StdResult, SubMsg, Uint128,
+    coin, ensure, ensure_eq, BankMsg, Binary, CosmosMsg, Decimal, DepsMut, Env, Event, MessageInfo,
+    Order, ReplyOn, Response, StdError, StdResult, SubMsg, Timestamp, Uint128, WasmMsg,
 };
-use cw_utils::{must_pay, nonpayable};
+use cw721::{Cw721Execute, Expiration};
+use cw_utils::{may_pay, must_pay, nonpayable, one_coin};
 use margined_common::errors::ContractError;
-use osmosis_std::types::{cosmos::base::v1beta1::Coin, osmosis::tokenfactory::v1beta1::MsgBurn};
+use margined_protocol::power::{
+    CollateralAsset, FlashMintCallback, NewMarket, OracleSource, SwapHop, UpdateConfig, VaultDelta,
+};
+use osmosis_std::types::{
+    cosmos::base::v1beta1::Coin,
+    osmosis::concentratedliquidity::v1beta1::{
+        ConcentratedliquidityQuerier, MsgCollectIncentives, MsgCollectSpreadRewards,
+        MsgWithdrawPosition,
+    },
+    osmosis::tokenfactory::v1beta1::MsgBurn,
+};
 use std::str::FromStr;
 
 pub fn handle_open_contract(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    market_id: Option<u64>,
 ) -> Result<Response, ContractError> {
     ensure!(
         OWNER.is_admin(deps.as_ref(), &info.sender)?,
         ContractError::Unauthorized {}
     );
 
-    let state: State = STATE.load(deps.storage)?;
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    let mut state: State = STATE.load(deps.storage, market_id)?;
     ensure!(!state.is_open, ContractError::IsOpen {});
 
-    let config: Config = CONFIG.load(deps.storage)?;
+    let config: Config = CONFIG.load(deps.storage, market_id)?;
 
     // get power token denom authority
-    let admin = get_denom_authority(deps.as_ref(), config.power_denom).unwrap();
+    let admin = get_denom_authority(deps.as_ref(), market_id, config.power_denom)?;
     ensure_eq!(admin, env.contract.address, ContractError::NotTokenAdmin {});
 
-    // set the contract to open
-    STATE.update(deps.storage, |mut state| -> StdResult<_> {
-        state.is_open = true;
-        state.is_paused = false;
-        Ok(state)
-    })?;
+    // set the market to open
+    state.is_open = true;
+    state.is_paused = false;
+
+    STATE.save(deps.storage, market_id, &state)?;
+
+    Ok(Response::new()
+        .add_event(Event::new("open_contract").add_attribute("market_id", market_id.to_string())))
+}
+
+pub fn handle_add_market(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    new_market: NewMarket,
+) -> Result<Response, ContractError> {
+    ensure!(
+        OWNER.is_admin(deps.as_ref(), &info.sender)?,
+        ContractError::Unauthorized {}
+    );
+
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    let base_denom = new_market.base_denom.clone();
+    let power_denom = new_market.power_denom.clone();
+
+    let market_id = create_market(deps, &env, new_market)?;
 
-    Ok(Response::new().add_event(Event::new("open_contract")))
+    Ok(
+        Response::new().add_event(Event::new("add_market").add_attributes([
+            ("market_id", &market_id.to_string()),
+            ("base_denom", &base_denom),
+            ("power_denom", &power_denom),
+        ])),
+    )
 }
 
 pub fn handle_update_config(
     deps: DepsMut,
     info: MessageInfo,
-    fee_rate: Option<String>,
-    fee_pool: Option<String>,
+    market_id: Option<u64>,
+    new_config: UpdateConfig,
 ) -> Result<Response, ContractError> {
-    let mut config = CONFIG.load(deps.storage)?;
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    let mut config = CONFIG.load(deps.storage, market_id)?;
 
     ensure!(
         OWNER.is_admin(deps.as_ref(), &info.sender)?,
         ContractError::Unauthorized {}
     );
 
-    let mut event = Event::new("update_config");
-    if let Some(fee_rate) = fee_rate {
+    let mut event = Event::new("update_config").add_attribute("market_id", market_id.to_string());
+    if let Some(fee_rate) = new_config.fee_rate {
         config.fee_rate = Decimal::from_str(&fee_rate)?;
         event = event.add_attribute("fee_rate", fee_rate);
     }
 
-    if let Some(fee_pool) = fee_pool {
+    if let Some(fee_pool) = new_config.fee_pool {
         config.fee_pool_contract = deps.api.addr_validate(&fee_pool)?;
         event = event.add_attribute("fee_pool", fee_pool);
     }
 
+    if let Some(min_liquidation_bonus) = new_config.min_liquidation_bonus {
+        config.min_liquidation_bonus = min_liquidation_bonus;
+        event = event.add_attribute("min_liquidation_bonus", min_liquidation_bonus.to_string());
+    }
+
+    if let Some(max_liquidation_bonus) = new_config.max_liquidation_bonus {
+        config.max_liquidation_bonus = max_liquidation_bonus;
+        event = event.add_attribute("max_liquidation_bonus", max_liquidation_bonus.to_string());
+    }
+
+    if let Some(liquidation_auction_duration) = new_config.liquidation_auction_duration {
+        config.liquidation_auction_duration = liquidation_auction_duration;
+        event = event.add_attribute(
+            "liquidation_auction_duration",
+            liquidation_auction_duration.to_string(),
+        );
+    }
+
+    if let Some(flash_mint_fee_rate) = new_config.flash_mint_fee_rate {
+        config.flash_mint_fee_rate = flash_mint_fee_rate;
+        event = event.add_attribute("flash_mint_fee_rate", flash_mint_fee_rate.to_string());
+    }
+
+    if let Some(fee_schedule) = new_config.fee_schedule {
+        if let Some(staking_contract) = &fee_schedule.staking_contract {
+            deps.api.addr_validate(staking_contract)?;
+        }
+
+        event = event.add_attributes([
+            ("fee_size_tiers", fee_schedule.size_tiers.len().to_string()),
+            (
+                "fee_staking_contract",
+                fee_schedule
+                    .staking_contract
+                    .clone()
+                    .unwrap_or_else(|| "none".to_string()),
+            ),
+            (
+                "fee_staking_discounts",
+                fee_schedule.staking_discounts.len().to_string(),
+            ),
+        ]);
+
+        config.fee_schedule = fee_schedule;
+    }
+
+    if let Some(fee_recipients) = new_config.fee_recipients {
+        for recipient in fee_recipients.iter() {
+            deps.api.addr_validate(&recipient.address)?;
+        }
+
+        event = event.add_attribute(
+            "fee_recipients",
+            fee_recipients
+                .iter()
+                .map(|recipient| format!("{}:{}", recipient.address, recipient.weight_bps))
+                .collect::<Vec<String>>()
+                .join(","),
+        );
+
+        config.fee_recipients = fee_recipients;
+    }
+
+    if let Some(insurance_fee_share) = new_config.insurance_fee_share {
+        config.insurance_fee_share = insurance_fee_share;
+        event = event.add_attribute("insurance_fee_share", insurance_fee_share.to_string());
+    }
+
+    if let Some(collateral_ratio_numerator) = new_config.collateral_ratio_numerator {
+        config.collateral_ratio_numerator = collateral_ratio_numerator;
+        event = event.add_attribute(
+            "collateral_ratio_numerator",
+            collateral_ratio_numerator.to_string(),
+        );
+    }
+
+    if let Some(collateral_ratio_denominator) = new_config.collateral_ratio_denominator {
+        config.collateral_ratio_denominator = collateral_ratio_denominator;
+        event = event.add_attribute(
+            "collateral_ratio_denominator",
+            collateral_ratio_denominator.to_string(),
+        );
+    }
+
+    if let Some(min_collateral) = new_config.min_collateral {
+        config.min_collateral = min_collateral;
+        event = event.add_attribute("min_collateral", min_collateral.to_string());
+    }
+
+    if let Some(twap_period) = new_config.twap_period {
+        config.twap_period = twap_period;
+        event = event.add_attribute("twap_period", twap_period.to_string());
+    }
+
+    if let Some(max_twap_period) = new_config.max_twap_period {
+        config.max_twap_period = max_twap_period;
+        event = event.add_attribute("max_twap_period", max_twap_period.to_string());
+    }
+
+    if let Some(funding_lower_bound) = new_config.funding_lower_bound {
+        config.funding_lower_bound = funding_lower_bound;
+        event = event.add_attribute("funding_lower_bound", funding_lower_bound.to_string());
+    }
+
+    if let Some(funding_upper_bound) = new_config.funding_upper_bound {
+        config.funding_upper_bound = funding_upper_bound;
+        event = event.add_attribute("funding_upper_bound", funding_upper_bound.to_string());
+    }
+
+    if let Some(oracle) = new_config.oracle {
+        for source in [Some(&oracle.primary), oracle.secondary.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            if let OracleSource::PriceFeed { contract, .. } = source {
+                deps.api.addr_validate(contract)?;
+            }
+        }
+
+        event = event.add_attributes([
+            ("oracle_primary", describe_source(&oracle.primary)),
+            (
+                "oracle_secondary",
+                oracle
+                    .secondary
+                    .as_ref()
+                    .map(describe_source)
+                    .unwrap_or_else(|| "none".to_string()),
+            ),
+            ("oracle_max_deviation", oracle.max_deviation.to_string()),
+        ]);
+
+        config.oracle = oracle;
+    }
+
+    if let Some(max_spot_deviation) = new_config.max_spot_deviation {
+        config.max_spot_deviation = max_spot_deviation;
+        event = event.add_attribute("max_spot_deviation", max_spot_deviation.to_string());
+    }
+
+    if let Some(power_price_type) = new_config.power_price_type {
+        event = event.add_attribute("power_price_type", describe_price_type(&power_price_type));
+        config.power_price_type = power_price_type;
+    }
+
     config.validate()?;
 
-    CONFIG.save(deps.storage, &config)?;
+    CONFIG.save(deps.storage, market_id, &config)?;
 
     Ok(Response::default().add_event(event))
 }
 
-pub fn handle_pause(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+pub fn handle_set_collateral_asset(
+    deps: DepsMut,
+    info: MessageInfo,
+    market_id: Option<u64>,
+    asset: CollateralAsset,
+) -> Result<Response, ContractError> {
+    ensure!(
+        OWNER.is_admin(deps.as_ref(), &info.sender)?,
+        ContractError::Unauthorized {}
+    );
+
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    let config = CONFIG.load(deps.storage, market_id)?;
+
+    validate_collateral_asset(deps.as_ref(), &config, &asset)?;
+
+    COLLATERAL_ASSETS.save(deps.storage, (market_id, &asset.denom), &asset)?;
+
+    // setting a delisted asset lists it again
+    DELISTED_COLLATERAL_ASSETS.remove(deps.storage, (market_id, &asset.denom));
+
+    Ok(
+        Response::new().add_event(Event::new("set_collateral_asset").add_attributes([
+            ("market_id", market_id.to_string()),
+            ("denom", asset.denom),
+            ("decimals", asset.decimals.to_string()),
+            ("haircut", asset.haircut.to_string()),
+        ])),
+    )
+}
+
+pub fn handle_delist_collateral_asset(
+    deps: DepsMut,
+    info: MessageInfo,
+    market_id: Option<u64>,
+    denom: String,
+) -> Result<Response, ContractError> {
+    ensure!(
+        OWNER.is_admin(deps.as_ref(), &info.sender)?,
+        ContractError::Unauthorized {}
+    );
+
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    ensure!(
+        COLLATERAL_ASSETS.has(deps.storage, (market_id, &denom)),
+        ContractError::TokenUnsupported(denom)
+    );
+
+    DELISTED_COLLATERAL_ASSETS.save(deps.storage, (market_id, &denom), &true)?;
+
+    Ok(Response::new().add_event(
+        Event::new("delist_collateral_asset")
+            .add_attributes([("market_id", market_id.to_string()), ("denom", denom)]),
+    ))
+}
+
+pub fn handle_pause(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    market_id: Option<u64>,
+) -> Result<Response, ContractError> {
     ensure!(
         OWNER.is_admin(deps.as_ref(), &info.sender)?,
         ContractError::Unauthorized {}
     );
 
-    let mut state = STATE.load(deps.storage)?;
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    let mut state = STATE.load(deps.storage, market_id)?;
 
     state.is_open_and_unpaused()?;
 
     state.is_paused = true;
     state.last_pause = env.block.time;
 
-    STATE.save(deps.storage, &state)?;
+    STATE.save(deps.storage, market_id, &state)?;
 
     let event = Event::new("pause");
 
     Ok(Response::default().add_event(
         event
             .add_attribute("is_paused", state.is_paused.to_string())
-            .add_attribute("last_pause", state.last_pause.to_string()),
+            .add_attribute("last_pause", state.last_pause.to_string())
+            .add_attribute("market_id", market_id.to_string()),
     ))
 }
 
@@ -107,13 +392,23 @@ pub fn handle_unpause(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    market_id: Option<u64>,
 ) -> Result<Response, ContractError> {
-    let mut state = STATE.load(deps.storage)?;
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    let mut state = STATE.load(deps.storage, market_id)?;
 
     if !state.is_open {
         return Err(ContractError::NotOpen {});
     }
 
+    ensure!(!state.is_shutdown, ContractError::IsShutdown {});
+
+    ensure!(
+        market_id != DEFAULT_MARKET_ID || !is_migrating_vaults(deps.storage)?,
+        StdError::generic_err("Cannot unpause until the vaults are migrated")
+    );
+
     let unpause_time = if !OWNER.is_admin(deps.as_ref(), &info.sender)? {
         state.last_pause.seconds() + WEEK_IN_SECONDS
     } else {
@@ -126,258 +421,1411 @@ pub fn handle_unpause(
 
     state.is_paused = false;
 
-    STATE.save(deps.storage, &state)?;
+    STATE.save(deps.storage, market_id, &state)?;
 
     let event = Event::new("unpause");
 
     Ok(Response::default().add_event(
         event
             .add_attribute("is_paused", state.is_paused.to_string())
-            .add_attribute("last_pause", state.last_pause.to_string()),
+            .add_attribute("last_pause", state.last_pause.to_string())
+            .add_attribute("market_id", market_id.to_string()),
     ))
 }
 
-pub fn handle_mint_power_perp(
-    deps: DepsMut,
-    env: Env,
-    info: MessageInfo,
-    mint_amount: Uint128,
-    vault_id: Option<u64>,
-    rebase: bool,
-) -> Result<Response, ContractError> {
-    mint(deps, env, info, mint_amount, vault_id, rebase, false)
-}
-
-pub fn handle_burn_power_perp(
+pub fn handle_migrate_vaults(
     deps: DepsMut,
-    env: Env,
     info: MessageInfo,
-    amount_to_withdraw: Option<Uint128>,
-    vault_id: u64,
+    limit: Option<u32>,
 ) -> Result<Response, ContractError> {
-    burn(deps, env, info, amount_to_withdraw, vault_id)
-}
+    ensure!(
+        OWNER.is_admin(deps.as_ref(), &info.sender)?,
+        ContractError::Unauthorized {}
+    );
 
-pub fn handle_open_short(
-    deps: DepsMut,
-    env: Env,
-    info: MessageInfo,
-    mint_amount: Uint128,
-    vault_id: Option<u64>,
-) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
 
-    let total_supply = get_total_supply(deps.as_ref(), config.power_denom)?;
+    ensure!(
+        is_migrating_vaults(deps.storage)?,
+        StdError::generic_err("No vaults to migrate")
+    );
 
-    TMP_CACHE.save(
-        deps.storage,
-        &TmpCacheValues {
-            total_supply: Some(total_supply),
-            sender: Some(info.sender.clone()),
-            vault_id,
-            ..Default::default()
-        },
-    )?;
+    let (vault_ids, is_complete) = migrate_legacy_vaults(deps.storage, limit)?;
 
-    mint(deps, env, info, mint_amount, vault_id, false, true)
+    Ok(
+        Response::new().add_event(Event::new("migrate_vaults").add_attributes([
+            ("migrated_vaults", vault_ids.len().to_string()),
+            ("is_complete", is_complete.to_string()),
+        ])),
+    )
 }
 
-pub fn handle_close_short(
-    deps: DepsMut,
+pub fn handle_shutdown(
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    amount_to_burn: Uint128,
-    amount_to_withdraw: Option<Uint128>,
-    vault_id: u64,
+    market_id: Option<u64>,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-
-    check_can_burn(
-        deps.as_ref().storage,
-        vault_id,
-        info.sender.clone(),
-        amount_to_burn,
-        amount_to_withdraw.unwrap_or(Uint128::zero()),
-    )?;
+    ensure!(
+        OWNER.is_admin(deps.as_ref(), &info.sender)?,
+        ContractError::Unauthorized {}
+    );
 
-    let power_balance = get_balance(
-        deps.as_ref(),
-        env.contract.address.to_string(),
-        config.power_denom.clone(),
-    )?;
+    let market_id = get_market_id(deps.storage, market_id)?;
 
-    let amount_to_swap =
-        must_pay(&info, &config.base_denom).map_err(|_| ContractError::InvalidFunds {})?;
+    let state = STATE.load(deps.storage, market_id)?;
+    ensure!(state.is_open, ContractError::NotOpen {});
+    ensure!(!state.is_shutdown, ContractError::IsShutdown {});
 
-    let swap_msg = create_swap_exact_amount_out_message(
-        env.contract.address.to_string(),
-        config.power_pool.id,
-        config.base_denom,
-        config.power_denom,
-        amount_to_burn.to_string(),
-        amount_to_swap.to_string(),
-    );
+    let config: Config = CONFIG.load(deps.storage, market_id)?;
 
-    let swap_submsg: SubMsg = SubMsg {
-        id: CLOSE_SHORT_REPLY_ID,
-        msg: swap_msg.into(),
-        gas_limit: None,
-        reply_on: ReplyOn::Success,
-    };
+    // final funding update, after which the normalisation factor is fixed
+    let normalisation_factor = apply_funding_rate(deps.branch(), env.clone(), market_id)?;
 
-    TMP_CACHE.save(
-        deps.storage,
-        &TmpCacheValues {
-            balance: Some(power_balance),
-            amount_to_swap: Some(amount_to_swap),
-            amount_to_withdraw,
-            sender: Some(info.sender),
-            vault_id: Some(vault_id),
-            ..Default::default()
-        },
+    let quote_price = get_scaled_base_price(
+        &deps.as_ref(),
+        market_id,
+        env.block.time.minus_seconds(config.twap_period),
+        env.block.time,
     )?;
 
-    Ok(Response::new().add_submessage(swap_submsg))
+    let settlement_price = normalisation_factor.checked_mul(quote_price).unwrap();
+
+    // collateral other than the base denom settles at the prices at shutdown
+    let assets = COLLATERAL_ASSETS
+        .prefix(market_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, asset)| asset))
+        .collect::<StdResult<Vec<CollateralAsset>>>()?;
+
+    for asset in assets {
+        let price = get_collateral_price(
+            &deps.as_ref(),
+            &config,
+            market_id,
+            &asset,
+            env.block.time.minus_seconds(config.twap_period),
+            env.block.time,
+        )?;
+
+        COLLATERAL_SETTLEMENT_PRICES.save(deps.storage, (market_id, &asset.denom), &price)?;
+    }
+
+    // reload as applying funding updates the state
+    let mut state = STATE.load(deps.storage, market_id)?;
+    state.is_shutdown = true;
+    state.settlement_price = Some(settlement_price);
+
+    STATE.save(deps.storage, market_id, &state)?;
+
+    Ok(Response::new().add_event(
+        Event::new("shutdown")
+            .add_attribute("settlement_price", settlement_price.to_string())
+            .add_attribute("normalisation_factor", normalisation_factor.to_string())
+            .add_attribute("market_id", market_id.to_string()),
+    ))
 }
 
-pub fn handle_liquidation(
-    mut deps: DepsMut,
+pub fn handle_redeem_shutdown(
+    deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    max_debt_amount: Uint128,
-    vault_id: u64,
+    market_id: Option<u64>,
 ) -> Result<Response, ContractError> {
-    STATE.load(deps.storage)?.is_open_and_unpaused()?;
-
-    let config: Config = CONFIG.load(deps.storage)?;
-    if !VAULTS.has(deps.storage, &vault_id) {
-        return Err(ContractError::VaultDoesNotExist {});
-    };
-
-    // liquidator does not require to send funds, rather it is burnt directly
-    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+    let market_id = get_market_id(deps.storage, market_id)?;
 
-    let cached_normalisation_factor = apply_funding_rate(deps.branch(), env.clone())?;
+    let state = STATE.load(deps.storage, market_id)?;
+    ensure!(state.is_shutdown, ContractError::NotShutdown {});
 
-    let (is_safe, _) = check_vault(
-        deps.as_ref(),
-        config.clone(),
-        vault_id,
-        cached_normalisation_factor,
-        env.block.time,
-    )?;
+    let config: Config = CONFIG.load(deps.storage, market_id)?;
 
-    ensure!(!is_safe, ContractError::SafeVault {});
+    let amount_to_redeem =
+        must_pay(&info, &config.power_denom).map_err(|_| ContractError::InvalidFunds {})?;
 
-    let vault = VAULTS.load(deps.storage, &vault_id)?;
+    let settlement_price = state.settlement_price.unwrap();
 
-    let (liquidation_amount, collateral_to_pay) =
-        get_liquidation_results(deps.as_ref(), env.clone(), max_debt_amount, vault.clone());
+    let base_amount = decimal_to_fixed(
+        Decimal::from_atomics(amount_to_redeem, config.power_decimals)
+            .unwrap()
+            .checked_mul(settlement_price)
+            .unwrap(),
+        config.base_decimals,
+    );
 
-    if max_debt_amount < liquidation_amount {
-        return Err(ContractError::InvalidLiquidation {});
+    ensure!(!base_amount.is_zero(), ContractError::ZeroTransfer {});
+
+    // collateral settled in place of base from vaults short of base is paid out first,
+    // at the settlement prices, so the base left covers the redemptions that follow
+    let settled = SETTLED_COLLATERAL
+        .prefix(market_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (denom, amount) = item?;
+            let asset = COLLATERAL_ASSETS.load(deps.storage, (market_id, &denom))?;
+            let price = COLLATERAL_SETTLEMENT_PRICES.load(deps.storage, (market_id, &denom))?;
+
+            Ok(PricedCollateral {
+                denom,
+                amount,
+                decimals: asset.decimals,
+                price,
+            })
+        })
+        .collect::<StdResult<Vec<PricedCollateral>>>()?;
+
+    let (collateral_paid, base_amount) =
+        select_collateral(config.base_decimals, &settled, base_amount)?;
+
+    for (asset, paid) in settled.iter().zip(collateral_paid.iter()) {
+        let left = asset.amount.checked_sub(paid.amount)?;
+        if left.is_zero() {
+            SETTLED_COLLATERAL.remove(deps.storage, (market_id, &asset.denom));
+        } else {
+            SETTLED_COLLATERAL.save(deps.storage, (market_id, &asset.denom), &left)?;
+        }
     }
 
-    burn_vault(
-        deps.storage,
-        vault_id,
-        vault.operator,
-        collateral_to_pay,
-        liquidation_amount,
-    )?;
-
-    // burn power perp token
     let msg_burn: CosmosMsg = MsgBurn {
         sender: env.contract.address.to_string(),
         amount: Some(Coin {
             denom: config.power_denom,
-            amount: liquidation_amount.to_string(),
+            amount: amount_to_redeem.to_string(),
         }),
-        burn_from_address: info.sender.to_string(),
+        burn_from_address: env.contract.address.to_string(),
     }
     .into();
 
-    // transfer collateral to sender
-    let msg_transfer: CosmosMsg = CosmosMsg::Bank(BankMsg::Send {
+    let mut funds = vec![];
+    if !base_amount.is_zero() {
+        funds.push(coin(base_amount.u128(), config.base_denom));
+    }
+    funds.extend(collateral_paid.clone());
+
+    let msg_transfer = CosmosMsg::Bank(BankMsg::Send {
         to_address: info.sender.to_string(),
-        amount: vec![coin(collateral_to_pay.u128(), config.base_denom)],
+        amount: funds,
     });
 
-    let liquidation_event = Event::new("liquidation").add_attributes([
-        ("liquidation_amount", &liquidation_amount.to_string()),
-        ("collateral_to_pay", &collateral_to_pay.to_string()),
-        ("vault_id", &vault_id.to_string()),
+    let mut redeem_event = Event::new("redeem_shutdown").add_attributes([
+        ("power_redeemed", &amount_to_redeem.to_string()),
+        ("base_paid", &base_amount.to_string()),
+        ("market_id", &market_id.to_string()),
     ]);
 
-    let funding_event = create_apply_funding_event(&cached_normalisation_factor.to_string());
+    if !collateral_paid.is_empty() {
+        redeem_event =
+            redeem_event.add_attribute("collateral_paid", format_coins(&collateral_paid));
+    }
 
     Ok(Response::new()
         .add_messages(vec![msg_burn, msg_transfer])
-        .add_events([liquidation_event, funding_event]))
+        .add_event(redeem_event))
 }
 
-pub fn handle_deposit(
-    mut deps: DepsMut,
+pub fn handle_withdraw_shutdown(
+    deps: DepsMut,
     env: Env,
     info: MessageInfo,
     vault_id: u64,
 ) -> Result<Response, ContractError> {
-    STATE.load(deps.storage)?.is_open_and_unpaused()?;
+    let market_id = get_vault_market_id(deps.storage, vault_id)?;
 
-    let config: Config = CONFIG.load(deps.storage)?;
+    let state = STATE.load(deps.storage, market_id)?;
+    ensure!(state.is_shutdown, ContractError::NotShutdown {});
 
-    if !VAULTS.has(deps.storage, &vault_id) {
-        return Err(ContractError::VaultDoesNotExist {});
-    };
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
 
-    let deposit_amount =
-        must_pay(&info, &config.base_denom).map_err(|_| ContractError::InvalidFunds {})?;
+    let config: Config = CONFIG.load(deps.storage, market_id)?;
 
-    let cached_normalisation_factor = apply_funding_rate(deps.branch(), env.clone())?;
+    let vault_owner = get_vault_owner_for_sender(deps.storage, &env.block, vault_id, &info.sender)?;
 
-    add_collateral(deps.storage, vault_id, info.sender, deposit_amount)?;
+    let vault = settle_vault(deps.storage, vault_id, vault_owner.clone())?;
 
-    let (is_safe, min_collateral) = check_vault(
-        deps.as_ref(),
-        config,
+    // debt is valued at the settlement price and stays in the contract to fund redemptions
+    let debt_value = decimal_to_fixed(
+        Decimal::from_atomics(vault.short_amount, config.power_decimals)
+            .unwrap()
+            .checked_mul(state.settlement_price.unwrap())
+            .unwrap(),
+        config.base_decimals,
+    );
+
+    let amount_to_withdraw = vault.collateral.saturating_sub(debt_value);
+
+    // debt above the base collateral is settled with the other collateral
+    let collateral = get_vault_collateral(deps.storage, vault_id)?
+        .into_iter()
+        .map(|(denom, amount)| {
+            let asset = COLLATERAL_ASSETS.load(deps.storage, (market_id, &denom))?;
+            let price = COLLATERAL_SETTLEMENT_PRICES.load(deps.storage, (market_id, &denom))?;
+
+            Ok(PricedCollateral {
+                denom,
+                amount,
+                decimals: asset.decimals,
+                price,
+            })
+        })
+        .collect::<StdResult<Vec<PricedCollateral>>>()?;
+
+    let (collateral_settled, _) = take_collateral(
+        deps.storage,
         vault_id,
-        cached_normalisation_factor,
-        env.block.time,
+        config.base_decimals,
+        collateral,
+        debt_value.saturating_sub(vault.collateral),
     )?;
 
-    ensure!(is_safe, ContractError::UnsafeVault {});
-    ensure!(min_collateral, ContractError::BelowMinCollateralAmount {});
+    // settled collateral stays in the contract to pay redemptions
+    for settled in collateral_settled.iter() {
+        SETTLED_COLLATERAL.update(
+            deps.storage,
+            (market_id, &settled.denom),
+            |amount| -> StdResult<_> {
+                Ok(amount.unwrap_or_default().checked_add(settled.amount)?)
+            },
+        )?;
+    }
 
-    let deposit_event = Event::new("deposit").add_attributes([
-        ("collateral_deposited", &deposit_amount.to_string()),
+    let mut funds = vec![];
+    if !amount_to_withdraw.is_zero() {
+        funds.push(coin(amount_to_withdraw.u128(), config.base_denom));
+    }
+
+    for (denom, amount) in get_vault_collateral(deps.storage, vault_id)? {
+        VAULT_COLLATERAL.remove(deps.storage, (vault_id, &denom));
+        funds.push(coin(amount.u128(), denom));
+    }
+
+    let mut response = Response::new();
+    if !funds.is_empty() {
+        response = response.add_message(BankMsg::Send {
+            to_address: vault_owner.to_string(),
+            amount: funds,
+        });
+    }
+
+    let mut withdraw_event = Event::new("withdraw_shutdown").add_attributes([
         ("vault_id", &vault_id.to_string()),
+        ("debt_value", &debt_value.to_string()),
+        ("collateral_withdrawn", &amount_to_withdraw.to_string()),
     ]);
 
-    let funding_event = create_apply_funding_event(&cached_normalisation_factor.to_string());
+    if !collateral_settled.is_empty() {
+        withdraw_event =
+            withdraw_event.add_attribute("collateral_settled", format_coins(&collateral_settled));
+    }
 
-    Ok(Response::new().add_events([deposit_event, funding_event]))
+    Ok(response.add_event(withdraw_event))
 }
 
-pub fn handle_withdrawal(
+pub fn handle_mint_power_perp(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    mint_amount: Uint128,
+    vault_id: Option<u64>,
+    rebase: bool,
+    market_id: Option<u64>,
+) -> Result<Response, ContractError> {
+    let market_id = resolve_market_id(deps.storage, market_id, vault_id)?;
+
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
+
+    check_circuit_breaker(deps.as_ref(), &env, market_id)?;
+
+    mint(
+        deps,
+        env,
+        info,
+        market_id,
+        mint_amount,
+        vault_id,
+        rebase,
+        None,
+    )
+}
+
+pub fn handle_burn_power_perp(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount_to_withdraw: Option<Uint128>,
+    vault_id: u64,
+) -> Result<Response, ContractError> {
+    burn(deps, env, info, amount_to_withdraw, vault_id)
+}
+
+pub fn handle_flash_mint(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    callback: FlashMintCallback,
+    vault_id: Option<u64>,
+    market_id: Option<u64>,
+) -> Result<Response, ContractError> {
+    let market_id = resolve_market_id(deps.storage, market_id, vault_id)?;
+
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
+
+    check_circuit_breaker(deps.as_ref(), &env, market_id)?;
+
+    let config: Config = CONFIG.load(deps.storage, market_id)?;
+
+    ensure!(!amount.is_zero(), ContractError::ZeroMint {});
+    ensure!(
+        !FLASH_MINT.exists(deps.storage),
+        ContractError::FlashMintInProgress {}
+    );
+
+    // the power not returned is kept by the callback, so only the owner may add it to
+    // the vault's debt
+    if let Some(vault_id) = vault_id {
+        ensure!(
+            VAULTS.has(deps.storage, &vault_id),
+            ContractError::VaultDoesNotExist {}
+        );
+
+        check_vault_owner(deps.storage, vault_id, &info.sender)?;
+    }
+
+    let callback_contract = deps.api.addr_validate(&callback.contract)?;
+
+    let fee = if config.flash_mint_fee_rate.is_zero() {
+        Decimal::zero()
+    } else {
+        let debt_value = calculate_debt_in_base(
+            deps.as_ref(),
+            env.clone(),
+            market_id,
+            Decimal::from_atomics(amount, config.power_decimals).unwrap(),
+        )?;
+
+        debt_value.checked_mul(config.flash_mint_fee_rate).unwrap()
+    };
+    let fee_amount = decimal_to_fixed(fee, config.base_decimals);
+
+    let fee_paid =
+        may_pay(&info, &config.base_denom).map_err(|_| ContractError::InvalidFunds {})?;
+    ensure!(fee_paid >= fee_amount, ContractError::InvalidFunds {});
+
+    let balance = get_balance(
+        deps.as_ref(),
+        env.contract.address.to_string(),
+        config.power_denom.clone(),
+    )?;
+
+    FLASH_MINT.save(
+        deps.storage,
+        &FlashMint {
+            market_id,
+            sender: info.sender.clone(),
+            amount,
+            vault_id,
+            balance,
+        },
+    )?;
+
+    let mut response = create_mint_message(
+        Response::new(),
+        env.contract.address.to_string(),
+        config.power_denom.clone(),
+        amount.to_string(),
+        callback_contract.to_string(),
+        None,
+    );
+
+    response = response.add_submessage(SubMsg {
+        id: FLASH_MINT_REPLY_ID,
+        msg: WasmMsg::Execute {
+            contract_addr: callback_contract.to_string(),
+            msg: callback.msg,
+            funds: vec![],
+        }
+        .into(),
+        gas_limit: None,
+        reply_on: ReplyOn::Success,
+    });
+
+    let fee_splits;
+    (response, fee_splits) = distribute_fee(deps.branch(), &config, market_id, fee, response)?;
+
+    let refund = fee_paid.checked_sub(fee_amount).unwrap();
+    if !refund.is_zero() {
+        response = response.add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![coin(refund.u128(), &config.base_denom)],
+        });
+    }
+
+    let mut flash_mint_event = Event::new("flash_mint").add_attributes([
+        ("amount", &amount.to_string()),
+        ("fee_amount", &fee_amount.to_string()),
+        ("fee_splits", &format_fee_splits(&fee_splits)),
+        ("callback_contract", &callback_contract.to_string()),
+        ("market_id", &market_id.to_string()),
+    ]);
+
+    if let Some(vault_id) = vault_id {
+        flash_mint_event = flash_mint_event.add_attribute("vault_id", vault_id.to_string());
+    }
+
+    Ok(response.add_event(flash_mint_event))
+}
+
+pub fn handle_adjust_vault(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    vault_id: u64,
+    collateral_delta: Option<VaultDelta>,
+    debt_delta: Option<VaultDelta>,
+) -> Result<Response, ContractError> {
+    let market_id = get_vault_market_id(deps.storage, vault_id)?;
+
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
+
+    check_circuit_breaker(deps.as_ref(), &env, market_id)?;
+
+    adjust_vault(deps, env, info, vault_id, collateral_delta, debt_delta)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_open_short(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    mint_amount: Uint128,
+    vault_id: Option<u64>,
+    min_base_out: Option<Uint128>,
+    deadline: Option<Timestamp>,
+    market_id: Option<u64>,
+    route: Option<Vec<SwapHop>>,
+) -> Result<Response, ContractError> {
+    check_deadline(&env, deadline)?;
+
+    let market_id = resolve_market_id(deps.storage, market_id, vault_id)?;
+
+    let config = CONFIG.load(deps.storage, market_id)?;
+
+    let route = validate_swap_route(
+        route,
+        SwapHop {
+            pool_id: config.power_pool.id,
+            token_in_denom: config.power_denom.clone(),
+            token_out_denom: config.base_denom,
+        },
+    )?;
+    ensure_eq!(
+        route[0].token_in_denom,
+        config.power_denom,
+        ContractError::InvalidSwapRoute("must start by selling power".to_string())
+    );
+
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
+
+    check_circuit_breaker(deps.as_ref(), &env, market_id)?;
+
+    // the power is minted to the contract and sold in the reply, the proceeds of an
+    // operator go to the vault owner
+    let recipient = get_mint_recipient(deps.storage, &env.block, vault_id, &info.sender)?;
+
+    let reply_id = save_reply_context(
+        deps.storage,
+        OPEN_SHORT_REPLY_ID,
+        &ReplyContext {
+            market_id,
+            sender: recipient,
+            vault_id,
+            mint_amount: Some(mint_amount),
+            min_amount_out: min_base_out,
+            amount_to_burn: None,
+            amount_to_swap: None,
+            amount_to_withdraw: None,
+            max_base_in: None,
+            route,
+            base_to_pair: None,
+            min_provided: None,
+        },
+    )?;
+
+    mint(
+        deps,
+        env,
+        info,
+        market_id,
+        mint_amount,
+        vault_id,
+        false,
+        Some(reply_id),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_close_short(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount_to_burn: Uint128,
+    amount_to_withdraw: Option<Uint128>,
+    vault_id: u64,
+    max_base_in: Option<Uint128>,
+    deadline: Option<Timestamp>,
+    route: Option<Vec<SwapHop>>,
+) -> Result<Response, ContractError> {
+    check_deadline(&env, deadline)?;
+
+    let market_id = get_vault_market_id(deps.storage, vault_id)?;
+
+    let config = CONFIG.load(deps.storage, market_id)?;
+
+    let vault_owner = get_vault_owner_for_sender(deps.storage, &env.block, vault_id, &info.sender)?;
+
+    check_can_burn(
+        deps.as_ref().storage,
+        vault_id,
+        vault_owner,
+        amount_to_burn,
+        amount_to_withdraw.unwrap_or(Uint128::zero()),
+    )?;
+
+    let route = validate_swap_route(
+        route,
+        SwapHop {
+            pool_id: config.power_pool.id,
+            token_in_denom: config.base_denom,
+            token_out_denom: config.power_denom.clone(),
+        },
+    )?;
+    ensure_eq!(
+        route[route.len() - 1].token_out_denom,
+        config.power_denom,
+        ContractError::InvalidSwapRoute("must end by buying power".to_string())
+    );
+
+    // the funds are paid in the first denom of the route
+    let amount_to_swap =
+        must_pay(&info, &route[0].token_in_denom).map_err(|_| ContractError::InvalidFunds {})?;
+
+    // the maximum input cannot exceed the funds sent, any unused funds are refunded
+    let max_base_in = max_base_in.unwrap_or(amount_to_swap);
+    if max_base_in > amount_to_swap {
+        return Err(ContractError::InvalidFunds {});
+    }
+
+    let swap_msg = create_swap_exact_amount_out_message(
+        env.contract.address.to_string(),
+        &route,
+        amount_to_burn.to_string(),
+        max_base_in.to_string(),
+    );
+
+    let reply_id = save_reply_context(
+        deps.storage,
+        CLOSE_SHORT_REPLY_ID,
+        &ReplyContext {
+            market_id,
+            sender: info.sender,
+            vault_id: Some(vault_id),
+            mint_amount: None,
+            min_amount_out: None,
+            amount_to_burn: Some(amount_to_burn),
+            amount_to_swap: Some(amount_to_swap),
+            amount_to_withdraw,
+            max_base_in: Some(max_base_in),
+            route,
+            base_to_pair: None,
+            min_provided: None,
+        },
+    )?;
+
+    let swap_submsg: SubMsg = SubMsg {
+        id: reply_id,
+        msg: swap_msg.into(),
+        gas_limit: None,
+        reply_on: ReplyOn::Success,
+    };
+
+    Ok(Response::new().add_submessage(swap_submsg))
+}
+
+pub fn handle_open_long(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    min_power_out: Option<Uint128>,
+    deadline: Option<Timestamp>,
+    market_id: Option<u64>,
+) -> Result<Response, ContractError> {
+    check_deadline(&env, deadline)?;
+
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
+
+    let config = CONFIG.load(deps.storage, market_id)?;
+
+    let amount_in =
+        must_pay(&info, &config.base_denom).map_err(|_| ContractError::InvalidFunds {})?;
+
+    let route = vec![SwapHop {
+        pool_id: config.power_pool.id,
+        token_in_denom: config.base_denom,
+        token_out_denom: config.power_denom,
+    }];
+
+    let swap_msg = create_swap_exact_amount_in_message(
+        env.contract.address.to_string(),
+        &route,
+        amount_in.to_string(),
+        min_power_out.unwrap_or(Uint128::one()).to_string(),
+    );
+
+    // the power bought is sent to the sender in the reply
+    let reply_id = save_reply_context(
+        deps.storage,
+        OPEN_LONG_SWAP_REPLY_ID,
+        &ReplyContext {
+            market_id,
+            sender: info.sender.clone(),
+            vault_id: None,
+            mint_amount: None,
+            min_amount_out: min_power_out,
+            amount_to_burn: None,
+            amount_to_swap: None,
+            amount_to_withdraw: None,
+            max_base_in: None,
+            route,
+            base_to_pair: None,
+            min_provided: None,
+        },
+    )?;
+
+    let swap_submsg: SubMsg = SubMsg {
+        id: reply_id,
+        msg: swap_msg.into(),
+        gas_limit: None,
+        reply_on: ReplyOn::Success,
+    };
+
+    let open_long_event = Event::new("open_long").add_attributes(vec![
+        ("market_id", market_id.to_string()),
+        ("sender", info.sender.to_string()),
+        ("amount_in", amount_in.to_string()),
+    ]);
+
+    Ok(Response::new()
+        .add_submessage(swap_submsg)
+        .add_event(open_long_event))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_mint_and_provide_liquidity(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    mint_amount: Uint128,
+    collateral_amount: Uint128,
+    vault_id: Option<u64>,
+    min_power_provided: Option<Uint128>,
+    min_base_provided: Option<Uint128>,
+    deadline: Option<Timestamp>,
+    market_id: Option<u64>,
+) -> Result<Response, ContractError> {
+    check_deadline(&env, deadline)?;
+
+    let market_id = resolve_market_id(deps.storage, market_id, vault_id)?;
+
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
+
+    check_circuit_breaker(deps.as_ref(), &env, market_id)?;
+
+    let config = CONFIG.load(deps.storage, market_id)?;
+
+    // the base sent beyond the collateral is provided with the power minted
+    let amount_sent =
+        must_pay(&info, &config.base_denom).map_err(|_| ContractError::InvalidFunds {})?;
+    ensure!(
+        amount_sent > collateral_amount,
+        ContractError::InvalidFunds {}
+    );
+    let base_to_pair = amount_sent.checked_sub(collateral_amount).unwrap();
+
+    // the position of an operator is held for the vault owner
+    let recipient = get_mint_recipient(deps.storage, &env.block, vault_id, &info.sender)?;
+
+    let reply_id = save_reply_context(
+        deps.storage,
+        MINT_AND_PROVIDE_LIQUIDITY_REPLY_ID,
+        &ReplyContext {
+            market_id,
+            sender: recipient,
+            vault_id,
+            mint_amount: Some(mint_amount),
+            min_amount_out: None,
+            amount_to_burn: None,
+            amount_to_swap: None,
+            amount_to_withdraw: None,
+            max_base_in: None,
+            route: vec![],
+            base_to_pair: Some(base_to_pair),
+            min_provided: Some((
+                min_power_provided.unwrap_or_default(),
+                min_base_provided.unwrap_or_default(),
+            )),
+        },
+    )?;
+
+    let mint_info = MessageInfo {
+        sender: info.sender,
+        funds: if collateral_amount.is_zero() {
+            vec![]
+        } else {
+            vec![coin(collateral_amount.u128(), config.base_denom)]
+        },
+    };
+
+    mint(
+        deps,
+        env,
+        mint_info,
+        market_id,
+        mint_amount,
+        vault_id,
+        false,
+        Some(reply_id),
+    )
+}
+
+pub fn handle_remove_liquidity(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    position_id: u64,
+) -> Result<Response, ContractError> {
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    let market_id = LIQUIDITY_POSITIONS
+        .may_load(deps.storage, (&info.sender, position_id))?
+        .ok_or(ContractError::Unauthorized {})?;
+    LIQUIDITY_POSITIONS.remove(deps.storage, (&info.sender, position_id));
+
+    let position = ConcentratedliquidityQuerier::new(&deps.querier)
+        .position_by_id(position_id)?
+        .position
+        .and_then(|breakdown| breakdown.position)
+        .ok_or_else(|| ContractError::generic_err("Position not found"))?;
+
+    let reply_id = save_reply_context(
+        deps.storage,
+        REMOVE_LIQUIDITY_REPLY_ID,
+        &ReplyContext {
+            market_id,
+            sender: info.sender.clone(),
+            vault_id: None,
+            mint_amount: None,
+            min_amount_out: None,
+            amount_to_burn: None,
+            amount_to_swap: None,
+            amount_to_withdraw: None,
+            max_base_in: None,
+            route: vec![],
+            base_to_pair: None,
+            min_provided: None,
+        },
+    )?;
+    let nonce = reply_nonce(reply_id);
+
+    // withdrawing the full position claims its rewards, so they are collected first
+    // and forwarded to the owner along with the withdrawn tokens
+    let collect_spread_rewards_submsg: SubMsg = SubMsg {
+        id: encode_reply_id(COLLECT_SPREAD_REWARDS_REPLY_ID, nonce),
+        msg: MsgCollectSpreadRewards {
+            position_ids: vec![position_id],
+            sender: env.contract.address.to_string(),
+        }
+        .into(),
+        gas_limit: None,
+        reply_on: ReplyOn::Success,
+    };
+
+    let collect_incentives_submsg: SubMsg = SubMsg {
+        id: encode_reply_id(COLLECT_INCENTIVES_REPLY_ID, nonce),
+        msg: MsgCollectIncentives {
+            position_ids: vec![position_id],
+            sender: env.contract.address.to_string(),
+        }
+        .into(),
+        gas_limit: None,
+        reply_on: ReplyOn::Success,
+    };
+
+    let withdraw_submsg: SubMsg = SubMsg {
+        id: reply_id,
+        msg: MsgWithdrawPosition {
+            position_id,
+            sender: env.contract.address.to_string(),
+            liquidity_amount: position.liquidity,
+        }
+        .into(),
+        gas_limit: None,
+        reply_on: ReplyOn::Success,
+    };
+
+    let remove_liquidity_event = Event::new("remove_liquidity").add_attributes(vec![
+        ("market_id", market_id.to_string()),
+        ("owner", info.sender.to_string()),
+        ("position_id", position_id.to_string()),
+    ]);
+
+    Ok(Response::new()
+        .add_submessage(collect_spread_rewards_submsg)
+        .add_submessage(collect_incentives_submsg)
+        .add_submessage(withdraw_submsg)
+        .add_event(remove_liquidity_event))
+}
+
+pub fn handle_liquidation(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    max_debt_amount: Uint128,
+    vault_id: u64,
+) -> Result<Response, ContractError> {
+    let market_id = get_vault_market_id(deps.storage, vault_id)?;
+
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
+
+    check_circuit_breaker(deps.as_ref(), &env, market_id)?;
+
+    let config: Config = CONFIG.load(deps.storage, market_id)?;
+
+    // liquidator does not require to send funds, rather it is burnt directly
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    let cached_normalisation_factor = apply_funding_rate(deps.branch(), env.clone(), market_id)?;
+
+    let (is_safe, _) = check_vault(
+        deps.as_ref(),
+        config.clone(),
+        vault_id,
+        cached_normalisation_factor,
+        env.block.time,
+    )?;
+
+    ensure!(!is_safe, ContractError::SafeVault {});
+
+    let auction_start_time = start_liquidation_auction(deps.storage, vault_id, env.block.time)?;
+    let liquidation_bonus =
+        calculate_liquidation_bonus(&config, auction_start_time, env.block.time);
+
+    let vault = VAULTS.load(deps.storage, &vault_id)?;
+
+    let collateral = get_priced_collateral(
+        &deps.as_ref(),
+        &config,
+        vault_id,
+        market_id,
+        env.block.time.minus_seconds(config.twap_period),
+        env.block.time,
+    )?;
+
+    let mut collateral_value = vault.collateral;
+    for asset in collateral.iter() {
+        collateral_value = collateral_value.checked_add(asset.value(config.base_decimals)?)?;
+    }
+
+    let (liquidation_amount, collateral_owed) = get_liquidation_results(
+        deps.as_ref(),
+        env.clone(),
+        max_debt_amount,
+        vault.clone(),
+        collateral_value,
+        liquidation_bonus,
+    )?;
+
+    if max_debt_amount < liquidation_amount {
+        return Err(ContractError::InvalidLiquidation {});
+    }
+
+    // the base collateral is paid first, then the other collateral covers the rest
+    let collateral_to_pay = collateral_owed.min(vault.collateral);
+
+    burn_vault(
+        deps.storage,
+        vault_id,
+        vault.operator,
+        collateral_to_pay,
+        liquidation_amount,
+    )?;
+
+    let (collateral_seized, shortfall) = take_collateral(
+        deps.storage,
+        vault_id,
+        config.base_decimals,
+        collateral,
+        collateral_owed.checked_sub(collateral_to_pay).unwrap(),
+    )?;
+
+    let (is_safe, _) = check_vault(
+        deps.as_ref(),
+        config.clone(),
+        vault_id,
+        cached_normalisation_factor,
+        env.block.time,
+    )?;
+
+    // auction ends once the vault is safe again, a later shortfall starts a new one
+    if is_safe {
+        clear_liquidation_auction(deps.storage, vault_id);
+    }
+
+    // insurance covers as much of the shortfall as it can, the rest is bad debt
+    let mut state = STATE.load(deps.storage, market_id)?;
+
+    let insurance_payout = shortfall.min(state.insurance_balance);
+    let bad_debt = shortfall.checked_sub(insurance_payout).unwrap();
+
+    if !shortfall.is_zero() {
+        state.insurance_balance = state
+            .insurance_balance
+            .checked_sub(insurance_payout)
+            .unwrap();
+        state.total_bad_debt = state.total_bad_debt.checked_add(bad_debt).unwrap();
+
+        STATE.save(deps.storage, market_id, &state)?;
+    }
+
+    let amount_to_pay = collateral_to_pay.checked_add(insurance_payout).unwrap();
+
+    // burn power perp token
+    let msg_burn: CosmosMsg = MsgBurn {
+        sender: env.contract.address.to_string(),
+        amount: Some(Coin {
+            denom: config.power_denom,
+            amount: liquidation_amount.to_string(),
+        }),
+        burn_from_address: info.sender.to_string(),
+    }
+    .into();
+
+    // transfer collateral and any insurance payout to sender
+    let mut funds = vec![coin(amount_to_pay.u128(), config.base_denom)];
+    funds.extend(collateral_seized.clone());
+
+    let msg_transfer: CosmosMsg = CosmosMsg::Bank(BankMsg::Send {
+        to_address: info.sender.to_string(),
+        amount: funds,
+    });
+
+    let mut liquidation_event = Event::new("liquidation").add_attributes([
+        ("liquidation_amount", &liquidation_amount.to_string()),
+        ("collateral_to_pay", &collateral_to_pay.to_string()),
+        ("liquidation_bonus", &liquidation_bonus.to_string()),
+        ("vault_id", &vault_id.to_string()),
+    ]);
+
+    if !collateral_seized.is_empty() {
+        liquidation_event =
+            liquidation_event.add_attribute("collateral_seized", format_coins(&collateral_seized));
+    }
+
+    let mut events = vec![liquidation_event];
+
+    if !shortfall.is_zero() {
+        events.push(Event::new("bad_debt").add_attributes([
+            ("shortfall", &shortfall.to_string()),
+            ("insurance_payout", &insurance_payout.to_string()),
+            ("bad_debt", &bad_debt.to_string()),
+            ("vault_id", &vault_id.to_string()),
+        ]));
+    }
+
+    events.push(create_apply_funding_event(
+        &cached_normalisation_factor.to_string(),
+        market_id,
+    ));
+
+    Ok(Response::new()
+        .add_messages(vec![msg_burn, msg_transfer])
+        .add_events(events))
+}
+
+pub fn handle_start_liquidation_auction(
     mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    amount: Uint128,
     vault_id: u64,
 ) -> Result<Response, ContractError> {
-    STATE.load(deps.storage)?.is_open_and_unpaused()?;
+    let market_id = get_vault_market_id(deps.storage, vault_id)?;
+
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
+
+    check_circuit_breaker(deps.as_ref(), &env, market_id)?;
+
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    let config: Config = CONFIG.load(deps.storage, market_id)?;
+
+    let cached_normalisation_factor = apply_funding_rate(deps.branch(), env.clone(), market_id)?;
+
+    let (is_safe, _) = check_vault(
+        deps.as_ref(),
+        config,
+        vault_id,
+        cached_normalisation_factor,
+        env.block.time,
+    )?;
+
+    ensure!(!is_safe, ContractError::SafeVault {});
+
+    // the auction runs from the first time the vault is recorded as unsafe
+    let start_time = start_liquidation_auction(deps.storage, vault_id, env.block.time)?;
+
+    Ok(Response::new().add_events([
+        Event::new("start_liquidation_auction").add_attributes([
+            ("vault_id", &vault_id.to_string()),
+            ("start_time", &start_time.seconds().to_string()),
+        ]),
+        create_apply_funding_event(&cached_normalisation_factor.to_string(), market_id),
+    ]))
+}
+
+pub fn handle_fund_insurance(
+    deps: DepsMut,
+    info: MessageInfo,
+    market_id: Option<u64>,
+) -> Result<Response, ContractError> {
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    let config: Config = CONFIG.load(deps.storage, market_id)?;
+
+    let amount = must_pay(&info, &config.base_denom).map_err(|_| ContractError::InvalidFunds {})?;
+
+    let mut state = STATE.load(deps.storage, market_id)?;
+    state.insurance_balance = state.insurance_balance.checked_add(amount).unwrap();
+
+    STATE.save(deps.storage, market_id, &state)?;
+
+    Ok(
+        Response::new().add_event(Event::new("fund_insurance").add_attributes([
+            ("amount", &amount.to_string()),
+            ("insurance_balance", &state.insurance_balance.to_string()),
+            ("market_id", &market_id.to_string()),
+        ])),
+    )
+}
+
+pub fn handle_deposit(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    vault_id: u64,
+) -> Result<Response, ContractError> {
+    let market_id = get_vault_market_id(deps.storage, vault_id)?;
+
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
+
+    let config: Config = CONFIG.load(deps.storage, market_id)?;
+
+    // collateral is the base denom or a whitelisted collateral asset
+    let deposit = one_coin(&info).map_err(|_| ContractError::InvalidFunds {})?;
+
+    let cached_normalisation_factor = apply_funding_rate(deps.branch(), env.clone(), market_id)?;
+
+    let vault_owner = get_vault_owner_for_sender(deps.storage, &env.block, vault_id, &info.sender)?;
+
+    if deposit.denom == config.base_denom {
+        add_collateral(deps.storage, vault_id, vault_owner, deposit.amount)?;
+    } else {
+        ensure!(
+            COLLATERAL_ASSETS.has(deps.storage, (market_id, &deposit.denom)),
+            ContractError::InvalidFunds {}
+        );
+
+        ensure!(
+            !DELISTED_COLLATERAL_ASSETS.has(deps.storage, (market_id, &deposit.denom)),
+            ContractError::TokenUnsupported(deposit.denom)
+        );
+
+        add_vault_collateral(
+            deps.storage,
+            vault_id,
+            vault_owner,
+            &deposit.denom,
+            deposit.amount,
+        )?;
+    }
+
+    let (is_safe, min_collateral) = check_vault(
+        deps.as_ref(),
+        config,
+        vault_id,
+        cached_normalisation_factor,
+        env.block.time,
+    )?;
+
+    ensure!(is_safe, ContractError::UnsafeVault {});
+    ensure!(min_collateral, ContractError::BelowMinCollateralAmount {});
+
+    clear_liquidation_auction(deps.storage, vault_id);
+
+    let deposit_event = Event::new("deposit").add_attributes([
+        ("collateral_deposited", &deposit.amount.to_string()),
+        ("denom", &deposit.denom),
+        ("vault_id", &vault_id.to_string()),
+    ]);
+
+    let funding_event =
+        create_apply_funding_event(&cached_normalisation_factor.to_string(), market_id);
+
+    Ok(Response::new().add_events([deposit_event, funding_event]))
+}
+
+pub fn handle_transfer_vault(
+    deps: DepsMut,
+    info: MessageInfo,
+    vault_id: u64,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    let market_id = get_vault_market_id(deps.storage, vault_id)?;
+
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
+
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    let recipient = deps.api.addr_validate(&recipient)?;
+
+    transfer_vault(
+        deps.storage,
+        vault_id,
+        info.sender.clone(),
+        recipient.clone(),
+    )?;
+
+    let transfer_event = Event::new("transfer_vault").add_attributes([
+        ("vault_id", &vault_id.to_string()),
+        ("sender", &info.sender.to_string()),
+        ("recipient", &recipient.to_string()),
+    ]);
+
+    Ok(Response::new().add_event(transfer_event))
+}
+
+pub fn handle_approve_operator(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    vault_id: u64,
+    spender: String,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    if !VAULTS.has(deps.storage, &vault_id) {
+        return Err(ContractError::VaultDoesNotExist {});
+    };
+
+    let spender = deps.api.addr_validate(&spender)?;
+
+    let expires = expires.unwrap_or_default();
+    ensure!(!expires.is_expired(&env.block), ContractError::Expired {});
+
+    approve_vault_operator(
+        deps.storage,
+        vault_id,
+        info.sender.clone(),
+        &spender,
+        expires,
+    )?;
+
+    let approve_event = Event::new("approve_operator").add_attributes([
+        ("vault_id", &vault_id.to_string()),
+        ("owner", &info.sender.to_string()),
+        ("spender", &spender.to_string()),
+        ("expires", &expires.to_string()),
+    ]);
+
+    Ok(Response::new().add_event(approve_event))
+}
 
-    let config: Config = CONFIG.load(deps.storage)?;
+pub fn handle_revoke_operator(
+    deps: DepsMut,
+    info: MessageInfo,
+    vault_id: u64,
+    spender: String,
+) -> Result<Response, ContractError> {
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
 
     if !VAULTS.has(deps.storage, &vault_id) {
         return Err(ContractError::VaultDoesNotExist {});
     };
 
+    let spender = deps.api.addr_validate(&spender)?;
+
+    revoke_vault_operator(deps.storage, vault_id, info.sender.clone(), &spender)?;
+
+    let revoke_event = Event::new("revoke_operator").add_attributes([
+        ("vault_id", &vault_id.to_string()),
+        ("owner", &info.sender.to_string()),
+        ("spender", &spender.to_string()),
+    ]);
+
+    Ok(Response::new().add_event(revoke_event))
+}
+
+pub fn handle_transfer_nft(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: String,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    let vault_id = parse_vault_token_id(&token_id)?;
+    let market_id = get_vault_market_id(deps.storage, vault_id)?;
+
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
+
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    let response = vault_nft()
+        .transfer_nft(deps.branch(), env, info, recipient, token_id)
+        .map_err(|err| ContractError::generic_err(err.to_string()))?;
+
+    sync_vault_operator(deps.storage, vault_id)?;
+
+    Ok(response)
+}
+
+pub fn handle_send_nft(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: String,
+    token_id: String,
+    msg: Binary,
+) -> Result<Response, ContractError> {
+    let vault_id = parse_vault_token_id(&token_id)?;
+    let market_id = get_vault_market_id(deps.storage, vault_id)?;
+
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
+
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    let response = vault_nft()
+        .send_nft(deps.branch(), env, info, contract, token_id, msg)
+        .map_err(|err| ContractError::generic_err(err.to_string()))?;
+
+    sync_vault_operator(deps.storage, vault_id)?;
+
+    Ok(response)
+}
+
+pub fn handle_approve_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    spender: String,
+    token_id: String,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    vault_nft()
+        .approve(deps, env, info, spender, token_id, expires)
+        .map_err(|err| ContractError::generic_err(err.to_string()))
+}
+
+pub fn handle_revoke_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    spender: String,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    vault_nft()
+        .revoke(deps, env, info, spender, token_id)
+        .map_err(|err| ContractError::generic_err(err.to_string()))
+}
+
+pub fn handle_approve_all_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    operator: String,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    vault_nft()
+        .approve_all(deps, env, info, operator, expires)
+        .map_err(|err| ContractError::generic_err(err.to_string()))
+}
+
+pub fn handle_revoke_all_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    operator: String,
+) -> Result<Response, ContractError> {
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    vault_nft()
+        .revoke_all(deps, env, info, operator)
+        .map_err(|err| ContractError::generic_err(err.to_string()))
+}
+
+pub fn handle_withdrawal(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    vault_id: u64,
+) -> Result<Response, ContractError> {
+    let market_id = get_vault_market_id(deps.storage, vault_id)?;
+
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
+
+    check_circuit_breaker(deps.as_ref(), &env, market_id)?;
+
+    let config: Config = CONFIG.load(deps.storage, market_id)?;
+
     nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
 
-    let cached_normalisation_factor = apply_funding_rate(deps.branch(), env.clone())?;
+    let cached_normalisation_factor = apply_funding_rate(deps.branch(), env.clone(), market_id)?;
+
+    let vault_owner = get_vault_owner_for_sender(deps.storage, &env.block, vault_id, &info.sender)?;
 
-    subtract_collateral(deps.storage, vault_id, info.sender.clone(), amount)?;
+    subtract_collateral(deps.storage, vault_id, vault_owner.clone(), amount)?;
 
     let (is_safe, min_collateral) = check_vault(
         deps.as_ref(),
@@ -390,9 +1838,11 @@ pub fn handle_withdrawal(
     ensure!(is_safe, ContractError::UnsafeVault {});
     ensure!(min_collateral, ContractError::BelowMinCollateralAmount {});
 
-    // transfer base to sender
+    clear_liquidation_auction(deps.storage, vault_id);
+
+    // transfer base to the vault owner
     let msg_transfer: CosmosMsg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: info.sender.to_string(),
+        to_address: vault_owner.to_string(),
         amount: vec![coin(amount.u128(), config.base_denom)],
     });
 
@@ -401,7 +1851,66 @@ pub fn handle_withdrawal(
         ("vault_id", &vault_id.to_string()),
     ]);
 
-    let funding_event = create_apply_funding_event(&cached_normalisation_factor.to_string());
+    let funding_event =
+        create_apply_funding_event(&cached_normalisation_factor.to_string(), market_id);
+
+    Ok(Response::new()
+        .add_messages(vec![msg_transfer])
+        .add_events([withdrawal_event, funding_event]))
+}
+
+pub fn handle_withdraw_collateral(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denom: String,
+    amount: Uint128,
+    vault_id: u64,
+) -> Result<Response, ContractError> {
+    let market_id = get_vault_market_id(deps.storage, vault_id)?;
+
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
+
+    check_circuit_breaker(deps.as_ref(), &env, market_id)?;
+
+    let config: Config = CONFIG.load(deps.storage, market_id)?;
+
+    nonpayable(&info).map_err(|_| ContractError::NonPayable {})?;
+
+    let cached_normalisation_factor = apply_funding_rate(deps.branch(), env.clone(), market_id)?;
+
+    let vault_owner = get_vault_owner_for_sender(deps.storage, &env.block, vault_id, &info.sender)?;
+
+    subtract_vault_collateral(deps.storage, vault_id, vault_owner.clone(), &denom, amount)?;
+
+    let (is_safe, min_collateral) = check_vault(
+        deps.as_ref(),
+        config,
+        vault_id,
+        cached_normalisation_factor,
+        env.block.time,
+    )?;
+
+    ensure!(is_safe, ContractError::UnsafeVault {});
+    ensure!(min_collateral, ContractError::BelowMinCollateralAmount {});
+
+    clear_liquidation_auction(deps.storage, vault_id);
+
+    let msg_transfer: CosmosMsg = CosmosMsg::Bank(BankMsg::Send {
+        to_address: vault_owner.to_string(),
+        amount: vec![coin(amount.u128(), &denom)],
+    });
+
+    let withdrawal_event = Event::new("withdraw").add_attributes([
+        ("collateral_withdrawn", &amount.to_string()),
+        ("denom", &denom),
+        ("vault_id", &vault_id.to_string()),
+    ]);
+
+    let funding_event =
+        create_apply_funding_event(&cached_normalisation_factor.to_string(), market_id);
 
     Ok(Response::new()
         .add_messages(vec![msg_transfer])
@@ -412,10 +1921,13 @@ pub fn handle_apply_funding(
     deps: DepsMut,
     env: Env,
     _info: MessageInfo,
+    market_id: Option<u64>,
 ) -> Result<Response, ContractError> {
-    let funding = apply_funding_rate(deps, env)?;
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    let funding = apply_funding_rate(deps, env, market_id)?;
 
-    let funding_event = create_apply_funding_event(&funding.to_string());
+    let funding_event = create_apply_funding_event(&funding.to_string(), market_id);
 
     Ok(Response::new().add_event(funding_event))
 }
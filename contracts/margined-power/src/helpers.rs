@@ -1,7 +1,7 @@
 use crate::{
-    contract::OPEN_SHORT_REPLY_ID,
-    queries::{get_pool_twap, get_scaled_pool_twap},
-    state::{CONFIG, LIQUIDATION_BOUNTY, TWAP_PERIOD},
+    oracle::{get_power_price, get_scaled_base_price},
+    queries::get_user_staked_amount,
+    state::{Config, CONFIG, MAX_SWAP_HOPS, TOTAL_FEE_WEIGHT_BPS},
     vault::{subtract_collateral, Vault},
 };
 
@@ -11,6 +11,7 @@ use cosmwasm_std::{
 };
 use injective_math::FPDecimal;
 use margined_common::errors::ContractError;
+use margined_protocol::power::SwapHop;
 use num::pow::Pow;
 use osmosis_std::types::{
     cosmos::base::v1beta1::Coin,
@@ -39,27 +40,45 @@ pub fn decimal_to_fixed(value: Decimal, decimal_places: u32) -> Uint128 {
         .unwrap()
 }
 
+/// Formats coins as a comma separated list for event attributes.
+pub fn format_coins(coins: &[cosmwasm_std::Coin]) -> String {
+    coins
+        .iter()
+        .map(|coin| coin.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
 pub fn calculate_fee(
     deps: DepsMut,
     env: Env,
+    market_id: u64,
     sender: Addr,
     vault_id: u64,
     power_amount: Decimal,
     deposit_amount: Decimal,
 ) -> StdResult<(Decimal, Decimal)> {
-    let config = CONFIG.load(deps.storage).unwrap();
-
-    if config.fee_rate.is_zero() {
+    let config = CONFIG.load(deps.storage, market_id)?;
+
+    if config.fee_rate.is_zero()
+        && config
+            .fee_schedule
+            .size_tiers
+            .iter()
+            .all(|tier| tier.fee_rate.is_zero())
+    {
         return Ok((Decimal::zero(), deposit_amount));
     }
 
-    let base_amount_value = calculate_debt_in_base(deps.as_ref(), env, power_amount).unwrap();
+    let base_amount_value = calculate_debt_in_base(deps.as_ref(), env, market_id, power_amount)?;
 
-    let fee_amount = base_amount_value.checked_mul(config.fee_rate).unwrap();
+    let (fee_rate, _) = get_fee_rate(deps.as_ref(), &config, &sender, base_amount_value)?;
+
+    let fee_amount = base_amount_value.checked_mul(fee_rate)?;
 
     // if the deposit is unsufficient to cover the fee, use the collateral deposited
-    let deposit_post_fees = if deposit_amount > fee_amount {
-        deposit_amount.checked_sub(fee_amount).unwrap()
+    let deposit_post_fees = if deposit_amount >= fee_amount {
+        deposit_amount.checked_sub(fee_amount)?
     } else {
         subtract_collateral(
             deps.storage,
@@ -73,17 +92,89 @@ pub fn calculate_fee(
     Ok((fee_amount, deposit_post_fees))
 }
 
-pub fn calculate_index(deps: Deps, start_time: Timestamp) -> StdResult<Decimal> {
-    let config = CONFIG.load(deps.storage).unwrap();
+/// Returns the fee rate of a mint of the given value in base, from the highest size
+/// tier reached less the discount of the highest staking tier the user reaches, along
+/// with the amount the user has staked.
+pub fn get_fee_rate(
+    deps: Deps,
+    config: &Config,
+    user: &Addr,
+    mint_value: Decimal,
+) -> StdResult<(Decimal, Uint128)> {
+    let schedule = &config.fee_schedule;
+
+    let mint_value = decimal_to_fixed(mint_value, config.base_decimals);
+    let fee_rate = schedule
+        .size_tiers
+        .iter()
+        .rev()
+        .find(|tier| mint_value >= tier.min_value)
+        .map_or(config.fee_rate, |tier| tier.fee_rate);
+
+    // a failing staking contract removes the discount rather than blocking mints
+    let staked_amount = match &schedule.staking_contract {
+        Some(staking_contract) if !schedule.staking_discounts.is_empty() => {
+            get_user_staked_amount(deps, staking_contract.clone(), user.to_string())
+                .unwrap_or_default()
+        }
+        _ => Uint128::zero(),
+    };
+
+    let discount = schedule
+        .staking_discounts
+        .iter()
+        .rev()
+        .find(|tier| staked_amount >= tier.min_staked)
+        .map_or(Decimal::zero(), |tier| tier.discount);
+
+    let fee_rate = fee_rate.checked_mul(Decimal::one().checked_sub(discount)?)?;
+
+    Ok((fee_rate, staked_amount))
+}
+
+/// Splits a fee between the fee recipients by their weights, or sends all of it to the
+/// fee pool if there are none. Shares are rounded down with the remainder going to the
+/// last recipient, so the whole fee is always distributed.
+pub fn split_fee(config: &Config, fee_amount: Uint128) -> Vec<(String, Uint128)> {
+    if config.fee_recipients.is_empty() {
+        return vec![(config.fee_pool_contract.to_string(), fee_amount)];
+    }
+
+    let mut remaining = fee_amount;
+    let mut splits: Vec<(String, Uint128)> = config
+        .fee_recipients
+        .iter()
+        .map(|recipient| {
+            let amount = fee_amount.multiply_ratio(recipient.weight_bps, TOTAL_FEE_WEIGHT_BPS);
+            remaining = remaining.checked_sub(amount).unwrap();
+
+            (recipient.address.clone(), amount)
+        })
+        .collect();
+
+    if let Some((_, amount)) = splits.last_mut() {
+        *amount = amount.checked_add(remaining).unwrap();
+    }
+
+    splits
+}
+
+/// Formats fee splits as a comma separated list of address:amount for event attributes.
+pub fn format_fee_splits(splits: &[(String, Uint128)]) -> String {
+    splits
+        .iter()
+        .map(|(address, amount)| format!("{}:{}", address, amount))
+        .collect::<Vec<String>>()
+        .join(",")
+}
 
-    let quote_price = get_scaled_pool_twap(
-        &deps,
-        config.base_pool.id,
-        config.base_denom.clone(),
-        config.base_pool.quote_denom,
-        start_time,
-    )
-    .unwrap();
+pub fn calculate_index(
+    deps: Deps,
+    market_id: u64,
+    start_time: Timestamp,
+    block_time: Timestamp,
+) -> StdResult<Decimal> {
+    let quote_price = get_scaled_base_price(&deps, market_id, start_time, block_time)?;
 
     let index = quote_price
         .checked_mul(quote_price)
@@ -96,28 +187,14 @@ pub fn calculate_index(deps: Deps, start_time: Timestamp) -> StdResult<Decimal>
 
 pub fn calculate_denormalized_mark(
     deps: Deps,
+    market_id: u64,
     start_time: Timestamp,
+    block_time: Timestamp,
     normalisation_factor: Decimal,
 ) -> StdResult<Decimal> {
-    let config = CONFIG.load(deps.storage).unwrap();
-
-    let quote_price = get_scaled_pool_twap(
-        &deps,
-        config.base_pool.id,
-        config.base_denom.clone(),
-        config.base_pool.quote_denom.clone(),
-        start_time,
-    )
-    .unwrap();
-
-    let power_price = get_pool_twap(
-        &deps,
-        config.power_pool.id,
-        config.power_denom,
-        config.base_denom,
-        start_time,
-    )
-    .unwrap();
+    let quote_price = get_scaled_base_price(&deps, market_id, start_time, block_time)?;
+
+    let power_price = get_power_price(&deps, market_id, start_time)?;
 
     let mark = quote_price
         .checked_mul(power_price)
@@ -128,20 +205,18 @@ pub fn calculate_denormalized_mark(
     Ok(mark)
 }
 
-pub fn calculate_debt_in_base(deps: Deps, env: Env, debt_amount: Decimal) -> StdResult<Decimal> {
-    let start_time = env.block.time.minus_seconds(TWAP_PERIOD);
-    let config = CONFIG.load(deps.storage).unwrap();
+pub fn calculate_debt_in_base(
+    deps: Deps,
+    env: Env,
+    market_id: u64,
+    debt_amount: Decimal,
+) -> StdResult<Decimal> {
+    let config = CONFIG.load(deps.storage, market_id)?;
+    let start_time = env.block.time.minus_seconds(config.twap_period);
 
-    let power_price = get_pool_twap(
-        &deps,
-        config.power_pool.id,
-        config.power_denom,
-        config.base_denom,
-        start_time,
-    )
-    .unwrap();
+    let power_price = get_power_price(&deps, market_id, start_time)?;
 
-    let debt_value = debt_amount.checked_mul(power_price).unwrap();
+    let debt_value = debt_amount.checked_mul(power_price)?;
 
     Ok(debt_value)
 }
@@ -152,12 +227,12 @@ pub fn create_mint_message(
     denom: String,
     amount: String,
     sender: String,
-    should_sell: bool,
+    sell_reply_id: Option<u64>,
 ) -> Response {
-    match should_sell {
-        true => {
+    match sell_reply_id {
+        Some(reply_id) => {
             let mint_submsg: SubMsg = SubMsg {
-                id: OPEN_SHORT_REPLY_ID,
+                id: reply_id,
                 msg: MsgMint {
                     sender: contract_address.clone(),
                     amount: Some(Coin { denom, amount }),
@@ -170,7 +245,7 @@ pub fn create_mint_message(
 
             response.add_submessage(mint_submsg)
         }
-        false => response.add_message(MsgMint {
+        None => response.add_message(MsgMint {
             sender: contract_address,
             amount: Some(Coin { denom, amount }),
             mint_to_address: sender,
@@ -180,54 +255,134 @@ pub fn create_mint_message(
 
 pub fn create_swap_exact_amount_in_message(
     sender: String,
-    pool_id: u64,
-    token_in_denom: String,
-    token_out_denom: String,
+    route: &[SwapHop],
     amount: String,
+    token_out_min_amount: String,
 ) -> MsgSwapExactAmountIn {
     MsgSwapExactAmountIn {
         sender,
-        routes: vec![SwapAmountInRoute {
-            pool_id,
-            token_out_denom,
-        }],
+        routes: route
+            .iter()
+            .map(|hop| SwapAmountInRoute {
+                pool_id: hop.pool_id,
+                token_out_denom: hop.token_out_denom.clone(),
+            })
+            .collect(),
         token_in: Some(Coin {
-            denom: token_in_denom,
+            denom: route[0].token_in_denom.clone(),
             amount,
         }),
-        token_out_min_amount: "1".to_string(),
+        token_out_min_amount,
     }
 }
 
+pub fn check_deadline(env: &Env, deadline: Option<Timestamp>) -> Result<(), ContractError> {
+    if let Some(deadline) = deadline {
+        if env.block.time > deadline {
+            return Err(ContractError::DeadlineExceeded {});
+        }
+    }
+
+    Ok(())
+}
+
 pub fn create_swap_exact_amount_out_message(
     sender: String,
-    pool_id: u64,
-    token_in_denom: String,
-    token_out_denom: String,
+    route: &[SwapHop],
     amount_out: String,
     token_in_max_amount: String,
 ) -> MsgSwapExactAmountOut {
     MsgSwapExactAmountOut {
         sender,
-        routes: vec![SwapAmountOutRoute {
-            pool_id,
-            token_in_denom,
-        }],
+        routes: route
+            .iter()
+            .map(|hop| SwapAmountOutRoute {
+                pool_id: hop.pool_id,
+                token_in_denom: hop.token_in_denom.clone(),
+            })
+            .collect(),
         token_out: Some(Coin {
-            denom: token_out_denom,
+            denom: route[route.len() - 1].token_out_denom.clone(),
             amount: amount_out,
         }),
         token_in_max_amount,
     }
 }
 
+/// Checks a route is made of at most `MAX_SWAP_HOPS` hops, each swapping the
+/// output of the previous one, and returns the single hop default if none is given.
+pub fn validate_swap_route(
+    route: Option<Vec<SwapHop>>,
+    default: SwapHop,
+) -> Result<Vec<SwapHop>, ContractError> {
+    let route = match route {
+        Some(route) => route,
+        None => return Ok(vec![default]),
+    };
+
+    if route.is_empty() || route.len() > MAX_SWAP_HOPS {
+        return Err(ContractError::InvalidSwapRoute(format!(
+            "must have between 1 and {} hops",
+            MAX_SWAP_HOPS
+        )));
+    }
+
+    for hop in route.iter() {
+        if hop.token_in_denom == hop.token_out_denom {
+            return Err(ContractError::InvalidSwapRoute(format!(
+                "pool {} swaps {} for itself",
+                hop.pool_id, hop.token_in_denom
+            )));
+        }
+    }
+
+    for hops in route.windows(2) {
+        if hops[0].token_out_denom != hops[1].token_in_denom {
+            return Err(ContractError::InvalidSwapRoute(format!(
+                "pool {} outputs {} but pool {} takes {}",
+                hops[0].pool_id, hops[0].token_out_denom, hops[1].pool_id, hops[1].token_in_denom
+            )));
+        }
+    }
+
+    Ok(route)
+}
+
+/// Liquidation bonus rises linearly from the minimum to the maximum over the
+/// auction duration, starting from the time the vault first became unsafe.
+pub fn calculate_liquidation_bonus(
+    config: &Config,
+    start_time: Timestamp,
+    block_time: Timestamp,
+) -> Decimal {
+    let elapsed = block_time.seconds().saturating_sub(start_time.seconds());
+
+    if elapsed >= config.liquidation_auction_duration {
+        return config.max_liquidation_bonus;
+    }
+
+    let bonus_range = config
+        .max_liquidation_bonus
+        .checked_sub(config.min_liquidation_bonus)
+        .unwrap();
+
+    config.min_liquidation_bonus
+        + bonus_range * Decimal::from_ratio(elapsed, config.liquidation_auction_duration)
+}
+
+/// Returns the debt to liquidate and the value in base owed to the liquidator, judged
+/// against the value of the whole collateral basket of the vault. Half the debt is
+/// liquidated unless that leaves dust or the basket cannot pay for it, in which case
+/// the vault is liquidated in full and the value owed may exceed the basket.
 pub fn get_liquidation_results(
     deps: Deps,
     env: Env,
     max_repayment_amount: Uint128,
     vault: Vault,
-) -> (Uint128, Uint128) {
-    let config = CONFIG.load(deps.storage).unwrap();
+    collateral_value: Uint128,
+    liquidation_bonus: Decimal,
+) -> StdResult<(Uint128, Uint128)> {
+    let config = CONFIG.load(deps.storage, vault.market_id)?;
 
     // first try just to liquidate half
     let max_liquidateable_amount = vault.short_amount.checked_div(2u128.into()).unwrap();
@@ -235,36 +390,53 @@ pub fn get_liquidation_results(
     let (mut liquidation_amount, mut collateral_to_pay) = get_liquidation_amount(
         deps,
         env.clone(),
+        vault.market_id,
         max_repayment_amount,
         max_liquidateable_amount,
-    );
+        liquidation_bonus,
+    )?;
 
     let half_base_denom = Uint128::from(10u128.pow(config.base_decimals))
         .checked_div(2u128.into())
         .unwrap();
 
-    if vault.collateral > collateral_to_pay
-        && vault.collateral.checked_sub(collateral_to_pay).unwrap() < half_base_denom
+    if collateral_value > collateral_to_pay
+        && collateral_value.checked_sub(collateral_to_pay).unwrap() < half_base_denom
     {
-        (liquidation_amount, collateral_to_pay) =
-            get_liquidation_amount(deps, env, max_repayment_amount, vault.short_amount);
+        (liquidation_amount, collateral_to_pay) = get_liquidation_amount(
+            deps,
+            env.clone(),
+            vault.market_id,
+            max_repayment_amount,
+            vault.short_amount,
+            liquidation_bonus,
+        )?;
     }
 
-    if collateral_to_pay > vault.collateral {
-        liquidation_amount = vault.short_amount;
-        collateral_to_pay = vault.collateral;
+    // an underwater vault is liquidated in full
+    if collateral_to_pay > collateral_value {
+        (liquidation_amount, collateral_to_pay) = get_liquidation_amount(
+            deps,
+            env,
+            vault.market_id,
+            vault.short_amount,
+            vault.short_amount,
+            liquidation_bonus,
+        )?;
     };
 
-    (liquidation_amount, collateral_to_pay)
+    Ok((liquidation_amount, collateral_to_pay))
 }
 
 pub fn get_liquidation_amount(
     deps: Deps,
     env: Env,
+    market_id: u64,
     input_amount: Uint128,
     max_liquidatable_amount: Uint128,
-) -> (Uint128, Uint128) {
-    let config = CONFIG.load(deps.storage).unwrap();
+    liquidation_bonus: Decimal,
+) -> StdResult<(Uint128, Uint128)> {
+    let config = CONFIG.load(deps.storage, market_id)?;
 
     let amount_to_liquidate = if input_amount > max_liquidatable_amount {
         max_liquidatable_amount
@@ -275,14 +447,14 @@ pub fn get_liquidation_amount(
     let decimals_amount_to_liquidate =
         Decimal::from_atomics(amount_to_liquidate, config.power_decimals).unwrap();
     let mut collateral_to_repay =
-        calculate_debt_in_base(deps, env, decimals_amount_to_liquidate).unwrap();
+        calculate_debt_in_base(deps, env, market_id, decimals_amount_to_liquidate)?;
 
-    // 10% liquidation bounty
-    collateral_to_repay = collateral_to_repay.checked_mul(LIQUIDATION_BOUNTY).unwrap();
+    // liquidation bonus determined by the auction
+    collateral_to_repay = collateral_to_repay.checked_mul(liquidation_bonus)?;
 
     let collateral_to_repay = decimal_to_fixed(collateral_to_repay, config.base_decimals);
 
-    (amount_to_liquidate, collateral_to_repay)
+    Ok((amount_to_liquidate, collateral_to_repay))
 }
 
 pub fn parse_response_result_data(result: SubMsgResult) -> Result<Binary, ContractError> {
@@ -295,6 +467,9 @@ pub fn parse_response_result_data(result: SubMsgResult) -> Result<Binary, Contra
     }
 }
 
-pub fn create_apply_funding_event(funding_rate: &str) -> Event {
-    Event::new("apply_funding").add_attribute("funding_rate", funding_rate)
+pub fn create_apply_funding_event(funding_rate: &str, market_id: u64) -> Event {
+    Event::new("apply_funding").add_attributes([
+        ("funding_rate", funding_rate),
+        ("market_id", &market_id.to_string()),
+    ])
 }
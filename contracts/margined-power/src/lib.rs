@@ -1,8 +1,14 @@
+pub mod circuit_breaker;
+pub mod collateral;
 pub mod contract;
 pub mod funding;
 pub mod handle;
 pub mod helpers;
+pub mod market;
+pub mod migration;
+pub mod nft;
 pub mod operations;
+pub mod oracle;
 pub mod queries;
 pub mod query;
 pub mod reply;
@@ -0,0 +1,156 @@
+use crate::{
+    state::{
+        Config, State, COLLATERAL_RATIO_DENOMINATOR, COLLATERAL_RATIO_NUMERATOR, CONFIG,
+        FUNDING_LOWER_BOUND, FUNDING_UPPER_BOUND, LIQUIDATION_AUCTION_DURATION, LIQUIDATION_BOUNTY,
+        MAX_TWAP_PERIOD, MIN_COLLATERAL, STATE, TWAP_PERIOD,
+    },
+    vault::get_vault_market_id,
+};
+
+use cosmwasm_std::{
+    ensure_eq, ensure_ne, Decimal, DepsMut, Env, Order, StdError, StdResult, Storage, Uint128,
+};
+use cw_storage_plus::Item;
+use margined_common::{
+    common::{check_denom_exists_in_pool, check_denom_metadata},
+    errors::ContractError,
+};
+use margined_protocol::power::{FeeSchedule, NewMarket, Oracle, Pool, PriceType, FUNDING_PERIOD};
+use std::str::FromStr;
+
+pub const DEFAULT_MARKET_ID: u64 = 1;
+
+pub const MARKETS_COUNTER: Item<u64> = Item::new("markets_counter");
+
+/// Returns the id of the market, defaulting to the market created on instantiation.
+pub fn get_market_id(storage: &dyn Storage, market_id: Option<u64>) -> StdResult<u64> {
+    let market_id = market_id.unwrap_or(DEFAULT_MARKET_ID);
+
+    if !CONFIG.has(storage, market_id) {
+        return Err(StdError::generic_err("Market not found"));
+    }
+
+    Ok(market_id)
+}
+
+/// Returns the market of the vault when operating an existing vault, which
+/// must match the market if one is given, otherwise the given market.
+pub fn resolve_market_id(
+    storage: &dyn Storage,
+    market_id: Option<u64>,
+    vault_id: Option<u64>,
+) -> Result<u64, ContractError> {
+    let vault_id = match vault_id {
+        Some(vault_id) => vault_id,
+        None => return Ok(get_market_id(storage, market_id)?),
+    };
+
+    let vault_market_id = get_vault_market_id(storage, vault_id)?;
+
+    if let Some(market_id) = market_id {
+        ensure_eq!(
+            market_id,
+            vault_market_id,
+            ContractError::VaultMarketMismatch(market_id)
+        );
+    }
+
+    Ok(vault_market_id)
+}
+
+/// Validates the market and saves its config and initial state, markets are
+/// created closed and paused until opened by the owner.
+pub fn create_market(
+    deps: DepsMut,
+    env: &Env,
+    new_market: NewMarket,
+) -> Result<u64, ContractError> {
+    let config = Config {
+        fee_rate: Decimal::from_str(&new_market.fee_rate)?,
+        fee_pool_contract: deps.api.addr_validate(&new_market.fee_pool)?,
+        query_contract: deps.api.addr_validate(&new_market.query_contract)?,
+        power_denom: new_market.power_denom.clone(),
+        base_denom: new_market.base_denom,
+        base_pool: Pool {
+            id: new_market.base_pool_id,
+            quote_denom: new_market.base_pool_quote,
+        },
+        power_pool: Pool {
+            id: new_market.power_pool_id,
+            quote_denom: new_market.power_denom,
+        },
+        funding_period: FUNDING_PERIOD,
+        base_decimals: new_market.base_decimals,
+        power_decimals: new_market.power_decimals,
+        min_liquidation_bonus: LIQUIDATION_BOUNTY,
+        max_liquidation_bonus: LIQUIDATION_BOUNTY,
+        liquidation_auction_duration: LIQUIDATION_AUCTION_DURATION,
+        insurance_fee_share: Decimal::zero(),
+        collateral_ratio_numerator: COLLATERAL_RATIO_NUMERATOR,
+        collateral_ratio_denominator: COLLATERAL_RATIO_DENOMINATOR,
+        min_collateral: MIN_COLLATERAL,
+        twap_period: TWAP_PERIOD,
+        max_twap_period: MAX_TWAP_PERIOD,
+        funding_lower_bound: FUNDING_LOWER_BOUND,
+        funding_upper_bound: FUNDING_UPPER_BOUND,
+        oracle: Oracle::default(),
+        max_spot_deviation: Decimal::zero(),
+        power_price_type: PriceType::default(),
+        flash_mint_fee_rate: Decimal::zero(),
+        fee_schedule: FeeSchedule::default(),
+        fee_recipients: vec![],
+    };
+
+    config.validate()?;
+
+    // validate denoms exist
+    check_denom_metadata(deps.as_ref(), &config.base_denom)
+        .map_err(|_| ContractError::InvalidDenom(config.base_denom.clone()))?;
+    check_denom_metadata(deps.as_ref(), &config.power_denom)
+        .map_err(|_| ContractError::InvalidDenom(config.power_denom.clone()))?;
+
+    // validate denoms are present in pool
+    check_denom_exists_in_pool(deps.as_ref(), config.base_pool.id, &config.base_denom)
+        .map_err(ContractError::Std)?;
+    check_denom_exists_in_pool(deps.as_ref(), config.power_pool.id, &config.base_denom)
+        .map_err(ContractError::Std)?;
+    check_denom_exists_in_pool(deps.as_ref(), config.power_pool.id, &config.power_denom)
+        .map_err(ContractError::Std)?;
+
+    // supply and balances of the power token are used to account for each market
+    for market in CONFIG.range(deps.storage, None, None, Order::Ascending) {
+        let (market_id, market_config) = market?;
+
+        ensure_ne!(
+            market_config.power_denom,
+            config.power_denom,
+            ContractError::generic_err(format!(
+                "Invalid power denom, already used by market {market_id}"
+            ))
+        );
+    }
+
+    let market_id = MARKETS_COUNTER.may_load(deps.storage)?.unwrap_or(0) + 1;
+
+    CONFIG.save(deps.storage, market_id, &config)?;
+
+    STATE.save(
+        deps.storage,
+        market_id,
+        &State {
+            is_open: false,
+            is_paused: true,
+            last_pause: env.block.time,
+            normalisation_factor: Decimal::one(),
+            last_funding_update: env.block.time,
+            is_shutdown: false,
+            settlement_price: None,
+            insurance_balance: Uint128::zero(),
+            total_bad_debt: Uint128::zero(),
+        },
+    )?;
+
+    MARKETS_COUNTER.save(deps.storage, &market_id)?;
+
+    Ok(market_id)
+}
@@ -0,0 +1,214 @@
+use crate::{
+    market::{DEFAULT_MARKET_ID, MARKETS_COUNTER},
+    nft::{mint_vault_nft, save_vault_nft_info},
+    state::{
+        Config, State, COLLATERAL_RATIO_DENOMINATOR, COLLATERAL_RATIO_NUMERATOR, CONFIG,
+        FUNDING_LOWER_BOUND, FUNDING_UPPER_BOUND, LIQUIDATION_AUCTION_DURATION, LIQUIDATION_BOUNTY,
+        MAX_TWAP_PERIOD, MIN_COLLATERAL, STATE, TWAP_PERIOD,
+    },
+    vault::{Vault, VAULTS},
+};
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Decimal, Empty, Order, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::{Bound, Item, Map};
+use margined_protocol::power::{FeeSchedule, Oracle, Pool, PriceType};
+
+// storage of contracts deployed before markets were added, holding a single market
+pub const LEGACY_CONFIG: Item<LegacyConfig> = Item::new("config");
+pub const LEGACY_STATE: Item<LegacyState> = Item::new("state");
+pub const LEGACY_TMP_CACHE: Item<Empty> = Item::new("tmp_cache"); // only removed
+pub const LEGACY_VAULTS: Map<&u64, LegacyVault> = Map::new("vaults");
+// last legacy vault migrated, set from the migration until every vault is migrated
+pub const LEGACY_VAULTS_CURSOR: Item<u64> = Item::new("legacy_vaults_cursor");
+
+const DEFAULT_MIGRATION_LIMIT: u32 = 30;
+const MAX_MIGRATION_LIMIT: u32 = 100;
+
+#[cw_serde]
+pub struct LegacyConfig {
+    pub query_contract: Addr,
+    pub fee_pool_contract: Addr,
+    pub fee_rate: Decimal,
+    pub power_denom: String,
+    pub base_denom: String,
+    pub base_pool: Pool,
+    pub power_pool: Pool,
+    pub funding_period: u64,
+    pub base_decimals: u32,
+    pub power_decimals: u32,
+}
+
+#[cw_serde]
+pub struct LegacyState {
+    pub is_open: bool,
+    pub is_paused: bool,
+    pub last_pause: Timestamp,
+    pub normalisation_factor: Decimal,
+    pub last_funding_update: Timestamp,
+}
+
+#[cw_serde]
+pub struct LegacyVault {
+    pub operator: Addr,
+    pub collateral: Uint128,
+    pub short_amount: Uint128,
+}
+
+/// Returns whether the contract still holds the single market storage.
+pub fn has_legacy_market(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(LEGACY_CONFIG.may_load(storage)?.is_some())
+}
+
+/// Moves the config and state of a single market contract to the default market,
+/// the settings added since take the values new markets are created with.
+pub fn migrate_legacy_market(storage: &mut dyn Storage) -> StdResult<()> {
+    let legacy_config = LEGACY_CONFIG.load(storage)?;
+    let legacy_state = LEGACY_STATE.load(storage)?;
+
+    let config = Config {
+        query_contract: legacy_config.query_contract,
+        fee_pool_contract: legacy_config.fee_pool_contract,
+        fee_rate: legacy_config.fee_rate,
+        power_denom: legacy_config.power_denom,
+        base_denom: legacy_config.base_denom,
+        base_pool: legacy_config.base_pool,
+        power_pool: legacy_config.power_pool,
+        funding_period: legacy_config.funding_period,
+        base_decimals: legacy_config.base_decimals,
+        power_decimals: legacy_config.power_decimals,
+        min_liquidation_bonus: LIQUIDATION_BOUNTY,
+        max_liquidation_bonus: LIQUIDATION_BOUNTY,
+        liquidation_auction_duration: LIQUIDATION_AUCTION_DURATION,
+        insurance_fee_share: Decimal::zero(),
+        collateral_ratio_numerator: COLLATERAL_RATIO_NUMERATOR,
+        collateral_ratio_denominator: COLLATERAL_RATIO_DENOMINATOR,
+        min_collateral: MIN_COLLATERAL,
+        twap_period: TWAP_PERIOD,
+        max_twap_period: MAX_TWAP_PERIOD,
+        funding_lower_bound: FUNDING_LOWER_BOUND,
+        funding_upper_bound: FUNDING_UPPER_BOUND,
+        oracle: Oracle::default(),
+        max_spot_deviation: Decimal::zero(),
+        power_price_type: PriceType::default(),
+        flash_mint_fee_rate: Decimal::zero(),
+        fee_schedule: FeeSchedule::default(),
+        fee_recipients: vec![],
+    };
+    config.validate()?;
+
+    let state = State {
+        is_open: legacy_state.is_open,
+        is_paused: legacy_state.is_paused,
+        last_pause: legacy_state.last_pause,
+        normalisation_factor: legacy_state.normalisation_factor,
+        last_funding_update: legacy_state.last_funding_update,
+        is_shutdown: false,
+        settlement_price: None,
+        insurance_balance: Uint128::zero(),
+        total_bad_debt: Uint128::zero(),
+    };
+
+    LEGACY_CONFIG.remove(storage);
+    LEGACY_STATE.remove(storage);
+    LEGACY_TMP_CACHE.remove(storage);
+
+    CONFIG.save(storage, DEFAULT_MARKET_ID, &config)?;
+    STATE.save(storage, DEFAULT_MARKET_ID, &state)?;
+    MARKETS_COUNTER.save(storage, &DEFAULT_MARKET_ID)?;
+
+    Ok(())
+}
+
+/// Starts the migration of the vaults of a single market contract, pausing the
+/// default market until `MigrateVaults` has migrated all of them. Returns whether
+/// there are vaults to migrate.
+pub fn start_legacy_vault_migration(
+    storage: &mut dyn Storage,
+    block_time: Timestamp,
+) -> StdResult<bool> {
+    save_vault_nft_info(storage)?;
+
+    let has_vaults = LEGACY_VAULTS
+        .keys_raw(storage, None, None, Order::Ascending)
+        .next()
+        .is_some();
+    if !has_vaults {
+        return Ok(false);
+    }
+
+    LEGACY_VAULTS_CURSOR.save(storage, &0u64)?;
+
+    let mut state = STATE.load(storage, DEFAULT_MARKET_ID)?;
+    state.is_paused = true;
+    state.last_pause = block_time;
+
+    STATE.save(storage, DEFAULT_MARKET_ID, &state)?;
+
+    Ok(true)
+}
+
+/// Returns whether vaults of a single market contract are still to be migrated.
+pub fn is_migrating_vaults(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(LEGACY_VAULTS_CURSOR.may_load(storage)?.is_some())
+}
+
+/// Resaves up to the limit of the vaults of a single market contract in the default
+/// market and mints each vault token to the vault owner, returning the ids of the
+/// vaults migrated and whether all of them have been.
+pub fn migrate_legacy_vaults(
+    storage: &mut dyn Storage,
+    limit: Option<u32>,
+) -> StdResult<(Vec<u64>, bool)> {
+    let limit = limit
+        .unwrap_or(DEFAULT_MIGRATION_LIMIT)
+        .min(MAX_MIGRATION_LIMIT) as usize;
+
+    let cursor = LEGACY_VAULTS_CURSOR.load(storage)?;
+
+    // vaults already migrated share the namespace, so only those after the cursor are read
+    let mut legacy_vaults = LEGACY_VAULTS
+        .range(
+            storage,
+            Some(Bound::exclusive(&cursor)),
+            None,
+            Order::Ascending,
+        )
+        .take(limit + 1)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let is_complete = legacy_vaults.len() <= limit;
+    legacy_vaults.truncate(limit);
+
+    let mut vault_ids = vec![];
+
+    for (vault_id, legacy_vault) in legacy_vaults {
+        // the legacy entry is removed first as the indexed map cannot read it to
+        // replace it, the owner index entry is unchanged
+        LEGACY_VAULTS.remove(storage, &vault_id);
+
+        VAULTS.save(
+            storage,
+            &vault_id,
+            &Vault {
+                market_id: DEFAULT_MARKET_ID,
+                operator: legacy_vault.operator.clone(),
+                collateral: legacy_vault.collateral,
+                short_amount: legacy_vault.short_amount,
+            },
+        )?;
+
+        // only the token owner can operate a vault
+        mint_vault_nft(storage, vault_id, legacy_vault.operator)?;
+
+        vault_ids.push(vault_id);
+    }
+
+    if is_complete {
+        LEGACY_VAULTS_CURSOR.remove(storage);
+    } else if let Some(last_vault_id) = vault_ids.last() {
+        LEGACY_VAULTS_CURSOR.save(storage, last_vault_id)?;
+    }
+
+    Ok((vault_ids, is_complete))
+}
@@ -0,0 +1,83 @@
+use cosmwasm_std::{Addr, Empty, StdError, StdResult, Storage};
+use cw721::ContractInfoResponse;
+use cw721_base::{state::TokenInfo, Cw721Contract};
+
+pub const VAULT_NFT_NAME: &str = "Margined Power Vault";
+pub const VAULT_NFT_SYMBOL: &str = "MPV";
+
+/// cw721 storage for the vault NFTs, each vault is represented by a token whose
+/// id is the vault id. The NFT owner is the only account that can operate the vault.
+pub type VaultNftContract<'a> = Cw721Contract<'a, Empty, Empty, Empty, Empty>;
+
+pub fn vault_nft<'a>() -> VaultNftContract<'a> {
+    VaultNftContract::default()
+}
+
+pub fn vault_token_id(vault_id: u64) -> String {
+    vault_id.to_string()
+}
+
+pub fn parse_vault_token_id(token_id: &str) -> StdResult<u64> {
+    token_id
+        .parse::<u64>()
+        .map_err(|_| StdError::generic_err(format!("Invalid vault token id: {token_id}")))
+}
+
+pub fn save_vault_nft_info(storage: &mut dyn Storage) -> StdResult<()> {
+    vault_nft().contract_info.save(
+        storage,
+        &ContractInfoResponse {
+            name: VAULT_NFT_NAME.to_string(),
+            symbol: VAULT_NFT_SYMBOL.to_string(),
+        },
+    )
+}
+
+pub fn mint_vault_nft(storage: &mut dyn Storage, vault_id: u64, owner: Addr) -> StdResult<()> {
+    let contract = vault_nft();
+
+    let token = TokenInfo {
+        owner,
+        approvals: vec![],
+        token_uri: None,
+        extension: Empty {},
+    };
+
+    contract
+        .tokens
+        .update(storage, &vault_token_id(vault_id), |old| match old {
+            Some(_) => Err(StdError::generic_err("Vault token already minted")),
+            None => Ok(token),
+        })?;
+
+    contract.increment_tokens(storage)?;
+
+    Ok(())
+}
+
+pub fn get_vault_nft_owner(storage: &dyn Storage, vault_id: u64) -> StdResult<Addr> {
+    let token = vault_nft()
+        .tokens
+        .load(storage, &vault_token_id(vault_id))?;
+
+    Ok(token.owner)
+}
+
+/// Moves the vault token to the recipient, approvals granted by the previous
+/// owner are cleared in the same way as a cw721 transfer.
+pub fn transfer_vault_nft(
+    storage: &mut dyn Storage,
+    vault_id: u64,
+    recipient: Addr,
+) -> StdResult<()> {
+    let contract = vault_nft();
+    let token_id = vault_token_id(vault_id);
+
+    let mut token = contract.tokens.load(storage, &token_id)?;
+    token.owner = recipient;
+    token.approvals = vec![];
+
+    contract.tokens.save(storage, &token_id, &token)?;
+
+    Ok(())
+}
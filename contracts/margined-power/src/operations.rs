@@ -1,36 +1,48 @@
 use crate::{
     funding::apply_funding_rate,
-    helpers::{calculate_fee, create_apply_funding_event, create_mint_message, decimal_to_fixed},
+    helpers::{
+        calculate_fee, create_apply_funding_event, create_mint_message, decimal_to_fixed,
+        format_fee_splits, split_fee,
+    },
     state::{Config, CONFIG, STATE},
-    vault::{burn_vault, check_vault, create_vault, update_vault, VAULTS},
+    vault::{
+        add_collateral, burn_vault, check_vault, clear_liquidation_auction, create_vault,
+        get_vault_market_id, get_vault_owner_for_sender, update_vault, VAULTS,
+    },
 };
 
 use cosmwasm_std::{
-    coin, ensure, BankMsg, CosmosMsg, Decimal, DepsMut, Env, Event, MessageInfo, Response, Uint128,
+    coin, ensure, BankMsg, CosmosMsg, Decimal, DepsMut, Env, Event, MessageInfo, Response,
+    StdError, Uint128,
 };
 use cw_utils::{may_pay, must_pay};
 use margined_common::errors::ContractError;
+use margined_protocol::power::VaultDelta;
 use osmosis_std::types::{cosmos::base::v1beta1::Coin, osmosis::tokenfactory::v1beta1::MsgBurn};
 
+#[allow(clippy::too_many_arguments)]
 pub fn mint(
     mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    market_id: u64,
     mint_amount: Uint128,
     vault_id: Option<u64>,
     rebase: bool,
-    should_sell: bool,
+    sell_reply_id: Option<u64>,
 ) -> Result<Response, ContractError> {
-    STATE.load(deps.storage)?.is_open_and_unpaused()?;
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
 
-    let config: Config = CONFIG.load(deps.storage)?;
+    let config: Config = CONFIG.load(deps.storage, market_id)?;
 
     ensure!(!mint_amount.is_zero(), ContractError::ZeroMint {});
 
     let collateral_sent =
         may_pay(&info, &config.base_denom).map_err(|_| ContractError::InvalidFunds {})?;
 
-    let cached_normalisation_factor = apply_funding_rate(deps.branch(), env.clone())?;
+    let cached_normalisation_factor = apply_funding_rate(deps.branch(), env.clone(), market_id)?;
 
     let mint_amount = match rebase {
         true => {
@@ -46,21 +58,29 @@ pub fn mint(
         false => mint_amount,
     };
 
-    let vault_id = match vault_id {
+    // approved operators mint against the owner's vault, minted tokens go to the owner
+    let (vault_id, vault_owner) = match vault_id {
         Some(vault_id) => {
             if !VAULTS.has(deps.storage, &vault_id) {
                 return Err(ContractError::VaultDoesNotExist {});
             };
 
-            vault_id
+            let vault_owner =
+                get_vault_owner_for_sender(deps.storage, &env.block, vault_id, &info.sender)?;
+
+            (vault_id, vault_owner)
         }
-        None => create_vault(deps.storage, info.sender.clone())?,
+        None => (
+            create_vault(deps.storage, info.sender.clone(), market_id)?,
+            info.sender.clone(),
+        ),
     };
 
     let (fee_amount, collateral_with_fee) = calculate_fee(
         deps.branch(),
         env.clone(),
-        info.sender.clone(),
+        market_id,
+        vault_owner.clone(),
         vault_id,
         Decimal::from_atomics(mint_amount, config.power_decimals).unwrap(),
         Decimal::from_atomics(collateral_sent, config.base_decimals).unwrap(),
@@ -69,7 +89,7 @@ pub fn mint(
     update_vault(
         deps.storage,
         vault_id,
-        info.sender.clone(),
+        vault_owner.clone(),
         decimal_to_fixed(collateral_with_fee, config.base_decimals),
         mint_amount,
     )?;
@@ -85,6 +105,8 @@ pub fn mint(
     ensure!(is_safe, ContractError::UnsafeVault {});
     ensure!(min_collateral, ContractError::BelowMinCollateralAmount {});
 
+    clear_liquidation_auction(deps.storage, vault_id);
+
     let mut response: Response = Response::new();
     if !mint_amount.is_zero() {
         response = create_mint_message(
@@ -92,34 +114,195 @@ pub fn mint(
             env.contract.address.to_string(),
             config.power_denom.clone(),
             mint_amount.to_string(),
-            info.sender.to_string(),
-            should_sell,
+            vault_owner.to_string(),
+            sell_reply_id,
         );
     }
 
-    if !fee_amount.is_zero() {
-        let fixed_fee_amount = decimal_to_fixed(fee_amount, config.base_decimals);
-
-        let msg_fee_transfer = BankMsg::Send {
-            to_address: config.fee_pool_contract.to_string(),
-            amount: vec![coin(fixed_fee_amount.u128(), config.base_denom)],
-        };
-
-        response = response.clone().add_message(msg_fee_transfer);
-    }
+    let (response, fee_splits) = distribute_fee(deps, &config, market_id, fee_amount, response)?;
 
     let mint_event = Event::new("mint").add_attributes([
         ("collateral_deposited", &collateral_sent.to_string()),
         ("mint_amount", &mint_amount.to_string()),
         ("fee_amount", &fee_amount.to_string()),
+        ("fee_splits", &format_fee_splits(&fee_splits)),
         ("vault_id", &vault_id.to_string()),
+        ("market_id", &market_id.to_string()),
     ]);
 
-    let funding_event = create_apply_funding_event(&cached_normalisation_factor.to_string());
+    let funding_event =
+        create_apply_funding_event(&cached_normalisation_factor.to_string(), market_id);
 
     Ok(response.add_events([mint_event, funding_event]))
 }
 
+/// Applies the changes to the base collateral and debt of a vault, checking its
+/// health once at the end so intermediate states are not required to be safe.
+pub fn adjust_vault(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    vault_id: u64,
+    collateral_delta: Option<VaultDelta>,
+    debt_delta: Option<VaultDelta>,
+) -> Result<Response, ContractError> {
+    let market_id = get_vault_market_id(deps.storage, vault_id)?;
+
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
+
+    let config: Config = CONFIG.load(deps.storage, market_id)?;
+
+    ensure!(
+        collateral_delta.is_some() || debt_delta.is_some(),
+        StdError::generic_err("Vault adjustment must change the collateral or debt")
+    );
+
+    let (collateral_deposited, collateral_withdrawn) = match collateral_delta {
+        Some(VaultDelta::Increase { amount }) => (amount, Uint128::zero()),
+        Some(VaultDelta::Decrease { amount }) => (Uint128::zero(), amount),
+        None => (Uint128::zero(), Uint128::zero()),
+    };
+
+    let (mint_amount, burn_amount) = match debt_delta {
+        Some(VaultDelta::Increase { amount }) => (amount, Uint128::zero()),
+        Some(VaultDelta::Decrease { amount }) => (Uint128::zero(), amount),
+        None => (Uint128::zero(), Uint128::zero()),
+    };
+
+    // funds sent must be exactly the collateral deposited and the power burnt
+    let mut expected_funds = vec![];
+    if !collateral_deposited.is_zero() {
+        expected_funds.push(coin(collateral_deposited.u128(), &config.base_denom));
+    }
+    if !burn_amount.is_zero() {
+        expected_funds.push(coin(burn_amount.u128(), &config.power_denom));
+    }
+
+    let mut funds = info.funds.clone();
+    funds.sort_by(|a, b| a.denom.cmp(&b.denom));
+    expected_funds.sort_by(|a, b| a.denom.cmp(&b.denom));
+
+    ensure!(funds == expected_funds, ContractError::InvalidFunds {});
+
+    let cached_normalisation_factor = apply_funding_rate(deps.branch(), env.clone(), market_id)?;
+
+    let vault_owner = get_vault_owner_for_sender(deps.storage, &env.block, vault_id, &info.sender)?;
+
+    if !collateral_deposited.is_zero() {
+        add_collateral(
+            deps.storage,
+            vault_id,
+            vault_owner.clone(),
+            collateral_deposited,
+        )?;
+    }
+
+    // the fee on minting is taken from the vault collateral
+    let fee_amount = if mint_amount.is_zero() {
+        Decimal::zero()
+    } else {
+        let (fee_amount, _) = calculate_fee(
+            deps.branch(),
+            env.clone(),
+            market_id,
+            vault_owner.clone(),
+            vault_id,
+            Decimal::from_atomics(mint_amount, config.power_decimals).unwrap(),
+            Decimal::zero(),
+        )?;
+
+        update_vault(
+            deps.storage,
+            vault_id,
+            vault_owner.clone(),
+            Uint128::zero(),
+            mint_amount,
+        )?;
+
+        fee_amount
+    };
+
+    if !burn_amount.is_zero() || !collateral_withdrawn.is_zero() {
+        burn_vault(
+            deps.storage,
+            vault_id,
+            vault_owner.clone(),
+            collateral_withdrawn,
+            burn_amount,
+        )?;
+    }
+
+    let (is_safe, min_collateral) = check_vault(
+        deps.as_ref(),
+        config.clone(),
+        vault_id,
+        cached_normalisation_factor,
+        env.block.time,
+    )?;
+
+    ensure!(is_safe, ContractError::UnsafeVault {});
+
+    // as when burning, a vault without debt may go below the minimum collateral
+    let vault = VAULTS.load(deps.storage, &vault_id)?;
+    ensure!(
+        min_collateral || vault.short_amount.is_zero(),
+        ContractError::BelowMinCollateralAmount {}
+    );
+
+    clear_liquidation_auction(deps.storage, vault_id);
+
+    let mut response = Response::new();
+
+    if !mint_amount.is_zero() {
+        response = create_mint_message(
+            response,
+            env.contract.address.to_string(),
+            config.power_denom.clone(),
+            mint_amount.to_string(),
+            vault_owner.to_string(),
+            None,
+        );
+    }
+
+    if !burn_amount.is_zero() {
+        response = response.add_message(MsgBurn {
+            sender: env.contract.address.to_string(),
+            amount: Some(Coin {
+                denom: config.power_denom.clone(),
+                amount: burn_amount.to_string(),
+            }),
+            burn_from_address: env.contract.address.to_string(),
+        });
+    }
+
+    if !collateral_withdrawn.is_zero() {
+        response = response.add_message(BankMsg::Send {
+            to_address: vault_owner.to_string(),
+            amount: vec![coin(collateral_withdrawn.u128(), &config.base_denom)],
+        });
+    }
+
+    let (response, fee_splits) = distribute_fee(deps, &config, market_id, fee_amount, response)?;
+
+    let adjust_event = Event::new("adjust_vault").add_attributes([
+        ("collateral_deposited", &collateral_deposited.to_string()),
+        ("collateral_withdrawn", &collateral_withdrawn.to_string()),
+        ("mint_amount", &mint_amount.to_string()),
+        ("burn_amount", &burn_amount.to_string()),
+        ("fee_amount", &fee_amount.to_string()),
+        ("fee_splits", &format_fee_splits(&fee_splits)),
+        ("vault_id", &vault_id.to_string()),
+        ("market_id", &market_id.to_string()),
+    ]);
+
+    let funding_event =
+        create_apply_funding_event(&cached_normalisation_factor.to_string(), market_id);
+
+    Ok(response.add_events([adjust_event, funding_event]))
+}
+
 pub fn burn(
     mut deps: DepsMut,
     env: Env,
@@ -127,13 +310,13 @@ pub fn burn(
     amount_to_withdraw: Option<Uint128>,
     vault_id: u64,
 ) -> Result<Response, ContractError> {
-    STATE.load(deps.storage)?.is_open_and_unpaused()?;
+    let market_id = get_vault_market_id(deps.storage, vault_id)?;
 
-    let config: Config = CONFIG.load(deps.storage)?;
+    STATE
+        .load(deps.storage, market_id)?
+        .is_open_and_unpaused()?;
 
-    if !VAULTS.has(deps.storage, &vault_id) {
-        return Err(ContractError::VaultDoesNotExist {});
-    };
+    let config: Config = CONFIG.load(deps.storage, market_id)?;
 
     let amount_to_withdraw = amount_to_withdraw.unwrap_or(Uint128::zero());
 
@@ -144,12 +327,14 @@ pub fn burn(
         return Err(ContractError::InvalidFunds {});
     }
 
-    let cached_normalisation_factor = apply_funding_rate(deps.branch(), env.clone())?;
+    let vault_owner = get_vault_owner_for_sender(deps.storage, &env.block, vault_id, &info.sender)?;
+
+    let cached_normalisation_factor = apply_funding_rate(deps.branch(), env.clone(), market_id)?;
 
     burn_vault(
         deps.storage,
         vault_id,
-        info.sender.clone(),
+        vault_owner.clone(),
         amount_to_withdraw,
         amount_to_burn,
     )?;
@@ -164,6 +349,8 @@ pub fn burn(
 
     ensure!(is_safe, ContractError::UnsafeVault {});
 
+    clear_liquidation_auction(deps.storage, vault_id);
+
     let mut messages = Vec::<CosmosMsg>::new();
 
     // burn power perp token
@@ -179,10 +366,10 @@ pub fn burn(
 
     messages.push(msg_burn);
 
-    // transfer base to sender
+    // transfer base to the vault owner
     if !amount_to_withdraw.is_zero() {
         let msg_transfer = CosmosMsg::Bank(BankMsg::Send {
-            to_address: info.sender.to_string(),
+            to_address: vault_owner.to_string(),
             amount: vec![coin(amount_to_withdraw.u128(), config.base_denom)],
         });
 
@@ -195,9 +382,55 @@ pub fn burn(
         ("vault_id", &vault_id.to_string()),
     ]);
 
-    let funding_event = create_apply_funding_event(&cached_normalisation_factor.to_string());
+    let funding_event =
+        create_apply_funding_event(&cached_normalisation_factor.to_string(), market_id);
 
     Ok(Response::new()
         .add_messages(messages)
         .add_events([burn_event, funding_event]))
 }
+
+/// Retains the insurance share of a fee and splits the rest between the fee recipients,
+/// returning the amount sent to each.
+pub fn distribute_fee(
+    deps: DepsMut,
+    config: &Config,
+    market_id: u64,
+    fee_amount: Decimal,
+    mut response: Response,
+) -> Result<(Response, Vec<(String, Uint128)>), ContractError> {
+    if fee_amount.is_zero() {
+        return Ok((response, vec![]));
+    }
+
+    let fixed_fee_amount = decimal_to_fixed(fee_amount, config.base_decimals);
+
+    // share of the fee retained by the contract to fund the insurance
+    let insurance_amount = fixed_fee_amount * config.insurance_fee_share;
+    if !insurance_amount.is_zero() {
+        let mut state = STATE.load(deps.storage, market_id)?;
+        state.insurance_balance = state
+            .insurance_balance
+            .checked_add(insurance_amount)
+            .unwrap();
+
+        STATE.save(deps.storage, market_id, &state)?;
+    }
+
+    let distributed_amount = fixed_fee_amount.checked_sub(insurance_amount).unwrap();
+    if distributed_amount.is_zero() {
+        return Ok((response, vec![]));
+    }
+
+    let fee_splits = split_fee(config, distributed_amount);
+    for (address, amount) in fee_splits.iter() {
+        if !amount.is_zero() {
+            response = response.add_message(BankMsg::Send {
+                to_address: address.clone(),
+                amount: vec![coin(amount.u128(), &config.base_denom)],
+            });
+        }
+    }
+
+    Ok((response, fee_splits))
+}
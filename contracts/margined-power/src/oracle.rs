@@ -0,0 +1,175 @@
+use crate::{
+    queries::{get_pool_geometric_twap, get_pool_twap},
+    state::{Config, CONFIG, INDEX_SCALE},
+};
+
+use cosmwasm_std::{
+    ensure, to_binary, Decimal, Deps, QueryRequest, StdError, StdResult, Timestamp, WasmQuery,
+};
+use margined_protocol::{
+    power::{OracleSource, PriceType},
+    query::{PriceFeedQueryMsg, PriceFeedResponse},
+};
+
+/// Returns the price of the base in quote from the oracle of the market, the
+/// secondary source is used when the primary fails and bounds its deviation.
+pub fn get_base_price(
+    deps: &Deps,
+    market_id: u64,
+    start_time: Timestamp,
+    block_time: Timestamp,
+) -> StdResult<Decimal> {
+    let config = CONFIG.load(deps.storage, market_id)?;
+
+    let primary = query_source(
+        deps,
+        market_id,
+        &config,
+        &config.oracle.primary,
+        start_time,
+        block_time,
+    );
+
+    let secondary = config
+        .oracle
+        .secondary
+        .as_ref()
+        .map(|source| query_source(deps, market_id, &config, source, start_time, block_time));
+
+    match (primary, secondary) {
+        (Ok(primary), Some(Ok(secondary))) => {
+            let max_difference = secondary.checked_mul(config.oracle.max_deviation)?;
+
+            ensure!(
+                primary.abs_diff(secondary) <= max_difference,
+                StdError::generic_err("Oracle sources deviate by more than the max deviation")
+            );
+
+            Ok(primary)
+        }
+        (Ok(primary), _) => Ok(primary),
+        (Err(_), Some(Ok(secondary))) => Ok(secondary),
+        (Err(err), _) => Err(err),
+    }
+}
+
+/// Returns the base price divided by the index scale, as used by the index.
+pub fn get_scaled_base_price(
+    deps: &Deps,
+    market_id: u64,
+    start_time: Timestamp,
+    block_time: Timestamp,
+) -> StdResult<Decimal> {
+    let price = get_base_price(deps, market_id, start_time, block_time)?;
+
+    Ok(price / Decimal::from_atomics(INDEX_SCALE, 0).unwrap())
+}
+
+/// Returns the price of the power in base from the power pool, averaged as
+/// configured for the market.
+pub fn get_power_price(deps: &Deps, market_id: u64, start_time: Timestamp) -> StdResult<Decimal> {
+    let config = CONFIG.load(deps.storage, market_id)?;
+
+    match config.power_price_type {
+        PriceType::ArithmeticTwap {} => get_pool_twap(
+            deps,
+            market_id,
+            config.power_pool.id,
+            config.power_denom,
+            config.base_denom,
+            start_time,
+        ),
+        PriceType::GeometricTwap {} => get_pool_geometric_twap(
+            deps,
+            market_id,
+            config.power_pool.id,
+            config.power_denom,
+            config.base_denom,
+            start_time,
+        ),
+    }
+}
+
+pub fn describe_price_type(price_type: &PriceType) -> String {
+    match price_type {
+        PriceType::ArithmeticTwap {} => "arithmetic_twap".to_string(),
+        PriceType::GeometricTwap {} => "geometric_twap".to_string(),
+    }
+}
+
+pub fn describe_source(source: &OracleSource) -> String {
+    match source {
+        OracleSource::ArithmeticTwap {} => "arithmetic_twap".to_string(),
+        OracleSource::GeometricTwap {} => "geometric_twap".to_string(),
+        OracleSource::PriceFeed { contract, .. } => format!("price_feed:{contract}"),
+    }
+}
+
+fn query_source(
+    deps: &Deps,
+    market_id: u64,
+    config: &Config,
+    source: &OracleSource,
+    start_time: Timestamp,
+    block_time: Timestamp,
+) -> StdResult<Decimal> {
+    let price = match source {
+        OracleSource::ArithmeticTwap {} => get_pool_twap(
+            deps,
+            market_id,
+            config.base_pool.id,
+            config.base_denom.clone(),
+            config.base_pool.quote_denom.clone(),
+            start_time,
+        )?,
+        OracleSource::GeometricTwap {} => get_pool_geometric_twap(
+            deps,
+            market_id,
+            config.base_pool.id,
+            config.base_denom.clone(),
+            config.base_pool.quote_denom.clone(),
+            start_time,
+        )?,
+        OracleSource::PriceFeed { contract, max_age } => query_price_feed(
+            deps,
+            contract.clone(),
+            config.base_denom.clone(),
+            config.base_pool.quote_denom.clone(),
+            *max_age,
+            block_time,
+        )?,
+    };
+
+    ensure!(
+        !price.is_zero(),
+        StdError::generic_err("Oracle price cannot be zero")
+    );
+
+    Ok(price)
+}
+
+/// Returns the price of the pair from a price feed contract, reverting if the
+/// price is older than the max age.
+pub fn query_price_feed(
+    deps: &Deps,
+    contract: String,
+    base_asset: String,
+    quote_asset: String,
+    max_age: u64,
+    block_time: Timestamp,
+) -> StdResult<Decimal> {
+    let res: PriceFeedResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: contract,
+        msg: to_binary(&PriceFeedQueryMsg::GetPrice {
+            base_asset,
+            quote_asset,
+        })?,
+    }))?;
+
+    ensure!(
+        res.last_updated.plus_seconds(max_age) >= block_time,
+        StdError::generic_err("Oracle price feed is stale")
+    );
+
+    Ok(res.price)
+}
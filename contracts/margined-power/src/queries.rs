@@ -1,20 +1,28 @@
-use crate::state::{CONFIG, INDEX_SCALE};
+use crate::state::CONFIG;
 
 use cosmwasm_std::{
-    to_binary, Decimal, Deps, QueryRequest, StdError, StdResult, Timestamp, Uint128, WasmQuery,
+    to_binary, Binary, Decimal, Deps, QueryRequest, StdError, StdResult, Timestamp, Uint128,
+    WasmQuery,
+};
+use margined_protocol::{
+    query::QueryMsg,
+    staking::{QueryMsg as StakingQueryMsg, UserStakedResponse},
+};
+use osmosis_std::types::{
+    cosmos::bank::v1beta1::BankQuerier, osmosis::concentratedliquidity::v1beta1::Pool,
+    osmosis::poolmanager::v1beta1::PoolmanagerQuerier,
 };
-use margined_protocol::query::QueryMsg;
-use osmosis_std::types::cosmos::bank::v1beta1::BankQuerier;
 use std::str::FromStr;
 
 pub fn get_pool_twap(
     deps: &Deps,
+    market_id: u64,
     pool_id: u64,
     base_asset: String,
     quote_asset: String,
     start_time: Timestamp,
 ) -> StdResult<Decimal> {
-    let config = CONFIG.load(deps.storage).unwrap();
+    let config = CONFIG.load(deps.storage, market_id)?;
 
     let price: Decimal = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
         contract_addr: config.query_contract.to_string(),
@@ -29,47 +37,59 @@ pub fn get_pool_twap(
     Ok(price)
 }
 
-pub fn get_scaled_pool_twap(
+pub fn get_pool_geometric_twap(
     deps: &Deps,
+    market_id: u64,
     pool_id: u64,
     base_asset: String,
     quote_asset: String,
     start_time: Timestamp,
 ) -> StdResult<Decimal> {
-    let config = CONFIG.load(deps.storage).unwrap();
-
-    let price: Decimal = deps
-        .querier
-        .query(&QueryRequest::Wasm(WasmQuery::Smart {
-            contract_addr: config.query_contract.to_string(),
-            msg: to_binary(&QueryMsg::GetArithmeticTwapToNow {
-                pool_id,
-                base_asset,
-                quote_asset,
-                start_time,
-            })?,
-        }))
-        .unwrap();
-
-    Ok(price / Decimal::from_atomics(INDEX_SCALE, 0).unwrap())
+    let config = CONFIG.load(deps.storage, market_id)?;
+
+    let price: Decimal = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: config.query_contract.to_string(),
+        msg: to_binary(&QueryMsg::GetGeometricTwapToNow {
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+        })?,
+    }))?;
+
+    Ok(price)
 }
 
-pub fn get_denom_authority(deps: Deps, denom: String) -> StdResult<String> {
-    let config = CONFIG.load(deps.storage).unwrap();
+pub fn get_pool_spot_price(
+    deps: &Deps,
+    market_id: u64,
+    pool_id: u64,
+    base_asset: String,
+    quote_asset: String,
+) -> StdResult<Decimal> {
+    let config = CONFIG.load(deps.storage, market_id)?;
 
-    let res: Option<String> = deps
-        .querier
-        .query(&QueryRequest::Wasm(WasmQuery::Smart {
-            contract_addr: config.query_contract.to_string(),
-            msg: to_binary(&QueryMsg::GetDenomAuthority { denom })?,
-        }))
-        .unwrap();
+    let price: Decimal = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: config.query_contract.to_string(),
+        msg: to_binary(&QueryMsg::GetSpotPrice {
+            pool_id,
+            base_asset,
+            quote_asset,
+        })?,
+    }))?;
+
+    Ok(price)
+}
 
-    if res.is_none() {
-        return Err(StdError::generic_err("No pool authority found"));
-    }
+pub fn get_denom_authority(deps: Deps, market_id: u64, denom: String) -> StdResult<String> {
+    let config = CONFIG.load(deps.storage, market_id)?;
 
-    Ok(res.unwrap())
+    let res: Option<String> = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: config.query_contract.to_string(),
+        msg: to_binary(&QueryMsg::GetDenomAuthority { denom })?,
+    }))?;
+
+    res.ok_or_else(|| StdError::generic_err("No pool authority found"))
 }
 
 pub fn get_total_supply(deps: Deps, denom: String) -> StdResult<Uint128> {
@@ -85,6 +105,32 @@ pub fn get_total_supply(deps: Deps, denom: String) -> StdResult<Uint128> {
     Ok(amount)
 }
 
+/// Returns the denoms of token0 and token1 of a concentrated liquidity pool.
+pub fn get_pool_denoms(deps: Deps, pool_id: u64) -> StdResult<(String, String)> {
+    let pool = PoolmanagerQuerier::new(&deps.querier)
+        .pool(pool_id)?
+        .pool
+        .ok_or_else(|| StdError::generic_err(format!("Pool {} not found", pool_id)))?;
+
+    let pool: Pool = Binary::from(pool.value).try_into()?;
+
+    Ok((pool.token0, pool.token1))
+}
+
+pub fn get_user_staked_amount(
+    deps: Deps,
+    staking_contract: String,
+    user: String,
+) -> StdResult<Uint128> {
+    let response: UserStakedResponse =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: staking_contract,
+            msg: to_binary(&StakingQueryMsg::GetUserStakedAmount { user })?,
+        }))?;
+
+    Ok(response.staked_amounts)
+}
+
 pub fn get_balance(deps: Deps, address: String, denom: String) -> StdResult<Uint128> {
     let bank = BankQuerier::new(&deps.querier);
 
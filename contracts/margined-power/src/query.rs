@@ -1,15 +1,36 @@
 use crate::{
-    funding::calculate_normalisation_factor,
-    helpers::calculate_denormalized_mark,
-    queries::{get_pool_twap, get_scaled_pool_twap},
-    state::{CONFIG, OWNER, STATE},
-    vault::{is_vault_safe, VAULTS, VAULTS_COUNTER},
+    collateral::{get_collateral_value, value_vault},
+    funding::{calculate_annualised_funding_rate, calculate_normalisation_factor},
+    helpers::{
+        calculate_debt_in_base, calculate_denormalized_mark, calculate_liquidation_bonus,
+        decimal_to_fixed, get_fee_rate,
+    },
+    market::get_market_id,
+    oracle::{get_base_price, get_scaled_base_price},
+    state::{
+        Config, COLLATERAL_ASSETS, CONFIG, DELISTED_COLLATERAL_ASSETS, FUNDING_HISTORY,
+        LIQUIDITY_POSITIONS, OWNER, STATE,
+    },
+    vault::{
+        calculate_health, get_vault_collateral, is_vault_safe, LIQUIDATION_AUCTIONS, VAULTS,
+        VAULTS_COUNTER, VAULT_APPROVALS,
+    },
 };
 
-use cosmwasm_std::{Addr, Decimal, Deps, Env, Order, StdError, StdResult, Timestamp};
+use cosmwasm_std::{
+    coin, Addr, Decimal, Deps, Env, Order, StdError, StdResult, Timestamp, Uint128,
+};
+use cw721::Approval;
 use cw_storage_plus::Bound;
 use margined_common::errors::ContractError;
-use margined_protocol::power::{ConfigResponse, StateResponse, UserVaultsResponse, VaultResponse};
+use margined_protocol::power::{
+    CollateralAssetsResponse, ConfigResponse, FeePreviewResponse, FundingHistoryResponse,
+    FundingRateResponse, LiquidationAuctionResponse, LiquidityPosition, MarketsResponse,
+    StateResponse, UnsafeVaultsResponse, UserLiquidityPositionsResponse, UserVaultsResponse,
+    VaultCollateralResponse, VaultHealthResponse, VaultOperatorsResponse, VaultResponse,
+    VaultsResponse,
+};
+use std::collections::BTreeMap;
 
 const DEFAULT_LIMIT: u32 = 10;
 const MAX_LIMIT: u32 = 50;
@@ -18,8 +39,10 @@ fn calculate_start_time(env: Env, period: u64) -> Timestamp {
     env.block.time.minus_seconds(period)
 }
 
-pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
-    let config = CONFIG.load(deps.storage).unwrap();
+pub fn query_config(deps: Deps, market_id: Option<u64>) -> StdResult<ConfigResponse> {
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    let config = CONFIG.load(deps.storage, market_id)?;
 
     Ok(ConfigResponse {
         query_contract: config.query_contract,
@@ -32,11 +55,30 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         funding_period: config.funding_period,
         base_decimals: config.base_decimals,
         power_decimals: config.power_decimals,
+        min_liquidation_bonus: config.min_liquidation_bonus,
+        max_liquidation_bonus: config.max_liquidation_bonus,
+        liquidation_auction_duration: config.liquidation_auction_duration,
+        insurance_fee_share: config.insurance_fee_share,
+        collateral_ratio_numerator: config.collateral_ratio_numerator,
+        collateral_ratio_denominator: config.collateral_ratio_denominator,
+        min_collateral: config.min_collateral,
+        twap_period: config.twap_period,
+        max_twap_period: config.max_twap_period,
+        funding_lower_bound: config.funding_lower_bound,
+        funding_upper_bound: config.funding_upper_bound,
+        oracle: config.oracle,
+        max_spot_deviation: config.max_spot_deviation,
+        power_price_type: config.power_price_type,
+        flash_mint_fee_rate: config.flash_mint_fee_rate,
+        fee_schedule: config.fee_schedule,
+        fee_recipients: config.fee_recipients,
     })
 }
 
-pub fn query_state(deps: Deps) -> StdResult<StateResponse> {
-    let state = STATE.load(deps.storage).unwrap();
+pub fn query_state(deps: Deps, market_id: Option<u64>) -> StdResult<StateResponse> {
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    let state = STATE.load(deps.storage, market_id)?;
 
     Ok(StateResponse {
         is_open: state.is_open,
@@ -44,9 +86,29 @@ pub fn query_state(deps: Deps) -> StdResult<StateResponse> {
         last_pause: state.last_pause,
         normalisation_factor: state.normalisation_factor,
         last_funding_update: state.last_funding_update,
+        is_shutdown: state.is_shutdown,
+        settlement_price: state.settlement_price,
+        insurance_balance: state.insurance_balance,
+        total_bad_debt: state.total_bad_debt,
     })
 }
 
+pub fn query_markets(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<MarketsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let markets = CONFIG
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(MarketsResponse { markets })
+}
+
 pub fn query_owner(deps: Deps) -> Result<Addr, ContractError> {
     if let Some(owner) = OWNER.get(deps)? {
         Ok(owner)
@@ -55,84 +117,294 @@ pub fn query_owner(deps: Deps) -> Result<Addr, ContractError> {
     }
 }
 
-pub fn get_normalisation_factor(deps: Deps, env: Env) -> StdResult<Decimal> {
-    let res = calculate_normalisation_factor(deps, env)?;
+pub fn get_normalisation_factor(
+    deps: Deps,
+    env: Env,
+    market_id: Option<u64>,
+) -> StdResult<Decimal> {
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    let res = calculate_normalisation_factor(deps, env, market_id)?;
 
     Ok(res)
 }
 
-pub fn get_index(deps: Deps, env: Env, period: u64) -> StdResult<Decimal> {
-    let config = CONFIG.load(deps.storage).unwrap();
+pub fn get_index(deps: Deps, env: Env, period: u64, market_id: Option<u64>) -> StdResult<Decimal> {
+    let market_id = get_market_id(deps.storage, market_id)?;
 
-    let start_time = calculate_start_time(env, period);
+    let start_time = calculate_start_time(env.clone(), period);
 
-    let quote_price = get_scaled_pool_twap(
-        &deps,
-        config.base_pool.id,
-        config.base_denom.clone(),
-        config.base_pool.quote_denom,
-        start_time,
-    )
-    .unwrap();
+    let quote_price = get_scaled_base_price(&deps, market_id, start_time, env.block.time)?;
 
     let index = quote_price.checked_mul(quote_price).unwrap();
 
     Ok(index)
 }
 
-pub fn get_unscaled_index(deps: Deps, env: Env, period: u64) -> StdResult<Decimal> {
-    let config = CONFIG.load(deps.storage).unwrap();
+pub fn get_unscaled_index(
+    deps: Deps,
+    env: Env,
+    period: u64,
+    market_id: Option<u64>,
+) -> StdResult<Decimal> {
+    let market_id = get_market_id(deps.storage, market_id)?;
 
-    let start_time = calculate_start_time(env, period);
+    let start_time = calculate_start_time(env.clone(), period);
 
-    let quote_price = get_pool_twap(
-        &deps,
-        config.base_pool.id,
-        config.base_denom.clone(),
-        config.base_pool.quote_denom,
-        start_time,
-    )
-    .unwrap();
+    let quote_price = get_base_price(&deps, market_id, start_time, env.block.time)?;
 
     let index = quote_price.checked_mul(quote_price).unwrap();
 
     Ok(index)
 }
 
-pub fn get_denormalised_mark(deps: Deps, env: Env, period: u64) -> StdResult<Decimal> {
+pub fn get_denormalised_mark(
+    deps: Deps,
+    env: Env,
+    period: u64,
+    market_id: Option<u64>,
+) -> StdResult<Decimal> {
+    let market_id = get_market_id(deps.storage, market_id)?;
+
     let start_time = calculate_start_time(env.clone(), period);
 
-    let normalisation_factor = calculate_normalisation_factor(deps, env)?;
+    let normalisation_factor = calculate_normalisation_factor(deps, env.clone(), market_id)?;
 
-    let result = calculate_denormalized_mark(deps, start_time, normalisation_factor)?;
+    let result = calculate_denormalized_mark(
+        deps,
+        market_id,
+        start_time,
+        env.block.time,
+        normalisation_factor,
+    )?;
 
     Ok(result)
 }
 
-pub fn get_denormalised_mark_for_funding(deps: Deps, env: Env, period: u64) -> StdResult<Decimal> {
-    let start_time = calculate_start_time(env, period);
+pub fn get_denormalised_mark_for_funding(
+    deps: Deps,
+    env: Env,
+    period: u64,
+    market_id: Option<u64>,
+) -> StdResult<Decimal> {
+    let market_id = get_market_id(deps.storage, market_id)?;
 
-    let state = STATE.load(deps.storage).unwrap();
+    let start_time = calculate_start_time(env.clone(), period);
 
-    let result = calculate_denormalized_mark(deps, start_time, state.normalisation_factor)?;
+    let state = STATE.load(deps.storage, market_id)?;
+
+    let result = calculate_denormalized_mark(
+        deps,
+        market_id,
+        start_time,
+        env.block.time,
+        state.normalisation_factor,
+    )?;
 
     Ok(result)
 }
 
 pub fn get_check_vault(deps: Deps, env: Env, vault_id: u64) -> StdResult<bool> {
-    let config = CONFIG.load(deps.storage).unwrap();
-    let normalisation_factor = calculate_normalisation_factor(deps, env.clone())?;
+    // a vault without debt is safe
+    let market_id = match VAULTS.may_load(deps.storage, &vault_id)? {
+        Some(vault) => vault.market_id,
+        None => return Ok(true),
+    };
 
-    let result =
-        is_vault_safe(deps, config, vault_id, normalisation_factor, env.block.time).unwrap();
+    let config = CONFIG.load(deps.storage, market_id)?;
+    let normalisation_factor = calculate_normalisation_factor(deps, env.clone(), market_id)?;
 
-    Ok(result)
+    is_vault_safe(deps, config, vault_id, normalisation_factor, env.block.time)
+}
+
+pub fn get_liquidation_auction(
+    deps: Deps,
+    env: Env,
+    vault_id: u64,
+) -> StdResult<LiquidationAuctionResponse> {
+    let vault = VAULTS.may_load(deps.storage, &vault_id)?;
+    if vault.is_none() {
+        return Err(StdError::generic_err("Vault not found"));
+    }
+
+    let market_id = vault.unwrap().market_id;
+
+    let config = CONFIG.load(deps.storage, market_id)?;
+
+    let normalisation_factor = calculate_normalisation_factor(deps, env.clone(), market_id)?;
+
+    let is_safe = is_vault_safe(
+        deps,
+        config.clone(),
+        vault_id,
+        normalisation_factor,
+        env.block.time,
+    )?;
+
+    // an unsafe vault has no auction until it is recorded by StartLiquidationAuction
+    // or its first liquidation
+    let start_time = if is_safe {
+        None
+    } else {
+        LIQUIDATION_AUCTIONS.may_load(deps.storage, vault_id)?
+    };
+
+    let liquidation_bonus = match start_time {
+        Some(start_time) => calculate_liquidation_bonus(&config, start_time, env.block.time),
+        None => config.min_liquidation_bonus,
+    };
+
+    let quote_price = get_scaled_base_price(
+        &deps,
+        market_id,
+        calculate_start_time(env.clone(), config.twap_period),
+        env.block.time,
+    )?;
+
+    let auction_price = normalisation_factor
+        .checked_mul(quote_price)?
+        .checked_mul(liquidation_bonus)?;
+
+    Ok(LiquidationAuctionResponse {
+        is_safe,
+        start_time,
+        liquidation_bonus,
+        auction_price,
+    })
+}
+
+pub fn get_vault_health(deps: Deps, env: Env, vault_id: u64) -> StdResult<VaultHealthResponse> {
+    let vault = VAULTS.may_load(deps.storage, &vault_id)?;
+    if vault.is_none() {
+        return Err(StdError::generic_err("Vault not found"));
+    }
+
+    let vault = vault.unwrap();
+
+    let config = CONFIG.load(deps.storage, vault.market_id)?;
+
+    let normalisation_factor = calculate_normalisation_factor(deps, env.clone(), vault.market_id)?;
+
+    let start_time = calculate_start_time(env.clone(), config.twap_period);
+
+    let quote_price = get_scaled_base_price(&deps, vault.market_id, start_time, env.block.time)?;
+
+    let base_collateral = vault.collateral;
+    let vault = value_vault(&deps, &config, vault_id, vault, start_time, env.block.time)?;
+
+    let mut health = calculate_health(&config, vault, normalisation_factor, quote_price);
+
+    // only the base denom is withdrawn through the max withdrawable
+    health.max_withdrawable = health.max_withdrawable.min(base_collateral);
+
+    Ok(health)
+}
+
+pub fn get_vault_collateral_balances(
+    deps: Deps,
+    env: Env,
+    vault_id: u64,
+) -> StdResult<VaultCollateralResponse> {
+    let vault = VAULTS
+        .may_load(deps.storage, &vault_id)?
+        .ok_or_else(|| StdError::generic_err("Vault not found"))?;
+
+    let config = CONFIG.load(deps.storage, vault.market_id)?;
+
+    let mut collateral = vec![coin(vault.collateral.u128(), config.base_denom.clone())];
+    for (denom, amount) in get_vault_collateral(deps.storage, vault_id)? {
+        collateral.push(coin(amount.u128(), denom));
+    }
+
+    let collateral_value = get_collateral_value(
+        &deps,
+        &config,
+        vault_id,
+        vault.market_id,
+        calculate_start_time(env.clone(), config.twap_period),
+        env.block.time,
+    )?
+    .checked_add(vault.collateral)?;
+
+    Ok(VaultCollateralResponse {
+        collateral,
+        collateral_value,
+    })
+}
+
+pub fn get_collateral_assets(
+    deps: Deps,
+    market_id: Option<u64>,
+) -> StdResult<CollateralAssetsResponse> {
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    let assets = COLLATERAL_ASSETS
+        .prefix(market_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, asset)| asset))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let delisted = DELISTED_COLLATERAL_ASSETS
+        .prefix(market_id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(CollateralAssetsResponse { assets, delisted })
+}
+
+pub fn get_insurance_balance(deps: Deps, market_id: Option<u64>) -> StdResult<Uint128> {
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    let state = STATE.load(deps.storage, market_id)?;
+
+    Ok(state.insurance_balance)
+}
+
+pub fn get_total_bad_debt(deps: Deps, market_id: Option<u64>) -> StdResult<Uint128> {
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    let state = STATE.load(deps.storage, market_id)?;
+
+    Ok(state.total_bad_debt)
+}
+
+pub fn get_funding_history(
+    deps: Deps,
+    market_id: Option<u64>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<FundingHistoryResponse> {
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let snapshots = FUNDING_HISTORY
+        .prefix(market_id)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, snapshot)| snapshot))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(FundingHistoryResponse { snapshots })
+}
+
+pub fn get_annualised_funding_rate(
+    deps: Deps,
+    env: Env,
+    period: u64,
+    market_id: Option<u64>,
+) -> StdResult<FundingRateResponse> {
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    calculate_annualised_funding_rate(deps, env, market_id, period)
 }
 
 pub fn get_vault(deps: Deps, vault_id: u64) -> StdResult<VaultResponse> {
     let vault = VAULTS.may_load(deps.storage, &vault_id)?;
     if let Some(vault) = vault {
         Ok(VaultResponse {
+            market_id: vault.market_id,
             operator: vault.operator,
             collateral: vault.collateral,
             short_amount: vault.short_amount,
@@ -142,6 +414,87 @@ pub fn get_vault(deps: Deps, vault_id: u64) -> StdResult<VaultResponse> {
     }
 }
 
+pub fn list_vaults(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<VaultsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|s| Bound::ExclusiveRaw(s.to_be_bytes().into()));
+
+    let vaults = VAULTS
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(VaultsResponse { vaults })
+}
+
+pub fn list_unsafe_vaults(
+    deps: Deps,
+    env: Env,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<UnsafeVaultsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|s| Bound::ExclusiveRaw(s.to_be_bytes().into()));
+
+    let page = VAULTS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let last_scanned = page.last().map(|(vault_id, _)| *vault_id);
+
+    // config and normalisation factor are shared by the vaults of a market
+    let mut markets: BTreeMap<u64, (Config, Decimal)> = BTreeMap::new();
+
+    let mut vaults: Vec<u64> = vec![];
+    for (vault_id, vault) in page {
+        if vault.short_amount.is_zero() {
+            continue;
+        }
+
+        if !markets.contains_key(&vault.market_id) {
+            let config = CONFIG.load(deps.storage, vault.market_id)?;
+            let normalisation_factor =
+                calculate_normalisation_factor(deps, env.clone(), vault.market_id)?;
+
+            markets.insert(vault.market_id, (config, normalisation_factor));
+        }
+
+        let (config, normalisation_factor) = markets[&vault.market_id].clone();
+
+        if !is_vault_safe(deps, config, vault_id, normalisation_factor, env.block.time)? {
+            vaults.push(vault_id);
+        }
+    }
+
+    Ok(UnsafeVaultsResponse {
+        vaults,
+        last_scanned,
+    })
+}
+
+pub fn get_vault_operators(deps: Deps, vault_id: u64) -> StdResult<VaultOperatorsResponse> {
+    if !VAULTS.has(deps.storage, &vault_id) {
+        return Err(StdError::generic_err("Vault not found"));
+    }
+
+    let operators = VAULT_APPROVALS
+        .prefix(vault_id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            item.map(|(spender, expires)| Approval {
+                spender: spender.to_string(),
+                expires,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(VaultOperatorsResponse { operators })
+}
+
 pub fn get_next_vault_id(deps: Deps) -> StdResult<u64> {
     let current_index = VAULTS_COUNTER.may_load(deps.storage)?.unwrap_or(0);
 
@@ -168,3 +521,61 @@ pub fn get_user_vaults(
 
     Ok(UserVaultsResponse { vaults })
 }
+
+pub fn get_fee_preview(
+    deps: Deps,
+    env: Env,
+    user: String,
+    amount: Uint128,
+    market_id: Option<u64>,
+) -> StdResult<FeePreviewResponse> {
+    let market_id = get_market_id(deps.storage, market_id)?;
+
+    let config = CONFIG.load(deps.storage, market_id)?;
+
+    let user = deps.api.addr_validate(&user)?;
+
+    let mint_value = calculate_debt_in_base(
+        deps,
+        env,
+        market_id,
+        Decimal::from_atomics(amount, config.power_decimals).unwrap(),
+    )?;
+
+    let (fee_rate, staked_amount) = get_fee_rate(deps, &config, &user, mint_value)?;
+
+    Ok(FeePreviewResponse {
+        mint_value: decimal_to_fixed(mint_value, config.base_decimals),
+        staked_amount,
+        fee_rate,
+        fee_amount: decimal_to_fixed(
+            mint_value.checked_mul(fee_rate).unwrap(),
+            config.base_decimals,
+        ),
+    })
+}
+
+pub fn get_user_liquidity_positions(
+    deps: Deps,
+    owner: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<UserLiquidityPositionsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let owner_addr = deps.api.addr_validate(&owner)?;
+    let positions = LIQUIDITY_POSITIONS
+        .prefix(&owner_addr)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|(position_id, market_id)| LiquidityPosition {
+                position_id,
+                market_id,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(UserLiquidityPositionsResponse { positions })
+}
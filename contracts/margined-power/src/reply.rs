@@ -1,47 +1,83 @@
 use crate::{
-    contract::OPEN_SHORT_SWAP_REPLY_ID,
-    helpers::{create_swap_exact_amount_in_message, parse_response_result_data},
-    operations::burn,
-    queries::{get_balance, get_total_supply},
-    state::{Config, CONFIG, TMP_CACHE},
+    contract::{OPEN_SHORT_SWAP_REPLY_ID, PROVIDE_LIQUIDITY_REPLY_ID, REPLY_KIND_BITS},
+    funding::apply_funding_rate,
+    helpers::{
+        calculate_fee, create_apply_funding_event, create_swap_exact_amount_in_message,
+        format_coins, format_fee_splits, parse_response_result_data,
+    },
+    operations::{burn, distribute_fee},
+    queries::{get_balance, get_pool_denoms},
+    state::{
+        Config, ReplyContext, CONFIG, FLASH_MINT, FULL_RANGE_MAX_TICK, FULL_RANGE_MIN_TICK,
+        LIQUIDITY_POSITIONS, NEXT_REPLY_NONCE, REPLY_CONTEXTS,
+    },
+    vault::{check_vault, clear_liquidation_auction, get_vault_owner_for_sender, update_vault},
 };
 
 use cosmwasm_std::{
-    coin, coins, BankMsg, CosmosMsg, DepsMut, Env, MessageInfo, Reply, ReplyOn, Response, SubMsg,
-    Uint128,
+    coin, coins, ensure, BankMsg, CosmosMsg, Decimal, DepsMut, Env, Event, MessageInfo, Reply,
+    ReplyOn, Response, StdError, StdResult, Storage, SubMsg, Uint128,
 };
 use margined_common::errors::ContractError;
 use osmosis_std::types::{
     cosmos::bank::v1beta1::MsgSend,
     cosmos::base::v1beta1::Coin,
+    osmosis::concentratedliquidity::v1beta1::{
+        MsgCollectIncentivesResponse, MsgCollectSpreadRewardsResponse, MsgCreatePosition,
+        MsgCreatePositionResponse, MsgWithdrawPositionResponse,
+    },
     osmosis::poolmanager::v1beta1::{MsgSwapExactAmountInResponse, MsgSwapExactAmountOutResponse},
+    osmosis::tokenfactory::v1beta1::MsgBurn,
 };
 use std::str::FromStr;
 
+/// Saves the context of a call awaiting a reply under a new nonce and returns
+/// the reply id encoding the nonce and the kind of reply.
+pub fn save_reply_context(
+    storage: &mut dyn Storage,
+    kind: u64,
+    context: &ReplyContext,
+) -> StdResult<u64> {
+    let nonce = NEXT_REPLY_NONCE.may_load(storage)?.unwrap_or(1);
+    NEXT_REPLY_NONCE.save(storage, &(nonce + 1))?;
+
+    REPLY_CONTEXTS.save(storage, nonce, context)?;
+
+    Ok(encode_reply_id(kind, nonce))
+}
+
+pub fn encode_reply_id(kind: u64, nonce: u64) -> u64 {
+    (nonce << REPLY_KIND_BITS) | kind
+}
+
+pub fn reply_kind(id: u64) -> u64 {
+    id & ((1 << REPLY_KIND_BITS) - 1)
+}
+
+pub fn reply_nonce(id: u64) -> u64 {
+    id >> REPLY_KIND_BITS
+}
+
 pub fn handle_open_short_reply(
     deps: DepsMut,
     env: Env,
-    _msg: Reply,
+    msg: Reply,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-
-    let current_total_supply = get_total_supply(deps.as_ref(), config.power_denom.clone())?;
-    let cache = TMP_CACHE.load(deps.storage)?;
-
-    let prev_total_supply = cache.total_supply.unwrap_or(Uint128::zero());
+    let nonce = reply_nonce(msg.id);
+    let context = REPLY_CONTEXTS.load(deps.storage, nonce)?;
 
-    let mint_amount = current_total_supply.checked_sub(prev_total_supply).unwrap();
+    // tokenfactory mint responses are empty, the amount minted is the amount requested
+    let mint_amount = context.mint_amount.unwrap();
 
     let swap_msg = create_swap_exact_amount_in_message(
         env.contract.address.to_string(),
-        config.power_pool.id,
-        config.power_denom,
-        config.base_denom,
+        &context.route,
         mint_amount.to_string(),
+        context.min_amount_out.unwrap_or(Uint128::one()).to_string(),
     );
 
     let swap_submsg: SubMsg = SubMsg {
-        id: OPEN_SHORT_SWAP_REPLY_ID,
+        id: encode_reply_id(OPEN_SHORT_SWAP_REPLY_ID, nonce),
         msg: swap_msg.into(),
         gas_limit: None,
         reply_on: ReplyOn::Success,
@@ -50,81 +86,195 @@ pub fn handle_open_short_reply(
     Ok(Response::new().add_submessage(swap_submsg))
 }
 
-pub fn handle_open_short_swap_reply(
+pub fn handle_swap_exact_amount_in_reply(
     deps: DepsMut,
     env: Env,
     msg: Reply,
 ) -> Result<Response, ContractError> {
+    let nonce = reply_nonce(msg.id);
+
     let data = parse_response_result_data(msg.result)?;
 
     let response: MsgSwapExactAmountInResponse = data.try_into().map_err(ContractError::Std)?;
 
-    let config: Config = CONFIG.load(deps.storage)?;
-    let cache = TMP_CACHE.load(deps.storage)?;
+    let context = REPLY_CONTEXTS.load(deps.storage, nonce)?;
+    REPLY_CONTEXTS.remove(deps.storage, nonce);
+
+    let token_out_amount = Uint128::from_str(&response.token_out_amount).unwrap();
+    if let Some(min_amount_out) = context.min_amount_out {
+        if token_out_amount < min_amount_out {
+            return Err(ContractError::InsufficientOutput(
+                token_out_amount,
+                min_amount_out,
+            ));
+        }
+    }
 
     let send_msg = MsgSend {
         from_address: env.contract.address.to_string(),
-        to_address: cache.sender.unwrap().to_string(),
+        to_address: context.sender.to_string(),
         amount: vec![Coin {
             amount: response.token_out_amount,
-            denom: config.base_denom,
+            denom: context.route[context.route.len() - 1]
+                .token_out_denom
+                .clone(),
         }],
     };
 
-    TMP_CACHE.remove(deps.storage);
-
     Ok(Response::new().add_message(send_msg))
 }
 
+pub fn handle_flash_mint_reply(
+    mut deps: DepsMut,
+    env: Env,
+    _msg: Reply,
+) -> Result<Response, ContractError> {
+    let flash_mint = FLASH_MINT.load(deps.storage)?;
+    FLASH_MINT.remove(deps.storage);
+
+    let config: Config = CONFIG.load(deps.storage, flash_mint.market_id)?;
+
+    // power sent back to the contract during the callback repays the flash mint
+    let current_balance = get_balance(
+        deps.as_ref(),
+        env.contract.address.to_string(),
+        config.power_denom.clone(),
+    )?;
+
+    let burn_amount = current_balance
+        .saturating_sub(flash_mint.balance)
+        .min(flash_mint.amount);
+    let debt_amount = flash_mint.amount.checked_sub(burn_amount).unwrap();
+
+    let mut response = Response::new();
+
+    if !burn_amount.is_zero() {
+        response = response.add_message(MsgBurn {
+            sender: env.contract.address.to_string(),
+            amount: Some(Coin {
+                denom: config.power_denom.clone(),
+                amount: burn_amount.to_string(),
+            }),
+            burn_from_address: env.contract.address.to_string(),
+        });
+    }
+
+    let mut repaid_event = Event::new("flash_mint_repaid").add_attributes([
+        ("burn_amount", &burn_amount.to_string()),
+        ("debt_amount", &debt_amount.to_string()),
+        ("market_id", &flash_mint.market_id.to_string()),
+    ]);
+
+    // power not returned is added to the debt of the vault, which must remain safe
+    if !debt_amount.is_zero() {
+        let vault_id = flash_mint
+            .vault_id
+            .ok_or(ContractError::FlashMintNotRepaid(debt_amount))?;
+
+        let cached_normalisation_factor =
+            apply_funding_rate(deps.branch(), env.clone(), flash_mint.market_id)?;
+
+        let vault_owner =
+            get_vault_owner_for_sender(deps.storage, &env.block, vault_id, &flash_mint.sender)?;
+
+        // the debt is charged the mint fee, taken from the vault collateral
+        let (fee_amount, _) = calculate_fee(
+            deps.branch(),
+            env.clone(),
+            flash_mint.market_id,
+            vault_owner.clone(),
+            vault_id,
+            Decimal::from_atomics(debt_amount, config.power_decimals).unwrap(),
+            Decimal::zero(),
+        )?;
+
+        update_vault(
+            deps.storage,
+            vault_id,
+            vault_owner,
+            Uint128::zero(),
+            debt_amount,
+        )?;
+
+        let (is_safe, min_collateral) = check_vault(
+            deps.as_ref(),
+            config.clone(),
+            vault_id,
+            cached_normalisation_factor,
+            env.block.time,
+        )?;
+
+        ensure!(is_safe, ContractError::UnsafeVault {});
+        ensure!(min_collateral, ContractError::BelowMinCollateralAmount {});
+
+        clear_liquidation_auction(deps.storage, vault_id);
+
+        let fee_splits;
+        (response, fee_splits) =
+            distribute_fee(deps, &config, flash_mint.market_id, fee_amount, response)?;
+
+        repaid_event = repaid_event.add_attributes([
+            ("vault_id", vault_id.to_string()),
+            ("fee_amount", fee_amount.to_string()),
+            ("fee_splits", format_fee_splits(&fee_splits)),
+        ]);
+        response = response.add_event(create_apply_funding_event(
+            &cached_normalisation_factor.to_string(),
+            flash_mint.market_id,
+        ));
+    }
+
+    Ok(response.add_event(repaid_event))
+}
+
 pub fn handle_close_short_reply(
     deps: DepsMut,
     env: Env,
     msg: Reply,
 ) -> Result<Response, ContractError> {
+    let nonce = reply_nonce(msg.id);
+
     let data = parse_response_result_data(msg.result)?;
 
     let response: MsgSwapExactAmountOutResponse = data.try_into().map_err(ContractError::Std)?;
 
     let token_in_amount = Uint128::from_str(&response.token_in_amount).unwrap();
 
-    let config: Config = CONFIG.load(deps.storage)?;
-    let current_balance = get_balance(
-        deps.as_ref(),
-        env.contract.address.to_string(),
-        config.power_denom.clone(),
-    )?;
-
-    let cache = TMP_CACHE.load(deps.storage)?;
+    let context = REPLY_CONTEXTS.load(deps.storage, nonce)?;
+    REPLY_CONTEXTS.remove(deps.storage, nonce);
 
-    let sender = cache.sender.unwrap();
-    let burn_amount = current_balance.checked_sub(cache.balance.unwrap()).unwrap();
+    let config: Config = CONFIG.load(deps.storage, context.market_id)?;
 
-    let info = MessageInfo {
-        sender: sender.clone(),
-        funds: coins(burn_amount.u128(), config.power_denom),
-    };
+    if let Some(max_base_in) = context.max_base_in {
+        if token_in_amount > max_base_in {
+            return Err(ContractError::ExcessiveInput(token_in_amount, max_base_in));
+        }
+    }
 
-    let vault_id = match cache.vault_id {
+    let vault_id = match context.vault_id {
         Some(id) => id,
         None => {
             return Err(ContractError::VaultDoesNotExist {});
         }
     };
 
-    TMP_CACHE.remove(deps.storage);
+    // the swap is for an exact amount out, so the power bought is the amount to burn
+    let burn_amount = context.amount_to_burn.unwrap();
 
-    let mut response = burn(deps, env, info, cache.amount_to_withdraw, vault_id)?;
+    let info = MessageInfo {
+        sender: context.sender.clone(),
+        funds: coins(burn_amount.u128(), config.power_denom),
+    };
 
-    if cache.amount_to_swap.unwrap() > token_in_amount {
-        let refund = cache
-            .amount_to_swap
-            .unwrap()
-            .checked_sub(token_in_amount)
-            .unwrap();
+    let mut response = burn(deps, env, info, context.amount_to_withdraw, vault_id)?;
+
+    let amount_to_swap = context.amount_to_swap.unwrap();
+    if amount_to_swap > token_in_amount {
+        let refund = amount_to_swap.checked_sub(token_in_amount).unwrap();
 
         let msg_transfer = CosmosMsg::Bank(BankMsg::Send {
-            to_address: sender.to_string(),
-            amount: vec![coin(refund.u128(), config.base_denom)],
+            to_address: context.sender.to_string(),
+            amount: vec![coin(refund.u128(), context.route[0].token_in_denom.clone())],
         });
 
         response = response.add_message(msg_transfer);
@@ -132,3 +282,209 @@ pub fn handle_close_short_reply(
 
     Ok(response)
 }
+
+pub fn handle_mint_and_provide_liquidity_reply(
+    deps: DepsMut,
+    env: Env,
+    msg: Reply,
+) -> Result<Response, ContractError> {
+    let nonce = reply_nonce(msg.id);
+    let context = REPLY_CONTEXTS.load(deps.storage, nonce)?;
+    let config = CONFIG.load(deps.storage, context.market_id)?;
+
+    // tokenfactory mint responses are empty, the amount minted is the amount requested
+    let mint_amount = context.mint_amount.ok_or(ContractError::InvalidReplyId)?;
+    let base_to_pair = context.base_to_pair.ok_or(ContractError::InvalidReplyId)?;
+    let (min_power, min_base) = context.min_provided.ok_or(ContractError::InvalidReplyId)?;
+
+    let (token0, _) = get_pool_denoms(deps.as_ref(), config.power_pool.id)?;
+    let (token_min_amount0, token_min_amount1) = if token0 == config.power_denom {
+        (min_power, min_base)
+    } else {
+        (min_base, min_power)
+    };
+
+    let mut tokens_provided = vec![
+        Coin {
+            denom: config.power_denom,
+            amount: mint_amount.to_string(),
+        },
+        Coin {
+            denom: config.base_denom,
+            amount: base_to_pair.to_string(),
+        },
+    ];
+    tokens_provided.sort_by(|a, b| a.denom.cmp(&b.denom));
+
+    let create_position_submsg: SubMsg = SubMsg {
+        id: encode_reply_id(PROVIDE_LIQUIDITY_REPLY_ID, nonce),
+        msg: MsgCreatePosition {
+            pool_id: config.power_pool.id,
+            sender: env.contract.address.to_string(),
+            lower_tick: FULL_RANGE_MIN_TICK,
+            upper_tick: FULL_RANGE_MAX_TICK,
+            tokens_provided,
+            token_min_amount0: token_min_amount0.to_string(),
+            token_min_amount1: token_min_amount1.to_string(),
+        }
+        .into(),
+        gas_limit: None,
+        reply_on: ReplyOn::Success,
+    };
+
+    Ok(Response::new().add_submessage(create_position_submsg))
+}
+
+pub fn handle_provide_liquidity_reply(
+    deps: DepsMut,
+    msg: Reply,
+) -> Result<Response, ContractError> {
+    let nonce = reply_nonce(msg.id);
+
+    let data = parse_response_result_data(msg.result)?;
+
+    let response: MsgCreatePositionResponse = data.try_into().map_err(ContractError::Std)?;
+
+    let context = REPLY_CONTEXTS.load(deps.storage, nonce)?;
+    REPLY_CONTEXTS.remove(deps.storage, nonce);
+
+    let config: Config = CONFIG.load(deps.storage, context.market_id)?;
+
+    let mint_amount = context.mint_amount.ok_or(ContractError::InvalidReplyId)?;
+    let base_to_pair = context.base_to_pair.ok_or(ContractError::InvalidReplyId)?;
+
+    // concentrated liquidity positions cannot be transferred, so the contract holds
+    // the position for the user until they remove it
+    LIQUIDITY_POSITIONS.save(
+        deps.storage,
+        (&context.sender, response.position_id),
+        &context.market_id,
+    )?;
+
+    // tokens not taken by the position are refunded
+    let (token0, _) = get_pool_denoms(deps.as_ref(), config.power_pool.id)?;
+    let amount0 = Uint128::from_str(&response.amount0)?;
+    let amount1 = Uint128::from_str(&response.amount1)?;
+    let (power_provided, base_provided) = if token0 == config.power_denom {
+        (amount0, amount1)
+    } else {
+        (amount1, amount0)
+    };
+
+    let refund: Vec<cosmwasm_std::Coin> = [
+        (
+            config.base_denom,
+            base_to_pair
+                .checked_sub(base_provided)
+                .map_err(StdError::from)?,
+        ),
+        (
+            config.power_denom,
+            mint_amount
+                .checked_sub(power_provided)
+                .map_err(StdError::from)?,
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, amount)| !amount.is_zero())
+    .map(|(denom, amount)| coin(amount.u128(), denom))
+    .collect();
+
+    let provide_liquidity_event = Event::new("provide_liquidity").add_attributes(vec![
+        ("market_id", context.market_id.to_string()),
+        ("owner", context.sender.to_string()),
+        ("position_id", response.position_id.to_string()),
+        ("liquidity_created", response.liquidity_created),
+        ("refund", format_coins(&refund)),
+    ]);
+
+    let mut response = Response::new().add_event(provide_liquidity_event);
+
+    if !refund.is_empty() {
+        response = response.add_message(BankMsg::Send {
+            to_address: context.sender.to_string(),
+            amount: refund,
+        });
+    }
+
+    Ok(response)
+}
+
+pub fn handle_collect_spread_rewards_reply(
+    deps: DepsMut,
+    msg: Reply,
+) -> Result<Response, ContractError> {
+    let context = REPLY_CONTEXTS.load(deps.storage, reply_nonce(msg.id))?;
+
+    let data = parse_response_result_data(msg.result)?;
+
+    let response: MsgCollectSpreadRewardsResponse = data.try_into().map_err(ContractError::Std)?;
+
+    send_collected(&context, response.collected_spread_rewards)
+}
+
+pub fn handle_collect_incentives_reply(
+    deps: DepsMut,
+    msg: Reply,
+) -> Result<Response, ContractError> {
+    let context = REPLY_CONTEXTS.load(deps.storage, reply_nonce(msg.id))?;
+
+    let data = parse_response_result_data(msg.result)?;
+
+    let response: MsgCollectIncentivesResponse = data.try_into().map_err(ContractError::Std)?;
+
+    send_collected(&context, response.collected_incentives)
+}
+
+pub fn handle_remove_liquidity_reply(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let nonce = reply_nonce(msg.id);
+
+    let data = parse_response_result_data(msg.result)?;
+
+    let response: MsgWithdrawPositionResponse = data.try_into().map_err(ContractError::Std)?;
+
+    let context = REPLY_CONTEXTS.load(deps.storage, nonce)?;
+    REPLY_CONTEXTS.remove(deps.storage, nonce);
+
+    let config: Config = CONFIG.load(deps.storage, context.market_id)?;
+
+    let (token0, token1) = get_pool_denoms(deps.as_ref(), config.power_pool.id)?;
+
+    send_collected(
+        &context,
+        vec![
+            Coin {
+                denom: token0,
+                amount: response.amount0,
+            },
+            Coin {
+                denom: token1,
+                amount: response.amount1,
+            },
+        ],
+    )
+}
+
+/// Sends the non-zero coins collected from a position to its owner.
+fn send_collected(context: &ReplyContext, coins: Vec<Coin>) -> Result<Response, ContractError> {
+    let mut collected = vec![];
+
+    for Coin { denom, amount } in coins {
+        let amount = Uint128::from_str(&amount)?;
+
+        if !amount.is_zero() {
+            collected.push(coin(amount.u128(), denom));
+        }
+    }
+
+    let mut response = Response::new();
+
+    if !collected.is_empty() {
+        response = response.add_message(BankMsg::Send {
+            to_address: context.sender.to_string(),
+            amount: collected,
+        });
+    }
+
+    Ok(response)
+}
@@ -1,23 +1,64 @@
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{ensure, ensure_ne, Addr, Decimal, StdError, StdResult, Timestamp, Uint128};
 use cw_controllers::Admin;
-use cw_storage_plus::Item;
+use cw_storage_plus::{Item, Map};
 use margined_common::ownership::OwnerProposal;
-use margined_protocol::power::{Pool, FUNDING_PERIOD};
+use margined_protocol::power::{
+    CollateralAsset, FeeRecipient, FeeSchedule, FundingSnapshot, Oracle, OracleSource, Pool,
+    PriceType, SwapHop, FUNDING_PERIOD,
+};
 
 pub const OWNER: Admin = Admin::new("owner");
 pub const OWNERSHIP_PROPOSAL: Item<OwnerProposal> = Item::new("ownership_proposals");
 
-pub const CONFIG: Item<Config> = Item::new("config");
-pub const STATE: Item<State> = Item::new("state");
+// config and state of each market, keyed by the market id
+pub const CONFIG: Map<u64, Config> = Map::new("config");
+pub const STATE: Map<u64, State> = Map::new("state");
 
-pub const TMP_CACHE: Item<TmpCacheValues> = Item::new("tmp_cache");
+// context of the calls awaiting a reply, keyed by the nonce encoded in the reply id
+pub const REPLY_CONTEXTS: Map<u64, ReplyContext> = Map::new("reply_contexts");
+pub const NEXT_REPLY_NONCE: Item<u64> = Item::new("next_reply_nonce");
+// flash mint in progress, set until the callback returns
+pub const FLASH_MINT: Item<FlashMint> = Item::new("flash_mint");
+
+// funding applied to each market, keyed by the market id and timestamp in seconds
+pub const FUNDING_HISTORY: Map<(u64, u64), FundingSnapshot> = Map::new("funding_history");
+
+// collateral accepted in addition to the base denom, keyed by the market id and denom
+pub const COLLATERAL_ASSETS: Map<(u64, &str), CollateralAsset> = Map::new("collateral_assets");
+// collateral assets closed to new deposits, still priced for the vaults holding them
+pub const DELISTED_COLLATERAL_ASSETS: Map<(u64, &str), bool> =
+    Map::new("delisted_collateral_assets");
+// prices in base of the collateral assets when the market was shutdown
+pub const COLLATERAL_SETTLEMENT_PRICES: Map<(u64, &str), Decimal> =
+    Map::new("collateral_settlement_prices");
+// collateral other than the base denom settled from vaults, held to pay redemptions
+pub const SETTLED_COLLATERAL: Map<(u64, &str), Uint128> = Map::new("settled_collateral");
+
+// market of the power pool positions held by the contract, keyed by the owner and position id
+pub const LIQUIDITY_POSITIONS: Map<(&Addr, u64), u64> = Map::new("liquidity_positions");
 
 pub const LIQUIDATION_BOUNTY: Decimal = Decimal::raw(1_100_000_000_000_000_000u128); // 110%
 pub const INDEX_SCALE: u128 = 10_000u128; // 1e4
 
 pub const WEEK_IN_SECONDS: u64 = 7 * 24 * 60 * 60; // 24 hours
+pub const YEAR_IN_SECONDS: u64 = 365 * 24 * 60 * 60; // 365 days
 pub const TWAP_PERIOD: u64 = 420; // 420 seconds (7 minutes)
+pub const MAX_TWAP_PERIOD: u64 = 48 * 60 * 60; // TWAP from pool can be no longer than 48 hours
+pub const LIQUIDATION_AUCTION_DURATION: u64 = 600; // 600 seconds (10 minutes)
+pub const MAX_SWAP_HOPS: usize = 3; // longest route accepted when opening or closing a short
+pub const FULL_RANGE_MIN_TICK: i64 = -108_000_000; // lowest tick of a concentrated liquidity pool
+pub const FULL_RANGE_MAX_TICK: i64 = 342_000_000; // highest tick of a concentrated liquidity pool
+pub const MAX_FEE_RECIPIENTS: usize = 10; // most recipients the mint fees can be split between
+pub const TOTAL_FEE_WEIGHT_BPS: u16 = 10_000; // 100% in basis points
+
+pub const COLLATERAL_RATIO_NUMERATOR: Decimal = Decimal::raw(3_000_000_000_000_000_000u128); // 3
+pub const COLLATERAL_RATIO_DENOMINATOR: Decimal = Decimal::raw(2_000_000_000_000_000_000u128); // 2
+pub const MAX_COLLATERAL_RATIO: Decimal = Decimal::raw(3_000_000_000_000_000_000u128); // 300%
+pub const MIN_COLLATERAL: Decimal = Decimal::raw(500_000_000_000_000_000u128); // 0.5
+pub const FUNDING_LOWER_BOUND: Decimal = Decimal::raw(800_000_000_000_000_000u128); // 80%
+pub const FUNDING_UPPER_BOUND: Decimal = Decimal::raw(1_400_000_000_000_000_000u128); // 140%
+pub const MAX_FUNDING_UPPER_BOUND: Decimal = Decimal::raw(2_000_000_000_000_000_000u128); // 200%
 
 #[cw_serde]
 pub struct Config {
@@ -31,6 +72,23 @@ pub struct Config {
     pub funding_period: u64, // Funding period in seconds
     pub base_decimals: u32, // Decimals of the underying token
     pub power_decimals: u32, // Decimals of the power perp token
+    pub min_liquidation_bonus: Decimal, // Liquidation bonus when a vault first becomes unsafe
+    pub max_liquidation_bonus: Decimal, // Liquidation bonus once the auction duration has elapsed
+    pub liquidation_auction_duration: u64, // Seconds for the bonus to rise from min to max
+    pub insurance_fee_share: Decimal, // Share of fees retained by the insurance fund
+    pub collateral_ratio_numerator: Decimal, // Vaults are safe while collateral * denominator >= debt * numerator
+    pub collateral_ratio_denominator: Decimal, // Denominator of the collateral ratio
+    pub min_collateral: Decimal,             // Minimum collateral of a vault in base denom
+    pub twap_period: u64,                    // Period in seconds of the TWAP used to price debt
+    pub max_twap_period: u64,                // Longest TWAP period used when applying funding
+    pub funding_lower_bound: Decimal, // Mark is clamped to at least this share of index for funding
+    pub funding_upper_bound: Decimal, // Mark is clamped to at most this share of index for funding
+    pub oracle: Oracle,               // Sources of the base price used for the index
+    pub max_spot_deviation: Decimal, // Spot and TWAP deviation that pauses the market, zero disables
+    pub power_price_type: PriceType, // Average of the power pool price used for the mark and debt
+    pub flash_mint_fee_rate: Decimal, // Fee rate on the value of flash minted power
+    pub fee_schedule: FeeSchedule,   // Fee rates by mint size and discounts for stakers
+    pub fee_recipients: Vec<FeeRecipient>, // Split of the fees, all sent to the fee pool if empty
 }
 
 impl Config {
@@ -58,6 +116,182 @@ impl Config {
             ))
         );
 
+        ensure!(
+            self.min_liquidation_bonus >= Decimal::one()
+                && self.min_liquidation_bonus <= self.max_liquidation_bonus
+                && self.max_liquidation_bonus <= Decimal::percent(150),
+            StdError::generic_err(
+                "Invalid liquidation bonus, must satisfy 100% <= min <= max <= 150%"
+            )
+        );
+
+        ensure!(
+            self.flash_mint_fee_rate < Decimal::one(),
+            StdError::generic_err("Invalid flash mint fee rate")
+        );
+
+        ensure!(
+            self.fee_schedule
+                .size_tiers
+                .iter()
+                .all(|tier| tier.fee_rate < Decimal::one())
+                && self
+                    .fee_schedule
+                    .size_tiers
+                    .windows(2)
+                    .all(|tiers| tiers[0].min_value < tiers[1].min_value),
+            StdError::generic_err(
+                "Invalid fee tiers, rates must be below 100% and minimum values ascending"
+            )
+        );
+
+        ensure!(
+            self.fee_schedule
+                .staking_discounts
+                .iter()
+                .all(|tier| tier.discount <= Decimal::one())
+                && self
+                    .fee_schedule
+                    .staking_discounts
+                    .windows(2)
+                    .all(|tiers| tiers[0].min_staked < tiers[1].min_staked),
+            StdError::generic_err(
+                "Invalid staking discounts, discounts must be at most 100% and minimum stakes ascending"
+            )
+        );
+
+        ensure!(
+            self.fee_schedule.staking_discounts.is_empty()
+                || self.fee_schedule.staking_contract.is_some(),
+            StdError::generic_err("Staking discounts require a staking contract")
+        );
+
+        ensure!(
+            self.fee_recipients.len() <= MAX_FEE_RECIPIENTS,
+            StdError::generic_err(format!(
+                "Invalid fee recipients, cannot be more than {}",
+                MAX_FEE_RECIPIENTS
+            ))
+        );
+
+        ensure!(
+            self.fee_recipients.is_empty()
+                || self
+                    .fee_recipients
+                    .iter()
+                    .map(|recipient| recipient.weight_bps as u32)
+                    .sum::<u32>()
+                    == TOTAL_FEE_WEIGHT_BPS as u32,
+            StdError::generic_err("Invalid fee recipients, weights must sum to 10000 bps")
+        );
+
+        ensure!(
+            self.fee_recipients
+                .iter()
+                .enumerate()
+                .all(|(i, recipient)| {
+                    recipient.weight_bps > 0
+                        && self.fee_recipients[..i]
+                            .iter()
+                            .all(|other| other.address != recipient.address)
+                }),
+            StdError::generic_err(
+                "Invalid fee recipients, weights must be positive and addresses unique"
+            )
+        );
+
+        ensure!(
+            self.insurance_fee_share <= Decimal::one(),
+            StdError::generic_err("Invalid insurance fee share")
+        );
+
+        ensure!(
+            self.liquidation_auction_duration <= WEEK_IN_SECONDS,
+            StdError::generic_err(format!(
+                "Invalid liquidation auction duration, cannot be greater than {} seconds",
+                WEEK_IN_SECONDS
+            ))
+        );
+
+        ensure!(
+            !self.collateral_ratio_denominator.is_zero()
+                && self.collateral_ratio_numerator > self.collateral_ratio_denominator,
+            StdError::generic_err("Invalid collateral ratio, must be greater than 100%")
+        );
+
+        let collateral_ratio = self
+            .collateral_ratio_numerator
+            .checked_div(self.collateral_ratio_denominator)
+            .map_err(|_| StdError::generic_err("Invalid collateral ratio"))?;
+
+        ensure!(
+            collateral_ratio <= MAX_COLLATERAL_RATIO,
+            StdError::generic_err(format!(
+                "Invalid collateral ratio, cannot be greater than {}",
+                MAX_COLLATERAL_RATIO
+            ))
+        );
+
+        ensure!(
+            collateral_ratio > self.max_liquidation_bonus,
+            StdError::generic_err(
+                "Invalid collateral ratio, must be greater than the maximum liquidation bonus"
+            )
+        );
+
+        ensure!(
+            !self.min_collateral.is_zero(),
+            StdError::generic_err("Invalid minimum collateral")
+        );
+
+        ensure!(
+            self.max_twap_period > 0 && self.max_twap_period <= MAX_TWAP_PERIOD,
+            StdError::generic_err(format!(
+                "Invalid max twap period, must be between 0 and {} seconds",
+                MAX_TWAP_PERIOD
+            ))
+        );
+
+        ensure!(
+            self.twap_period > 0 && self.twap_period <= self.max_twap_period,
+            StdError::generic_err(format!(
+                "Invalid twap period, must be between 0 and {} seconds",
+                self.max_twap_period
+            ))
+        );
+
+        ensure!(
+            !self.funding_lower_bound.is_zero()
+                && self.funding_lower_bound <= Decimal::one()
+                && self.funding_upper_bound >= Decimal::one()
+                && self.funding_upper_bound <= MAX_FUNDING_UPPER_BOUND,
+            StdError::generic_err(
+                "Invalid funding bounds, must satisfy 0% < lower <= 100% <= upper <= 200%"
+            )
+        );
+
+        ensure!(
+            !self.oracle.max_deviation.is_zero() && self.oracle.max_deviation < Decimal::one(),
+            StdError::generic_err("Invalid oracle max deviation, must be between 0% and 100%")
+        );
+
+        ensure!(
+            self.max_spot_deviation <= Decimal::one(),
+            StdError::generic_err("Invalid max spot deviation, cannot be greater than 100%")
+        );
+
+        for source in [Some(&self.oracle.primary), self.oracle.secondary.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            if let OracleSource::PriceFeed { max_age, .. } = source {
+                ensure!(
+                    *max_age > 0,
+                    StdError::generic_err("Invalid oracle price feed max age")
+                );
+            }
+        }
+
         ensure_ne!(
             self.power_denom,
             self.base_denom,
@@ -76,11 +310,15 @@ impl Config {
 
 #[cw_serde]
 pub struct State {
-    pub is_open: bool,                  // Whether the contract is open
-    pub is_paused: bool,                // Whether the contract is paused
-    pub last_pause: Timestamp,          // Last time contract was paused
-    pub normalisation_factor: Decimal,  // Normalisation factor
-    pub last_funding_update: Timestamp, // Last funding update timestamp
+    pub is_open: bool,                     // Whether the contract is open
+    pub is_paused: bool,                   // Whether the contract is paused
+    pub last_pause: Timestamp,             // Last time contract was paused
+    pub normalisation_factor: Decimal,     // Normalisation factor
+    pub last_funding_update: Timestamp,    // Last funding update timestamp
+    pub is_shutdown: bool,                 // Whether the contract has been shutdown
+    pub settlement_price: Option<Decimal>, // Price of one power token in base at shutdown
+    pub insurance_balance: Uint128,        // Base held to cover liquidation shortfalls
+    pub total_bad_debt: Uint128,           // Liquidation shortfall in base not covered by insurance
 }
 
 impl State {
@@ -95,17 +333,36 @@ impl State {
             StdError::generic_err("Cannot perform action as contract is paused")
         );
 
+        ensure!(
+            !self.is_shutdown,
+            StdError::generic_err("Cannot perform action as contract is shutdown")
+        );
+
         Ok(())
     }
 }
 
 #[cw_serde]
-#[derive(Default)]
-pub struct TmpCacheValues {
-    pub total_supply: Option<Uint128>,
-    pub balance: Option<Uint128>,
+pub struct ReplyContext {
+    pub market_id: u64,
+    pub sender: Addr,
+    pub vault_id: Option<u64>,
+    pub mint_amount: Option<Uint128>, // power minted to sell when opening a short
+    pub min_amount_out: Option<Uint128>, // minimum received from an exact amount in swap
+    pub amount_to_burn: Option<Uint128>, // power bought to burn when closing a short
     pub amount_to_swap: Option<Uint128>,
     pub amount_to_withdraw: Option<Uint128>,
-    pub sender: Option<Addr>,
+    pub max_base_in: Option<Uint128>,
+    pub route: Vec<SwapHop>, // swapped when opening or closing a position
+    pub base_to_pair: Option<Uint128>, // base provided with the minted power
+    pub min_provided: Option<(Uint128, Uint128)>, // minimum power and base taken by a position
+}
+
+#[cw_serde]
+pub struct FlashMint {
+    pub market_id: u64,
+    pub sender: Addr,
+    pub amount: Uint128,
     pub vault_id: Option<u64>,
+    pub balance: Uint128, // power held by the contract before the flash mint
 }
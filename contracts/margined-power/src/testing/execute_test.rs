@@ -42,8 +42,13 @@ fn test_initialise_contract() {
         .data
         .address;
 
-    wasm.execute(&address, &ExecuteMsg::SetOpen {}, &[], &env.signer)
-        .unwrap_err();
+    wasm.execute(
+        &address,
+        &ExecuteMsg::SetOpen { market_id: None },
+        &[],
+        &env.signer,
+    )
+    .unwrap_err();
 
     token
         .change_admin(
@@ -56,10 +61,17 @@ fn test_initialise_contract() {
         )
         .unwrap();
 
-    wasm.execute(&address, &ExecuteMsg::SetOpen {}, &[], &env.signer)
-        .unwrap();
+    wasm.execute(
+        &address,
+        &ExecuteMsg::SetOpen { market_id: None },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
 
-    let state: State = wasm.query(&address, &QueryMsg::State {}).unwrap();
+    let state: State = wasm
+        .query(&address, &QueryMsg::State { market_id: None })
+        .unwrap();
     assert!(state.is_open);
 
     let owner: Addr = wasm.query(&address, &QueryMsg::Owner {}).unwrap();
@@ -1,11 +1,15 @@
 use crate::{
     contract::CONTRACT_NAME,
-    state::{Config, State},
+    state::{
+        Config, State, COLLATERAL_RATIO_DENOMINATOR, COLLATERAL_RATIO_NUMERATOR,
+        FUNDING_LOWER_BOUND, FUNDING_UPPER_BOUND, LIQUIDATION_AUCTION_DURATION, LIQUIDATION_BOUNTY,
+        MAX_TWAP_PERIOD, MIN_COLLATERAL, TWAP_PERIOD,
+    },
     testing::test_utils::{MOCK_FEE_POOL_ADDR, MOCK_QUERY_ADDR},
 };
 
-use cosmwasm_std::{coin, Addr, Decimal};
-use margined_protocol::power::{InstantiateMsg, Pool, QueryMsg};
+use cosmwasm_std::{coin, Addr, Decimal, Uint128};
+use margined_protocol::power::{FeeSchedule, InstantiateMsg, Oracle, Pool, PriceType, QueryMsg};
 use margined_testing::{helpers::store_code, power_env::PowerEnv};
 use osmosis_test_tube::{Account, Module, Wasm};
 use std::str::FromStr;
@@ -50,7 +54,9 @@ fn test_instantiation() {
 
     let timestamp = app.get_block_timestamp();
 
-    let config: Config = wasm.query(&address, &QueryMsg::Config {}).unwrap();
+    let config: Config = wasm
+        .query(&address, &QueryMsg::Config { market_id: None })
+        .unwrap();
     assert_eq!(
         config,
         Config {
@@ -70,10 +76,29 @@ fn test_instantiation() {
             fee_rate: Decimal::from_str("0.1".to_string().as_str()).unwrap(),
             base_decimals: 6u32,
             power_decimals: 6u32,
+            min_liquidation_bonus: LIQUIDATION_BOUNTY,
+            max_liquidation_bonus: LIQUIDATION_BOUNTY,
+            liquidation_auction_duration: LIQUIDATION_AUCTION_DURATION,
+            insurance_fee_share: Decimal::zero(),
+            collateral_ratio_numerator: COLLATERAL_RATIO_NUMERATOR,
+            collateral_ratio_denominator: COLLATERAL_RATIO_DENOMINATOR,
+            min_collateral: MIN_COLLATERAL,
+            twap_period: TWAP_PERIOD,
+            max_twap_period: MAX_TWAP_PERIOD,
+            funding_lower_bound: FUNDING_LOWER_BOUND,
+            funding_upper_bound: FUNDING_UPPER_BOUND,
+            oracle: Oracle::default(),
+            max_spot_deviation: Decimal::zero(),
+            power_price_type: PriceType::default(),
+            flash_mint_fee_rate: Decimal::zero(),
+            fee_schedule: FeeSchedule::default(),
+            fee_recipients: vec![],
         }
     );
 
-    let state: State = wasm.query(&address, &QueryMsg::State {}).unwrap();
+    let state: State = wasm
+        .query(&address, &QueryMsg::State { market_id: None })
+        .unwrap();
     assert_eq!(
         state,
         State {
@@ -81,6 +106,10 @@ fn test_instantiation() {
             is_paused: true,
             normalisation_factor: Decimal::one(),
             last_funding_update: timestamp,
+            is_shutdown: false,
+            settlement_price: None,
+            insurance_balance: Uint128::zero(),
+            total_bad_debt: Uint128::zero(),
             last_pause: timestamp,
         }
     );
@@ -1,7 +1,7 @@
-use crate::contract::CONTRACT_NAME;
+use crate::{contract::CONTRACT_NAME, market::DEFAULT_MARKET_ID};
 
 use cosmwasm_std::{coin, Addr, Decimal, Uint128};
-use margined_protocol::power::{ExecuteMsg, QueryMsg, StateResponse, VaultResponse};
+use margined_protocol::power::{ExecuteMsg, QueryMsg, StateResponse, SwapHop, VaultResponse};
 use margined_testing::{helpers::parse_event_attribute, power_env::PowerEnv};
 use osmosis_test_tube::{
     osmosis_std::types::{
@@ -35,7 +35,7 @@ fn test_close_short() {
     {
         wasm.execute(
             &perp_address,
-            &ExecuteMsg::ApplyFunding {},
+            &ExecuteMsg::ApplyFunding { market_id: None },
             &[],
             &env.signer,
         )
@@ -67,7 +67,9 @@ fn test_close_short() {
 
         env.app.increase_time(10u64);
 
-        let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
 
         let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
 
@@ -101,6 +103,10 @@ fn test_close_short() {
                 &ExecuteMsg::OpenShort {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
+                    min_base_out: None,
+                    deadline: None,
+                    market_id: None,
+                    route: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -156,6 +162,9 @@ fn test_close_short() {
                 amount_to_burn: Uint128::from(VAULT_MINT_AMOUNT),
                 amount_to_withdraw: Some(VAULT_COLLATERAL.into()),
                 vault_id,
+                max_base_in: None,
+                deadline: None,
+                route: None,
             },
             &[coin(amount_to_swap, env.denoms["base"].clone())],
             &env.traders[1],
@@ -170,6 +179,7 @@ fn test_close_short() {
     assert_eq!(
         vault,
         VaultResponse {
+            market_id: DEFAULT_MARKET_ID,
             operator: Addr::unchecked(env.traders[1].address()),
             collateral: Uint128::zero(),
             short_amount: Uint128::from(1u128), // dust is left over
@@ -205,7 +215,7 @@ fn test_close_short_no_withdrawal() {
     {
         wasm.execute(
             &perp_address,
-            &ExecuteMsg::ApplyFunding {},
+            &ExecuteMsg::ApplyFunding { market_id: None },
             &[],
             &env.signer,
         )
@@ -237,7 +247,9 @@ fn test_close_short_no_withdrawal() {
 
         env.app.increase_time(10u64);
 
-        let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
 
         let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
 
@@ -271,6 +283,10 @@ fn test_close_short_no_withdrawal() {
                 &ExecuteMsg::OpenShort {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
+                    min_base_out: None,
+                    deadline: None,
+                    market_id: None,
+                    route: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -326,6 +342,9 @@ fn test_close_short_no_withdrawal() {
                 amount_to_burn: Uint128::from(VAULT_MINT_AMOUNT),
                 amount_to_withdraw: None,
                 vault_id,
+                max_base_in: None,
+                deadline: None,
+                route: None,
             },
             &[coin(amount_to_swap, env.denoms["base"].clone())],
             &env.traders[1],
@@ -340,6 +359,7 @@ fn test_close_short_no_withdrawal() {
     assert_eq!(
         vault,
         VaultResponse {
+            market_id: DEFAULT_MARKET_ID,
             operator: Addr::unchecked(env.traders[1].address()),
             collateral: Uint128::from(VAULT_COLLATERAL),
             short_amount: Uint128::from(1u128), // dust is left over
@@ -378,7 +398,7 @@ fn test_close_short_additional_funds_sent() {
     {
         wasm.execute(
             &perp_address,
-            &ExecuteMsg::ApplyFunding {},
+            &ExecuteMsg::ApplyFunding { market_id: None },
             &[],
             &env.signer,
         )
@@ -410,7 +430,9 @@ fn test_close_short_additional_funds_sent() {
 
         env.app.increase_time(10u64);
 
-        let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
 
         let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
 
@@ -444,6 +466,10 @@ fn test_close_short_additional_funds_sent() {
                 &ExecuteMsg::OpenShort {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
+                    min_base_out: None,
+                    deadline: None,
+                    market_id: None,
+                    route: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -499,6 +525,9 @@ fn test_close_short_additional_funds_sent() {
                 amount_to_burn: Uint128::from(VAULT_MINT_AMOUNT),
                 amount_to_withdraw: Some(VAULT_COLLATERAL.into()),
                 vault_id,
+                max_base_in: None,
+                deadline: None,
+                route: None,
             },
             &[coin(
                 amount_to_swap + 1_000_000u128,
@@ -516,6 +545,7 @@ fn test_close_short_additional_funds_sent() {
     assert_eq!(
         vault,
         VaultResponse {
+            market_id: DEFAULT_MARKET_ID,
             operator: Addr::unchecked(env.traders[1].address()),
             collateral: Uint128::zero(),
             short_amount: Uint128::from(1u128), // dust is left over
@@ -545,7 +575,7 @@ fn test_fail_close_short_insufficient_funds() {
     {
         wasm.execute(
             &perp_address,
-            &ExecuteMsg::ApplyFunding {},
+            &ExecuteMsg::ApplyFunding { market_id: None },
             &[],
             &env.signer,
         )
@@ -577,7 +607,9 @@ fn test_fail_close_short_insufficient_funds() {
 
         env.app.increase_time(10u64);
 
-        let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
 
         let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
 
@@ -610,6 +642,10 @@ fn test_fail_close_short_insufficient_funds() {
                 &ExecuteMsg::OpenShort {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
+                    min_base_out: None,
+                    deadline: None,
+                    market_id: None,
+                    route: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -661,6 +697,9 @@ fn test_fail_close_short_insufficient_funds() {
                     amount_to_burn: Uint128::from(VAULT_MINT_AMOUNT),
                     amount_to_withdraw: Some(VAULT_COLLATERAL.into()),
                     vault_id,
+                    max_base_in: None,
+                    deadline: None,
+                    route: None,
                 },
                 &[coin(amount_to_swap - 1u128, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -688,7 +727,7 @@ fn test_fail_close_short_burn_greater_than_vault() {
     {
         wasm.execute(
             &perp_address,
-            &ExecuteMsg::ApplyFunding {},
+            &ExecuteMsg::ApplyFunding { market_id: None },
             &[],
             &env.signer,
         )
@@ -720,7 +759,9 @@ fn test_fail_close_short_burn_greater_than_vault() {
 
         env.app.increase_time(10u64);
 
-        let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
 
         let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
 
@@ -752,6 +793,10 @@ fn test_fail_close_short_burn_greater_than_vault() {
                 &ExecuteMsg::OpenShort {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
+                    min_base_out: None,
+                    deadline: None,
+                    market_id: None,
+                    route: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -793,6 +838,9 @@ fn test_fail_close_short_burn_greater_than_vault() {
                     amount_to_burn: Uint128::from(VAULT_MINT_AMOUNT) + Uint128::one(),
                     amount_to_withdraw: None,
                     vault_id,
+                    max_base_in: None,
+                    deadline: None,
+                    route: None,
                 },
                 &[coin(amount_to_swap, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -820,7 +868,7 @@ fn test_fail_close_short_withdraw_greater_than_vault() {
     {
         wasm.execute(
             &perp_address,
-            &ExecuteMsg::ApplyFunding {},
+            &ExecuteMsg::ApplyFunding { market_id: None },
             &[],
             &env.signer,
         )
@@ -852,7 +900,9 @@ fn test_fail_close_short_withdraw_greater_than_vault() {
 
         env.app.increase_time(10u64);
 
-        let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
 
         let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
 
@@ -884,6 +934,10 @@ fn test_fail_close_short_withdraw_greater_than_vault() {
                 &ExecuteMsg::OpenShort {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
+                    min_base_out: None,
+                    deadline: None,
+                    market_id: None,
+                    route: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -925,6 +979,9 @@ fn test_fail_close_short_withdraw_greater_than_vault() {
                     amount_to_burn: Uint128::from(VAULT_MINT_AMOUNT),
                     amount_to_withdraw: Some(Uint128::from(VAULT_COLLATERAL) + Uint128::one()),
                     vault_id,
+                    max_base_in: None,
+                    deadline: None,
+                    route: None,
                 },
                 &[coin(amount_to_swap, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -951,7 +1008,7 @@ fn test_fail_close_short_incorrect_sender() {
     {
         wasm.execute(
             &perp_address,
-            &ExecuteMsg::ApplyFunding {},
+            &ExecuteMsg::ApplyFunding { market_id: None },
             &[],
             &env.signer,
         )
@@ -983,7 +1040,9 @@ fn test_fail_close_short_incorrect_sender() {
 
         env.app.increase_time(10u64);
 
-        let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
 
         let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
 
@@ -1016,6 +1075,10 @@ fn test_fail_close_short_incorrect_sender() {
                 &ExecuteMsg::OpenShort {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
+                    min_base_out: None,
+                    deadline: None,
+                    market_id: None,
+                    route: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -1051,6 +1114,9 @@ fn test_fail_close_short_incorrect_sender() {
                     amount_to_burn: Uint128::from(VAULT_MINT_AMOUNT),
                     amount_to_withdraw: Some(VAULT_COLLATERAL.into()),
                     vault_id,
+                    max_base_in: None,
+                    deadline: None,
+                    route: None,
                 },
                 &[coin(power_exposure.u128(), env.denoms["base"].clone())],
                 &env.traders[0],
@@ -1077,7 +1143,7 @@ fn test_fail_close_short_incorrect_funds() {
     {
         wasm.execute(
             &perp_address,
-            &ExecuteMsg::ApplyFunding {},
+            &ExecuteMsg::ApplyFunding { market_id: None },
             &[],
             &env.signer,
         )
@@ -1109,7 +1175,9 @@ fn test_fail_close_short_incorrect_funds() {
 
         env.app.increase_time(10u64);
 
-        let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
 
         let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
 
@@ -1142,6 +1210,10 @@ fn test_fail_close_short_incorrect_funds() {
                 &ExecuteMsg::OpenShort {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
+                    min_base_out: None,
+                    deadline: None,
+                    market_id: None,
+                    route: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -1177,6 +1249,9 @@ fn test_fail_close_short_incorrect_funds() {
                     amount_to_burn: Uint128::from(VAULT_MINT_AMOUNT),
                     amount_to_withdraw: Some(VAULT_COLLATERAL.into()),
                     vault_id,
+                    max_base_in: None,
+                    deadline: None,
+                    route: None,
                 },
                 &[coin(power_exposure.u128(), env.denoms["gas"].clone())],
                 &env.traders[1],
@@ -1189,3 +1264,242 @@ fn test_fail_close_short_incorrect_funds() {
         });
     }
 }
+
+#[test]
+fn test_open_and_close_short_with_route() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let concentrated_liquidity = ConcentratedLiquidity::new(&env.app);
+    let pool_manager = PoolManager::new(&env.app);
+
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), false);
+
+    // apply funding
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::ApplyFunding { market_id: None },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+    }
+
+    // owner closes position and makes a much liquid one
+    {
+        let res = concentrated_liquidity
+            .query_user_positions(&CLTypes::UserPositionsRequest {
+                pool_id: env.power_pool_id,
+                address: env.owner.address(),
+                pagination: None,
+            })
+            .unwrap();
+
+        let position = res.positions[0].clone().position.unwrap();
+
+        concentrated_liquidity
+            .withdraw_position(
+                CLTypes::MsgWithdrawPosition {
+                    position_id: position.position_id,
+                    sender: env.owner.address(),
+                    liquidity_amount: position.liquidity,
+                },
+                &env.owner,
+            )
+            .unwrap();
+
+        env.app.increase_time(10u64);
+
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
+
+        let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
+
+        let target_price = Decimal::one().checked_div(target_price_power).unwrap();
+
+        let lower_tick = env.price_to_tick(target_price * Decimal::percent(90), 100u128.into());
+        let upper_tick = env.price_to_tick(target_price * Decimal::percent(110), 100u128.into());
+
+        env.create_position(
+            lower_tick,
+            upper_tick,
+            "3_000_000_000".to_string(),
+            "1_000_000_000".to_string(),
+        )
+    }
+
+    // we increase time else the functions get unhappy
+    env.app.increase_time(200000u64);
+
+    let power_to_base = SwapHop {
+        pool_id: env.power_pool_id,
+        token_in_denom: env.denoms["power"].clone(),
+        token_out_denom: env.denoms["base"].clone(),
+    };
+    let base_to_quote = SwapHop {
+        pool_id: env.base_pool_id,
+        token_in_denom: env.denoms["base"].clone(),
+        token_out_denom: env.denoms["quote"].clone(),
+    };
+    let quote_to_base = SwapHop {
+        pool_id: env.base_pool_id,
+        token_in_denom: env.denoms["quote"].clone(),
+        token_out_denom: env.denoms["base"].clone(),
+    };
+    let base_to_power = SwapHop {
+        pool_id: env.power_pool_id,
+        token_in_denom: env.denoms["base"].clone(),
+        token_out_denom: env.denoms["power"].clone(),
+    };
+
+    // should revert if the route does not start by selling power
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::OpenShort {
+                    amount: Uint128::from(VAULT_MINT_AMOUNT),
+                    vault_id: None,
+                    min_base_out: None,
+                    deadline: None,
+                    market_id: None,
+                    route: Some(vec![base_to_quote.clone()]),
+                },
+                &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
+                &env.traders[1],
+            )
+            .unwrap_err();
+
+        assert_eq!(err,
+            RunnerError::ExecuteError {
+            msg: "failed to execute message; message index: 0: Invalid swap route: must start by selling power: execute wasm contract failed".to_string()
+        });
+    }
+
+    // should revert if the hops are not contiguous
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::OpenShort {
+                amount: Uint128::from(VAULT_MINT_AMOUNT),
+                vault_id: None,
+                min_base_out: None,
+                deadline: None,
+                market_id: None,
+                route: Some(vec![power_to_base.clone(), quote_to_base.clone()]),
+            },
+            &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
+            &env.traders[1],
+        )
+        .unwrap_err();
+    }
+
+    let vault_id: u64;
+
+    // should pay the proceeds of the short in quote
+    {
+        let base_balance_before =
+            env.get_balance(env.traders[1].address(), env.denoms["base"].clone());
+        let quote_balance_before =
+            env.get_balance(env.traders[1].address(), env.denoms["quote"].clone());
+
+        let open_short_response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::OpenShort {
+                    amount: Uint128::from(VAULT_MINT_AMOUNT),
+                    vault_id: None,
+                    min_base_out: None,
+                    deadline: None,
+                    market_id: None,
+                    route: Some(vec![power_to_base, base_to_quote]),
+                },
+                &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
+                &env.traders[1],
+            )
+            .unwrap();
+
+        vault_id = u64::from_str(&parse_event_attribute(
+            open_short_response.events,
+            "wasm-mint",
+            "vault_id",
+        ))
+        .unwrap();
+
+        let base_balance_after =
+            env.get_balance(env.traders[1].address(), env.denoms["base"].clone());
+        let quote_balance_after =
+            env.get_balance(env.traders[1].address(), env.denoms["quote"].clone());
+
+        assert_eq!(
+            base_balance_before - base_balance_after,
+            Uint128::from(VAULT_COLLATERAL)
+        );
+        assert!(quote_balance_after > quote_balance_before);
+    }
+
+    // should buy back the power with quote and refund the excess
+    {
+        env.app.increase_time(1u64);
+
+        let vault: VaultResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+
+        let base_in = pool_manager
+            .query_single_pool_swap_exact_amount_out(
+                &PMTypes::EstimateSinglePoolSwapExactAmountOutRequest {
+                    pool_id: env.power_pool_id,
+                    token_in_denom: env.denoms["base"].clone(),
+                    token_out: format!("{}{}", vault.short_amount, env.denoms["power"].clone()),
+                },
+            )
+            .unwrap()
+            .token_in_amount;
+
+        let quote_in = pool_manager
+            .query_single_pool_swap_exact_amount_out(
+                &PMTypes::EstimateSinglePoolSwapExactAmountOutRequest {
+                    pool_id: env.base_pool_id,
+                    token_in_denom: env.denoms["quote"].clone(),
+                    token_out: format!("{}{}", base_in, env.denoms["base"].clone()),
+                },
+            )
+            .unwrap()
+            .token_in_amount;
+
+        let quote_in = u128::from_str(&quote_in).unwrap();
+
+        let quote_balance_before =
+            env.get_balance(env.traders[1].address(), env.denoms["quote"].clone());
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::CloseShort {
+                amount_to_burn: Uint128::from(VAULT_MINT_AMOUNT),
+                amount_to_withdraw: Some(VAULT_COLLATERAL.into()),
+                vault_id,
+                max_base_in: None,
+                deadline: None,
+                route: Some(vec![quote_to_base, base_to_power]),
+            },
+            &[coin(quote_in + 1_000_000u128, env.denoms["quote"].clone())],
+            &env.traders[1],
+        )
+        .unwrap();
+
+        let quote_balance_after =
+            env.get_balance(env.traders[1].address(), env.denoms["quote"].clone());
+        assert_eq!(
+            quote_balance_before - quote_balance_after,
+            Uint128::from(quote_in)
+        );
+
+        let vault: VaultResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+        assert_eq!(vault.collateral, Uint128::zero());
+    }
+}
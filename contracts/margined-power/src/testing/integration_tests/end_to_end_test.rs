@@ -2,7 +2,8 @@ use crate::{contract::CONTRACT_NAME, vault::Vault};
 
 use cosmwasm_std::{coin, Addr, Decimal, Uint128};
 use margined_protocol::power::{
-    ConfigResponse, ExecuteMsg, Pool, QueryMsg, StateResponse, VaultResponse,
+    ConfigResponse, ExecuteMsg, FeeSchedule, Oracle, Pool, PriceType, QueryMsg, StateResponse,
+    UpdateConfig, VaultResponse,
 };
 use margined_testing::{helpers::parse_event_attribute, power_env::PowerEnv};
 use osmosis_test_tube::{
@@ -54,8 +55,11 @@ fn test_end_to_end_flow() {
         wasm.execute(
             &perp_address,
             &ExecuteMsg::UpdateConfig {
-                fee_rate: Some("0.01".to_string()),
-                fee_pool: None,
+                new_config: UpdateConfig {
+                    fee_rate: Some("0.01".to_string()),
+                    ..Default::default()
+                },
+                market_id: None,
             },
             &[],
             &env.signer,
@@ -67,7 +71,7 @@ fn test_end_to_end_flow() {
     {
         wasm.execute(
             &perp_address,
-            &ExecuteMsg::ApplyFunding {},
+            &ExecuteMsg::ApplyFunding { market_id: None },
             &[],
             &env.signer,
         )
@@ -99,7 +103,9 @@ fn test_end_to_end_flow() {
 
         env.app.increase_time(10u64);
 
-        let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
 
         let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
 
@@ -155,6 +161,7 @@ fn test_end_to_end_flow() {
                 amount: Uint128::from(SHORT_AMOUNT),
                 vault_id: None,
                 rebase: false,
+                market_id: None,
             },
             &[],
             &env.traders[0],
@@ -171,6 +178,7 @@ fn test_end_to_end_flow() {
                     amount: Uint128::from(SHORT_AMOUNT),
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(COLLATERAL_AMOUNT, env.denoms["base"].clone())],
                 &env.traders[0],
@@ -229,7 +237,10 @@ fn test_end_to_end_flow() {
     }
 
     let norm: Decimal = wasm
-        .query(&perp_address, &QueryMsg::GetNormalisationFactor {})
+        .query(
+            &perp_address,
+            &QueryMsg::GetNormalisationFactor { market_id: None },
+        )
         .unwrap();
     println!("norm: {:#?}", norm);
 
@@ -573,7 +584,9 @@ fn test_end_to_end_flow() {
 
     // Validate all queries work as anticipated
     {
-        let config: ConfigResponse = wasm.query(&perp_address, &QueryMsg::Config {}).unwrap();
+        let config: ConfigResponse = wasm
+            .query(&perp_address, &QueryMsg::Config { market_id: None })
+            .unwrap();
         assert_eq!(
             config,
             ConfigResponse {
@@ -593,6 +606,23 @@ fn test_end_to_end_flow() {
                 funding_period: 1512000u64,
                 base_decimals: 6u32,
                 power_decimals: 6u32,
+                min_liquidation_bonus: Decimal::percent(110),
+                max_liquidation_bonus: Decimal::percent(110),
+                liquidation_auction_duration: 600u64,
+                insurance_fee_share: Decimal::zero(),
+                collateral_ratio_numerator: Decimal::from_ratio(3u128, 1u128),
+                collateral_ratio_denominator: Decimal::from_ratio(2u128, 1u128),
+                min_collateral: Decimal::percent(50),
+                twap_period: 420u64,
+                max_twap_period: 172800u64,
+                funding_lower_bound: Decimal::percent(80),
+                funding_upper_bound: Decimal::percent(140),
+                oracle: Oracle::default(),
+                max_spot_deviation: Decimal::zero(),
+                power_price_type: PriceType::default(),
+                flash_mint_fee_rate: Decimal::zero(),
+                fee_schedule: FeeSchedule::default(),
+                fee_recipients: vec![],
             }
         );
     }
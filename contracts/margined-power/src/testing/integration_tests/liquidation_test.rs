@@ -54,6 +54,7 @@ fn test_liquidate_normal_vault_when_price_is_2x() {
                     amount: Uint128::from(VAULT_0_MINT_AMOUNT),
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(VAULT_0_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[0],
@@ -79,6 +80,7 @@ fn test_liquidate_normal_vault_when_price_is_2x() {
                     amount: Uint128::from(VAULT_1_MINT_AMOUNT),
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(VAULT_1_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -104,6 +106,7 @@ fn test_liquidate_normal_vault_when_price_is_2x() {
                     amount: Uint128::from(VAULT_2_MINT_AMOUNT),
                     vault_id: None,
                     rebase: true,
+                    market_id: None,
                 },
                 &[coin(VAULT_2_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -311,6 +314,7 @@ fn test_liquidate_normal_vault_when_price_is_2x() {
                 amount: mint_amount,
                 vault_id: None,
                 rebase: false,
+                market_id: None,
             },
             &[coin(collateral_required.into(), env.denoms["base"].clone())],
             &env.liquidator,
@@ -0,0 +1,385 @@
+use crate::{contract::CONTRACT_NAME, market::DEFAULT_MARKET_ID};
+
+use cosmwasm_std::{coin, Decimal, Timestamp, Uint128};
+use margined_protocol::power::{
+    ExecuteMsg, LiquidityPosition, QueryMsg, StateResponse, UserLiquidityPositionsResponse,
+    VaultResponse,
+};
+use margined_testing::{helpers::parse_event_attribute, power_env::PowerEnv};
+use osmosis_test_tube::{
+    osmosis_std::types::osmosis::concentratedliquidity::v1beta1 as CLTypes, Account,
+    ConcentratedLiquidity, Module, OsmosisTestApp, RunnerError, Wasm,
+};
+use std::str::FromStr;
+
+const VAULT_COLLATERAL: u128 = 910_000u128;
+const VAULT_MINT_AMOUNT: u128 = 2_000_000u128;
+const BASE_TO_PAIR: u128 = 1_000_000u128;
+
+fn setup_liquid_power_pool(env: &PowerEnv, wasm: &Wasm<OsmosisTestApp>) -> String {
+    let concentrated_liquidity = ConcentratedLiquidity::new(&env.app);
+
+    let (perp_address, _) = env.deploy_power(wasm, CONTRACT_NAME.to_string(), false);
+
+    wasm.execute(
+        &perp_address,
+        &ExecuteMsg::ApplyFunding { market_id: None },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    // owner closes position and makes a much liquid one
+    let res = concentrated_liquidity
+        .query_user_positions(&CLTypes::UserPositionsRequest {
+            pool_id: env.power_pool_id,
+            address: env.owner.address(),
+            pagination: None,
+        })
+        .unwrap();
+
+    let position = res.positions[0].clone().position.unwrap();
+
+    concentrated_liquidity
+        .withdraw_position(
+            CLTypes::MsgWithdrawPosition {
+                position_id: position.position_id,
+                sender: env.owner.address(),
+                liquidity_amount: position.liquidity,
+            },
+            &env.owner,
+        )
+        .unwrap();
+
+    env.app.increase_time(10u64);
+
+    let state: StateResponse = wasm
+        .query(&perp_address, &QueryMsg::State { market_id: None })
+        .unwrap();
+
+    let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
+
+    let target_price = Decimal::one().checked_div(target_price_power).unwrap();
+
+    let lower_tick = env.price_to_tick(target_price * Decimal::percent(90), 100u128.into());
+    let upper_tick = env.price_to_tick(target_price * Decimal::percent(110), 100u128.into());
+
+    env.create_position(
+        lower_tick,
+        upper_tick,
+        "3_000_000_000".to_string(),
+        "1_000_000_000".to_string(),
+    );
+
+    // we increase time else the functions get unhappy
+    env.app.increase_time(200000u64);
+
+    perp_address
+}
+
+#[test]
+fn test_open_long() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let perp_address = setup_liquid_power_pool(&env, &wasm);
+
+    // should revert if less than the minimum power is bought
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::OpenLong {
+                    min_power_out: Some(Uint128::from(1_000_000_000u128)),
+                    deadline: None,
+                    market_id: None,
+                },
+                &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
+                &env.traders[1],
+            )
+            .unwrap_err();
+        assert!(matches!(err, RunnerError::ExecuteError { .. }));
+    }
+
+    // should send the power bought to the sender
+    {
+        let base_balance_before =
+            env.get_balance(env.traders[1].address(), env.denoms["base"].clone());
+        let power_balance_before =
+            env.get_balance(env.traders[1].address(), env.denoms["power"].clone());
+
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::OpenLong {
+                    min_power_out: None,
+                    deadline: None,
+                    market_id: None,
+                },
+                &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
+                &env.traders[1],
+            )
+            .unwrap();
+
+        let power_out = Uint128::from_str(
+            &parse_event_attribute(response.events, "token_swapped", "tokens_out")
+                .replace(&env.denoms["power"], ""),
+        )
+        .unwrap();
+        assert!(!power_out.is_zero());
+
+        let base_balance_after =
+            env.get_balance(env.traders[1].address(), env.denoms["base"].clone());
+        let power_balance_after =
+            env.get_balance(env.traders[1].address(), env.denoms["power"].clone());
+
+        assert_eq!(
+            base_balance_before - base_balance_after,
+            Uint128::from(VAULT_COLLATERAL)
+        );
+        assert_eq!(power_balance_after - power_balance_before, power_out);
+    }
+}
+
+#[test]
+fn test_mint_and_provide_liquidity() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let perp_address = setup_liquid_power_pool(&env, &wasm);
+
+    // should revert if no base is left to pair with the power
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::MintAndProvideLiquidity {
+                    amount: Uint128::from(VAULT_MINT_AMOUNT),
+                    collateral_amount: Uint128::from(VAULT_COLLATERAL),
+                    vault_id: None,
+                    min_power_provided: None,
+                    min_base_provided: None,
+                    deadline: None,
+                    market_id: None,
+                },
+                &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
+                &env.traders[1],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Invalid funds: execute wasm contract failed".to_string()
+            }
+        );
+    }
+
+    // should revert once the deadline has passed
+    {
+        let deadline = Timestamp::from_seconds(env.app.get_block_time_seconds() as u64 - 1u64);
+
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::MintAndProvideLiquidity {
+                    amount: Uint128::from(VAULT_MINT_AMOUNT),
+                    collateral_amount: Uint128::from(VAULT_COLLATERAL),
+                    vault_id: None,
+                    min_power_provided: None,
+                    min_base_provided: None,
+                    deadline: Some(deadline),
+                    market_id: None,
+                },
+                &[coin(
+                    VAULT_COLLATERAL + BASE_TO_PAIR,
+                    env.denoms["base"].clone(),
+                )],
+                &env.traders[1],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Transaction deadline has passed: execute wasm contract failed".to_string()
+            }
+        );
+    }
+
+    // should revert if the position takes less than the minimum amounts
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::MintAndProvideLiquidity {
+                amount: Uint128::from(VAULT_MINT_AMOUNT),
+                collateral_amount: Uint128::from(VAULT_COLLATERAL),
+                vault_id: None,
+                min_power_provided: Some(Uint128::from(VAULT_MINT_AMOUNT)),
+                min_base_provided: Some(Uint128::from(BASE_TO_PAIR + 1)),
+                deadline: None,
+                market_id: None,
+            },
+            &[coin(
+                VAULT_COLLATERAL + BASE_TO_PAIR,
+                env.denoms["base"].clone(),
+            )],
+            &env.traders[1],
+        )
+        .unwrap_err();
+    }
+
+    let position_id: u64;
+
+    // should mint against the collateral and hold the position for the sender
+    {
+        let base_balance_before =
+            env.get_balance(env.traders[1].address(), env.denoms["base"].clone());
+        let contract_base_before =
+            env.get_balance(perp_address.clone(), env.denoms["base"].clone());
+        let contract_power_before =
+            env.get_balance(perp_address.clone(), env.denoms["power"].clone());
+
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::MintAndProvideLiquidity {
+                    amount: Uint128::from(VAULT_MINT_AMOUNT),
+                    collateral_amount: Uint128::from(VAULT_COLLATERAL),
+                    vault_id: None,
+                    min_power_provided: None,
+                    min_base_provided: None,
+                    deadline: None,
+                    market_id: None,
+                },
+                &[coin(
+                    VAULT_COLLATERAL + BASE_TO_PAIR,
+                    env.denoms["base"].clone(),
+                )],
+                &env.traders[1],
+            )
+            .unwrap();
+
+        position_id = u64::from_str(&parse_event_attribute(
+            response.events.clone(),
+            "wasm-provide_liquidity",
+            "position_id",
+        ))
+        .unwrap();
+
+        let vault_id = u64::from_str(&parse_event_attribute(
+            response.events,
+            "wasm-mint",
+            "vault_id",
+        ))
+        .unwrap();
+
+        let vault: VaultResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+        assert_eq!(vault.market_id, DEFAULT_MARKET_ID);
+        assert_eq!(vault.collateral, Uint128::from(VAULT_COLLATERAL));
+        assert_eq!(vault.short_amount, Uint128::from(VAULT_MINT_AMOUNT));
+
+        // the base not taken by the position is refunded
+        let base_balance_after =
+            env.get_balance(env.traders[1].address(), env.denoms["base"].clone());
+        let base_spent = base_balance_before - base_balance_after;
+        assert!(base_spent > Uint128::from(VAULT_COLLATERAL));
+        assert!(base_spent <= Uint128::from(VAULT_COLLATERAL + BASE_TO_PAIR));
+
+        // the contract keeps only the collateral
+        assert_eq!(
+            env.get_balance(perp_address.clone(), env.denoms["base"].clone())
+                - contract_base_before,
+            Uint128::from(VAULT_COLLATERAL)
+        );
+        assert_eq!(
+            env.get_balance(perp_address.clone(), env.denoms["power"].clone()),
+            contract_power_before
+        );
+
+        let positions: UserLiquidityPositionsResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetUserLiquidityPositions {
+                    user: env.traders[1].address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            positions.positions,
+            vec![LiquidityPosition {
+                position_id,
+                market_id: DEFAULT_MARKET_ID,
+            }]
+        );
+    }
+
+    // should revert if the sender does not own the position
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::RemoveLiquidity { position_id },
+                &[],
+                &env.traders[0],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Unauthorized: execute wasm contract failed".to_string()
+            }
+        );
+    }
+
+    // should withdraw the position to its owner
+    {
+        let base_balance_before =
+            env.get_balance(env.traders[1].address(), env.denoms["base"].clone());
+        let power_balance_before =
+            env.get_balance(env.traders[1].address(), env.denoms["power"].clone());
+        let contract_base_before =
+            env.get_balance(perp_address.clone(), env.denoms["base"].clone());
+        let contract_power_before =
+            env.get_balance(perp_address.clone(), env.denoms["power"].clone());
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::RemoveLiquidity { position_id },
+            &[],
+            &env.traders[1],
+        )
+        .unwrap();
+
+        let base_balance_after =
+            env.get_balance(env.traders[1].address(), env.denoms["base"].clone());
+        let power_balance_after =
+            env.get_balance(env.traders[1].address(), env.denoms["power"].clone());
+        assert!(base_balance_after > base_balance_before);
+        assert!(power_balance_after > power_balance_before);
+
+        // everything withdrawn is sent on to the owner
+        assert_eq!(
+            env.get_balance(perp_address.clone(), env.denoms["base"].clone()),
+            contract_base_before
+        );
+        assert_eq!(
+            env.get_balance(perp_address.clone(), env.denoms["power"].clone()),
+            contract_power_before
+        );
+
+        let positions: UserLiquidityPositionsResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetUserLiquidityPositions {
+                    user: env.traders[1].address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert!(positions.positions.is_empty());
+    }
+}
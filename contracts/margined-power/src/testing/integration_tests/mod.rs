@@ -1,5 +1,7 @@
 mod close_short_test;
 mod end_to_end_test;
 mod liquidation_test;
+mod long_test;
 mod open_short_test;
 mod oracle_attack_test;
+mod slippage_test;
@@ -1,8 +1,16 @@
-use crate::contract::CONTRACT_NAME;
+use crate::{contract::CONTRACT_NAME, market::DEFAULT_MARKET_ID};
 
-use cosmwasm_std::{coin, Addr, Decimal, Uint128};
-use margined_protocol::power::{ExecuteMsg, QueryMsg, StateResponse, VaultResponse};
-use margined_testing::{helpers::parse_event_attribute, power_env::PowerEnv};
+use cosmwasm_std::{coin, to_binary, Addr, CosmosMsg, Decimal, Uint128, WasmMsg};
+use margined_protocol::power::{
+    ExecuteMsg, QueryMsg, StateResponse, UserVaultsResponse, VaultResponse,
+};
+use margined_testing::{
+    helpers::{parse_event_attribute, store_code},
+    power_env::PowerEnv,
+};
+use mock_flash_receiver::contract::{
+    ExecuteMsg as FlashReceiverExecuteMsg, InstantiateMsg as FlashReceiverInstantiateMsg,
+};
 use osmosis_test_tube::{
     osmosis_std::types::{
         cosmos::bank::v1beta1::MsgSend, cosmos::base::v1beta1::Coin,
@@ -34,7 +42,7 @@ fn test_open_short() {
     {
         wasm.execute(
             &perp_address,
-            &ExecuteMsg::ApplyFunding {},
+            &ExecuteMsg::ApplyFunding { market_id: None },
             &[],
             &env.signer,
         )
@@ -66,7 +74,9 @@ fn test_open_short() {
 
         env.app.increase_time(10u64);
 
-        let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
 
         let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
 
@@ -96,6 +106,10 @@ fn test_open_short() {
                 &ExecuteMsg::OpenShort {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
+                    min_base_out: None,
+                    deadline: None,
+                    market_id: None,
+                    route: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -126,6 +140,7 @@ fn test_open_short() {
         assert_eq!(
             vault,
             VaultResponse {
+                market_id: DEFAULT_MARKET_ID,
                 operator: Addr::unchecked(env.traders[1].address()),
                 collateral: Uint128::from(VAULT_COLLATERAL),
                 short_amount: Uint128::from(VAULT_MINT_AMOUNT),
@@ -165,7 +180,7 @@ fn test_open_short_existing_vault() {
     {
         wasm.execute(
             &perp_address,
-            &ExecuteMsg::ApplyFunding {},
+            &ExecuteMsg::ApplyFunding { market_id: None },
             &[],
             &env.signer,
         )
@@ -197,7 +212,9 @@ fn test_open_short_existing_vault() {
 
         env.app.increase_time(10u64);
 
-        let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
 
         let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
 
@@ -229,6 +246,7 @@ fn test_open_short_existing_vault() {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -253,6 +271,10 @@ fn test_open_short_existing_vault() {
                 &ExecuteMsg::OpenShort {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: Some(vault_id),
+                    min_base_out: None,
+                    deadline: None,
+                    market_id: None,
+                    route: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -272,6 +294,7 @@ fn test_open_short_existing_vault() {
         assert_eq!(
             vault,
             VaultResponse {
+                market_id: DEFAULT_MARKET_ID,
                 operator: Addr::unchecked(env.traders[1].address()),
                 collateral: Uint128::from(VAULT_COLLATERAL * 2),
                 short_amount: Uint128::from(VAULT_MINT_AMOUNT * 2),
@@ -305,7 +328,7 @@ fn test_fail_open_short_existing_vault_incorrect_user() {
     {
         wasm.execute(
             &perp_address,
-            &ExecuteMsg::ApplyFunding {},
+            &ExecuteMsg::ApplyFunding { market_id: None },
             &[],
             &env.signer,
         )
@@ -337,7 +360,9 @@ fn test_fail_open_short_existing_vault_incorrect_user() {
 
         env.app.increase_time(10u64);
 
-        let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
 
         let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
 
@@ -369,6 +394,7 @@ fn test_fail_open_short_existing_vault_incorrect_user() {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -393,6 +419,10 @@ fn test_fail_open_short_existing_vault_incorrect_user() {
                 &ExecuteMsg::OpenShort {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: Some(vault_id),
+                    min_base_out: None,
+                    deadline: None,
+                    market_id: None,
+                    route: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[0],
@@ -438,7 +468,7 @@ fn test_fail_open_short_insufficient_funds() {
     {
         wasm.execute(
             &perp_address,
-            &ExecuteMsg::ApplyFunding {},
+            &ExecuteMsg::ApplyFunding { market_id: None },
             &[],
             &env.signer,
         )
@@ -470,7 +500,9 @@ fn test_fail_open_short_insufficient_funds() {
 
         env.app.increase_time(10u64);
 
-        let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
 
         let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
 
@@ -500,6 +532,10 @@ fn test_fail_open_short_insufficient_funds() {
                 &ExecuteMsg::OpenShort {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
+                    min_base_out: None,
+                    deadline: None,
+                    market_id: None,
+                    route: None,
                 },
                 &[coin(VAULT_COLLATERAL + 1u128, env.denoms["base"].clone())],
                 &trader,
@@ -525,7 +561,7 @@ fn test_fail_open_short_incorrect_funds() {
     {
         wasm.execute(
             &perp_address,
-            &ExecuteMsg::ApplyFunding {},
+            &ExecuteMsg::ApplyFunding { market_id: None },
             &[],
             &env.signer,
         )
@@ -557,7 +593,9 @@ fn test_fail_open_short_incorrect_funds() {
 
         env.app.increase_time(10u64);
 
-        let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
 
         let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
 
@@ -587,6 +625,10 @@ fn test_fail_open_short_incorrect_funds() {
                 &ExecuteMsg::OpenShort {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
+                    min_base_out: None,
+                    deadline: None,
+                    market_id: None,
+                    route: None,
                 },
                 &[coin(VAULT_COLLATERAL + 1u128, env.denoms["gas"].clone())],
                 &env.traders[0],
@@ -630,7 +672,7 @@ fn test_fail_open_short_insufficient_liquidity() {
     {
         wasm.execute(
             &perp_address,
-            &ExecuteMsg::ApplyFunding {},
+            &ExecuteMsg::ApplyFunding { market_id: None },
             &[],
             &env.signer,
         )
@@ -645,6 +687,10 @@ fn test_fail_open_short_insufficient_liquidity() {
                 &ExecuteMsg::OpenShort {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
+                    min_base_out: None,
+                    deadline: None,
+                    market_id: None,
+                    route: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &trader,
@@ -656,3 +702,168 @@ fn test_fail_open_short_insufficient_liquidity() {
         });
     }
 }
+
+#[test]
+fn test_open_short_twice_in_one_transaction() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let bank = Bank::new(&env.app);
+    let concentrated_liquidity = ConcentratedLiquidity::new(&env.app);
+
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), false);
+
+    // apply funding
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::ApplyFunding { market_id: None },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+    }
+
+    // owner closes position and makes a much liquid one
+    {
+        let res = concentrated_liquidity
+            .query_user_positions(&CLTypes::UserPositionsRequest {
+                pool_id: env.power_pool_id,
+                address: env.owner.address(),
+                pagination: None,
+            })
+            .unwrap();
+
+        let position = res.positions[0].clone().position.unwrap();
+
+        concentrated_liquidity
+            .withdraw_position(
+                CLTypes::MsgWithdrawPosition {
+                    position_id: position.position_id,
+                    sender: env.owner.address(),
+                    liquidity_amount: position.liquidity,
+                },
+                &env.owner,
+            )
+            .unwrap();
+
+        env.app.increase_time(10u64);
+
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
+
+        let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
+
+        let target_price = Decimal::one().checked_div(target_price_power).unwrap();
+
+        let lower_tick = env.price_to_tick(target_price * Decimal::percent(90), 100u128.into());
+        let upper_tick = env.price_to_tick(target_price * Decimal::percent(110), 100u128.into());
+
+        env.create_position(
+            lower_tick,
+            upper_tick,
+            "3_000_000_000".to_string(),
+            "1_000_000_000".to_string(),
+        );
+    }
+
+    // we increase time else the functions get unhappy
+    env.app.increase_time(200000u64);
+
+    // a contract opening two shorts in the same transaction
+    let code_id = store_code(&wasm, &env.signer, "mock_flash_receiver".to_string());
+    let receiver_address = wasm
+        .instantiate(
+            code_id,
+            &FlashReceiverInstantiateMsg {},
+            None,
+            Some("mock-flash-receiver"),
+            &[],
+            &env.signer,
+        )
+        .unwrap()
+        .data
+        .address;
+
+    bank.send(
+        MsgSend {
+            from_address: env.traders[1].address(),
+            to_address: receiver_address.clone(),
+            amount: vec![Coin {
+                amount: (2 * VAULT_COLLATERAL).to_string(),
+                denom: env.denoms["base"].clone(),
+            }],
+        },
+        &env.traders[1],
+    )
+    .unwrap();
+
+    let open_short: CosmosMsg = WasmMsg::Execute {
+        contract_addr: perp_address.clone(),
+        msg: to_binary(&ExecuteMsg::OpenShort {
+            amount: Uint128::from(VAULT_MINT_AMOUNT),
+            vault_id: None,
+            min_base_out: None,
+            deadline: None,
+            market_id: None,
+            route: None,
+        })
+        .unwrap(),
+        funds: vec![coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
+    }
+    .into();
+
+    // each short should sell its own mint and pay the proceeds to the sender
+    {
+        let response = wasm
+            .execute(
+                &receiver_address,
+                &FlashReceiverExecuteMsg::Execute {
+                    msgs: vec![open_short.clone(), open_short],
+                },
+                &[],
+                &env.traders[1],
+            )
+            .unwrap();
+
+        let swaps: Vec<Uint128> = response
+            .events
+            .iter()
+            .filter(|event| event.ty == "token_swapped")
+            .map(|event| {
+                let tokens_out = event
+                    .attributes
+                    .iter()
+                    .find(|attribute| attribute.key == "tokens_out")
+                    .unwrap();
+
+                Uint128::from_str(&tokens_out.value.replace(&env.denoms["base"], "")).unwrap()
+            })
+            .collect();
+        assert_eq!(swaps.len(), 2);
+
+        let receiver_base_balance =
+            env.get_balance(receiver_address.clone(), env.denoms["base"].clone());
+        assert_eq!(receiver_base_balance, swaps[0] + swaps[1]);
+
+        let user_vaults: UserVaultsResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetUserVaults {
+                    user: receiver_address.clone(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(user_vaults.vaults.len(), 2);
+
+        for vault_id in user_vaults.vaults {
+            let vault: VaultResponse = wasm
+                .query(&perp_address, &QueryMsg::GetVault { vault_id })
+                .unwrap();
+            assert_eq!(vault.short_amount, Uint128::from(VAULT_MINT_AMOUNT));
+        }
+    }
+}
@@ -38,6 +38,7 @@ fn test_scenario_base_price_spikes_100_percent() {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[0],
@@ -100,7 +101,13 @@ fn test_scenario_base_price_spikes_100_percent() {
         // index price is updated if requesting with period 1
         {
             let new_index_price: Decimal = wasm
-                .query(&perp_address, &QueryMsg::GetUnscaledIndex { period: 1 })
+                .query(
+                    &perp_address,
+                    &QueryMsg::GetUnscaledIndex {
+                        period: 1,
+                        market_id: None,
+                    },
+                )
                 .unwrap();
 
             assert_eq!(
@@ -125,6 +132,7 @@ fn test_scenario_base_price_spikes_100_percent() {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -140,6 +148,7 @@ fn test_scenario_base_price_spikes_100_percent() {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(MIN_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -156,7 +165,13 @@ fn test_scenario_base_price_spikes_100_percent() {
         // index price is updated if requesting with period 1
         {
             let new_index_price: Decimal = wasm
-                .query(&perp_address, &QueryMsg::GetUnscaledIndex { period: 180 })
+                .query(
+                    &perp_address,
+                    &QueryMsg::GetUnscaledIndex {
+                        period: 180,
+                        market_id: None,
+                    },
+                )
                 .unwrap();
 
             assert_eq!(
@@ -181,6 +196,7 @@ fn test_scenario_base_price_spikes_100_percent() {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -210,6 +226,7 @@ fn test_scenario_base_price_crashes_50_percent() {
                     amount: Uint128::from(VAULT_MINT_AMOUNT),
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[0],
@@ -272,7 +289,13 @@ fn test_scenario_base_price_crashes_50_percent() {
         // index price is updated if requesting with period 1
         {
             let new_index_price: Decimal = wasm
-                .query(&perp_address, &QueryMsg::GetUnscaledIndex { period: 1 })
+                .query(
+                    &perp_address,
+                    &QueryMsg::GetUnscaledIndex {
+                        period: 1,
+                        market_id: None,
+                    },
+                )
                 .unwrap();
 
             assert_eq!(
@@ -303,6 +326,7 @@ fn test_scenario_base_price_crashes_50_percent() {
                     amount: attack_mint_amount,
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -319,7 +343,13 @@ fn test_scenario_base_price_crashes_50_percent() {
         // index price is updated if requesting with period 60
         {
             let new_index_price: Decimal = wasm
-                .query(&perp_address, &QueryMsg::GetUnscaledIndex { period: 60 })
+                .query(
+                    &perp_address,
+                    &QueryMsg::GetUnscaledIndex {
+                        period: 60,
+                        market_id: None,
+                    },
+                )
                 .unwrap();
 
             assert_eq!(
@@ -342,6 +372,7 @@ fn test_scenario_base_price_crashes_50_percent() {
                     amount: attack_super_high_mint_amount,
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
                 &env.traders[1],
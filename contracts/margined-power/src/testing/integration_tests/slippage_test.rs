@@ -0,0 +1,235 @@
+use crate::contract::CONTRACT_NAME;
+
+use cosmwasm_std::{coin, Decimal, Timestamp, Uint128};
+use margined_protocol::power::{ExecuteMsg, QueryMsg, StateResponse};
+use margined_testing::{helpers::parse_event_attribute, power_env::PowerEnv};
+use osmosis_test_tube::{
+    osmosis_std::types::osmosis::concentratedliquidity::v1beta1 as CLTypes, Account,
+    ConcentratedLiquidity, Module, RunnerError, Wasm,
+};
+use std::str::FromStr;
+
+const VAULT_COLLATERAL: u128 = 910_000u128;
+const VAULT_MINT_AMOUNT: u128 = 2_000_000u128;
+
+#[test]
+fn test_open_and_close_short_slippage() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let concentrated_liquidity = ConcentratedLiquidity::new(&env.app);
+
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), false);
+
+    // apply funding
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::ApplyFunding { market_id: None },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+    }
+
+    // owner closes position and makes a much liquid one
+    {
+        let res = concentrated_liquidity
+            .query_user_positions(&CLTypes::UserPositionsRequest {
+                pool_id: env.power_pool_id,
+                address: env.owner.address(),
+                pagination: None,
+            })
+            .unwrap();
+
+        let position = res.positions[0].clone().position.unwrap();
+
+        concentrated_liquidity
+            .withdraw_position(
+                CLTypes::MsgWithdrawPosition {
+                    position_id: position.position_id,
+                    sender: env.owner.address(),
+                    liquidity_amount: position.liquidity,
+                },
+                &env.owner,
+            )
+            .unwrap();
+
+        env.app.increase_time(10u64);
+
+        let state: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
+
+        let target_price_power = env.calculate_target_power_price(state.normalisation_factor);
+
+        let target_price = Decimal::one().checked_div(target_price_power).unwrap();
+
+        let lower_tick = env.price_to_tick(target_price * Decimal::percent(90), 100u128.into());
+        let upper_tick = env.price_to_tick(target_price * Decimal::percent(110), 100u128.into());
+
+        env.create_position(
+            lower_tick,
+            upper_tick,
+            "3_000_000_000".to_string(),
+            "1_000_000_000".to_string(),
+        );
+    }
+
+    // we increase time else the functions get unhappy
+    env.app.increase_time(200000u64);
+
+    // open short fails once the deadline has passed
+    {
+        let deadline = Timestamp::from_seconds(env.app.get_block_time_seconds() as u64 - 1u64);
+
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::OpenShort {
+                    amount: Uint128::from(VAULT_MINT_AMOUNT),
+                    vault_id: None,
+                    min_base_out: None,
+                    deadline: Some(deadline),
+                    market_id: None,
+                    route: None,
+                },
+                &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
+                &env.traders[1],
+            )
+            .unwrap_err();
+        assert_eq!(err,
+            RunnerError::ExecuteError {
+            msg: "failed to execute message; message index: 0: Transaction deadline has passed: execute wasm contract failed".to_string()
+        });
+    }
+
+    // open short fails when the swap returns less than the minimum
+    {
+        let res = wasm.execute(
+            &perp_address,
+            &ExecuteMsg::OpenShort {
+                amount: Uint128::from(VAULT_MINT_AMOUNT),
+                vault_id: None,
+                min_base_out: Some(Uint128::from(VAULT_MINT_AMOUNT)),
+                deadline: None,
+                market_id: None,
+                route: None,
+            },
+            &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
+            &env.traders[1],
+        );
+        assert!(res.is_err());
+    }
+
+    // open short succeeds within the bounds
+    let vault_id: u64;
+    {
+        let deadline = Timestamp::from_seconds(env.app.get_block_time_seconds() as u64 + 60u64);
+
+        let open_short_response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::OpenShort {
+                    amount: Uint128::from(VAULT_MINT_AMOUNT),
+                    vault_id: None,
+                    min_base_out: Some(Uint128::one()),
+                    deadline: Some(deadline),
+                    market_id: None,
+                    route: None,
+                },
+                &[coin(VAULT_COLLATERAL, env.denoms["base"].clone())],
+                &env.traders[1],
+            )
+            .unwrap();
+
+        vault_id = u64::from_str(&parse_event_attribute(
+            open_short_response.events,
+            "wasm-mint",
+            "vault_id",
+        ))
+        .unwrap();
+    }
+
+    env.app.increase_time(1u64);
+
+    let amount_to_swap = 1_000_000u128;
+
+    // close short fails when the maximum input is greater than the funds sent
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::CloseShort {
+                    amount_to_burn: Uint128::from(VAULT_MINT_AMOUNT),
+                    amount_to_withdraw: Some(VAULT_COLLATERAL.into()),
+                    vault_id,
+                    max_base_in: Some(Uint128::from(amount_to_swap + 1u128)),
+                    deadline: None,
+                    route: None,
+                },
+                &[coin(amount_to_swap, env.denoms["base"].clone())],
+                &env.traders[1],
+            )
+            .unwrap_err();
+        assert_eq!(err,
+            RunnerError::ExecuteError {
+            msg: "failed to execute message; message index: 0: Invalid funds: execute wasm contract failed".to_string()
+        });
+    }
+
+    // close short fails when the swap requires more than the maximum input
+    {
+        let res = wasm.execute(
+            &perp_address,
+            &ExecuteMsg::CloseShort {
+                amount_to_burn: Uint128::from(VAULT_MINT_AMOUNT),
+                amount_to_withdraw: Some(VAULT_COLLATERAL.into()),
+                vault_id,
+                max_base_in: Some(Uint128::one()),
+                deadline: None,
+                route: None,
+            },
+            &[coin(amount_to_swap, env.denoms["base"].clone())],
+            &env.traders[1],
+        );
+        assert!(res.is_err());
+    }
+
+    // close short succeeds within the bounds and refunds unused base
+    {
+        let trader_base_balance_start: Uint128 =
+            env.get_balance(env.traders[1].address(), env.denoms["base"].clone());
+
+        let close_short_response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::CloseShort {
+                    amount_to_burn: Uint128::from(VAULT_MINT_AMOUNT),
+                    amount_to_withdraw: Some(VAULT_COLLATERAL.into()),
+                    vault_id,
+                    max_base_in: Some(Uint128::from(amount_to_swap)),
+                    deadline: None,
+                    route: None,
+                },
+                &[coin(amount_to_swap, env.denoms["base"].clone())],
+                &env.traders[1],
+            )
+            .unwrap();
+
+        let base_spent = Uint128::from_str(
+            &parse_event_attribute(close_short_response.events, "token_swapped", "tokens_in")
+                .replace(&env.denoms["base"], ""),
+        )
+        .unwrap();
+
+        let trader_base_balance_end: Uint128 =
+            env.get_balance(env.traders[1].address(), env.denoms["base"].clone());
+
+        assert!(base_spent <= Uint128::from(amount_to_swap));
+        assert_eq!(
+            trader_base_balance_end,
+            trader_base_balance_start + Uint128::from(VAULT_COLLATERAL) - base_spent
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{
+    contract::{execute, migrate},
+    market::{DEFAULT_MARKET_ID, MARKETS_COUNTER},
+    migration::{
+        LegacyConfig, LegacyState, LegacyVault, LEGACY_CONFIG, LEGACY_STATE, LEGACY_VAULTS,
+    },
+    nft::{get_vault_nft_owner, vault_nft},
+    state::{CONFIG, LIQUIDATION_BOUNTY, OWNER, STATE},
+    testing::test_utils::{MOCK_FEE_POOL_ADDR, MOCK_QUERY_ADDR},
+    vault::{check_vault_owner, VAULTS},
+};
+
+use cosmwasm_std::{
+    testing::{mock_dependencies, mock_env, mock_info},
+    Addr, Attribute, Decimal, DepsMut, Order, StdResult, Timestamp, Uint128,
+};
+use margined_common::errors::ContractError;
+use margined_protocol::power::{ExecuteMsg, MigrateMsg, Pool, FUNDING_PERIOD};
+use std::str::FromStr;
+
+#[test]
+fn test_migrate_legacy_market() {
+    let mut deps = mock_dependencies();
+
+    let owner = Addr::unchecked("owner");
+
+    LEGACY_CONFIG
+        .save(
+            deps.as_mut().storage,
+            &LegacyConfig {
+                query_contract: Addr::unchecked(MOCK_QUERY_ADDR),
+                fee_pool_contract: Addr::unchecked(MOCK_FEE_POOL_ADDR),
+                fee_rate: Decimal::from_str("0.1").unwrap(),
+                power_denom: "power".to_string(),
+                base_denom: "base".to_string(),
+                base_pool: Pool {
+                    id: 1,
+                    quote_denom: "quote".to_string(),
+                },
+                power_pool: Pool {
+                    id: 2,
+                    quote_denom: "power".to_string(),
+                },
+                funding_period: FUNDING_PERIOD,
+                base_decimals: 6,
+                power_decimals: 6,
+            },
+        )
+        .unwrap();
+    LEGACY_STATE
+        .save(
+            deps.as_mut().storage,
+            &LegacyState {
+                is_open: true,
+                is_paused: false,
+                last_pause: Timestamp::from_seconds(100),
+                normalisation_factor: Decimal::from_str("0.9").unwrap(),
+                last_funding_update: Timestamp::from_seconds(200),
+            },
+        )
+        .unwrap();
+    for vault_id in 1..=2u64 {
+        LEGACY_VAULTS
+            .save(
+                deps.as_mut().storage,
+                &vault_id,
+                &LegacyVault {
+                    operator: owner.clone(),
+                    collateral: Uint128::from(vault_id * 1_000u64),
+                    short_amount: Uint128::from(vault_id * 100u64),
+                },
+            )
+            .unwrap();
+    }
+
+    let response = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert!(response
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "migrating_vaults" && attr.value == "true"));
+
+    // the single market is now the default market
+    assert!(LEGACY_CONFIG.may_load(&deps.storage).unwrap().is_none());
+    assert_eq!(
+        MARKETS_COUNTER.load(&deps.storage).unwrap(),
+        DEFAULT_MARKET_ID
+    );
+
+    let config = CONFIG.load(&deps.storage, DEFAULT_MARKET_ID).unwrap();
+    assert_eq!(config.power_denom, "power".to_string());
+    assert_eq!(config.fee_rate, Decimal::from_str("0.1").unwrap());
+    assert_eq!(config.min_liquidation_bonus, LIQUIDATION_BOUNTY);
+    assert!(config.fee_recipients.is_empty());
+
+    // the market is paused until the vaults are migrated
+    let state = STATE.load(&deps.storage, DEFAULT_MARKET_ID).unwrap();
+    assert!(state.is_open);
+    assert!(state.is_paused);
+    assert_eq!(
+        state.normalisation_factor,
+        Decimal::from_str("0.9").unwrap()
+    );
+    assert!(!state.is_shutdown);
+    assert!(state.insurance_balance.is_zero());
+
+    OWNER
+        .set(deps.as_mut(), Some(Addr::unchecked("admin")))
+        .unwrap();
+
+    let migrate_vaults = |deps: DepsMut, sender: &str| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info(sender, &[]),
+            ExecuteMsg::MigrateVaults { limit: Some(1u32) },
+        )
+    };
+
+    // should revert if the sender is not the owner
+    {
+        let err = migrate_vaults(deps.as_mut(), "other").unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    // should keep the market paused while vaults remain
+    {
+        let response = migrate_vaults(deps.as_mut(), "admin").unwrap();
+        assert_eq!(
+            response.events[0].attributes,
+            vec![
+                Attribute::new("migrated_vaults", "1"),
+                Attribute::new("is_complete", "false"),
+            ]
+        );
+        assert!(LEGACY_VAULTS.load(&deps.storage, &2u64).is_ok());
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::UnPause { market_id: None },
+        )
+        .unwrap_err();
+    }
+
+    // should complete once the last vault is migrated
+    {
+        let response = migrate_vaults(deps.as_mut(), "admin").unwrap();
+        assert_eq!(
+            response.events[0].attributes,
+            vec![
+                Attribute::new("migrated_vaults", "1"),
+                Attribute::new("is_complete", "true"),
+            ]
+        );
+
+        migrate_vaults(deps.as_mut(), "admin").unwrap_err();
+    }
+
+    // vaults are kept in the default market and listed for their owner
+    let vault = VAULTS.load(&deps.storage, &2u64).unwrap();
+    assert_eq!(vault.market_id, DEFAULT_MARKET_ID);
+    assert_eq!(vault.operator, owner);
+    assert_eq!(vault.collateral, Uint128::from(2_000u128));
+    assert_eq!(vault.short_amount, Uint128::from(200u128));
+
+    let owner_vaults = VAULTS
+        .idx
+        .owner
+        .prefix(owner.clone())
+        .keys(&deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()
+        .unwrap();
+    assert_eq!(owner_vaults, vec![1u64, 2u64]);
+
+    // each vault is represented by a token held by its owner
+    assert_eq!(vault_nft().token_count(&deps.storage).unwrap(), 2u64);
+    for vault_id in 1..=2u64 {
+        assert_eq!(get_vault_nft_owner(&deps.storage, vault_id).unwrap(), owner);
+        check_vault_owner(&deps.storage, vault_id, &owner).unwrap();
+    }
+    check_vault_owner(&deps.storage, 1u64, &Addr::unchecked("other")).unwrap_err();
+
+    // the owner unpauses the market once the vaults are migrated
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin", &[]),
+        ExecuteMsg::UnPause { market_id: None },
+    )
+    .unwrap();
+
+    // migrating again leaves the markets unchanged
+    let state = STATE.load(&deps.storage, DEFAULT_MARKET_ID).unwrap();
+    let response = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert!(!response
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "migrating_vaults"));
+    assert_eq!(STATE.load(&deps.storage, DEFAULT_MARKET_ID).unwrap(), state);
+}
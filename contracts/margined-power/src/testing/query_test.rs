@@ -2,7 +2,8 @@ use crate::{contract::CONTRACT_NAME, state::Config};
 
 use cosmwasm_std::{coins, Decimal, Uint128};
 use margined_protocol::power::{
-    ConfigResponse, ExecuteMsg, QueryMsg, StateResponse, UserVaultsResponse,
+    ConfigResponse, ExecuteMsg, QueryMsg, StateResponse, UserVaultsResponse, VaultHealthResponse,
+    VaultsResponse,
 };
 use margined_testing::power_env::{PowerEnv, BASE_PRICE, SCALED_POWER_PRICE};
 use mock_query::contract::ExecuteMsg as MockQueryExecuteMsg;
@@ -17,7 +18,9 @@ fn test_query() {
 
     let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
 
-    let config: Config = wasm.query(&perp_address, &QueryMsg::Config {}).unwrap();
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
 
     // add prices to mock pools
     {
@@ -44,19 +47,34 @@ fn test_query() {
     }
 
     let index: Decimal = wasm
-        .query(&perp_address, &QueryMsg::GetIndex { period: 1u64 })
+        .query(
+            &perp_address,
+            &QueryMsg::GetIndex {
+                period: 1u64,
+                market_id: None,
+            },
+        )
         .unwrap();
     assert_eq!(index, Decimal::from_str("0.09").unwrap());
 
     let unscaled_index: Decimal = wasm
-        .query(&perp_address, &QueryMsg::GetUnscaledIndex { period: 1u64 })
+        .query(
+            &perp_address,
+            &QueryMsg::GetUnscaledIndex {
+                period: 1u64,
+                market_id: None,
+            },
+        )
         .unwrap();
     assert_eq!(unscaled_index, Decimal::from_str("9000000").unwrap());
 
     let denomalised_mark: Decimal = wasm
         .query(
             &perp_address,
-            &QueryMsg::GetDenormalisedMark { period: 1u64 },
+            &QueryMsg::GetDenormalisedMark {
+                period: 1u64,
+                market_id: None,
+            },
         )
         .unwrap();
     assert_eq!(
@@ -67,7 +85,10 @@ fn test_query() {
     let denomalised_mark_funding: Decimal = wasm
         .query(
             &perp_address,
-            &QueryMsg::GetDenormalisedMarkFunding { period: 1u64 },
+            &QueryMsg::GetDenormalisedMarkFunding {
+                period: 1u64,
+                market_id: None,
+            },
         )
         .unwrap();
     assert_eq!(denomalised_mark_funding, Decimal::from_str("909").unwrap());
@@ -80,8 +101,12 @@ fn test_vault_queries() {
     let wasm = Wasm::new(&env.app);
     let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
 
-    let config: ConfigResponse = wasm.query(&perp_address, &QueryMsg::Config {}).unwrap();
-    let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+    let config: ConfigResponse = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+    let state: StateResponse = wasm
+        .query(&perp_address, &QueryMsg::State { market_id: None })
+        .unwrap();
 
     assert_eq!(Decimal::one(), state.normalisation_factor);
 
@@ -109,7 +134,7 @@ fn test_vault_queries() {
 
     wasm.execute(
         &perp_address,
-        &ExecuteMsg::ApplyFunding {},
+        &ExecuteMsg::ApplyFunding { market_id: None },
         &[],
         &env.signer,
     )
@@ -135,6 +160,7 @@ fn test_vault_queries() {
                     amount: Uint128::from(1_000_000u128),
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &collateral,
                 &env.traders[0],
@@ -151,6 +177,33 @@ fn test_vault_queries() {
         assert_eq!(next_vault_id, 21u64);
     }
 
+    // list vaults
+    {
+        let response: VaultsResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::ListVaults {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        let expected_result: Vec<u64> = (1..=10).collect();
+        assert_eq!(response.vaults, expected_result);
+
+        let response: VaultsResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::ListVaults {
+                    start_after: Some(15u64),
+                    limit: Some(3u32),
+                },
+            )
+            .unwrap();
+        let expected_result: Vec<u64> = (16..=18).collect();
+        assert_eq!(response.vaults, expected_result);
+    }
+
     // get user vaults
     {
         let response: UserVaultsResponse = wasm
@@ -199,3 +252,102 @@ fn test_vault_queries() {
         assert_eq!(response.vaults, expected_result);
     }
 }
+
+#[test]
+fn test_query_vault_health() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: ConfigResponse = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    wasm.execute(
+        config.query_contract.as_ref(),
+        &MockQueryExecuteMsg::AppendPrice {
+            pool_id: env.base_pool_id,
+            price: Decimal::from_atomics(BASE_PRICE, 6u32).unwrap(),
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    wasm.execute(
+        config.query_contract.as_ref(),
+        &MockQueryExecuteMsg::AppendPrice {
+            pool_id: env.power_pool_id,
+            price: Decimal::from_atomics(SCALED_POWER_PRICE, 6u32).unwrap(),
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    // should revert if the vault does not exist
+    {
+        wasm.query::<QueryMsg, VaultHealthResponse>(
+            &perp_address,
+            &QueryMsg::GetVaultHealth { vault_id: 1u64 },
+        )
+        .unwrap_err();
+    }
+
+    wasm.execute(
+        &perp_address,
+        &ExecuteMsg::MintPowerPerp {
+            amount: Uint128::from(100_000_000u128),
+            vault_id: None,
+            rebase: false,
+            market_id: None,
+        },
+        &coins(60_000_000u128, &env.denoms["base"]),
+        &env.traders[0],
+    )
+    .unwrap();
+
+    let health: VaultHealthResponse = wasm
+        .query(&perp_address, &QueryMsg::GetVaultHealth { vault_id: 1u64 })
+        .unwrap();
+
+    let is_safe: bool = wasm
+        .query(&perp_address, &QueryMsg::CheckVault { vault_id: 1u64 })
+        .unwrap();
+
+    assert_eq!(health.is_safe, is_safe);
+    assert!(health.above_min_collateral);
+    assert!(health.collateral_ratio.unwrap() > Decimal::from_str("1.5").unwrap());
+    assert_eq!(
+        health.debt_value_in_quote,
+        health.debt_value_in_base * Decimal::from_atomics(BASE_PRICE, 6u32).unwrap()
+    );
+    assert!(health.liquidation_price.unwrap() > Decimal::from_atomics(BASE_PRICE, 6u32).unwrap());
+    assert!(!health.max_mintable.is_zero());
+    assert!(!health.max_withdrawable.is_zero());
+
+    // withdrawing the max withdrawable leaves the vault safe
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Withdraw {
+                amount: health.max_withdrawable,
+                vault_id: 1u64,
+            },
+            &[],
+            &env.traders[0],
+        )
+        .unwrap();
+
+        let is_safe: bool = wasm
+            .query(&perp_address, &QueryMsg::CheckVault { vault_id: 1u64 })
+            .unwrap();
+        assert!(is_safe);
+
+        let health: VaultHealthResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVaultHealth { vault_id: 1u64 })
+            .unwrap();
+        assert!(health.collateral_ratio.unwrap() >= Decimal::from_str("1.5").unwrap());
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{contract::CONTRACT_NAME, state::Config};
+
+use cosmwasm_std::{coin, Decimal, Uint128};
+use margined_protocol::power::{ExecuteMsg, QueryMsg, VaultDelta, VaultResponse};
+use margined_testing::{helpers::parse_event_attribute, power_env::PowerEnv};
+use osmosis_test_tube::{Account, Module, RunnerError, Wasm};
+use std::str::FromStr;
+
+#[test]
+fn test_adjust_vault() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.base_pool_id,
+        Decimal::from_str("3000.0").unwrap(),
+    );
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.power_pool_id,
+        Decimal::from_str("3030.0").unwrap(),
+    );
+
+    // mint at exactly the collateral ratio
+    let mint_response = wasm
+        .execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: Uint128::from(100_000_000u128),
+                vault_id: None,
+                rebase: false,
+                market_id: None,
+            },
+            &[coin(45_000_000u128, env.denoms["base"].clone())],
+            &env.traders[0],
+        )
+        .unwrap();
+
+    let vault_id = u64::from_str(&parse_event_attribute(
+        mint_response.events,
+        "wasm-mint",
+        "vault_id",
+    ))
+    .unwrap();
+
+    // should revert if nothing changes
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::AdjustVault {
+                vault_id,
+                collateral_delta: None,
+                debt_delta: None,
+            },
+            &[],
+            &env.traders[0],
+        )
+        .unwrap_err();
+    }
+
+    // should revert if the funds do not match the increases
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::AdjustVault {
+                    vault_id,
+                    collateral_delta: Some(VaultDelta::Decrease {
+                        amount: Uint128::from(10_000_000u128),
+                    }),
+                    debt_delta: Some(VaultDelta::Decrease {
+                        amount: Uint128::from(40_000_000u128),
+                    }),
+                },
+                &[coin(20_000_000u128, env.denoms["power"].clone())],
+                &env.traders[0],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Invalid funds: execute wasm contract failed".to_string()
+            }
+        );
+    }
+
+    // should revert when withdrawing before burning
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::Withdraw {
+                    amount: Uint128::from(10_000_000u128),
+                    vault_id,
+                },
+                &[],
+                &env.traders[0],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Vault is not safe, cannot perform operation: execute wasm contract failed".to_string()
+            }
+        );
+    }
+
+    // should withdraw and burn in one message when the final vault is safe
+    {
+        let balance_before = env.get_balance(env.traders[0].address(), env.denoms["base"].clone());
+
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::AdjustVault {
+                    vault_id,
+                    collateral_delta: Some(VaultDelta::Decrease {
+                        amount: Uint128::from(10_000_000u128),
+                    }),
+                    debt_delta: Some(VaultDelta::Decrease {
+                        amount: Uint128::from(40_000_000u128),
+                    }),
+                },
+                &[coin(40_000_000u128, env.denoms["power"].clone())],
+                &env.traders[0],
+            )
+            .unwrap();
+
+        assert_eq!(
+            parse_event_attribute(response.events.clone(), "wasm-adjust_vault", "burn_amount"),
+            "40000000"
+        );
+        assert_eq!(
+            parse_event_attribute(response.events, "wasm-adjust_vault", "collateral_withdrawn"),
+            "10000000"
+        );
+
+        let balance_after = env.get_balance(env.traders[0].address(), env.denoms["base"].clone());
+        assert_eq!(
+            balance_after - balance_before,
+            Uint128::from(10_000_000u128)
+        );
+
+        let vault: VaultResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+        assert_eq!(vault.collateral, Uint128::from(35_000_000u128));
+        assert_eq!(vault.short_amount, Uint128::from(60_000_000u128));
+    }
+
+    // should deposit and mint in one message
+    {
+        let balance_before = env.get_balance(env.traders[0].address(), env.denoms["power"].clone());
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::AdjustVault {
+                vault_id,
+                collateral_delta: Some(VaultDelta::Increase {
+                    amount: Uint128::from(15_000_000u128),
+                }),
+                debt_delta: Some(VaultDelta::Increase {
+                    amount: Uint128::from(40_000_000u128),
+                }),
+            },
+            &[coin(15_000_000u128, env.denoms["base"].clone())],
+            &env.traders[0],
+        )
+        .unwrap();
+
+        let balance_after = env.get_balance(env.traders[0].address(), env.denoms["power"].clone());
+        assert_eq!(
+            balance_after - balance_before,
+            Uint128::from(40_000_000u128)
+        );
+
+        let vault: VaultResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+        assert_eq!(vault.collateral, Uint128::from(50_000_000u128));
+        assert_eq!(vault.short_amount, Uint128::from(100_000_000u128));
+    }
+
+    // should revert if the final vault is unsafe
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::AdjustVault {
+                    vault_id,
+                    collateral_delta: Some(VaultDelta::Decrease {
+                        amount: Uint128::from(10_000_000u128),
+                    }),
+                    debt_delta: None,
+                },
+                &[],
+                &env.traders[0],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Vault is not safe, cannot perform operation: execute wasm contract failed".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{
+    contract::CONTRACT_NAME,
+    state::{Config, LIQUIDATION_AUCTION_DURATION},
+};
+
+use cosmwasm_std::{coin, Decimal, Uint128};
+use margined_protocol::power::{ExecuteMsg, LiquidationAuctionResponse, QueryMsg, UpdateConfig};
+use margined_testing::{
+    helpers::parse_event_attribute,
+    power_env::{PowerEnv, BASE_PRICE, ONE, SCALE_FACTOR},
+};
+use mock_query::contract::ExecuteMsg as MockQueryExecuteMsg;
+use osmosis_test_tube::{Module, Wasm};
+use std::str::FromStr;
+
+#[test]
+fn test_liquidation_auction() {
+    pub const SCALED_POWER_PRICE: u128 = 303_000 * ONE / SCALE_FACTOR;
+
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    wasm.execute(
+        config.query_contract.as_ref(),
+        &MockQueryExecuteMsg::AppendPrice {
+            pool_id: env.base_pool_id,
+            price: Decimal::from_atomics(BASE_PRICE, 6u32).unwrap(),
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    wasm.execute(
+        config.query_contract.as_ref(),
+        &MockQueryExecuteMsg::AppendPrice {
+            pool_id: env.power_pool_id,
+            price: Decimal::from_atomics(SCALED_POWER_PRICE, 6u32).unwrap(),
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    // configure an auction from 102% to 110%
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::UpdateConfig {
+                new_config: UpdateConfig {
+                    min_liquidation_bonus: Some(Decimal::percent(102)),
+                    max_liquidation_bonus: Some(Decimal::percent(110)),
+                    ..Default::default()
+                },
+                market_id: None,
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+    }
+
+    let vault_id: u64;
+
+    // open vault
+    {
+        let mint_response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::MintPowerPerp {
+                    amount: Uint128::from(100_000_000u128),
+                    vault_id: None,
+                    rebase: true,
+                    market_id: None,
+                },
+                &[coin(45_000_049u128, env.denoms["base"].clone())],
+                &env.traders[0],
+            )
+            .unwrap();
+
+        vault_id = u64::from_str(&parse_event_attribute(
+            mint_response.events,
+            "wasm-mint",
+            "vault_id",
+        ))
+        .unwrap();
+
+        let auction: LiquidationAuctionResponse = wasm
+            .query(&perp_address, &QueryMsg::GetLiquidationAuction { vault_id })
+            .unwrap();
+
+        assert!(auction.is_safe);
+        assert_eq!(auction.start_time, None);
+
+        // should not start an auction for a safe vault
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::StartLiquidationAuction { vault_id },
+            &[],
+            &env.traders[1],
+        )
+        .unwrap_err();
+    }
+
+    // set base price to make the vault underwater
+    {
+        pub const SCALED_POWER_PRICE: u128 = 4_040 * ONE / SCALE_FACTOR;
+        pub const BASE_PRICE: u128 = 4_000_000_000;
+        wasm.execute(
+            config.query_contract.as_ref(),
+            &MockQueryExecuteMsg::AppendPrice {
+                pool_id: env.base_pool_id,
+                price: Decimal::from_atomics(BASE_PRICE, 6u32).unwrap(),
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+
+        wasm.execute(
+            config.query_contract.as_ref(),
+            &MockQueryExecuteMsg::AppendPrice {
+                pool_id: env.power_pool_id,
+                price: Decimal::from_atomics(SCALED_POWER_PRICE, 6u32).unwrap(),
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+    }
+
+    // an unsafe vault has no auction until it is recorded
+    {
+        let auction: LiquidationAuctionResponse = wasm
+            .query(&perp_address, &QueryMsg::GetLiquidationAuction { vault_id })
+            .unwrap();
+
+        assert!(!auction.is_safe);
+        assert_eq!(auction.start_time, None);
+        assert_eq!(auction.liquidation_bonus, Decimal::percent(102));
+    }
+
+    // anyone can record the start of the auction without liquidating
+    let start_time = {
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::StartLiquidationAuction { vault_id },
+                &[],
+                &env.traders[1],
+            )
+            .unwrap();
+
+        let start_time = u64::from_str(&parse_event_attribute(
+            response.events,
+            "wasm-start_liquidation_auction",
+            "start_time",
+        ))
+        .unwrap();
+
+        let auction: LiquidationAuctionResponse = wasm
+            .query(&perp_address, &QueryMsg::GetLiquidationAuction { vault_id })
+            .unwrap();
+
+        assert_eq!(auction.start_time.unwrap().seconds(), start_time);
+        assert_eq!(auction.liquidation_bonus, Decimal::percent(102));
+
+        auction.start_time.unwrap()
+    };
+
+    // recording the auction again keeps the original start time
+    {
+        env.app.increase_time(LIQUIDATION_AUCTION_DURATION / 4);
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::StartLiquidationAuction { vault_id },
+            &[],
+            &env.traders[1],
+        )
+        .unwrap();
+
+        let auction: LiquidationAuctionResponse = wasm
+            .query(&perp_address, &QueryMsg::GetLiquidationAuction { vault_id })
+            .unwrap();
+
+        assert_eq!(auction.start_time, Some(start_time));
+    }
+
+    // the first liquidation pays the bonus accrued since the vault was recorded as unsafe
+    {
+        env.app.increase_time(LIQUIDATION_AUCTION_DURATION / 4);
+
+        let liquidate_response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::Liquidate {
+                    vault_id,
+                    max_debt_amount: Uint128::from(1_000_000u128),
+                },
+                &[],
+                &env.signer,
+            )
+            .unwrap();
+
+        let liquidation_bonus = Decimal::from_str(&parse_event_attribute(
+            liquidate_response.events,
+            "wasm-liquidation",
+            "liquidation_bonus",
+        ))
+        .unwrap();
+        assert!(liquidation_bonus > Decimal::percent(102));
+        assert!(liquidation_bonus < Decimal::percent(110));
+
+        let auction: LiquidationAuctionResponse = wasm
+            .query(&perp_address, &QueryMsg::GetLiquidationAuction { vault_id })
+            .unwrap();
+
+        assert!(!auction.is_safe);
+        assert_eq!(auction.start_time, Some(start_time));
+        assert!(auction.liquidation_bonus >= liquidation_bonus);
+        assert!(auction.auction_price > Decimal::zero());
+    }
+
+    // bonus reaches the maximum once the duration has elapsed
+    {
+        env.app.increase_time(LIQUIDATION_AUCTION_DURATION);
+
+        let auction: LiquidationAuctionResponse = wasm
+            .query(&perp_address, &QueryMsg::GetLiquidationAuction { vault_id })
+            .unwrap();
+
+        assert_eq!(auction.start_time, Some(start_time));
+        assert_eq!(auction.liquidation_bonus, Decimal::percent(110));
+    }
+
+    // auction is cleared once the vault is safe again
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Deposit { vault_id },
+            &[coin(100_000_000u128, env.denoms["base"].clone())],
+            &env.traders[0],
+        )
+        .unwrap();
+
+        let auction: LiquidationAuctionResponse = wasm
+            .query(&perp_address, &QueryMsg::GetLiquidationAuction { vault_id })
+            .unwrap();
+
+        assert!(auction.is_safe);
+        assert_eq!(auction.start_time, None);
+        assert_eq!(auction.liquidation_bonus, Decimal::percent(102));
+    }
+}
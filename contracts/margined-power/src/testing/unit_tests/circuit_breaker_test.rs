@@ -0,0 +1,226 @@
+use crate::{
+    contract::CONTRACT_NAME,
+    state::{Config, State},
+};
+
+use cosmwasm_std::{coin, Decimal, Uint128};
+use margined_protocol::power::{ConfigResponse, ExecuteMsg, QueryMsg, UpdateConfig};
+use margined_testing::{helpers::parse_event_attribute, power_env::PowerEnv};
+use mock_query::contract::{ExecuteMsg as MockQueryExecuteMsg, PricePoint};
+use osmosis_test_tube::{Account, Module, RunnerError, Wasm};
+use std::str::FromStr;
+
+#[test]
+fn test_circuit_breaker() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.base_pool_id,
+        Decimal::from_str("3000.0").unwrap(),
+    );
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.power_pool_id,
+        Decimal::from_str("3030.0").unwrap(),
+    );
+
+    let set_spot_price = |pool_id: u64, price: &str| {
+        wasm.execute(
+            config.query_contract.as_ref(),
+            &MockQueryExecuteMsg::SetSpotPrice {
+                pool_id,
+                price: Decimal::from_str(price).unwrap(),
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+    };
+
+    let mint = || {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: Uint128::from(1_000_000u128),
+                vault_id: None,
+                rebase: false,
+                market_id: None,
+            },
+            &[coin(1_000_000u128, env.denoms["base"].clone())],
+            &env.traders[0],
+        )
+    };
+
+    // should revert if the max spot deviation is greater than 100%
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::UpdateConfig {
+                market_id: None,
+                new_config: UpdateConfig {
+                    max_spot_deviation: Some(Decimal::percent(101)),
+                    ..Default::default()
+                },
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap_err();
+    }
+
+    // should be ignored while disabled
+    {
+        set_spot_price(env.base_pool_id, "6000.0");
+        mint().unwrap();
+    }
+
+    wasm.execute(
+        &perp_address,
+        &ExecuteMsg::UpdateConfig {
+            market_id: None,
+            new_config: UpdateConfig {
+                max_spot_deviation: Some(Decimal::percent(10)),
+                ..Default::default()
+            },
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    let config: ConfigResponse = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+    assert_eq!(config.max_spot_deviation, Decimal::percent(10));
+
+    // should revert without pausing the market when the spot price deviates
+    {
+        let balance_before = env.get_balance(env.traders[0].address(), env.denoms["base"].clone());
+
+        let err = mint().unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: format!("failed to execute message; message index: 0: Circuit breaker tripped: pool {} spot price deviates from twap by 1: execute wasm contract failed", env.base_pool_id)
+            }
+        );
+
+        let balance_after = env.get_balance(env.traders[0].address(), env.denoms["base"].clone());
+        assert_eq!(balance_before, balance_after);
+
+        let state: State = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
+        assert!(!state.is_paused);
+    }
+
+    // should not trip while only the spot price deviates
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::TripCircuitBreaker { market_id: None },
+                &[],
+                &env.traders[1],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Generic error: Pool prices are within the max spot deviation: execute wasm contract failed".to_string()
+            }
+        );
+
+        let state: State = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
+        assert!(!state.is_paused);
+    }
+
+    // should check the power pool as well
+    {
+        set_spot_price(env.base_pool_id, "3000.0");
+        set_spot_price(env.power_pool_id, "2500.0");
+
+        let err = mint().unwrap_err();
+        assert!(err.to_string().contains(&format!(
+            "Circuit breaker tripped: pool {}",
+            env.power_pool_id
+        )));
+    }
+
+    // should allow minting once the spot price is within the max deviation
+    {
+        set_spot_price(env.power_pool_id, "3100.0");
+
+        let response = mint().unwrap();
+        assert!(response
+            .events
+            .iter()
+            .all(|event| event.ty != "wasm-circuit_breaker"));
+    }
+
+    // should let anyone pause the market once the recent twap deviates from the twap
+    {
+        // the base price doubles after a twap period and holds for half a period
+        wasm.execute(
+            config.query_contract.as_ref(),
+            &MockQueryExecuteMsg::SetPricePath {
+                pool_id: env.base_pool_id,
+                base_asset: env.denoms["base"].clone(),
+                quote_asset: env.denoms["quote"].clone(),
+                path: vec![
+                    PricePoint {
+                        delay: 0u64,
+                        price: Decimal::from_str("3000.0").unwrap(),
+                    },
+                    PricePoint {
+                        delay: config.twap_period,
+                        price: Decimal::from_str("6000.0").unwrap(),
+                    },
+                ],
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+
+        env.app
+            .increase_time(config.twap_period + config.twap_period / 2);
+
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::TripCircuitBreaker { market_id: None },
+                &[],
+                &env.traders[1],
+            )
+            .unwrap();
+
+        let reason = parse_event_attribute(response.events, "wasm-circuit_breaker", "reason");
+        assert!(reason.contains(&format!("pool {} recent twap", env.base_pool_id)));
+
+        let state: State = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
+        assert!(state.is_paused);
+
+        let err = mint().unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Generic error: Cannot perform action as contract is paused: execute wasm contract failed".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,769 @@
+use crate::{contract::CONTRACT_NAME, helpers::decimal_to_fixed, state::Config};
+
+use cosmwasm_std::{coin, Decimal, Uint128};
+use margined_protocol::power::{
+    CollateralAsset, CollateralAssetsResponse, CollateralPriceSource, ExecuteMsg, QueryMsg,
+    StateResponse, VaultCollateralResponse, VaultResponse,
+};
+use margined_testing::{helpers::parse_event_attribute, power_env::PowerEnv};
+use osmosis_test_tube::{Account, Module, OsmosisTestApp, RunnerError, Wasm};
+use std::str::FromStr;
+
+#[test]
+fn test_multi_collateral_vault() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.base_pool_id,
+        Decimal::from_str("3000.0").unwrap(),
+    );
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.power_pool_id,
+        Decimal::from_str("3030.0").unwrap(),
+    );
+
+    let asset = CollateralAsset {
+        denom: env.denoms["quote"].clone(),
+        decimals: 6u32,
+        haircut: Decimal::percent(10),
+        price_source: CollateralPriceSource::Quote {},
+    };
+
+    // should revert if the sender is not the owner
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::SetCollateralAsset {
+                    market_id: None,
+                    asset: asset.clone(),
+                },
+                &[],
+                &env.traders[0],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Unauthorized: execute wasm contract failed".to_string()
+            }
+        );
+    }
+
+    // should revert if the base denom is set as collateral
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::SetCollateralAsset {
+                market_id: None,
+                asset: CollateralAsset {
+                    denom: env.denoms["base"].clone(),
+                    ..asset.clone()
+                },
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap_err();
+    }
+
+    wasm.execute(
+        &perp_address,
+        &ExecuteMsg::SetCollateralAsset {
+            market_id: None,
+            asset: asset.clone(),
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    let assets: CollateralAssetsResponse = wasm
+        .query(
+            &perp_address,
+            &QueryMsg::GetCollateralAssets { market_id: None },
+        )
+        .unwrap();
+    assert_eq!(assets.assets, vec![asset.clone()]);
+
+    let mint_response = wasm
+        .execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: Uint128::from(50_000_000u128),
+                vault_id: None,
+                rebase: false,
+                market_id: None,
+            },
+            &[coin(30_000_000u128, env.denoms["base"].clone())],
+            &env.traders[0],
+        )
+        .unwrap();
+
+    let vault_id = u64::from_str(&parse_event_attribute(
+        mint_response.events,
+        "wasm-mint",
+        "vault_id",
+    ))
+    .unwrap();
+
+    // should revert if the denom is not whitelisted
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::Deposit { vault_id },
+                &[coin(1_000_000u128, env.denoms["gas"].clone())],
+                &env.traders[0],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Invalid funds: execute wasm contract failed".to_string()
+            }
+        );
+    }
+
+    // should value the deposited collateral at the base price after the haircut
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Deposit { vault_id },
+            &[coin(60_000_000_000u128, env.denoms["quote"].clone())],
+            &env.traders[0],
+        )
+        .unwrap();
+
+        let collateral: VaultCollateralResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVaultCollateral { vault_id })
+            .unwrap();
+        assert_eq!(
+            collateral.collateral,
+            vec![
+                coin(30_000_000u128, env.denoms["base"].clone()),
+                coin(60_000_000_000u128, env.denoms["quote"].clone()),
+            ]
+        );
+
+        // 60,000 quote is 20 base, 18 base after the haircut
+        let expected_value = Uint128::from(48_000_000u128);
+        assert!(collateral.collateral_value.abs_diff(expected_value) <= Uint128::one());
+    }
+
+    // should mint against the deposited collateral
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: Uint128::from(50_000_000u128),
+                vault_id: Some(vault_id),
+                rebase: false,
+                market_id: None,
+            },
+            &[],
+            &env.traders[0],
+        )
+        .unwrap();
+
+        let is_safe: bool = wasm
+            .query(&perp_address, &QueryMsg::CheckVault { vault_id })
+            .unwrap();
+        assert!(is_safe);
+    }
+
+    // should revert if the withdrawal makes the vault unsafe
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::WithdrawCollateral {
+                    denom: env.denoms["quote"].clone(),
+                    amount: Uint128::from(60_000_000_000u128),
+                    vault_id,
+                },
+                &[],
+                &env.traders[0],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Vault is not safe, cannot perform operation: execute wasm contract failed".to_string()
+            }
+        );
+    }
+
+    // should withdraw collateral while the vault stays safe
+    {
+        let balance_before = env.get_balance(env.traders[0].address(), env.denoms["quote"].clone());
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::WithdrawCollateral {
+                denom: env.denoms["quote"].clone(),
+                amount: Uint128::from(6_000_000_000u128),
+                vault_id,
+            },
+            &[],
+            &env.traders[0],
+        )
+        .unwrap();
+
+        let balance_after = env.get_balance(env.traders[0].address(), env.denoms["quote"].clone());
+        assert_eq!(
+            balance_after - balance_before,
+            Uint128::from(6_000_000_000u128)
+        );
+
+        let collateral: VaultCollateralResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVaultCollateral { vault_id })
+            .unwrap();
+        assert_eq!(
+            collateral.collateral[1],
+            coin(54_000_000_000u128, env.denoms["quote"].clone())
+        );
+    }
+
+    // should revert if the sender is not the owner
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::DelistCollateralAsset {
+                    market_id: None,
+                    denom: env.denoms["quote"].clone(),
+                },
+                &[],
+                &env.traders[0],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Unauthorized: execute wasm contract failed".to_string()
+            }
+        );
+    }
+
+    // should stop new deposits of a delisted asset but keep valuing the vault with it
+    {
+        let value_before: VaultCollateralResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVaultCollateral { vault_id })
+            .unwrap();
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::DelistCollateralAsset {
+                market_id: None,
+                denom: env.denoms["quote"].clone(),
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+
+        let assets: CollateralAssetsResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetCollateralAssets { market_id: None },
+            )
+            .unwrap();
+        assert_eq!(assets.delisted, vec![env.denoms["quote"].clone()]);
+
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::Deposit { vault_id },
+                &[coin(1_000_000u128, env.denoms["quote"].clone())],
+                &env.traders[0],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Token denom 'usdc' is not supported: execute wasm contract failed".to_string()
+            }
+        );
+
+        let collateral: VaultCollateralResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVaultCollateral { vault_id })
+            .unwrap();
+        assert_eq!(collateral.collateral_value, value_before.collateral_value);
+
+        let is_safe: bool = wasm
+            .query(&perp_address, &QueryMsg::CheckVault { vault_id })
+            .unwrap();
+        assert!(is_safe);
+
+        // the delisted asset can still be withdrawn
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::WithdrawCollateral {
+                denom: env.denoms["quote"].clone(),
+                amount: Uint128::from(1_000_000_000u128),
+                vault_id,
+            },
+            &[],
+            &env.traders[0],
+        )
+        .unwrap();
+    }
+
+    // should accept deposits again once the asset is set again
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::SetCollateralAsset {
+                market_id: None,
+                asset,
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Deposit { vault_id },
+            &[coin(1_000_000u128, env.denoms["quote"].clone())],
+            &env.traders[0],
+        )
+        .unwrap();
+    }
+}
+
+#[test]
+fn test_pool_collateral_with_different_decimals() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.base_pool_id,
+        Decimal::from_str("3000.0").unwrap(),
+    );
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.power_pool_id,
+        Decimal::from_str("3030.0").unwrap(),
+    );
+
+    // an asset of 18 decimals worth 10 base, quoted by the pool in raw units
+    let pool_id = 99u64;
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        pool_id,
+        Decimal::from_str("0.00000000001").unwrap(),
+    );
+
+    wasm.execute(
+        &perp_address,
+        &ExecuteMsg::SetCollateralAsset {
+            market_id: None,
+            asset: CollateralAsset {
+                denom: env.denoms["gas"].clone(),
+                decimals: 18u32,
+                haircut: Decimal::percent(10),
+                price_source: CollateralPriceSource::Pool {
+                    pool_id,
+                    quote_denom: env.denoms["base"].clone(),
+                },
+            },
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    let mint_response = wasm
+        .execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: Uint128::from(10_000_000u128),
+                vault_id: None,
+                rebase: false,
+                market_id: None,
+            },
+            &[coin(5_000_000u128, env.denoms["base"].clone())],
+            &env.traders[0],
+        )
+        .unwrap();
+
+    let vault_id = u64::from_str(&parse_event_attribute(
+        mint_response.events,
+        "wasm-mint",
+        "vault_id",
+    ))
+    .unwrap();
+
+    // should value the collateral per whole token rather than per raw unit
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Deposit { vault_id },
+            &[coin(500_000_000_000_000_000u128, env.denoms["gas"].clone())],
+            &env.traders[0],
+        )
+        .unwrap();
+
+        let collateral: VaultCollateralResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVaultCollateral { vault_id })
+            .unwrap();
+
+        // 0.5 of the asset is 5 base, 4.5 base after the haircut
+        let expected_value = Uint128::from(9_500_000u128);
+        assert!(collateral.collateral_value.abs_diff(expected_value) <= Uint128::one());
+    }
+
+    // should mint against the collateral while the vault stays safe
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: Uint128::from(5_000_000u128),
+                vault_id: Some(vault_id),
+                rebase: false,
+                market_id: None,
+            },
+            &[],
+            &env.traders[0],
+        )
+        .unwrap();
+
+        let is_safe: bool = wasm
+            .query(&perp_address, &QueryMsg::CheckVault { vault_id })
+            .unwrap();
+        assert!(is_safe);
+    }
+}
+
+/// Opens a vault of 5 base and 135,000 quote, worth 45.5 base after the haircut,
+/// with 100 power of debt worth 30 base, returning the vault id.
+fn open_basket_vault(env: &PowerEnv, wasm: &Wasm<OsmosisTestApp>, perp_address: &str) -> u64 {
+    wasm.execute(
+        perp_address,
+        &ExecuteMsg::SetCollateralAsset {
+            market_id: None,
+            asset: CollateralAsset {
+                denom: env.denoms["quote"].clone(),
+                decimals: 6u32,
+                haircut: Decimal::percent(10),
+                price_source: CollateralPriceSource::Quote {},
+            },
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    let mint_response = wasm
+        .execute(
+            perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: Uint128::from(10_000_000u128),
+                vault_id: None,
+                rebase: false,
+                market_id: None,
+            },
+            &[coin(5_000_000u128, env.denoms["base"].clone())],
+            &env.traders[0],
+        )
+        .unwrap();
+
+    let vault_id = u64::from_str(&parse_event_attribute(
+        mint_response.events,
+        "wasm-mint",
+        "vault_id",
+    ))
+    .unwrap();
+
+    wasm.execute(
+        perp_address,
+        &ExecuteMsg::Deposit { vault_id },
+        &[coin(135_000_000_000u128, env.denoms["quote"].clone())],
+        &env.traders[0],
+    )
+    .unwrap();
+
+    wasm.execute(
+        perp_address,
+        &ExecuteMsg::MintPowerPerp {
+            amount: Uint128::from(90_000_000u128),
+            vault_id: Some(vault_id),
+            rebase: false,
+            market_id: None,
+        },
+        &[],
+        &env.traders[0],
+    )
+    .unwrap();
+
+    vault_id
+}
+
+#[test]
+fn test_liquidate_multi_collateral_vault() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.base_pool_id,
+        Decimal::from_str("3000.0").unwrap(),
+    );
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.power_pool_id,
+        Decimal::from_str("3030.0").unwrap(),
+    );
+
+    let vault_id = open_basket_vault(&env, &wasm, &perp_address);
+
+    // the liquidator mints the power to repay from a vault of its own
+    wasm.execute(
+        &perp_address,
+        &ExecuteMsg::MintPowerPerp {
+            amount: Uint128::from(60_000_000u128),
+            vault_id: None,
+            rebase: false,
+            market_id: None,
+        },
+        &[coin(100_000_000u128, env.denoms["base"].clone())],
+        &env.traders[1],
+    )
+    .unwrap();
+
+    // a 10% rise in the power price makes the vault unsafe
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.power_pool_id,
+        Decimal::from_str("3333.0").unwrap(),
+    );
+
+    // should liquidate half the debt, paying the base before seizing the quote
+    {
+        let quote_before = env.get_balance(env.traders[1].address(), env.denoms["quote"].clone());
+
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::Liquidate {
+                    vault_id,
+                    max_debt_amount: Uint128::from(100_000_000u128),
+                },
+                &[],
+                &env.traders[1],
+            )
+            .unwrap();
+
+        assert_eq!(
+            parse_event_attribute(
+                response.events.clone(),
+                "wasm-liquidation",
+                "liquidation_amount"
+            ),
+            "50000000"
+        );
+        assert_eq!(
+            parse_event_attribute(
+                response.events.clone(),
+                "wasm-liquidation",
+                "collateral_to_pay"
+            ),
+            "5000000"
+        );
+        assert!(!response
+            .events
+            .iter()
+            .any(|event| event.ty == "wasm-bad_debt"));
+
+        let quote_seized =
+            env.get_balance(env.traders[1].address(), env.denoms["quote"].clone()) - quote_before;
+        assert!(!quote_seized.is_zero());
+        assert_eq!(
+            parse_event_attribute(response.events, "wasm-liquidation", "collateral_seized"),
+            coin(quote_seized.u128(), env.denoms["quote"].clone()).to_string()
+        );
+
+        let vault: VaultResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+        assert_eq!(vault.short_amount, Uint128::from(50_000_000u128));
+
+        let collateral: VaultCollateralResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVaultCollateral { vault_id })
+            .unwrap();
+        assert_eq!(
+            collateral.collateral,
+            vec![coin(
+                135_000_000_000u128 - quote_seized.u128(),
+                env.denoms["quote"].clone()
+            )]
+        );
+
+        let total_bad_debt: Uint128 = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetTotalBadDebt { market_id: None },
+            )
+            .unwrap();
+        assert!(total_bad_debt.is_zero());
+    }
+}
+
+#[test]
+fn test_shutdown_multi_collateral_vault() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.base_pool_id,
+        Decimal::from_str("3000.0").unwrap(),
+    );
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.power_pool_id,
+        Decimal::from_str("3030.0").unwrap(),
+    );
+
+    let vault_id = open_basket_vault(&env, &wasm, &perp_address);
+
+    wasm.execute(
+        &perp_address,
+        &ExecuteMsg::Shutdown { market_id: None },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    let state: StateResponse = wasm
+        .query(&perp_address, &QueryMsg::State { market_id: None })
+        .unwrap();
+    let debt_value = decimal_to_fixed(
+        Decimal::from_atomics(100_000_000u128, 6u32).unwrap() * state.settlement_price.unwrap(),
+        6u32,
+    );
+    assert!(debt_value > Uint128::from(5_000_000u128));
+
+    // should settle the debt above the base collateral with the quote, returning the rest
+    let quote_settled = {
+        let quote_before = env.get_balance(env.traders[0].address(), env.denoms["quote"].clone());
+        let base_before = env.get_balance(env.traders[0].address(), env.denoms["base"].clone());
+
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::WithdrawShutdown { vault_id },
+                &[],
+                &env.traders[0],
+            )
+            .unwrap();
+
+        let quote_returned =
+            env.get_balance(env.traders[0].address(), env.denoms["quote"].clone()) - quote_before;
+        let quote_settled = Uint128::from(135_000_000_000u128) - quote_returned;
+
+        assert_eq!(
+            parse_event_attribute(
+                response.events,
+                "wasm-withdraw_shutdown",
+                "collateral_settled"
+            ),
+            coin(quote_settled.u128(), env.denoms["quote"].clone()).to_string()
+        );
+        assert_eq!(
+            env.get_balance(env.traders[0].address(), env.denoms["base"].clone()),
+            base_before
+        );
+
+        // the settled quote stays in the contract rather than going to the fee pool
+        assert!(env
+            .get_balance(env.fee_pool.address(), env.denoms["quote"].clone())
+            .is_zero());
+        assert_eq!(
+            env.get_balance(perp_address.clone(), env.denoms["quote"].clone()),
+            quote_settled
+        );
+
+        quote_settled
+    };
+
+    // should pay the redemption with the settled quote and the base for the rest
+    {
+        let quote_before = env.get_balance(env.traders[0].address(), env.denoms["quote"].clone());
+        let base_before = env.get_balance(env.traders[0].address(), env.denoms["base"].clone());
+
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::RedeemShutdown { market_id: None },
+                &[coin(100_000_000u128, env.denoms["power"].clone())],
+                &env.traders[0],
+            )
+            .unwrap();
+
+        assert_eq!(
+            parse_event_attribute(
+                response.events.clone(),
+                "wasm-redeem_shutdown",
+                "collateral_paid"
+            ),
+            coin(quote_settled.u128(), env.denoms["quote"].clone()).to_string()
+        );
+
+        let quote_paid =
+            env.get_balance(env.traders[0].address(), env.denoms["quote"].clone()) - quote_before;
+        let base_paid =
+            env.get_balance(env.traders[0].address(), env.denoms["base"].clone()) - base_before;
+
+        assert_eq!(quote_paid, quote_settled);
+        assert!(base_paid.abs_diff(Uint128::from(5_000_000u128)) <= Uint128::one());
+        assert_eq!(
+            parse_event_attribute(response.events, "wasm-redeem_shutdown", "base_paid"),
+            base_paid.to_string()
+        );
+
+        assert!(env
+            .get_balance(perp_address.clone(), env.denoms["quote"].clone())
+            .is_zero());
+    }
+}
@@ -2,7 +2,7 @@ use crate::contract::CONTRACT_NAME;
 
 use cosmwasm_std::{coin, coins, Decimal, Uint128};
 use margined_protocol::power::{
-    ConfigResponse, ExecuteMsg, QueryMsg, StateResponse, VaultResponse,
+    ConfigResponse, ExecuteMsg, QueryMsg, StateResponse, UpdateConfig, VaultResponse,
 };
 use margined_testing::{
     helpers::parse_event_attribute,
@@ -23,8 +23,12 @@ fn test_combined_actions() {
     let bank = Bank::new(&env.app);
     let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
 
-    let config: ConfigResponse = wasm.query(&perp_address, &QueryMsg::Config {}).unwrap();
-    let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+    let config: ConfigResponse = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+    let state: StateResponse = wasm
+        .query(&perp_address, &QueryMsg::State { market_id: None })
+        .unwrap();
 
     assert_eq!(Decimal::one(), state.normalisation_factor);
 
@@ -52,7 +56,7 @@ fn test_combined_actions() {
 
     wasm.execute(
         &perp_address,
-        &ExecuteMsg::ApplyFunding {},
+        &ExecuteMsg::ApplyFunding { market_id: None },
         &[],
         &env.signer,
     )
@@ -77,6 +81,7 @@ fn test_combined_actions() {
                         amount: mint_amount,
                         vault_id: None,
                         rebase: false,
+                        market_id: None,
                     },
                     &funds,
                     &env.traders[0],
@@ -114,6 +119,7 @@ fn test_combined_actions() {
                         amount: Uint128::from(amount),
                         vault_id: None,
                         rebase: false,
+                        market_id: None,
                     },
                     &collateral,
                     &env.traders[0],
@@ -159,6 +165,7 @@ fn test_combined_actions() {
                         amount: Uint128::zero(),
                         vault_id: Some(vault_id),
                         rebase: false,
+                        market_id: None,
                     },
                     &collateral,
                     &env.traders[0],
@@ -185,6 +192,7 @@ fn test_combined_actions() {
                     amount: Uint128::from(amount),
                     vault_id: Some(vault_id),
                     rebase: false,
+                    market_id: None,
                 },
                 &collateral,
                 &env.traders[0],
@@ -229,6 +237,7 @@ fn test_combined_actions() {
                     amount: Uint128::from(amount),
                     vault_id: Some(vault_id),
                     rebase: false,
+                    market_id: None,
                 },
                 &[],
                 &env.traders[0],
@@ -259,6 +268,7 @@ fn test_combined_actions() {
                         amount: Uint128::zero(),
                         vault_id: Some(vault_id),
                         rebase: false,
+                        market_id: None,
                     },
                     &collateral,
                     &env.traders[0],
@@ -284,6 +294,7 @@ fn test_combined_actions() {
                         amount: Uint128::zero(),
                         vault_id: Some(vault_id),
                         rebase: false,
+                        market_id: None,
                     },
                     &[],
                     &env.traders[0],
@@ -314,6 +325,7 @@ fn test_combined_actions() {
                     amount: Uint128::from(amount),
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &collateral,
                 &env.traders[1],
@@ -368,8 +380,12 @@ fn test_deposit_and_withdraw_with_fee() {
     let bank = Bank::new(&env.app);
     let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
 
-    let config: ConfigResponse = wasm.query(&perp_address, &QueryMsg::Config {}).unwrap();
-    let state: StateResponse = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+    let config: ConfigResponse = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+    let state: StateResponse = wasm
+        .query(&perp_address, &QueryMsg::State { market_id: None })
+        .unwrap();
 
     assert_eq!(Decimal::one(), state.normalisation_factor);
 
@@ -397,7 +413,7 @@ fn test_deposit_and_withdraw_with_fee() {
 
     wasm.execute(
         &perp_address,
-        &ExecuteMsg::ApplyFunding {},
+        &ExecuteMsg::ApplyFunding { market_id: None },
         &[],
         &env.signer,
     )
@@ -410,14 +426,19 @@ fn test_deposit_and_withdraw_with_fee() {
         wasm.execute(
             &perp_address,
             &ExecuteMsg::UpdateConfig {
-                fee_rate: Some("0.001".to_string()),
-                fee_pool: None,
+                new_config: UpdateConfig {
+                    fee_rate: Some("0.001".to_string()),
+                    ..Default::default()
+                },
+                market_id: None,
             },
             &[],
             &env.signer,
         )
         .unwrap();
-        let config: ConfigResponse = wasm.query(&perp_address, &QueryMsg::Config {}).unwrap();
+        let config: ConfigResponse = wasm
+            .query(&perp_address, &QueryMsg::Config { market_id: None })
+            .unwrap();
 
         assert_eq!(config.fee_rate, Decimal::from_str("0.001").unwrap());
     }
@@ -433,6 +454,7 @@ fn test_deposit_and_withdraw_with_fee() {
                     amount: Uint128::from(amount),
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &[],
                 &env.traders[1],
@@ -459,6 +481,7 @@ fn test_deposit_and_withdraw_with_fee() {
                     amount: Uint128::from(amount),
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &collateral,
                 &env.traders[1],
@@ -516,6 +539,7 @@ fn test_deposit_and_withdraw_with_fee() {
                 amount: Uint128::from(amount),
                 vault_id: Some(vault_id),
                 rebase: false,
+                market_id: None,
             },
             &[],
             &env.traders[1],
@@ -572,6 +596,7 @@ fn test_deposit_and_withdraw_with_fee() {
                     amount: Uint128::from(amount),
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &collateral,
                 &env.traders[0],
@@ -629,6 +654,7 @@ fn test_deposit_and_withdraw_with_fee() {
                 amount: Uint128::from(amount),
                 vault_id: Some(vault_id),
                 rebase: false,
+                market_id: None,
             },
             &[],
             &env.traders[0],
@@ -665,14 +691,19 @@ fn test_deposit_and_withdraw_with_fee() {
         wasm.execute(
             &perp_address,
             &ExecuteMsg::UpdateConfig {
-                fee_rate: Some("0.0".to_string()),
-                fee_pool: None,
+                new_config: UpdateConfig {
+                    fee_rate: Some("0.0".to_string()),
+                    ..Default::default()
+                },
+                market_id: None,
             },
             &[],
             &env.signer,
         )
         .unwrap();
-        let config: ConfigResponse = wasm.query(&perp_address, &QueryMsg::Config {}).unwrap();
+        let config: ConfigResponse = wasm
+            .query(&perp_address, &QueryMsg::Config { market_id: None })
+            .unwrap();
 
         assert_eq!(config.fee_rate, Decimal::zero());
     }
@@ -1,7 +1,18 @@
-use crate::state::Config;
+use crate::{
+    contract::CONTRACT_NAME,
+    state::{
+        Config, COLLATERAL_RATIO_DENOMINATOR, COLLATERAL_RATIO_NUMERATOR, FUNDING_LOWER_BOUND,
+        FUNDING_UPPER_BOUND, LIQUIDATION_AUCTION_DURATION, LIQUIDATION_BOUNTY, MAX_TWAP_PERIOD,
+        MIN_COLLATERAL, TWAP_PERIOD,
+    },
+};
 
 use cosmwasm_std::{Addr, Decimal};
-use margined_protocol::power::Pool;
+use margined_protocol::power::{
+    ConfigResponse, ExecuteMsg, FeeSchedule, Oracle, Pool, PriceType, QueryMsg, UpdateConfig,
+};
+use margined_testing::{helpers::parse_event_attribute, power_env::PowerEnv};
+use osmosis_test_tube::{Module, RunnerError, Wasm};
 
 #[test]
 fn test_config_validation() {
@@ -24,6 +35,23 @@ fn test_config_validation() {
             funding_period: 100,
             base_decimals: 60,
             power_decimals: 6,
+            min_liquidation_bonus: LIQUIDATION_BOUNTY,
+            max_liquidation_bonus: LIQUIDATION_BOUNTY,
+            liquidation_auction_duration: LIQUIDATION_AUCTION_DURATION,
+            insurance_fee_share: Decimal::zero(),
+            collateral_ratio_numerator: COLLATERAL_RATIO_NUMERATOR,
+            collateral_ratio_denominator: COLLATERAL_RATIO_DENOMINATOR,
+            min_collateral: MIN_COLLATERAL,
+            twap_period: TWAP_PERIOD,
+            max_twap_period: MAX_TWAP_PERIOD,
+            funding_lower_bound: FUNDING_LOWER_BOUND,
+            funding_upper_bound: FUNDING_UPPER_BOUND,
+            oracle: Oracle::default(),
+            max_spot_deviation: Decimal::zero(),
+            power_price_type: PriceType::default(),
+            flash_mint_fee_rate: Decimal::zero(),
+            fee_schedule: FeeSchedule::default(),
+            fee_recipients: vec![],
         };
 
         let err = config.validate().unwrap_err();
@@ -49,6 +77,23 @@ fn test_config_validation() {
             funding_period: 100,
             base_decimals: 6,
             power_decimals: 19,
+            min_liquidation_bonus: LIQUIDATION_BOUNTY,
+            max_liquidation_bonus: LIQUIDATION_BOUNTY,
+            liquidation_auction_duration: LIQUIDATION_AUCTION_DURATION,
+            insurance_fee_share: Decimal::zero(),
+            collateral_ratio_numerator: COLLATERAL_RATIO_NUMERATOR,
+            collateral_ratio_denominator: COLLATERAL_RATIO_DENOMINATOR,
+            min_collateral: MIN_COLLATERAL,
+            twap_period: TWAP_PERIOD,
+            max_twap_period: MAX_TWAP_PERIOD,
+            funding_lower_bound: FUNDING_LOWER_BOUND,
+            funding_upper_bound: FUNDING_UPPER_BOUND,
+            oracle: Oracle::default(),
+            max_spot_deviation: Decimal::zero(),
+            power_price_type: PriceType::default(),
+            flash_mint_fee_rate: Decimal::zero(),
+            fee_schedule: FeeSchedule::default(),
+            fee_recipients: vec![],
         };
 
         let err = config.validate().unwrap_err();
@@ -74,6 +119,23 @@ fn test_config_validation() {
             funding_period: 0,
             base_decimals: 6,
             power_decimals: 6,
+            min_liquidation_bonus: LIQUIDATION_BOUNTY,
+            max_liquidation_bonus: LIQUIDATION_BOUNTY,
+            liquidation_auction_duration: LIQUIDATION_AUCTION_DURATION,
+            insurance_fee_share: Decimal::zero(),
+            collateral_ratio_numerator: COLLATERAL_RATIO_NUMERATOR,
+            collateral_ratio_denominator: COLLATERAL_RATIO_DENOMINATOR,
+            min_collateral: MIN_COLLATERAL,
+            twap_period: TWAP_PERIOD,
+            max_twap_period: MAX_TWAP_PERIOD,
+            funding_lower_bound: FUNDING_LOWER_BOUND,
+            funding_upper_bound: FUNDING_UPPER_BOUND,
+            oracle: Oracle::default(),
+            max_spot_deviation: Decimal::zero(),
+            power_price_type: PriceType::default(),
+            flash_mint_fee_rate: Decimal::zero(),
+            fee_schedule: FeeSchedule::default(),
+            fee_recipients: vec![],
         };
 
         let err = config.validate().unwrap_err();
@@ -102,6 +164,23 @@ fn test_config_validation() {
             funding_period: 100,
             base_decimals: 6,
             power_decimals: 6,
+            min_liquidation_bonus: LIQUIDATION_BOUNTY,
+            max_liquidation_bonus: LIQUIDATION_BOUNTY,
+            liquidation_auction_duration: LIQUIDATION_AUCTION_DURATION,
+            insurance_fee_share: Decimal::zero(),
+            collateral_ratio_numerator: COLLATERAL_RATIO_NUMERATOR,
+            collateral_ratio_denominator: COLLATERAL_RATIO_DENOMINATOR,
+            min_collateral: MIN_COLLATERAL,
+            twap_period: TWAP_PERIOD,
+            max_twap_period: MAX_TWAP_PERIOD,
+            funding_lower_bound: FUNDING_LOWER_BOUND,
+            funding_upper_bound: FUNDING_UPPER_BOUND,
+            oracle: Oracle::default(),
+            max_spot_deviation: Decimal::zero(),
+            power_price_type: PriceType::default(),
+            flash_mint_fee_rate: Decimal::zero(),
+            fee_schedule: FeeSchedule::default(),
+            fee_recipients: vec![],
         };
 
         let err = config.validate().unwrap_err();
@@ -130,6 +209,23 @@ fn test_config_validation() {
             funding_period: 100,
             base_decimals: 6,
             power_decimals: 6,
+            min_liquidation_bonus: LIQUIDATION_BOUNTY,
+            max_liquidation_bonus: LIQUIDATION_BOUNTY,
+            liquidation_auction_duration: LIQUIDATION_AUCTION_DURATION,
+            insurance_fee_share: Decimal::zero(),
+            collateral_ratio_numerator: COLLATERAL_RATIO_NUMERATOR,
+            collateral_ratio_denominator: COLLATERAL_RATIO_DENOMINATOR,
+            min_collateral: MIN_COLLATERAL,
+            twap_period: TWAP_PERIOD,
+            max_twap_period: MAX_TWAP_PERIOD,
+            funding_lower_bound: FUNDING_LOWER_BOUND,
+            funding_upper_bound: FUNDING_UPPER_BOUND,
+            oracle: Oracle::default(),
+            max_spot_deviation: Decimal::zero(),
+            power_price_type: PriceType::default(),
+            flash_mint_fee_rate: Decimal::zero(),
+            fee_schedule: FeeSchedule::default(),
+            fee_recipients: vec![],
         };
 
         let err = config.validate().unwrap_err();
@@ -138,4 +234,282 @@ fn test_config_validation() {
             "Generic error: Invalid base and power pool id must be different"
         );
     }
+
+    // invalid liquidation bonus, minimum greater than maximum
+    {
+        let config = Config {
+            fee_rate: Decimal::percent(0),
+            fee_pool_contract: Addr::unchecked("fee_pool".to_string()),
+            query_contract: Addr::unchecked("query".to_string()),
+            power_denom: "power".to_string(),
+            base_denom: "base".to_string(),
+            base_pool: Pool {
+                id: 1,
+                quote_denom: "base_quote".to_string(),
+            },
+            power_pool: Pool {
+                id: 2,
+                quote_denom: "power_quote".to_string(),
+            },
+            funding_period: 100,
+            base_decimals: 6,
+            power_decimals: 6,
+            min_liquidation_bonus: Decimal::percent(110),
+            max_liquidation_bonus: Decimal::percent(105),
+            liquidation_auction_duration: LIQUIDATION_AUCTION_DURATION,
+            insurance_fee_share: Decimal::zero(),
+            collateral_ratio_numerator: COLLATERAL_RATIO_NUMERATOR,
+            collateral_ratio_denominator: COLLATERAL_RATIO_DENOMINATOR,
+            min_collateral: MIN_COLLATERAL,
+            twap_period: TWAP_PERIOD,
+            max_twap_period: MAX_TWAP_PERIOD,
+            funding_lower_bound: FUNDING_LOWER_BOUND,
+            funding_upper_bound: FUNDING_UPPER_BOUND,
+            oracle: Oracle::default(),
+            max_spot_deviation: Decimal::zero(),
+            power_price_type: PriceType::default(),
+            flash_mint_fee_rate: Decimal::zero(),
+            fee_schedule: FeeSchedule::default(),
+            fee_recipients: vec![],
+        };
+
+        let err = config.validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Generic error: Invalid liquidation bonus, must satisfy 100% <= min <= max <= 150%"
+        );
+    }
+
+    // invalid collateral ratio, not above 100%
+    {
+        let config = Config {
+            fee_rate: Decimal::percent(0),
+            fee_pool_contract: Addr::unchecked("fee_pool".to_string()),
+            query_contract: Addr::unchecked("query".to_string()),
+            power_denom: "power".to_string(),
+            base_denom: "base".to_string(),
+            base_pool: Pool {
+                id: 1,
+                quote_denom: "base_quote".to_string(),
+            },
+            power_pool: Pool {
+                id: 2,
+                quote_denom: "power_quote".to_string(),
+            },
+            funding_period: 100,
+            base_decimals: 6,
+            power_decimals: 6,
+            min_liquidation_bonus: LIQUIDATION_BOUNTY,
+            max_liquidation_bonus: LIQUIDATION_BOUNTY,
+            liquidation_auction_duration: LIQUIDATION_AUCTION_DURATION,
+            insurance_fee_share: Decimal::zero(),
+            collateral_ratio_numerator: Decimal::one(),
+            collateral_ratio_denominator: Decimal::one(),
+            min_collateral: MIN_COLLATERAL,
+            twap_period: TWAP_PERIOD,
+            max_twap_period: MAX_TWAP_PERIOD,
+            funding_lower_bound: FUNDING_LOWER_BOUND,
+            funding_upper_bound: FUNDING_UPPER_BOUND,
+            oracle: Oracle::default(),
+            max_spot_deviation: Decimal::zero(),
+            power_price_type: PriceType::default(),
+            flash_mint_fee_rate: Decimal::zero(),
+            fee_schedule: FeeSchedule::default(),
+            fee_recipients: vec![],
+        };
+
+        let err = config.validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Generic error: Invalid collateral ratio, must be greater than 100%"
+        );
+    }
+
+    // invalid twap period, greater than max twap period
+    {
+        let config = Config {
+            fee_rate: Decimal::percent(0),
+            fee_pool_contract: Addr::unchecked("fee_pool".to_string()),
+            query_contract: Addr::unchecked("query".to_string()),
+            power_denom: "power".to_string(),
+            base_denom: "base".to_string(),
+            base_pool: Pool {
+                id: 1,
+                quote_denom: "base_quote".to_string(),
+            },
+            power_pool: Pool {
+                id: 2,
+                quote_denom: "power_quote".to_string(),
+            },
+            funding_period: 100,
+            base_decimals: 6,
+            power_decimals: 6,
+            min_liquidation_bonus: LIQUIDATION_BOUNTY,
+            max_liquidation_bonus: LIQUIDATION_BOUNTY,
+            liquidation_auction_duration: LIQUIDATION_AUCTION_DURATION,
+            insurance_fee_share: Decimal::zero(),
+            collateral_ratio_numerator: COLLATERAL_RATIO_NUMERATOR,
+            collateral_ratio_denominator: COLLATERAL_RATIO_DENOMINATOR,
+            min_collateral: MIN_COLLATERAL,
+            twap_period: MAX_TWAP_PERIOD + 1,
+            max_twap_period: MAX_TWAP_PERIOD,
+            funding_lower_bound: FUNDING_LOWER_BOUND,
+            funding_upper_bound: FUNDING_UPPER_BOUND,
+            oracle: Oracle::default(),
+            max_spot_deviation: Decimal::zero(),
+            power_price_type: PriceType::default(),
+            flash_mint_fee_rate: Decimal::zero(),
+            fee_schedule: FeeSchedule::default(),
+            fee_recipients: vec![],
+        };
+
+        let err = config.validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Generic error: Invalid twap period, must be between 0 and 172800 seconds"
+        );
+    }
+
+    // invalid funding bounds, lower bound above index
+    {
+        let config = Config {
+            fee_rate: Decimal::percent(0),
+            fee_pool_contract: Addr::unchecked("fee_pool".to_string()),
+            query_contract: Addr::unchecked("query".to_string()),
+            power_denom: "power".to_string(),
+            base_denom: "base".to_string(),
+            base_pool: Pool {
+                id: 1,
+                quote_denom: "base_quote".to_string(),
+            },
+            power_pool: Pool {
+                id: 2,
+                quote_denom: "power_quote".to_string(),
+            },
+            funding_period: 100,
+            base_decimals: 6,
+            power_decimals: 6,
+            min_liquidation_bonus: LIQUIDATION_BOUNTY,
+            max_liquidation_bonus: LIQUIDATION_BOUNTY,
+            liquidation_auction_duration: LIQUIDATION_AUCTION_DURATION,
+            insurance_fee_share: Decimal::zero(),
+            collateral_ratio_numerator: COLLATERAL_RATIO_NUMERATOR,
+            collateral_ratio_denominator: COLLATERAL_RATIO_DENOMINATOR,
+            min_collateral: MIN_COLLATERAL,
+            twap_period: TWAP_PERIOD,
+            max_twap_period: MAX_TWAP_PERIOD,
+            funding_lower_bound: Decimal::percent(120),
+            funding_upper_bound: FUNDING_UPPER_BOUND,
+            oracle: Oracle::default(),
+            max_spot_deviation: Decimal::zero(),
+            power_price_type: PriceType::default(),
+            flash_mint_fee_rate: Decimal::zero(),
+            fee_schedule: FeeSchedule::default(),
+            fee_recipients: vec![],
+        };
+
+        let err = config.validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Generic error: Invalid funding bounds, must satisfy 0% < lower <= 100% <= upper <= 200%"
+        );
+    }
+}
+
+#[test]
+fn test_update_config_risk_parameters() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), false);
+
+    // should revert if not the owner
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::UpdateConfig {
+                    new_config: UpdateConfig {
+                        twap_period: Some(600u64),
+                        ..Default::default()
+                    },
+                    market_id: None,
+                },
+                &[],
+                &env.traders[0],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Unauthorized: execute wasm contract failed".to_string()
+            }
+        );
+    }
+
+    // should revert if the parameters are out of bounds
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::UpdateConfig {
+                    new_config: UpdateConfig {
+                        twap_period: Some(MAX_TWAP_PERIOD + 1),
+                        ..Default::default()
+                    },
+                    market_id: None,
+                },
+                &[],
+                &env.signer,
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Generic error: Invalid twap period, must be between 0 and 172800 seconds: execute wasm contract failed".to_string()
+            }
+        );
+    }
+
+    // owner updates the risk parameters
+    {
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::UpdateConfig {
+                    new_config: UpdateConfig {
+                        collateral_ratio_numerator: Some(Decimal::percent(175)),
+                        collateral_ratio_denominator: Some(Decimal::one()),
+                        min_collateral: Some(Decimal::one()),
+                        twap_period: Some(600u64),
+                        max_twap_period: Some(24 * 60 * 60),
+                        funding_lower_bound: Some(Decimal::percent(90)),
+                        funding_upper_bound: Some(Decimal::percent(120)),
+                        ..Default::default()
+                    },
+                    market_id: None,
+                },
+                &[],
+                &env.signer,
+            )
+            .unwrap();
+
+        assert_eq!(
+            parse_event_attribute(response.events, "wasm-update_config", "twap_period"),
+            "600"
+        );
+
+        let config: ConfigResponse = wasm
+            .query(&perp_address, &QueryMsg::Config { market_id: None })
+            .unwrap();
+
+        assert_eq!(config.collateral_ratio_numerator, Decimal::percent(175));
+        assert_eq!(config.collateral_ratio_denominator, Decimal::one());
+        assert_eq!(config.min_collateral, Decimal::one());
+        assert_eq!(config.twap_period, 600u64);
+        assert_eq!(config.max_twap_period, 24 * 60 * 60);
+        assert_eq!(config.funding_lower_bound, Decimal::percent(90));
+        assert_eq!(config.funding_upper_bound, Decimal::percent(120));
+    }
 }
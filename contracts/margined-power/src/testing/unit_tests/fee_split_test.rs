@@ -0,0 +1,175 @@
+use crate::{contract::CONTRACT_NAME, state::Config};
+
+use cosmwasm_std::{coin, Decimal, Uint128};
+use margined_protocol::power::{
+    ConfigResponse, ExecuteMsg, FeePreviewResponse, FeeRecipient, QueryMsg, UpdateConfig,
+};
+use margined_testing::{helpers::parse_event_attribute, power_env::PowerEnv};
+use osmosis_test_tube::{Account, Module, Wasm};
+use std::str::FromStr;
+
+#[test]
+fn test_fee_split() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.base_pool_id,
+        Decimal::from_str("3000.0").unwrap(),
+    );
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.power_pool_id,
+        Decimal::from_str("3030.0").unwrap(),
+    );
+
+    let insurance_fund = env.app.init_account(&[]).unwrap();
+    let treasury = env.app.init_account(&[]).unwrap();
+
+    let fee_recipients = vec![
+        FeeRecipient {
+            address: env.fee_pool.address(),
+            weight_bps: 5_000,
+        },
+        FeeRecipient {
+            address: insurance_fund.address(),
+            weight_bps: 3_000,
+        },
+        FeeRecipient {
+            address: treasury.address(),
+            weight_bps: 2_000,
+        },
+    ];
+
+    // should revert if the weights do not sum to 100%
+    {
+        let mut invalid_recipients = fee_recipients.clone();
+        invalid_recipients[2].weight_bps = 1_000;
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::UpdateConfig {
+                market_id: None,
+                new_config: UpdateConfig {
+                    fee_recipients: Some(invalid_recipients),
+                    ..Default::default()
+                },
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap_err();
+    }
+
+    // should revert if a recipient is listed twice
+    {
+        let mut invalid_recipients = fee_recipients.clone();
+        invalid_recipients[2].address = env.fee_pool.address();
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::UpdateConfig {
+                market_id: None,
+                new_config: UpdateConfig {
+                    fee_recipients: Some(invalid_recipients),
+                    ..Default::default()
+                },
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap_err();
+    }
+
+    wasm.execute(
+        &perp_address,
+        &ExecuteMsg::UpdateConfig {
+            market_id: None,
+            new_config: UpdateConfig {
+                fee_rate: Some("0.01".to_string()),
+                fee_recipients: Some(fee_recipients.clone()),
+                ..Default::default()
+            },
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    let config: ConfigResponse = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+    assert_eq!(config.fee_recipients, fee_recipients);
+
+    // should split the mint fee between the recipients by weight
+    {
+        let preview: FeePreviewResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetFeePreview {
+                    user: env.traders[0].address(),
+                    amount: Uint128::from(10_000_000u128),
+                    market_id: None,
+                },
+            )
+            .unwrap();
+        assert!(!preview.fee_amount.is_zero());
+
+        let fee_pool_before = env.get_balance(env.fee_pool.address(), env.denoms["base"].clone());
+
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::MintPowerPerp {
+                    amount: Uint128::from(10_000_000u128),
+                    vault_id: None,
+                    rebase: false,
+                    market_id: None,
+                },
+                &[coin(45_000_000u128, env.denoms["base"].clone())],
+                &env.traders[0],
+            )
+            .unwrap();
+
+        let fee_pool_amount =
+            env.get_balance(env.fee_pool.address(), env.denoms["base"].clone()) - fee_pool_before;
+        let insurance_fund_amount =
+            env.get_balance(insurance_fund.address(), env.denoms["base"].clone());
+        let treasury_amount = env.get_balance(treasury.address(), env.denoms["base"].clone());
+
+        assert_eq!(
+            fee_pool_amount,
+            preview.fee_amount.multiply_ratio(5_000u128, 10_000u128)
+        );
+        assert_eq!(
+            insurance_fund_amount,
+            preview.fee_amount.multiply_ratio(3_000u128, 10_000u128)
+        );
+        assert_eq!(
+            fee_pool_amount + insurance_fund_amount + treasury_amount,
+            preview.fee_amount
+        );
+
+        assert_eq!(
+            parse_event_attribute(response.events, "wasm-mint", "fee_splits"),
+            format!(
+                "{}:{},{}:{},{}:{}",
+                env.fee_pool.address(),
+                fee_pool_amount,
+                insurance_fund.address(),
+                insurance_fund_amount,
+                treasury.address(),
+                treasury_amount
+            )
+        );
+    }
+}
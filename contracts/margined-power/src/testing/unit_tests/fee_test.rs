@@ -0,0 +1,226 @@
+use crate::{contract::CONTRACT_NAME, state::Config};
+
+use cosmwasm_std::{coin, Decimal, Uint128};
+use margined_protocol::{
+    power::{
+        ExecuteMsg, FeePreviewResponse, FeeSchedule, FeeTier, QueryMsg, StakingDiscount,
+        UpdateConfig,
+    },
+    staking::{ExecuteMsg as StakingExecuteMsg, InstantiateMsg as StakingInstantiateMsg},
+};
+use margined_testing::{helpers::store_code, power_env::PowerEnv};
+use osmosis_test_tube::{Account, Module, Wasm};
+use std::str::FromStr;
+
+#[test]
+fn test_fee_schedule() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.base_pool_id,
+        Decimal::from_str("3000.0").unwrap(),
+    );
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.power_pool_id,
+        Decimal::from_str("3030.0").unwrap(),
+    );
+
+    let code_id = store_code(&wasm, &env.signer, "margined_staking".to_string());
+    let staking_address = wasm
+        .instantiate(
+            code_id,
+            &StakingInstantiateMsg {
+                fee_collector: env.fee_pool.address(),
+                deposit_denom: env.denoms["power"].clone(),
+                reward_denom: env.denoms["power"].clone(),
+                deposit_decimals: 6u32,
+                reward_decimals: 6u32,
+                tokens_per_interval: 1_000_000u128.into(),
+            },
+            None,
+            Some("margined-staking-contract"),
+            &[],
+            &env.signer,
+        )
+        .unwrap()
+        .data
+        .address;
+
+    wasm.execute(
+        &staking_address,
+        &StakingExecuteMsg::Unpause {},
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    let fee_schedule = FeeSchedule {
+        size_tiers: vec![FeeTier {
+            min_value: Uint128::from(10_000_000u128),
+            fee_rate: Decimal::permille(5),
+        }],
+        staking_contract: Some(staking_address.clone()),
+        staking_discounts: vec![StakingDiscount {
+            min_staked: Uint128::from(1_000_000u128),
+            discount: Decimal::percent(50),
+        }],
+    };
+
+    // should revert if the tiers are not ascending
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::UpdateConfig {
+                market_id: None,
+                new_config: UpdateConfig {
+                    fee_schedule: Some(FeeSchedule {
+                        size_tiers: vec![
+                            FeeTier {
+                                min_value: Uint128::from(10_000_000u128),
+                                fee_rate: Decimal::permille(5),
+                            },
+                            FeeTier {
+                                min_value: Uint128::from(1_000_000u128),
+                                fee_rate: Decimal::permille(8),
+                            },
+                        ],
+                        ..fee_schedule.clone()
+                    }),
+                    ..Default::default()
+                },
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap_err();
+    }
+
+    wasm.execute(
+        &perp_address,
+        &ExecuteMsg::UpdateConfig {
+            market_id: None,
+            new_config: UpdateConfig {
+                fee_rate: Some("0.01".to_string()),
+                fee_schedule: Some(fee_schedule),
+                ..Default::default()
+            },
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    let preview = |amount: u128| -> FeePreviewResponse {
+        wasm.query(
+            &perp_address,
+            &QueryMsg::GetFeePreview {
+                user: env.traders[0].address(),
+                amount: Uint128::from(amount),
+                market_id: None,
+            },
+        )
+        .unwrap()
+    };
+
+    // should apply the fee rate below the first tier and the tier rate above it
+    {
+        let small = preview(1_000_000u128);
+        assert_eq!(small.fee_rate, Decimal::percent(1));
+        assert_eq!(small.staked_amount, Uint128::zero());
+
+        let large = preview(100_000_000u128);
+        assert!(large.mint_value >= Uint128::from(10_000_000u128));
+        assert_eq!(large.fee_rate, Decimal::permille(5));
+    }
+
+    // should charge the previewed fee when minting
+    {
+        let expected = preview(10_000_000u128);
+        assert!(!expected.fee_amount.is_zero());
+
+        let fee_pool_before = env.get_balance(env.fee_pool.address(), env.denoms["base"].clone());
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: Uint128::from(10_000_000u128),
+                vault_id: None,
+                rebase: false,
+                market_id: None,
+            },
+            &[coin(45_000_000u128, env.denoms["base"].clone())],
+            &env.traders[0],
+        )
+        .unwrap();
+
+        let fee_pool_after = env.get_balance(env.fee_pool.address(), env.denoms["base"].clone());
+        assert_eq!(fee_pool_after - fee_pool_before, expected.fee_amount);
+    }
+
+    // should discount the fee of stakers
+    {
+        wasm.execute(
+            &staking_address,
+            &StakingExecuteMsg::Stake {},
+            &[coin(1_000_000u128, env.denoms["power"].clone())],
+            &env.traders[0],
+        )
+        .unwrap();
+
+        let discounted = preview(1_000_000u128);
+        assert_eq!(discounted.staked_amount, Uint128::from(1_000_000u128));
+        assert_eq!(discounted.fee_rate, Decimal::permille(5));
+    }
+
+    // should charge the full fee rather than revert if the staking query fails
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::UpdateConfig {
+                market_id: None,
+                new_config: UpdateConfig {
+                    fee_schedule: Some(FeeSchedule {
+                        size_tiers: vec![],
+                        staking_contract: Some(perp_address.clone()),
+                        staking_discounts: vec![StakingDiscount {
+                            min_staked: Uint128::from(1_000_000u128),
+                            discount: Decimal::percent(50),
+                        }],
+                    }),
+                    ..Default::default()
+                },
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+
+        let undiscounted = preview(1_000_000u128);
+        assert_eq!(undiscounted.staked_amount, Uint128::zero());
+        assert_eq!(undiscounted.fee_rate, Decimal::percent(1));
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: Uint128::from(1_000_000u128),
+                vault_id: None,
+                rebase: false,
+                market_id: None,
+            },
+            &[coin(5_000_000u128, env.denoms["base"].clone())],
+            &env.traders[0],
+        )
+        .unwrap();
+    }
+}
@@ -0,0 +1,421 @@
+use crate::{contract::CONTRACT_NAME, state::Config};
+
+use cosmwasm_std::{coin, to_binary, BankMsg, CosmosMsg, Decimal, Uint128};
+use margined_protocol::power::{
+    ExecuteMsg, FeeRecipient, FlashMintCallback, QueryMsg, StateResponse, UpdateConfig,
+    VaultResponse,
+};
+use margined_testing::{
+    helpers::{parse_event_attribute, store_code},
+    power_env::PowerEnv,
+};
+use mock_flash_receiver::contract::{
+    ExecuteMsg as FlashReceiverExecuteMsg, InstantiateMsg as FlashReceiverInstantiateMsg,
+};
+use osmosis_test_tube::{Account, Module, RunnerError, Wasm};
+use std::str::FromStr;
+
+#[test]
+fn test_flash_mint() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.base_pool_id,
+        Decimal::from_str("3000.0").unwrap(),
+    );
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.power_pool_id,
+        Decimal::from_str("3030.0").unwrap(),
+    );
+
+    let code_id = store_code(&wasm, &env.signer, "mock_flash_receiver".to_string());
+    let receiver_address = wasm
+        .instantiate(
+            code_id,
+            &FlashReceiverInstantiateMsg {},
+            None,
+            Some("mock-flash-receiver"),
+            &[],
+            &env.signer,
+        )
+        .unwrap()
+        .data
+        .address;
+
+    let callback = |msgs: Vec<CosmosMsg>| FlashMintCallback {
+        contract: receiver_address.clone(),
+        msg: to_binary(&FlashReceiverExecuteMsg::Execute { msgs }).unwrap(),
+    };
+
+    let repay = |amount: u128| -> Vec<CosmosMsg> {
+        vec![BankMsg::Send {
+            to_address: perp_address.clone(),
+            amount: vec![coin(amount, env.denoms["power"].clone())],
+        }
+        .into()]
+    };
+
+    wasm.execute(
+        &perp_address,
+        &ExecuteMsg::UpdateConfig {
+            market_id: None,
+            new_config: UpdateConfig {
+                flash_mint_fee_rate: Some(Decimal::percent(1)),
+                ..Default::default()
+            },
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    // should burn the power returned and charge the fee, refunding the excess
+    {
+        let supply_before = env.get_total_supply(env.denoms["power"].clone());
+        let base_before = env.get_balance(env.traders[0].address(), env.denoms["base"].clone());
+        let fee_pool_before = env.get_balance(env.fee_pool.address(), env.denoms["base"].clone());
+
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::FlashMint {
+                    amount: Uint128::from(10_000_000u128),
+                    callback: callback(repay(10_000_000u128)),
+                    vault_id: None,
+                    market_id: None,
+                },
+                &[coin(10_000_000u128, env.denoms["base"].clone())],
+                &env.traders[0],
+            )
+            .unwrap();
+
+        let fee_amount = Uint128::from_str(&parse_event_attribute(
+            response.events.clone(),
+            "wasm-flash_mint",
+            "fee_amount",
+        ))
+        .unwrap();
+        assert!(!fee_amount.is_zero());
+
+        assert_eq!(
+            parse_event_attribute(response.events, "wasm-flash_mint_repaid", "burn_amount"),
+            "10000000"
+        );
+
+        let supply_after = env.get_total_supply(env.denoms["power"].clone());
+        assert_eq!(supply_before, supply_after);
+
+        let base_after = env.get_balance(env.traders[0].address(), env.denoms["base"].clone());
+        assert_eq!(base_before - base_after, fee_amount);
+
+        let fee_pool_after = env.get_balance(env.fee_pool.address(), env.denoms["base"].clone());
+        assert_eq!(fee_pool_after - fee_pool_before, fee_amount);
+    }
+
+    // should revert if the fee is not paid
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::FlashMint {
+                    amount: Uint128::from(10_000_000u128),
+                    callback: callback(repay(10_000_000u128)),
+                    vault_id: None,
+                    market_id: None,
+                },
+                &[],
+                &env.traders[0],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Invalid funds: execute wasm contract failed".to_string()
+            }
+        );
+    }
+
+    wasm.execute(
+        &perp_address,
+        &ExecuteMsg::UpdateConfig {
+            market_id: None,
+            new_config: UpdateConfig {
+                flash_mint_fee_rate: Some(Decimal::zero()),
+                ..Default::default()
+            },
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    // should revert if the power is not returned and no vault covers it
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::FlashMint {
+                    amount: Uint128::from(10_000_000u128),
+                    callback: callback(repay(5_000_000u128)),
+                    vault_id: None,
+                    market_id: None,
+                },
+                &[],
+                &env.traders[0],
+            )
+            .unwrap_err();
+        assert!(
+            matches!(err, RunnerError::ExecuteError { msg } if msg.contains("Flash mint not repaid, 5000000 power outstanding"))
+        );
+    }
+
+    let mint_response = wasm
+        .execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: Uint128::from(10_000_000u128),
+                vault_id: None,
+                rebase: false,
+                market_id: None,
+            },
+            &[coin(45_000_000u128, env.denoms["base"].clone())],
+            &env.traders[0],
+        )
+        .unwrap();
+
+    let vault_id = u64::from_str(&parse_event_attribute(
+        mint_response.events,
+        "wasm-mint",
+        "vault_id",
+    ))
+    .unwrap();
+
+    // should add the power not returned to the debt of the vault
+    {
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::FlashMint {
+                    amount: Uint128::from(30_000_000u128),
+                    callback: callback(repay(10_000_000u128)),
+                    vault_id: Some(vault_id),
+                    market_id: None,
+                },
+                &[],
+                &env.traders[0],
+            )
+            .unwrap();
+
+        assert_eq!(
+            parse_event_attribute(response.events, "wasm-flash_mint_repaid", "debt_amount"),
+            "20000000"
+        );
+
+        let vault: VaultResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+        assert_eq!(vault.short_amount, Uint128::from(30_000_000u128));
+
+        let receiver_balance =
+            env.get_balance(receiver_address.clone(), env.denoms["power"].clone());
+        assert_eq!(receiver_balance, Uint128::from(20_000_000u128));
+    }
+
+    // should charge the mint fee on the debt added, taken from the vault collateral
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::UpdateConfig {
+                market_id: None,
+                new_config: UpdateConfig {
+                    fee_rate: Some("0.01".to_string()),
+                    ..Default::default()
+                },
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+
+        let fee_pool_before = env.get_balance(env.fee_pool.address(), env.denoms["base"].clone());
+
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::FlashMint {
+                    amount: Uint128::from(10_000_000u128),
+                    callback: callback(vec![]),
+                    vault_id: Some(vault_id),
+                    market_id: None,
+                },
+                &[],
+                &env.traders[0],
+            )
+            .unwrap();
+
+        let fee_amount = Decimal::from_str(&parse_event_attribute(
+            response.events,
+            "wasm-flash_mint_repaid",
+            "fee_amount",
+        ))
+        .unwrap();
+        assert!(!fee_amount.is_zero());
+
+        let fee_pool_after = env.get_balance(env.fee_pool.address(), env.denoms["base"].clone());
+
+        let vault: VaultResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+        assert_eq!(vault.short_amount, Uint128::from(40_000_000u128));
+        assert!(!(fee_pool_after - fee_pool_before).is_zero());
+        assert_eq!(
+            Uint128::from(45_000_000u128) - vault.collateral,
+            fee_pool_after - fee_pool_before
+        );
+    }
+
+    // should revert if the vault is unsafe once the debt is added
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::FlashMint {
+                    amount: Uint128::from(100_000_000u128),
+                    callback: callback(vec![]),
+                    vault_id: Some(vault_id),
+                    market_id: None,
+                },
+                &[],
+                &env.traders[0],
+            )
+            .unwrap_err();
+        assert!(
+            matches!(err, RunnerError::ExecuteError { msg } if msg.contains("Vault is not safe, cannot perform operation"))
+        );
+    }
+
+    // should revert if the sender does not own the vault, even as an approved operator
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::ApproveOperator {
+                vault_id,
+                spender: env.traders[1].address(),
+                expires: None,
+            },
+            &[],
+            &env.traders[0],
+        )
+        .unwrap();
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::FlashMint {
+                amount: Uint128::from(10_000_000u128),
+                callback: callback(vec![]),
+                vault_id: Some(vault_id),
+                market_id: None,
+            },
+            &[],
+            &env.traders[1],
+        )
+        .unwrap_err();
+    }
+
+    // should split the flash mint fee like the mint fees, keeping the insurance share
+    {
+        let treasury = env.app.init_account(&[]).unwrap();
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::UpdateConfig {
+                market_id: None,
+                new_config: UpdateConfig {
+                    flash_mint_fee_rate: Some(Decimal::percent(1)),
+                    insurance_fee_share: Some(Decimal::percent(10)),
+                    fee_recipients: Some(vec![
+                        FeeRecipient {
+                            address: env.fee_pool.address(),
+                            weight_bps: 5_000,
+                        },
+                        FeeRecipient {
+                            address: treasury.address(),
+                            weight_bps: 5_000,
+                        },
+                    ]),
+                    ..Default::default()
+                },
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+
+        let fee_pool_before = env.get_balance(env.fee_pool.address(), env.denoms["base"].clone());
+        let state_before: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
+
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::FlashMint {
+                    amount: Uint128::from(10_000_000u128),
+                    callback: callback(repay(10_000_000u128)),
+                    vault_id: None,
+                    market_id: None,
+                },
+                &[coin(10_000_000u128, env.denoms["base"].clone())],
+                &env.traders[0],
+            )
+            .unwrap();
+
+        let fee_amount = Uint128::from_str(&parse_event_attribute(
+            response.events.clone(),
+            "wasm-flash_mint",
+            "fee_amount",
+        ))
+        .unwrap();
+        assert!(!fee_amount.is_zero());
+
+        let state_after: StateResponse = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
+        let insurance_amount = state_after.insurance_balance - state_before.insurance_balance;
+        assert_eq!(insurance_amount, fee_amount * Decimal::percent(10));
+
+        let fee_pool_amount =
+            env.get_balance(env.fee_pool.address(), env.denoms["base"].clone()) - fee_pool_before;
+        let treasury_amount = env.get_balance(treasury.address(), env.denoms["base"].clone());
+        assert_eq!(
+            fee_pool_amount,
+            (fee_amount - insurance_amount).multiply_ratio(5_000u128, 10_000u128)
+        );
+        assert_eq!(
+            insurance_amount + fee_pool_amount + treasury_amount,
+            fee_amount
+        );
+
+        assert_eq!(
+            parse_event_attribute(response.events, "wasm-flash_mint", "fee_splits"),
+            format!(
+                "{}:{},{}:{}",
+                env.fee_pool.address(),
+                fee_pool_amount,
+                treasury.address(),
+                treasury_amount
+            )
+        );
+    }
+}
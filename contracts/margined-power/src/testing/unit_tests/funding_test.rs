@@ -1,11 +1,14 @@
 use crate::{
     contract::CONTRACT_NAME,
-    state::{Config, State},
+    state::{Config, State, FUNDING_UPPER_BOUND},
 };
 
 use cosmwasm_std::{coins, Decimal, Uint128};
-use margined_protocol::power::{ExecuteMsg, QueryMsg};
-use margined_testing::{helpers::parse_event_attribute, power_env::PowerEnv};
+use margined_protocol::power::{ExecuteMsg, FundingHistoryResponse, FundingRateResponse, QueryMsg};
+use margined_testing::{
+    helpers::parse_event_attribute,
+    power_env::{PowerEnv, BASE_PRICE, SCALED_POWER_PRICE},
+};
 use mock_query::contract::ExecuteMsg as MockQueryExecuteMsg;
 use osmosis_test_tube::{Module, Wasm};
 use std::str::FromStr;
@@ -17,8 +20,12 @@ fn test_funding_actions() {
     let wasm = Wasm::new(&env.app);
     let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
 
-    let config: Config = wasm.query(&perp_address, &QueryMsg::Config {}).unwrap();
-    let state: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+    let state: State = wasm
+        .query(&perp_address, &QueryMsg::State { market_id: None })
+        .unwrap();
 
     assert_eq!(Decimal::one(), state.normalisation_factor);
 
@@ -46,7 +53,7 @@ fn test_funding_actions() {
 
     wasm.execute(
         &perp_address,
-        &ExecuteMsg::ApplyFunding {},
+        &ExecuteMsg::ApplyFunding { market_id: None },
         &[],
         &env.signer,
     )
@@ -56,7 +63,9 @@ fn test_funding_actions() {
     {
         // should apply the correct normalisation factor for funding
         {
-            let state_before: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state_before: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
             assert_eq!(
                 Decimal::raw(999_994_436_783_524_723u128),
                 state_before.normalisation_factor
@@ -66,13 +75,15 @@ fn test_funding_actions() {
 
             wasm.execute(
                 &perp_address,
-                &ExecuteMsg::ApplyFunding {},
+                &ExecuteMsg::ApplyFunding { market_id: None },
                 &[],
                 &env.signer,
             )
             .unwrap();
 
-            let state_after: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state_after: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
 
             let expected_normalisation_factor: Decimal = Decimal::raw(997_593_962_860_445_984u128);
 
@@ -111,13 +122,15 @@ fn test_funding_actions() {
 
             wasm.execute(
                 &perp_address,
-                &ExecuteMsg::ApplyFunding {},
+                &ExecuteMsg::ApplyFunding { market_id: None },
                 &[],
                 &env.signer,
             )
             .unwrap();
 
-            let state_after: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state_after: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
 
             let expected_normalisation_factor: Decimal = Decimal::raw(995_199_251_244_479_588u128);
 
@@ -156,13 +169,15 @@ fn test_funding_actions() {
 
             wasm.execute(
                 &perp_address,
-                &ExecuteMsg::ApplyFunding {},
+                &ExecuteMsg::ApplyFunding { market_id: None },
                 &[],
                 &env.signer,
             )
             .unwrap();
 
-            let state_after: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state_after: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
 
             let expected_normalisation_factor: Decimal = Decimal::raw(992_810_288_103_280_623u128);
             assert_eq!(
@@ -199,13 +214,15 @@ fn test_funding_actions() {
             // apply funding 0
             wasm.execute(
                 &perp_address,
-                &ExecuteMsg::ApplyFunding {},
+                &ExecuteMsg::ApplyFunding { market_id: None },
                 &[],
                 &env.signer,
             )
             .unwrap();
 
-            let state: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
             let expected_normalisation_factor: Decimal = Decimal::raw(992_806_974_302_083_222u128);
 
             assert_eq!(expected_normalisation_factor, state.normalisation_factor);
@@ -214,13 +231,15 @@ fn test_funding_actions() {
             env.app.increase_time(10u64);
             wasm.execute(
                 &perp_address,
-                &ExecuteMsg::ApplyFunding {},
+                &ExecuteMsg::ApplyFunding { market_id: None },
                 &[],
                 &env.signer,
             )
             .unwrap();
 
-            let state: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
             let expected_normalisation_factor: Decimal = Decimal::raw(992_803_660_511_946_624u128);
 
             assert_eq!(expected_normalisation_factor, state.normalisation_factor);
@@ -229,13 +248,15 @@ fn test_funding_actions() {
             env.app.increase_time(10u64);
             wasm.execute(
                 &perp_address,
-                &ExecuteMsg::ApplyFunding {},
+                &ExecuteMsg::ApplyFunding { market_id: None },
                 &[],
                 &env.signer,
             )
             .unwrap();
 
-            let state: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
             let expected_normalisation_factor: Decimal = Decimal::raw(992_800_346_732_870_791u128);
 
             assert_eq!(expected_normalisation_factor, state.normalisation_factor);
@@ -278,6 +299,7 @@ fn test_funding_actions() {
                     amount: max_power_to_mint,
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &coins(collateral_amount.u128(), env.denoms["base"].to_string()),
                 &env.traders[0],
@@ -297,6 +319,7 @@ fn test_funding_actions() {
                     amount: expected_amount_can_mint + Uint128::from(1u128),
                     vault_id: Some(vault_id),
                     rebase: false,
+                    market_id: None,
                 },
                 &[],
                 &env.traders[0],
@@ -313,6 +336,7 @@ fn test_funding_actions() {
                     amount: expected_amount_can_mint,
                     vault_id: Some(vault_id),
                     rebase: false,
+                    market_id: None,
                 },
                 &[],
                 &env.traders[0],
@@ -330,6 +354,7 @@ fn test_funding_actions() {
                     amount: max_power_to_mint,
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &coins(collateral_amount.u128(), env.denoms["base"].to_string()),
                 &env.traders[0],
@@ -378,7 +403,9 @@ fn test_funding_actions() {
     {
         // Should get capped normalisation factor when mark = 0
         {
-            let state_before: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state_before: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
             assert_eq!(
                 Decimal::raw(985_657_792_683_450_661u128),
                 state_before.normalisation_factor
@@ -410,13 +437,15 @@ fn test_funding_actions() {
 
             wasm.execute(
                 &perp_address,
-                &ExecuteMsg::ApplyFunding {},
+                &ExecuteMsg::ApplyFunding { market_id: None },
                 &[],
                 &env.signer,
             )
             .unwrap();
 
-            let state_after: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state_after: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
 
             let expected_normalisation_factor: Decimal = Decimal::raw(987_230_796_556_633_421u128);
 
@@ -428,7 +457,9 @@ fn test_funding_actions() {
 
         // Should get capped normalisation factor when index = 0
         {
-            let state_before: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state_before: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
             assert_eq!(
                 Decimal::raw(987_230_796_556_633_421u128),
                 state_before.normalisation_factor
@@ -460,13 +491,15 @@ fn test_funding_actions() {
 
             wasm.execute(
                 &perp_address,
-                &ExecuteMsg::ApplyFunding {},
+                &ExecuteMsg::ApplyFunding { market_id: None },
                 &[],
                 &env.signer,
             )
             .unwrap();
 
-            let state_after: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state_after: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
 
             let expected_normalisation_factor: Decimal = Decimal::raw(984_859_865_721_874_489u128);
 
@@ -504,13 +537,15 @@ fn test_funding_actions() {
 
             wasm.execute(
                 &perp_address,
-                &ExecuteMsg::ApplyFunding {},
+                &ExecuteMsg::ApplyFunding { market_id: None },
                 &[],
                 &env.signer,
             )
             .unwrap();
 
-            let state_after: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state_after: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
 
             let expected_normalisation_factor: Decimal = Decimal::raw(966_103_783_878_107_701u128);
 
@@ -523,13 +558,15 @@ fn test_funding_actions() {
 
             wasm.execute(
                 &perp_address,
-                &ExecuteMsg::ApplyFunding {},
+                &ExecuteMsg::ApplyFunding { market_id: None },
                 &[],
                 &env.signer,
             )
             .unwrap();
 
-            let state_after: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state_after: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
 
             let expected_normalisation_factor: Decimal = Decimal::raw(956_860_653_194_208_855u128);
 
@@ -542,13 +579,15 @@ fn test_funding_actions() {
 
             wasm.execute(
                 &perp_address,
-                &ExecuteMsg::ApplyFunding {},
+                &ExecuteMsg::ApplyFunding { market_id: None },
                 &[],
                 &env.signer,
             )
             .unwrap();
 
-            let state_after: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state_after: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
 
             let expected_normalisation_factor: Decimal = Decimal::raw(947_705_955_519_542_909u128);
 
@@ -559,3 +598,164 @@ fn test_funding_actions() {
         }
     }
 }
+
+#[test]
+fn test_funding_history() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    for (pool_id, price) in [
+        (env.base_pool_id, BASE_PRICE),
+        (env.power_pool_id, SCALED_POWER_PRICE),
+    ] {
+        wasm.execute(
+            config.query_contract.as_ref(),
+            &MockQueryExecuteMsg::AppendPrice {
+                pool_id,
+                price: Decimal::from_atomics(price, 6u32).unwrap(),
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+    }
+
+    // should record a snapshot each time funding is applied
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::ApplyFunding { market_id: None },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+
+        env.app.increase_time(10_795u64); // 3 hours
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::ApplyFunding { market_id: None },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+
+        let history: FundingHistoryResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetFundingHistory {
+                    market_id: None,
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(history.snapshots.len(), 2);
+
+        let first = &history.snapshots[0];
+        assert_eq!(first.old_normalisation_factor, Decimal::one());
+        assert_eq!(first.index, Decimal::from_str("0.09").unwrap());
+        assert_eq!(first.mark, Decimal::from_str("0.0903").unwrap());
+        assert!(!first.is_clamped);
+
+        let second = &history.snapshots[1];
+        assert!(second.timestamp > first.timestamp);
+        assert_eq!(
+            second.old_normalisation_factor,
+            first.new_normalisation_factor
+        );
+        assert!(second.new_normalisation_factor < second.old_normalisation_factor);
+
+        let state: State = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
+        assert_eq!(state.normalisation_factor, second.new_normalisation_factor);
+    }
+
+    // should report longs paying shorts while the mark is above the index
+    {
+        let funding_rate: FundingRateResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetAnnualisedFundingRate {
+                    period: config.twap_period,
+                    market_id: None,
+                },
+            )
+            .unwrap();
+        assert!(funding_rate.longs_pay);
+        assert!(!funding_rate.annualised_rate.is_zero());
+    }
+
+    // should record when the mark is clamped to the funding bounds
+    {
+        wasm.execute(
+            config.query_contract.as_ref(),
+            &MockQueryExecuteMsg::AppendPrice {
+                pool_id: env.power_pool_id,
+                price: Decimal::from_str("3030.0").unwrap(),
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+
+        env.app.increase_time(10_795u64); // 3 hours
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::ApplyFunding { market_id: None },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+
+        let history: FundingHistoryResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetFundingHistory {
+                    market_id: None,
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(history.snapshots.len(), 3);
+
+        let last = &history.snapshots[2];
+        assert!(last.is_clamped);
+        assert_eq!(last.mark, last.index * FUNDING_UPPER_BOUND);
+
+        // should paginate by timestamp
+        let page: FundingHistoryResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetFundingHistory {
+                    market_id: None,
+                    start_after: Some(history.snapshots[0].timestamp.seconds()),
+                    limit: Some(1u32),
+                },
+            )
+            .unwrap();
+        assert_eq!(page.snapshots, vec![history.snapshots[1].clone()]);
+    }
+
+    // should revert if the market does not exist
+    {
+        wasm.query::<QueryMsg, FundingHistoryResponse>(
+            &perp_address,
+            &QueryMsg::GetFundingHistory {
+                market_id: Some(2u64),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap_err();
+    }
+}
@@ -24,8 +24,12 @@ fn test_deployment() {
     let wasm = Wasm::new(&env.app);
     let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
 
-    let config: Config = wasm.query(&perp_address, &QueryMsg::Config {}).unwrap();
-    let state: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+    let state: State = wasm
+        .query(&perp_address, &QueryMsg::State { market_id: None })
+        .unwrap();
 
     assert_eq!(Decimal::one(), state.normalisation_factor);
 
@@ -53,7 +57,7 @@ fn test_deployment() {
 
     wasm.execute(
         &perp_address,
-        &ExecuteMsg::ApplyFunding {},
+        &ExecuteMsg::ApplyFunding { market_id: None },
         &[],
         &env.signer,
     )
@@ -61,7 +65,9 @@ fn test_deployment() {
 
     // check power deployment
     {
-        let config: Config = wasm.query(&perp_address, &QueryMsg::Config {}).unwrap();
+        let config: Config = wasm
+            .query(&perp_address, &QueryMsg::Config { market_id: None })
+            .unwrap();
         assert_eq!(env.denoms["power"], config.power_denom);
     }
 }
@@ -110,8 +116,12 @@ fn test_basic_actions() {
     let bank = Bank::new(&env.app);
     let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
 
-    let config: Config = wasm.query(&perp_address, &QueryMsg::Config {}).unwrap();
-    let state: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+    let state: State = wasm
+        .query(&perp_address, &QueryMsg::State { market_id: None })
+        .unwrap();
 
     assert_eq!(Decimal::one(), state.normalisation_factor);
 
@@ -141,7 +151,7 @@ fn test_basic_actions() {
 
     wasm.execute(
         &perp_address,
-        &ExecuteMsg::ApplyFunding {},
+        &ExecuteMsg::ApplyFunding { market_id: None },
         &[],
         &env.signer,
     )
@@ -154,9 +164,14 @@ fn test_basic_actions() {
             // increase timestamp
             env.app.increase_time(30u64);
 
-            let state: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
             let normalisation_factor: Decimal = wasm
-                .query(&perp_address, &QueryMsg::GetNormalisationFactor {})
+                .query(
+                    &perp_address,
+                    &QueryMsg::GetNormalisationFactor { market_id: None },
+                )
                 .unwrap();
             assert!(state.normalisation_factor > normalisation_factor);
 
@@ -164,28 +179,38 @@ fn test_basic_actions() {
             env.app.increase_time(30u64);
 
             let normalisation_factor_after: Decimal = wasm
-                .query(&perp_address, &QueryMsg::GetNormalisationFactor {})
+                .query(
+                    &perp_address,
+                    &QueryMsg::GetNormalisationFactor { market_id: None },
+                )
                 .unwrap();
             assert!(normalisation_factor > normalisation_factor_after);
         }
 
         // should allow anyone to call apply funding
         {
-            let state: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
             let expected_normalisation_factor: Decimal = wasm
-                .query(&perp_address, &QueryMsg::GetNormalisationFactor {})
+                .query(
+                    &perp_address,
+                    &QueryMsg::GetNormalisationFactor { market_id: None },
+                )
                 .unwrap();
             assert!(state.normalisation_factor > expected_normalisation_factor);
 
             wasm.execute(
                 &perp_address,
-                &ExecuteMsg::ApplyFunding {},
+                &ExecuteMsg::ApplyFunding { market_id: None },
                 &[],
                 &env.traders[1], // one of the trading accounts should be able to call this
             )
             .unwrap();
 
-            let state_after: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+            let state_after: State = wasm
+                .query(&perp_address, &QueryMsg::State { market_id: None })
+                .unwrap();
 
             assert!(
                 state_after
@@ -209,13 +234,19 @@ fn test_basic_actions() {
             let mark_price: Decimal = wasm
                 .query(
                     &perp_address,
-                    &QueryMsg::GetDenormalisedMark { period: 30u64 },
+                    &QueryMsg::GetDenormalisedMark {
+                        period: 30u64,
+                        market_id: None,
+                    },
                 )
                 .unwrap();
             let mark_price_funding: Decimal = wasm
                 .query(
                     &perp_address,
-                    &QueryMsg::GetDenormalisedMarkFunding { period: 30u64 },
+                    &QueryMsg::GetDenormalisedMarkFunding {
+                        period: 30u64,
+                        market_id: None,
+                    },
                 )
                 .unwrap();
 
@@ -232,7 +263,13 @@ fn test_basic_actions() {
         // should be able to get scaled index
         {
             let index: Decimal = wasm
-                .query(&perp_address, &QueryMsg::GetUnscaledIndex { period: 30u64 })
+                .query(
+                    &perp_address,
+                    &QueryMsg::GetUnscaledIndex {
+                        period: 30u64,
+                        market_id: None,
+                    },
+                )
                 .unwrap();
 
             let eth_squared =
@@ -257,6 +294,7 @@ fn test_basic_actions() {
                         amount: Uint128::from(500_000u128),
                         vault_id: None,
                         rebase: false,
+                        market_id: None,
                     },
                     &[coin(500_000u64.into(), env.denoms["base"].to_string())],
                     &env.signer,
@@ -309,6 +347,7 @@ fn test_basic_actions() {
                     amount: Uint128::from(100u128),
                     vault_id: Some(10u64),
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(100u64.into(), env.denoms["base"].to_string())],
                 &env.signer,
@@ -420,6 +459,7 @@ fn test_basic_actions() {
                     amount: Uint128::from(100u128),
                     vault_id: Some(vault_id),
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(100u128, env.denoms["base"].to_string())],
                 &env.traders[0],
@@ -435,6 +475,7 @@ fn test_basic_actions() {
                     amount: Uint128::from(100u128),
                     vault_id: Some(110u64),
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(100u128, env.denoms["base"].to_string())],
                 &env.signer,
@@ -465,6 +506,7 @@ fn test_basic_actions() {
                     amount: Uint128::from(amount),
                     vault_id: Some(vault_id),
                     rebase: false,
+                    market_id: None,
                 },
                 &[],
                 &env.signer,
@@ -505,6 +547,7 @@ fn test_basic_actions() {
                     amount: Uint128::from(amount),
                     vault_id: Some(vault_id),
                     rebase: false,
+                    market_id: None,
                 },
                 &[],
                 &env.signer,
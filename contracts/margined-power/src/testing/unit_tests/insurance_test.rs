@@ -0,0 +1,229 @@
+use crate::{
+    contract::CONTRACT_NAME,
+    state::{Config, State},
+    vault::Vault,
+};
+
+use cosmwasm_std::{coin, Decimal, Uint128};
+use margined_protocol::power::{ExecuteMsg, QueryMsg};
+use margined_testing::{
+    helpers::parse_event_attribute,
+    power_env::{PowerEnv, BASE_PRICE, ONE, SCALE_FACTOR},
+};
+use mock_query::contract::ExecuteMsg as MockQueryExecuteMsg;
+use osmosis_test_tube::{Account, Module, RunnerError, Wasm};
+use std::str::FromStr;
+
+const INSURANCE_AMOUNT: u128 = 100_000u128;
+
+#[test]
+fn test_insurance_covers_liquidation_shortfall() {
+    pub const SCALED_POWER_PRICE: u128 = 303_000 * ONE / SCALE_FACTOR;
+
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    wasm.execute(
+        config.query_contract.as_ref(),
+        &MockQueryExecuteMsg::AppendPrice {
+            pool_id: env.base_pool_id,
+            price: Decimal::from_atomics(BASE_PRICE, 6u32).unwrap(),
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    wasm.execute(
+        config.query_contract.as_ref(),
+        &MockQueryExecuteMsg::AppendPrice {
+            pool_id: env.power_pool_id,
+            price: Decimal::from_atomics(SCALED_POWER_PRICE, 6u32).unwrap(),
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    // should revert funding the insurance without funds
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::FundInsurance { market_id: None },
+                &[],
+                &env.signer,
+            )
+            .unwrap_err();
+        assert_eq!(err,
+            RunnerError::ExecuteError {
+            msg: "failed to execute message; message index: 0: Invalid funds: execute wasm contract failed".to_string()
+        });
+    }
+
+    // fund the insurance
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::FundInsurance { market_id: None },
+            &[coin(INSURANCE_AMOUNT, env.denoms["base"].clone())],
+            &env.signer,
+        )
+        .unwrap();
+
+        let insurance_balance: Uint128 = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetInsuranceBalance { market_id: None },
+            )
+            .unwrap();
+        assert_eq!(insurance_balance, Uint128::from(INSURANCE_AMOUNT));
+    }
+
+    let vault_id: u64;
+
+    // open vault
+    {
+        let mint_response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::MintPowerPerp {
+                    amount: Uint128::from(2_000_000u128),
+                    vault_id: None,
+                    rebase: true,
+                    market_id: None,
+                },
+                &[coin(900_000u128, env.denoms["base"].clone())],
+                &env.traders[1],
+            )
+            .unwrap();
+
+        vault_id = u64::from_str(&parse_event_attribute(
+            mint_response.events,
+            "wasm-mint",
+            "vault_id",
+        ))
+        .unwrap();
+    }
+
+    // set base price to make the vault insolvent
+    {
+        pub const SCALED_POWER_PRICE: u128 = 16_160 * ONE / SCALE_FACTOR;
+        pub const BASE_PRICE: u128 = 8_000_000_000;
+        wasm.execute(
+            config.query_contract.as_ref(),
+            &MockQueryExecuteMsg::AppendPrice {
+                pool_id: env.base_pool_id,
+                price: Decimal::from_atomics(BASE_PRICE, 6u32).unwrap(),
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+
+        wasm.execute(
+            config.query_contract.as_ref(),
+            &MockQueryExecuteMsg::AppendPrice {
+                pool_id: env.power_pool_id,
+                price: Decimal::from_atomics(SCALED_POWER_PRICE, 6u32).unwrap(),
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+    }
+
+    // liquidate the whole vault, insurance covers part of the shortfall
+    {
+        let vault_before: Vault = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+
+        let liquidator_balance_before =
+            env.get_balance(env.signer.address(), env.denoms["base"].clone());
+
+        let liquidate_response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::Liquidate {
+                    vault_id,
+                    max_debt_amount: vault_before.short_amount,
+                },
+                &[],
+                &env.signer,
+            )
+            .unwrap();
+
+        let collateral_to_pay = Uint128::from_str(&parse_event_attribute(
+            liquidate_response.events.clone(),
+            "wasm-liquidation",
+            "collateral_to_pay",
+        ))
+        .unwrap();
+        assert_eq!(collateral_to_pay, vault_before.collateral);
+
+        let shortfall = Uint128::from_str(&parse_event_attribute(
+            liquidate_response.events.clone(),
+            "wasm-bad_debt",
+            "shortfall",
+        ))
+        .unwrap();
+
+        let insurance_payout = Uint128::from_str(&parse_event_attribute(
+            liquidate_response.events.clone(),
+            "wasm-bad_debt",
+            "insurance_payout",
+        ))
+        .unwrap();
+        assert_eq!(insurance_payout, Uint128::from(INSURANCE_AMOUNT));
+
+        let bad_debt = Uint128::from_str(&parse_event_attribute(
+            liquidate_response.events,
+            "wasm-bad_debt",
+            "bad_debt",
+        ))
+        .unwrap();
+        assert_eq!(bad_debt, shortfall - insurance_payout);
+
+        let vault_after: Vault = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+        assert!(vault_after.short_amount.is_zero());
+        assert!(vault_after.collateral.is_zero());
+
+        let liquidator_balance_after =
+            env.get_balance(env.signer.address(), env.denoms["base"].clone());
+        assert_eq!(
+            liquidator_balance_after,
+            liquidator_balance_before + collateral_to_pay + insurance_payout
+        );
+
+        let insurance_balance: Uint128 = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetInsuranceBalance { market_id: None },
+            )
+            .unwrap();
+        assert!(insurance_balance.is_zero());
+
+        let total_bad_debt: Uint128 = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetTotalBadDebt { market_id: None },
+            )
+            .unwrap();
+        assert_eq!(total_bad_debt, bad_debt);
+
+        let state: State = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
+        assert_eq!(state.total_bad_debt, bad_debt);
+        assert!(state.insurance_balance.is_zero());
+    }
+}
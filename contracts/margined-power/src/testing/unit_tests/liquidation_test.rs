@@ -5,7 +5,7 @@ use crate::{
 };
 
 use cosmwasm_std::{coin, Decimal, Uint128};
-use margined_protocol::power::{ExecuteMsg, QueryMsg};
+use margined_protocol::power::{ExecuteMsg, QueryMsg, UnsafeVaultsResponse};
 use margined_testing::{
     helpers::parse_event_attribute,
     power_env::{PowerEnv, BASE_PRICE, ONE, SCALE_FACTOR},
@@ -27,8 +27,12 @@ fn test_liquidation_profitable() {
     let bank = Bank::new(&env.app);
     let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
 
-    let config: Config = wasm.query(&perp_address, &QueryMsg::Config {}).unwrap();
-    let state: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+    let state: State = wasm
+        .query(&perp_address, &QueryMsg::State { market_id: None })
+        .unwrap();
 
     assert_eq!(Decimal::one(), state.normalisation_factor);
 
@@ -59,7 +63,9 @@ fn test_liquidation_profitable() {
 
     // open vault id 1
     {
-        let state: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+        let state: State = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
 
         assert_eq!(Decimal::one(), state.normalisation_factor);
         let mint_amount = 100_000_000u128;
@@ -81,6 +87,7 @@ fn test_liquidation_profitable() {
                     amount: Uint128::from(mint_amount),
                     vault_id: None,
                     rebase: true,
+                    market_id: None,
                 },
                 &[coin(deposit_amount, env.denoms["base"].clone())],
                 &env.traders[0],
@@ -133,6 +140,7 @@ fn test_liquidation_profitable() {
                     amount: Uint128::from(mint_amount),
                     vault_id: None,
                     rebase: true,
+                    market_id: None,
                 },
                 &[coin(deposit_amount, env.denoms["base"].clone())],
                 &env.traders[1],
@@ -241,6 +249,21 @@ fn test_liquidation_profitable() {
         );
     }
 
+    // should not list safe vaults as unsafe
+    {
+        let response: UnsafeVaultsResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::ListUnsafeVaults {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert!(response.vaults.is_empty());
+        assert_eq!(response.last_scanned, Some(vault_id_2));
+    }
+
     // set base price to make the vault underwater
     {
         pub const SCALED_POWER_PRICE: u128 = 4_040 * ONE / SCALE_FACTOR;
@@ -268,6 +291,45 @@ fn test_liquidation_profitable() {
         .unwrap();
     }
 
+    // should list the underwater vaults
+    {
+        let response: UnsafeVaultsResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::ListUnsafeVaults {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(response.vaults, vec![vault_id_1, vault_id_2]);
+        assert_eq!(response.last_scanned, Some(vault_id_2));
+
+        let response: UnsafeVaultsResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::ListUnsafeVaults {
+                    start_after: None,
+                    limit: Some(1u32),
+                },
+            )
+            .unwrap();
+        assert_eq!(response.vaults, vec![vault_id_1]);
+        assert_eq!(response.last_scanned, Some(vault_id_1));
+
+        let response: UnsafeVaultsResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::ListUnsafeVaults {
+                    start_after: Some(vault_id_2),
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert!(response.vaults.is_empty());
+        assert_eq!(response.last_scanned, None);
+    }
+
     // should revert if the vault becomes dust after liqudiation
     {
         // TODO: we don't have the concept of dust, (yet)
@@ -425,8 +487,12 @@ fn test_liquidation_unprofitable() {
     let bank = Bank::new(&env.app);
     let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
 
-    let config: Config = wasm.query(&perp_address, &QueryMsg::Config {}).unwrap();
-    let state: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+    let state: State = wasm
+        .query(&perp_address, &QueryMsg::State { market_id: None })
+        .unwrap();
 
     assert_eq!(Decimal::one(), state.normalisation_factor);
 
@@ -455,7 +521,9 @@ fn test_liquidation_unprofitable() {
     let vault_id_1: u64;
     // open vault id 1
     {
-        let state: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+        let state: State = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
 
         assert_eq!(Decimal::one(), state.normalisation_factor);
         let mint_amount = 100_000_000u128;
@@ -468,6 +536,7 @@ fn test_liquidation_unprofitable() {
                     amount: Uint128::from(mint_amount),
                     vault_id: None,
                     rebase: true,
+                    market_id: None,
                 },
                 &[coin(deposit_amount, env.denoms["base"].clone())],
                 &env.traders[0],
@@ -0,0 +1,377 @@
+use crate::{
+    contract::CONTRACT_NAME,
+    market::DEFAULT_MARKET_ID,
+    state::{Config, State},
+};
+
+use cosmwasm_std::{coin, Decimal, Uint128};
+use margined_protocol::power::{ExecuteMsg, MarketsResponse, NewMarket, QueryMsg, VaultResponse};
+use margined_testing::{
+    helpers::parse_event_attribute,
+    power_env::{PowerEnv, BASE_PRICE, ONE, SCALE_FACTOR},
+};
+use mock_query::contract::ExecuteMsg as MockQueryExecuteMsg;
+use osmosis_test_tube::{
+    osmosis_std::types::{
+        cosmos::base::v1beta1::Coin,
+        osmosis::tokenfactory::v1beta1::{MsgChangeAdmin, MsgCreateDenom, MsgMint},
+    },
+    Account, Gamm, Module, RunnerError, TokenFactory, Wasm,
+};
+use std::str::FromStr;
+
+#[test]
+fn test_multiple_markets() {
+    pub const SCALED_POWER_PRICE: u128 = 303_000 * ONE / SCALE_FACTOR;
+
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let gamm = Gamm::new(&env.app);
+    let token = TokenFactory::new(&env.app);
+
+    let (perp_address, query_address) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    // create the power token and pool of the second market
+    let power_denom = token
+        .create_denom(
+            MsgCreateDenom {
+                sender: env.signer.address(),
+                subdenom: "squbase".to_string(),
+            },
+            &env.signer,
+        )
+        .unwrap()
+        .data
+        .new_token_denom;
+
+    token
+        .mint(
+            MsgMint {
+                sender: env.signer.address(),
+                amount: Some(Coin {
+                    denom: power_denom.clone(),
+                    amount: "1000000000000".to_string(),
+                }),
+                mint_to_address: env.signer.address(),
+            },
+            &env.signer,
+        )
+        .unwrap();
+
+    let power_pool_id = gamm
+        .create_basic_pool(
+            &[
+                coin(1_000_000_000, env.denoms["base"].clone()),
+                coin(3_000_000_000, power_denom.clone()),
+            ],
+            &env.signer,
+        )
+        .unwrap()
+        .data
+        .pool_id;
+
+    let new_market = NewMarket {
+        fee_rate: "0.0".to_string(),
+        fee_pool: env.fee_pool.address(),
+        query_contract: query_address.clone(),
+        base_denom: env.denoms["base"].clone(),
+        power_denom: power_denom.clone(),
+        base_pool_id: env.base_pool_id,
+        base_pool_quote: env.denoms["quote"].clone(),
+        power_pool_id,
+        base_decimals: 6u32,
+        power_decimals: 6u32,
+    };
+
+    // should revert if not the owner adding the market
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::AddMarket {
+                    new_market: new_market.clone(),
+                },
+                &[],
+                &env.traders[0],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Unauthorized: execute wasm contract failed".to_string()
+            }
+        );
+    }
+
+    // should revert if the power denom is used by another market
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::AddMarket {
+                    new_market: NewMarket {
+                        power_denom: env.denoms["power"].clone(),
+                        power_pool_id: env.power_pool_id,
+                        ..new_market.clone()
+                    },
+                },
+                &[],
+                &env.signer,
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Generic error: Invalid power denom, already used by market 1: execute wasm contract failed".to_string()
+            }
+        );
+    }
+
+    // should revert if the power denom is not in the power pool
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::AddMarket {
+                new_market: NewMarket {
+                    power_pool_id: env.power_pool_id,
+                    ..new_market.clone()
+                },
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap_err();
+    }
+
+    let market_id = {
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::AddMarket { new_market },
+                &[],
+                &env.signer,
+            )
+            .unwrap();
+
+        u64::from_str(&parse_event_attribute(
+            response.events,
+            "wasm-add_market",
+            "market_id",
+        ))
+        .unwrap()
+    };
+
+    // markets have their own config and state
+    {
+        assert_eq!(market_id, DEFAULT_MARKET_ID + 1);
+
+        let markets: MarketsResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetMarkets {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(markets.markets, vec![DEFAULT_MARKET_ID, market_id]);
+
+        let config: Config = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::Config {
+                    market_id: Some(market_id),
+                },
+            )
+            .unwrap();
+        assert_eq!(config.power_denom, power_denom);
+        assert_eq!(config.power_pool.id, power_pool_id);
+
+        let state: State = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::State {
+                    market_id: Some(market_id),
+                },
+            )
+            .unwrap();
+        assert!(!state.is_open);
+        assert!(state.is_paused);
+
+        wasm.query::<QueryMsg, Config>(
+            &perp_address,
+            &QueryMsg::Config {
+                market_id: Some(market_id + 1),
+            },
+        )
+        .unwrap_err();
+    }
+
+    // should revert minting before the market is open
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: Uint128::from(100_000_000u128),
+                vault_id: None,
+                rebase: true,
+                market_id: Some(market_id),
+            },
+            &[coin(45_000_049u128, env.denoms["base"].clone())],
+            &env.traders[0],
+        )
+        .unwrap_err();
+    }
+
+    // open the market
+    {
+        token
+            .change_admin(
+                MsgChangeAdmin {
+                    sender: env.signer.address(),
+                    new_admin: perp_address.clone(),
+                    denom: power_denom.clone(),
+                },
+                &env.signer,
+            )
+            .unwrap();
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::SetOpen {
+                market_id: Some(market_id),
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+
+        for pool_id in [env.base_pool_id, env.power_pool_id, power_pool_id] {
+            let price = if pool_id == env.base_pool_id {
+                BASE_PRICE
+            } else {
+                SCALED_POWER_PRICE
+            };
+
+            wasm.execute(
+                &query_address,
+                &MockQueryExecuteMsg::AppendPrice {
+                    pool_id,
+                    price: Decimal::from_atomics(price, 6u32).unwrap(),
+                },
+                &[],
+                &env.signer,
+            )
+            .unwrap();
+        }
+    }
+
+    let vault_id: u64;
+
+    // vaults are opened in the market and mint its power token
+    {
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::MintPowerPerp {
+                    amount: Uint128::from(100_000_000u128),
+                    vault_id: None,
+                    rebase: true,
+                    market_id: Some(market_id),
+                },
+                &[coin(45_000_049u128, env.denoms["base"].clone())],
+                &env.traders[0],
+            )
+            .unwrap();
+
+        vault_id = u64::from_str(&parse_event_attribute(
+            response.events,
+            "wasm-mint",
+            "vault_id",
+        ))
+        .unwrap();
+
+        let vault: VaultResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+        assert_eq!(vault.market_id, market_id);
+        assert_eq!(vault.short_amount, Uint128::from(100_000_000u128));
+
+        let balance = env.get_balance(env.traders[0].address(), power_denom.clone());
+        assert_eq!(balance, Uint128::from(100_000_000u128));
+
+        let balance = env.get_balance(env.traders[0].address(), env.denoms["power"].clone());
+        assert!(balance.is_zero());
+    }
+
+    // should revert if the vault is in another market
+    {
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::MintPowerPerp {
+                    amount: Uint128::from(1_000_000u128),
+                    vault_id: Some(vault_id),
+                    rebase: true,
+                    market_id: Some(DEFAULT_MARKET_ID),
+                },
+                &[coin(1_000_000u128, env.denoms["base"].clone())],
+                &env.traders[0],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Vault does not belong to market 1: execute wasm contract failed".to_string()
+            }
+        );
+    }
+
+    // pausing a market does not pause the others
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Pause { market_id: None },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: Uint128::from(100_000_000u128),
+                vault_id: None,
+                rebase: true,
+                market_id: None,
+            },
+            &[coin(45_000_049u128, env.denoms["base"].clone())],
+            &env.traders[1],
+        )
+        .unwrap_err();
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Deposit { vault_id },
+            &[coin(1_000_000u128, env.denoms["base"].clone())],
+            &env.traders[0],
+        )
+        .unwrap();
+
+        let state: State = wasm
+            .query(&perp_address, &QueryMsg::State { market_id: None })
+            .unwrap();
+        assert!(state.is_paused);
+
+        let state: State = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::State {
+                    market_id: Some(market_id),
+                },
+            )
+            .unwrap();
+        assert!(!state.is_paused);
+    }
+}
@@ -1,7 +1,21 @@
+mod adjust_vault_test;
+mod auction_test;
+mod circuit_breaker_test;
+mod collateral_test;
 mod combined_test;
 mod config_test;
+mod fee_split_test;
+mod fee_test;
+mod flash_mint_test;
 mod funding_test;
 mod general_test;
+mod insurance_test;
 mod liquidation_test;
+mod market_test;
+mod nft_test;
+mod operator_test;
+mod oracle_test;
 mod permissions_test;
+mod shutdown_test;
+mod transfer_test;
 mod vault_test;
@@ -0,0 +1,191 @@
+use crate::{
+    contract::CONTRACT_NAME,
+    nft::{VAULT_NFT_NAME, VAULT_NFT_SYMBOL},
+    state::Config,
+};
+
+use cosmwasm_std::{coin, Decimal, Uint128};
+use cw721::{ContractInfoResponse, NumTokensResponse, OwnerOfResponse, TokensResponse};
+use margined_protocol::power::{ExecuteMsg, QueryMsg, UserVaultsResponse, VaultResponse};
+use margined_testing::{helpers::parse_event_attribute, power_env::PowerEnv};
+use osmosis_test_tube::{Account, Module, Wasm};
+use std::str::FromStr;
+
+#[test]
+fn test_vault_nft() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.base_pool_id,
+        Decimal::from_str("3000.0").unwrap(),
+    );
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.power_pool_id,
+        Decimal::from_str("3030.0").unwrap(),
+    );
+
+    let contract_info: ContractInfoResponse = wasm
+        .query(&perp_address, &QueryMsg::ContractInfo {})
+        .unwrap();
+    assert_eq!(contract_info.name, VAULT_NFT_NAME);
+    assert_eq!(contract_info.symbol, VAULT_NFT_SYMBOL);
+
+    let mint_response = wasm
+        .execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: Uint128::from(10_000_000u128),
+                vault_id: None,
+                rebase: false,
+                market_id: None,
+            },
+            &[coin(45_000_000u128, env.denoms["base"].to_string())],
+            &env.traders[0],
+        )
+        .unwrap();
+
+    let vault_id = u64::from_str(&parse_event_attribute(
+        mint_response.events,
+        "wasm-mint",
+        "vault_id",
+    ))
+    .unwrap();
+    let token_id = vault_id.to_string();
+
+    // should mint a token for the new vault
+    {
+        let num_tokens: NumTokensResponse =
+            wasm.query(&perp_address, &QueryMsg::NumTokens {}).unwrap();
+        assert_eq!(num_tokens.count, 1u64);
+
+        let owner: OwnerOfResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::OwnerOf {
+                    token_id: token_id.clone(),
+                    include_expired: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(owner.owner, env.traders[0].address());
+
+        let tokens: TokensResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::Tokens {
+                    owner: env.traders[0].address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(tokens.tokens, vec![token_id.clone()]);
+    }
+
+    // should fail if sender does not own the token
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::TransferNft {
+                recipient: env.traders[1].address(),
+                token_id: token_id.clone(),
+            },
+            &[],
+            &env.traders[1],
+        )
+        .unwrap_err();
+    }
+
+    // approved spender can transfer the token and the vault follows it
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Approve {
+                spender: env.traders[2].address(),
+                token_id: token_id.clone(),
+                expires: None,
+            },
+            &[],
+            &env.traders[0],
+        )
+        .unwrap();
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::TransferNft {
+                recipient: env.traders[1].address(),
+                token_id: token_id.clone(),
+            },
+            &[],
+            &env.traders[2],
+        )
+        .unwrap();
+
+        let owner: OwnerOfResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::OwnerOf {
+                    token_id: token_id.clone(),
+                    include_expired: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(owner.owner, env.traders[1].address());
+        assert!(owner.approvals.is_empty());
+
+        let vault: VaultResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+        assert_eq!(vault.operator, env.traders[1].address());
+
+        let user_vaults: UserVaultsResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetUserVaults {
+                    user: env.traders[1].address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(user_vaults.vaults, vec![vault_id]);
+    }
+
+    // previous owner can no longer operate the vault
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Deposit { vault_id },
+            &[coin(1_000_000u128, env.denoms["base"].to_string())],
+            &env.traders[0],
+        )
+        .unwrap_err();
+    }
+
+    // new owner can operate the vault
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Deposit { vault_id },
+            &[coin(1_000_000u128, env.denoms["base"].to_string())],
+            &env.traders[1],
+        )
+        .unwrap();
+
+        let vault: VaultResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+        assert_eq!(vault.collateral, Uint128::from(46_000_000u128));
+    }
+}
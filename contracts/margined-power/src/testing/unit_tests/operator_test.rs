@@ -0,0 +1,250 @@
+use crate::{contract::CONTRACT_NAME, state::Config};
+
+use cosmwasm_std::{coin, Decimal, Uint128};
+use cw721::Expiration;
+use margined_protocol::power::{ExecuteMsg, QueryMsg, VaultOperatorsResponse, VaultResponse};
+use margined_testing::{helpers::parse_event_attribute, power_env::PowerEnv};
+use osmosis_test_tube::{Account, Module, Wasm};
+use std::str::FromStr;
+
+#[test]
+fn test_vault_operators() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.base_pool_id,
+        Decimal::from_str("3000.0").unwrap(),
+    );
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.power_pool_id,
+        Decimal::from_str("3030.0").unwrap(),
+    );
+
+    let owner = &env.traders[0];
+    let keeper = &env.traders[1];
+
+    let mint_response = wasm
+        .execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: Uint128::from(10_000_000u128),
+                vault_id: None,
+                rebase: false,
+                market_id: None,
+            },
+            &[coin(45_000_000u128, env.denoms["base"].to_string())],
+            owner,
+        )
+        .unwrap();
+
+    let vault_id = u64::from_str(&parse_event_attribute(
+        mint_response.events,
+        "wasm-mint",
+        "vault_id",
+    ))
+    .unwrap();
+
+    // should fail if keeper is not approved
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Deposit { vault_id },
+            &[coin(1_000_000u128, env.denoms["base"].to_string())],
+            keeper,
+        )
+        .unwrap_err();
+    }
+
+    // should fail if approval is granted by anyone other than the owner
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::ApproveOperator {
+                vault_id,
+                spender: keeper.address(),
+                expires: None,
+            },
+            &[],
+            keeper,
+        )
+        .unwrap_err();
+    }
+
+    // should fail if approval is already expired
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::ApproveOperator {
+                vault_id,
+                spender: keeper.address(),
+                expires: Some(Expiration::AtHeight(1u64)),
+            },
+            &[],
+            owner,
+        )
+        .unwrap_err();
+    }
+
+    // approved keeper can deposit into the vault
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::ApproveOperator {
+                vault_id,
+                spender: keeper.address(),
+                expires: None,
+            },
+            &[],
+            owner,
+        )
+        .unwrap();
+
+        let operators: VaultOperatorsResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVaultOperators { vault_id })
+            .unwrap();
+        assert_eq!(operators.operators.len(), 1usize);
+        assert_eq!(operators.operators[0].spender, keeper.address());
+        assert_eq!(operators.operators[0].expires, Expiration::Never {});
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Deposit { vault_id },
+            &[coin(5_000_000u128, env.denoms["base"].to_string())],
+            keeper,
+        )
+        .unwrap();
+
+        let vault: VaultResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+        assert_eq!(vault.operator, owner.address());
+        assert_eq!(vault.collateral, Uint128::from(50_000_000u128));
+    }
+
+    // withdrawals by the keeper are paid to the owner
+    {
+        let owner_balance_before = env.get_balance(owner.address(), env.denoms["base"].clone());
+        let keeper_balance_before = env.get_balance(keeper.address(), env.denoms["base"].clone());
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Withdraw {
+                amount: Uint128::from(5_000_000u128),
+                vault_id,
+            },
+            &[],
+            keeper,
+        )
+        .unwrap();
+
+        let owner_balance_after = env.get_balance(owner.address(), env.denoms["base"].clone());
+        let keeper_balance_after = env.get_balance(keeper.address(), env.denoms["base"].clone());
+
+        assert_eq!(
+            owner_balance_after - owner_balance_before,
+            Uint128::from(5_000_000u128)
+        );
+        assert_eq!(keeper_balance_after, keeper_balance_before);
+    }
+
+    // power minted by the keeper is sent to the owner
+    {
+        let owner_balance_before = env.get_balance(owner.address(), env.denoms["power"].clone());
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: Uint128::from(1_000_000u128),
+                vault_id: Some(vault_id),
+                rebase: false,
+                market_id: None,
+            },
+            &[],
+            keeper,
+        )
+        .unwrap();
+
+        let owner_balance_after = env.get_balance(owner.address(), env.denoms["power"].clone());
+
+        assert_eq!(
+            owner_balance_after - owner_balance_before,
+            Uint128::from(1_000_000u128)
+        );
+        assert!(env
+            .get_balance(keeper.address(), env.denoms["power"].clone())
+            .is_zero());
+    }
+
+    // proceeds of a short opened by the keeper are sent to the owner
+    {
+        let owner_balance_before = env.get_balance(owner.address(), env.denoms["base"].clone());
+        let keeper_balance_before = env.get_balance(keeper.address(), env.denoms["base"].clone());
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::OpenShort {
+                amount: Uint128::from(1_000_000u128),
+                vault_id: Some(vault_id),
+                min_base_out: None,
+                deadline: None,
+                market_id: None,
+                route: None,
+            },
+            &[],
+            keeper,
+        )
+        .unwrap();
+
+        let owner_balance_after = env.get_balance(owner.address(), env.denoms["base"].clone());
+        let keeper_balance_after = env.get_balance(keeper.address(), env.denoms["base"].clone());
+
+        assert!(owner_balance_after > owner_balance_before);
+        assert_eq!(keeper_balance_after, keeper_balance_before);
+        assert!(env
+            .get_balance(keeper.address(), env.denoms["power"].clone())
+            .is_zero());
+
+        let vault: VaultResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+        assert_eq!(vault.short_amount, Uint128::from(12_000_000u128));
+    }
+
+    // revoked keeper can no longer operate the vault
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::RevokeOperator {
+                vault_id,
+                spender: keeper.address(),
+            },
+            &[],
+            owner,
+        )
+        .unwrap();
+
+        let operators: VaultOperatorsResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVaultOperators { vault_id })
+            .unwrap();
+        assert!(operators.operators.is_empty());
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Deposit { vault_id },
+            &[coin(1_000_000u128, env.denoms["base"].to_string())],
+            keeper,
+        )
+        .unwrap_err();
+    }
+}
@@ -0,0 +1,388 @@
+use crate::{contract::CONTRACT_NAME, state::Config};
+
+use cosmwasm_std::{coin, Decimal, Uint128};
+use margined_protocol::{
+    power::{ConfigResponse, ExecuteMsg, Oracle, OracleSource, PriceType, QueryMsg, UpdateConfig},
+    query::QueryMsg as PriceQueryMsg,
+};
+use margined_testing::{
+    helpers::parse_event_attribute,
+    power_env::{PowerEnv, BASE_PRICE, SCALED_POWER_PRICE},
+};
+use mock_query::contract::{ExecuteMsg as MockQueryExecuteMsg, PricePoint};
+use osmosis_test_tube::{Module, RunnerError, Wasm};
+use std::str::FromStr;
+
+#[test]
+fn test_oracle_sources() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    // a second mock query contract serves as the price feed
+    let feed_address = env.deploy_query_contracts(&wasm, true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    let set_pool_price = |pool_id: u64, price: &str| {
+        wasm.execute(
+            config.query_contract.as_ref(),
+            &MockQueryExecuteMsg::AppendPrice {
+                pool_id,
+                price: Decimal::from_str(price).unwrap(),
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+    };
+
+    let set_feed_price = |price: &str| {
+        wasm.execute(
+            &feed_address,
+            &MockQueryExecuteMsg::SetFeedPrice {
+                base_asset: env.denoms["base"].clone(),
+                quote_asset: env.denoms["quote"].clone(),
+                price: Decimal::from_str(price).unwrap(),
+            },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
+    };
+
+    let set_oracle = |oracle: Oracle| {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::UpdateConfig {
+                market_id: None,
+                new_config: UpdateConfig {
+                    oracle: Some(oracle),
+                    ..Default::default()
+                },
+            },
+            &[],
+            &env.signer,
+        )
+    };
+
+    let get_index = || {
+        wasm.query::<QueryMsg, Decimal>(
+            &perp_address,
+            &QueryMsg::GetIndex {
+                period: 1u64,
+                market_id: None,
+            },
+        )
+    };
+
+    set_pool_price(
+        env.base_pool_id,
+        &Decimal::from_atomics(BASE_PRICE, 6u32).unwrap().to_string(),
+    );
+    set_pool_price(
+        env.power_pool_id,
+        &Decimal::from_atomics(SCALED_POWER_PRICE, 6u32)
+            .unwrap()
+            .to_string(),
+    );
+    set_feed_price("3000.0");
+
+    // should default to the arithmetic twap of the base pool
+    {
+        let config: ConfigResponse = wasm
+            .query(&perp_address, &QueryMsg::Config { market_id: None })
+            .unwrap();
+        assert_eq!(config.oracle, Oracle::default());
+
+        assert_eq!(get_index().unwrap(), Decimal::from_str("0.09").unwrap());
+    }
+
+    // should revert if the oracle is invalid
+    {
+        let err = set_oracle(Oracle {
+            max_deviation: Decimal::zero(),
+            ..Oracle::default()
+        })
+        .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Generic error: Invalid oracle max deviation, must be between 0% and 100%: execute wasm contract failed".to_string()
+            }
+        );
+
+        set_oracle(Oracle {
+            secondary: Some(OracleSource::PriceFeed {
+                contract: "feed".to_string(),
+                max_age: 600u64,
+            }),
+            ..Oracle::default()
+        })
+        .unwrap_err();
+
+        let err = set_oracle(Oracle {
+            secondary: Some(OracleSource::PriceFeed {
+                contract: feed_address.clone(),
+                max_age: 0u64,
+            }),
+            ..Oracle::default()
+        })
+        .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Generic error: Invalid oracle price feed max age: execute wasm contract failed".to_string()
+            }
+        );
+    }
+
+    // should bound the base pool by the price feed
+    {
+        let oracle = Oracle {
+            primary: OracleSource::ArithmeticTwap {},
+            secondary: Some(OracleSource::PriceFeed {
+                contract: feed_address.clone(),
+                max_age: 600u64,
+            }),
+            max_deviation: Decimal::percent(10),
+        };
+        set_oracle(oracle.clone()).unwrap();
+
+        let config: ConfigResponse = wasm
+            .query(&perp_address, &QueryMsg::Config { market_id: None })
+            .unwrap();
+        assert_eq!(config.oracle, oracle);
+
+        assert_eq!(get_index().unwrap(), Decimal::from_str("0.09").unwrap());
+
+        // manipulated pool deviates from the feed
+        set_pool_price(env.base_pool_id, "6000.0");
+        get_index().unwrap_err();
+
+        let err = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::MintPowerPerp {
+                    amount: Uint128::from(1_000_000u128),
+                    vault_id: None,
+                    rebase: false,
+                    market_id: None,
+                },
+                &[coin(1_000_000u128, env.denoms["base"].clone())],
+                &env.traders[0],
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            RunnerError::ExecuteError {
+                msg: "failed to execute message; message index: 0: Generic error: Oracle sources deviate by more than the max deviation: execute wasm contract failed".to_string()
+            }
+        );
+
+        // within the max deviation the primary is used
+        set_feed_price("5500.0");
+        assert_eq!(get_index().unwrap(), Decimal::from_str("0.36").unwrap());
+
+        set_pool_price(env.base_pool_id, "3000.0");
+        set_feed_price("3000.0");
+    }
+
+    // should fall back to the secondary when the price feed is stale
+    {
+        set_oracle(Oracle {
+            primary: OracleSource::PriceFeed {
+                contract: feed_address.clone(),
+                max_age: 600u64,
+            },
+            secondary: Some(OracleSource::ArithmeticTwap {}),
+            max_deviation: Decimal::percent(20),
+        })
+        .unwrap();
+
+        set_feed_price("3300.0");
+        assert_eq!(get_index().unwrap(), Decimal::from_str("0.1089").unwrap());
+
+        env.app.increase_time(601u64);
+
+        assert_eq!(get_index().unwrap(), Decimal::from_str("0.09").unwrap());
+    }
+
+    // should price the power pool with the configured average
+    {
+        let get_mark = || {
+            wasm.query::<QueryMsg, Decimal>(
+                &perp_address,
+                &QueryMsg::GetDenormalisedMark {
+                    period: 1u64,
+                    market_id: None,
+                },
+            )
+            .unwrap()
+        };
+
+        let arithmetic_mark = get_mark();
+
+        let response = wasm
+            .execute(
+                &perp_address,
+                &ExecuteMsg::UpdateConfig {
+                    market_id: None,
+                    new_config: UpdateConfig {
+                        power_price_type: Some(PriceType::GeometricTwap {}),
+                        ..Default::default()
+                    },
+                },
+                &[],
+                &env.signer,
+            )
+            .unwrap();
+        assert_eq!(
+            parse_event_attribute(response.events, "wasm-update_config", "power_price_type"),
+            "geometric_twap"
+        );
+
+        let config: ConfigResponse = wasm
+            .query(&perp_address, &QueryMsg::Config { market_id: None })
+            .unwrap();
+        assert_eq!(config.power_price_type, PriceType::GeometricTwap {});
+
+        // mock query serves the same price for both averages
+        assert_eq!(get_mark(), arithmetic_mark);
+    }
+}
+
+#[test]
+fn test_time_series_prices() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.power_pool_id,
+        Decimal::from_atomics(SCALED_POWER_PRICE, 6u32).unwrap(),
+    );
+
+    // base price doubles after 1000 seconds
+    wasm.execute(
+        config.query_contract.as_ref(),
+        &MockQueryExecuteMsg::SetPricePath {
+            pool_id: env.base_pool_id,
+            base_asset: env.denoms["base"].clone(),
+            quote_asset: env.denoms["quote"].clone(),
+            path: vec![
+                PricePoint {
+                    delay: 0u64,
+                    price: Decimal::from_str("2000.0").unwrap(),
+                },
+                PricePoint {
+                    delay: 1000u64,
+                    price: Decimal::from_str("4000.0").unwrap(),
+                },
+            ],
+        },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    let path_start = env.app.get_block_timestamp();
+
+    env.app.increase_time(2000u64);
+
+    let query_twap = |msg: PriceQueryMsg| {
+        wasm.query::<PriceQueryMsg, Decimal>(config.query_contract.as_ref(), &msg)
+    };
+
+    // should average the prices over the window
+    {
+        let twap = query_twap(PriceQueryMsg::GetArithmeticTwap {
+            pool_id: env.base_pool_id,
+            base_asset: env.denoms["base"].clone(),
+            quote_asset: env.denoms["quote"].clone(),
+            start_time: path_start.plus_seconds(500u64),
+            end_time: path_start.plus_seconds(1500u64),
+        })
+        .unwrap();
+        assert_eq!(twap, Decimal::from_str("3000.0").unwrap());
+
+        let twap = query_twap(PriceQueryMsg::GetGeometricTwap {
+            pool_id: env.base_pool_id,
+            base_asset: env.denoms["base"].clone(),
+            quote_asset: env.denoms["quote"].clone(),
+            start_time: path_start.plus_seconds(500u64),
+            end_time: path_start.plus_seconds(1500u64),
+        })
+        .unwrap();
+        assert!(
+            twap.abs_diff(Decimal::from_str("2828.427124746190097603").unwrap())
+                < Decimal::from_str("0.001").unwrap()
+        );
+    }
+
+    // should invert the prices for the opposite direction
+    {
+        let twap = query_twap(PriceQueryMsg::GetArithmeticTwap {
+            pool_id: env.base_pool_id,
+            base_asset: env.denoms["quote"].clone(),
+            quote_asset: env.denoms["base"].clone(),
+            start_time: path_start.plus_seconds(500u64),
+            end_time: path_start.plus_seconds(1500u64),
+        })
+        .unwrap();
+        assert_eq!(twap, Decimal::from_str("0.000375").unwrap());
+    }
+
+    // should revert if the window is not covered by observations
+    {
+        query_twap(PriceQueryMsg::GetArithmeticTwap {
+            pool_id: env.base_pool_id,
+            base_asset: env.denoms["base"].clone(),
+            quote_asset: env.denoms["quote"].clone(),
+            start_time: path_start.minus_seconds(1u64),
+            end_time: path_start.plus_seconds(1500u64),
+        })
+        .unwrap_err();
+
+        query_twap(PriceQueryMsg::GetArithmeticTwap {
+            pool_id: env.base_pool_id,
+            base_asset: env.denoms["base"].clone(),
+            quote_asset: env.denoms["quote"].clone(),
+            start_time: path_start,
+            end_time: path_start.plus_seconds(5000u64),
+        })
+        .unwrap_err();
+    }
+
+    // should use the latest price for windows after the last observation
+    {
+        let index: Decimal = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetIndex {
+                    period: 500u64,
+                    market_id: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(index, Decimal::from_str("0.16").unwrap());
+
+        let spot_price = query_twap(PriceQueryMsg::GetSpotPrice {
+            pool_id: env.base_pool_id,
+            base_asset: env.denoms["base"].clone(),
+            quote_asset: env.denoms["quote"].clone(),
+        })
+        .unwrap();
+        assert_eq!(spot_price, Decimal::from_str("4000.0").unwrap());
+    }
+}
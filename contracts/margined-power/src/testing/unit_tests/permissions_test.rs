@@ -44,7 +44,9 @@ fn test_permissions() {
 
     let timestamp = env.app.get_block_timestamp();
 
-    let state: State = wasm.query(&address, &QueryMsg::State {}).unwrap();
+    let state: State = wasm
+        .query(&address, &QueryMsg::State { market_id: None })
+        .unwrap();
     assert_eq!(
         state,
         State {
@@ -52,6 +54,10 @@ fn test_permissions() {
             is_paused: true,
             normalisation_factor: Decimal::one(),
             last_funding_update: timestamp,
+            is_shutdown: false,
+            settlement_price: None,
+            insurance_balance: Uint128::zero(),
+            total_bad_debt: Uint128::zero(),
             last_pause: timestamp,
         }
     );
@@ -61,7 +67,12 @@ fn test_permissions() {
         // pause should fail, contract not set open
         {
             let err = wasm
-                .execute(&address, &ExecuteMsg::Pause {}, &[], &env.signer)
+                .execute(
+                    &address,
+                    &ExecuteMsg::Pause { market_id: None },
+                    &[],
+                    &env.signer,
+                )
                 .unwrap_err();
             assert_eq!(
                 err,
@@ -74,7 +85,12 @@ fn test_permissions() {
         // unpause should fail, contract not set open
         {
             let err = wasm
-                .execute(&address, &ExecuteMsg::UnPause {}, &[], &env.signer)
+                .execute(
+                    &address,
+                    &ExecuteMsg::UnPause { market_id: None },
+                    &[],
+                    &env.signer,
+                )
                 .unwrap_err();
             assert_eq!(
                 err,
@@ -93,6 +109,7 @@ fn test_permissions() {
                         amount: Uint128::from(1_000_000u128),
                         vault_id: None,
                         rebase: false,
+                        market_id: None,
                     },
                     &[],
                     &env.signer,
@@ -190,7 +207,12 @@ fn test_permissions() {
     // set contract open, should fail as not admin
     {
         let err = wasm
-            .execute(&address, &ExecuteMsg::SetOpen {}, &[], &env.signer)
+            .execute(
+                &address,
+                &ExecuteMsg::SetOpen { market_id: None },
+                &[],
+                &env.signer,
+            )
             .unwrap_err();
         assert_eq!(
             err,
@@ -213,10 +235,17 @@ fn test_permissions() {
             )
             .unwrap();
 
-        wasm.execute(&address, &ExecuteMsg::SetOpen {}, &[], &env.signer)
-            .unwrap();
+        wasm.execute(
+            &address,
+            &ExecuteMsg::SetOpen { market_id: None },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
 
-        let state: State = wasm.query(&address, &QueryMsg::State {}).unwrap();
+        let state: State = wasm
+            .query(&address, &QueryMsg::State { market_id: None })
+            .unwrap();
         assert_eq!(
             state,
             State {
@@ -224,6 +253,10 @@ fn test_permissions() {
                 is_paused: false,
                 normalisation_factor: Decimal::one(),
                 last_funding_update: timestamp,
+                is_shutdown: false,
+                settlement_price: None,
+                insurance_balance: Uint128::zero(),
+                total_bad_debt: Uint128::zero(),
                 last_pause: timestamp,
             }
         );
@@ -232,7 +265,12 @@ fn test_permissions() {
     // set contract open, should fail as already open
     {
         let err = wasm
-            .execute(&address, &ExecuteMsg::SetOpen {}, &[], &env.signer)
+            .execute(
+                &address,
+                &ExecuteMsg::SetOpen { market_id: None },
+                &[],
+                &env.signer,
+            )
             .unwrap_err();
         assert_eq!(
             err,
@@ -246,12 +284,19 @@ fn test_permissions() {
     {
         // pause contract
         {
-            wasm.execute(&address, &ExecuteMsg::Pause {}, &[], &env.signer)
-                .unwrap();
+            wasm.execute(
+                &address,
+                &ExecuteMsg::Pause { market_id: None },
+                &[],
+                &env.signer,
+            )
+            .unwrap();
 
             let latest_timestamp = env.app.get_block_timestamp();
 
-            let state: State = wasm.query(&address, &QueryMsg::State {}).unwrap();
+            let state: State = wasm
+                .query(&address, &QueryMsg::State { market_id: None })
+                .unwrap();
             assert_eq!(
                 state,
                 State {
@@ -259,6 +304,10 @@ fn test_permissions() {
                     is_paused: true,
                     normalisation_factor: Decimal::one(),
                     last_funding_update: timestamp,
+                    is_shutdown: false,
+                    settlement_price: None,
+                    insurance_balance: Uint128::zero(),
+                    total_bad_debt: Uint128::zero(),
                     last_pause: latest_timestamp,
                 }
             );
@@ -267,7 +316,12 @@ fn test_permissions() {
         // pause should fail, contract not set open
         {
             let err = wasm
-                .execute(&address, &ExecuteMsg::Pause {}, &[], &env.signer)
+                .execute(
+                    &address,
+                    &ExecuteMsg::Pause { market_id: None },
+                    &[],
+                    &env.signer,
+                )
                 .unwrap_err();
             assert_eq!(
                 err,
@@ -286,6 +340,7 @@ fn test_permissions() {
                         amount: Uint128::from(1_000_000u128),
                         vault_id: None,
                         rebase: false,
+                        market_id: None,
                     },
                     &[],
                     &env.signer,
@@ -417,7 +472,9 @@ fn test_pause_unpause() {
 
     let timestamp = env.app.get_block_timestamp();
 
-    let state: State = wasm.query(&address, &QueryMsg::State {}).unwrap();
+    let state: State = wasm
+        .query(&address, &QueryMsg::State { market_id: None })
+        .unwrap();
     assert_eq!(
         state,
         State {
@@ -425,6 +482,10 @@ fn test_pause_unpause() {
             is_paused: true,
             normalisation_factor: Decimal::one(),
             last_funding_update: timestamp,
+            is_shutdown: false,
+            settlement_price: None,
+            insurance_balance: Uint128::zero(),
+            total_bad_debt: Uint128::zero(),
             last_pause: timestamp,
         }
     );
@@ -434,7 +495,12 @@ fn test_pause_unpause() {
         // pause should fail, contract not set open
         {
             let err = wasm
-                .execute(&address, &ExecuteMsg::Pause {}, &[], &env.signer)
+                .execute(
+                    &address,
+                    &ExecuteMsg::Pause { market_id: None },
+                    &[],
+                    &env.signer,
+                )
                 .unwrap_err();
             assert_eq!(
                 err,
@@ -447,7 +513,12 @@ fn test_pause_unpause() {
         // unpause should fail, contract not set open
         {
             let err = wasm
-                .execute(&address, &ExecuteMsg::UnPause {}, &[], &env.signer)
+                .execute(
+                    &address,
+                    &ExecuteMsg::UnPause { market_id: None },
+                    &[],
+                    &env.signer,
+                )
                 .unwrap_err();
             assert_eq!(
                 err,
@@ -471,10 +542,17 @@ fn test_pause_unpause() {
             )
             .unwrap();
 
-        wasm.execute(&address, &ExecuteMsg::SetOpen {}, &[], &env.signer)
-            .unwrap();
+        wasm.execute(
+            &address,
+            &ExecuteMsg::SetOpen { market_id: None },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
 
-        let state: State = wasm.query(&address, &QueryMsg::State {}).unwrap();
+        let state: State = wasm
+            .query(&address, &QueryMsg::State { market_id: None })
+            .unwrap();
         assert_eq!(
             state,
             State {
@@ -482,6 +560,10 @@ fn test_pause_unpause() {
                 is_paused: false,
                 normalisation_factor: Decimal::one(),
                 last_funding_update: timestamp,
+                is_shutdown: false,
+                settlement_price: None,
+                insurance_balance: Uint128::zero(),
+                total_bad_debt: Uint128::zero(),
                 last_pause: timestamp,
             }
         );
@@ -492,10 +574,17 @@ fn test_pause_unpause() {
 
     // pause contract
     {
-        wasm.execute(&address, &ExecuteMsg::Pause {}, &[], &env.signer)
-            .unwrap();
+        wasm.execute(
+            &address,
+            &ExecuteMsg::Pause { market_id: None },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
 
-        let state: State = wasm.query(&address, &QueryMsg::State {}).unwrap();
+        let state: State = wasm
+            .query(&address, &QueryMsg::State { market_id: None })
+            .unwrap();
         assert_eq!(
             state,
             State {
@@ -503,6 +592,10 @@ fn test_pause_unpause() {
                 is_paused: true,
                 normalisation_factor: Decimal::one(),
                 last_funding_update: timestamp,
+                is_shutdown: false,
+                settlement_price: None,
+                insurance_balance: Uint128::zero(),
+                total_bad_debt: Uint128::zero(),
                 last_pause: latest_timestamp.plus_seconds(5u64),
             }
         );
@@ -511,7 +604,12 @@ fn test_pause_unpause() {
     // pause should fail, contract already paused
     {
         let err = wasm
-            .execute(&address, &ExecuteMsg::Pause {}, &[], &env.signer)
+            .execute(
+                &address,
+                &ExecuteMsg::Pause { market_id: None },
+                &[],
+                &env.signer,
+            )
             .unwrap_err();
         assert_eq!(
             err,
@@ -524,7 +622,12 @@ fn test_pause_unpause() {
     // unpause should fail, timer has not expired
     {
         let err = wasm
-            .execute(&address, &ExecuteMsg::UnPause {}, &[], &env.traders[0])
+            .execute(
+                &address,
+                &ExecuteMsg::UnPause { market_id: None },
+                &[],
+                &env.traders[0],
+            )
             .unwrap_err();
         assert_eq!(
             err,
@@ -538,10 +641,17 @@ fn test_pause_unpause() {
 
     // unpause should pass
     {
-        wasm.execute(&address, &ExecuteMsg::UnPause {}, &[], &env.traders[0])
-            .unwrap();
+        wasm.execute(
+            &address,
+            &ExecuteMsg::UnPause { market_id: None },
+            &[],
+            &env.traders[0],
+        )
+        .unwrap();
 
-        let state: State = wasm.query(&address, &QueryMsg::State {}).unwrap();
+        let state: State = wasm
+            .query(&address, &QueryMsg::State { market_id: None })
+            .unwrap();
         assert_eq!(
             state,
             State {
@@ -549,6 +659,10 @@ fn test_pause_unpause() {
                 is_paused: false,
                 normalisation_factor: Decimal::one(),
                 last_funding_update: timestamp,
+                is_shutdown: false,
+                settlement_price: None,
+                insurance_balance: Uint128::zero(),
+                total_bad_debt: Uint128::zero(),
                 last_pause: latest_timestamp.plus_seconds(5u64),
             }
         );
@@ -559,10 +673,17 @@ fn test_pause_unpause() {
 
     // pause contract
     {
-        wasm.execute(&address, &ExecuteMsg::Pause {}, &[], &env.signer)
-            .unwrap();
+        wasm.execute(
+            &address,
+            &ExecuteMsg::Pause { market_id: None },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
 
-        let state: State = wasm.query(&address, &QueryMsg::State {}).unwrap();
+        let state: State = wasm
+            .query(&address, &QueryMsg::State { market_id: None })
+            .unwrap();
         assert_eq!(
             state,
             State {
@@ -570,16 +691,27 @@ fn test_pause_unpause() {
                 is_paused: true,
                 normalisation_factor: Decimal::one(),
                 last_funding_update: timestamp,
+                is_shutdown: false,
+                settlement_price: None,
+                insurance_balance: Uint128::zero(),
+                total_bad_debt: Uint128::zero(),
                 last_pause: latest_timestamp.plus_seconds(5u64),
             }
         );
     }
     // unpause should pass
     {
-        wasm.execute(&address, &ExecuteMsg::UnPause {}, &[], &env.signer)
-            .unwrap();
+        wasm.execute(
+            &address,
+            &ExecuteMsg::UnPause { market_id: None },
+            &[],
+            &env.signer,
+        )
+        .unwrap();
 
-        let state: State = wasm.query(&address, &QueryMsg::State {}).unwrap();
+        let state: State = wasm
+            .query(&address, &QueryMsg::State { market_id: None })
+            .unwrap();
         assert_eq!(
             state,
             State {
@@ -587,6 +719,10 @@ fn test_pause_unpause() {
                 is_paused: false,
                 normalisation_factor: Decimal::one(),
                 last_funding_update: timestamp,
+                is_shutdown: false,
+                settlement_price: None,
+                insurance_balance: Uint128::zero(),
+                total_bad_debt: Uint128::zero(),
                 last_pause: latest_timestamp.plus_seconds(5u64),
             }
         );
@@ -0,0 +1,206 @@
+use crate::{contract::CONTRACT_NAME, helpers::decimal_to_fixed, state::Config};
+
+use cosmwasm_std::{coin, Decimal, Uint128};
+use margined_protocol::power::{ExecuteMsg, QueryMsg, StateResponse, VaultResponse};
+use margined_testing::{helpers::parse_event_attribute, power_env::PowerEnv};
+use osmosis_test_tube::{Account, Module, Wasm};
+use std::str::FromStr;
+
+#[test]
+fn test_shutdown_and_settlement() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.base_pool_id,
+        Decimal::from_str("3000.0").unwrap(),
+    );
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.power_pool_id,
+        Decimal::from_str("3030.0").unwrap(),
+    );
+
+    let mint_amount = Uint128::from(100_000_000u128);
+    let collateral_amount = Uint128::from(45_000_000u128);
+
+    let mint_response = wasm
+        .execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: mint_amount,
+                vault_id: None,
+                rebase: false,
+                market_id: None,
+            },
+            &[coin(
+                collateral_amount.u128(),
+                env.denoms["base"].to_string(),
+            )],
+            &env.traders[0],
+        )
+        .unwrap();
+
+    let vault_id = u64::from_str(&parse_event_attribute(
+        mint_response.events,
+        "wasm-mint",
+        "vault_id",
+    ))
+    .unwrap();
+
+    // should fail to settle before shutdown
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::WithdrawShutdown { vault_id },
+            &[],
+            &env.traders[0],
+        )
+        .unwrap_err();
+    }
+
+    // should fail if sender is not the owner
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Shutdown { market_id: None },
+            &[],
+            &env.traders[0],
+        )
+        .unwrap_err();
+    }
+
+    env.app.increase_time(60u64);
+
+    wasm.execute(
+        &perp_address,
+        &ExecuteMsg::Shutdown { market_id: None },
+        &[],
+        &env.signer,
+    )
+    .unwrap();
+
+    let state: StateResponse = wasm
+        .query(&perp_address, &QueryMsg::State { market_id: None })
+        .unwrap();
+    assert!(state.is_shutdown);
+    let settlement_price = state.settlement_price.unwrap();
+    assert!(!settlement_price.is_zero());
+
+    // should not be able to shutdown twice, mint or unpause
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Shutdown { market_id: None },
+            &[],
+            &env.signer,
+        )
+        .unwrap_err();
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: mint_amount,
+                vault_id: None,
+                rebase: false,
+                market_id: None,
+            },
+            &[coin(
+                collateral_amount.u128(),
+                env.denoms["base"].to_string(),
+            )],
+            &env.traders[1],
+        )
+        .unwrap_err();
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::UnPause { market_id: None },
+            &[],
+            &env.signer,
+        )
+        .unwrap_err();
+    }
+
+    // funding stops after shutdown
+    {
+        env.app.increase_time(86400u64);
+
+        let normalisation_factor: Decimal = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetNormalisationFactor { market_id: None },
+            )
+            .unwrap();
+        assert_eq!(normalisation_factor, state.normalisation_factor);
+    }
+
+    // power holders redeem at the settlement price
+    {
+        let redeem_amount = Uint128::from(50_000_000u128);
+        let expected_base = decimal_to_fixed(
+            Decimal::from_atomics(redeem_amount, 6u32).unwrap() * settlement_price,
+            6u32,
+        );
+
+        let balance_before = env.get_balance(env.traders[0].address(), env.denoms["base"].clone());
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::RedeemShutdown { market_id: None },
+            &[coin(redeem_amount.u128(), env.denoms["power"].to_string())],
+            &env.traders[0],
+        )
+        .unwrap();
+
+        let balance_after = env.get_balance(env.traders[0].address(), env.denoms["base"].clone());
+        assert_eq!(balance_after - balance_before, expected_base);
+    }
+
+    // vault owners withdraw collateral net of their debt
+    {
+        let debt_value = decimal_to_fixed(
+            Decimal::from_atomics(mint_amount, 6u32).unwrap() * settlement_price,
+            6u32,
+        );
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::WithdrawShutdown { vault_id },
+            &[],
+            &env.traders[1],
+        )
+        .unwrap_err();
+
+        let balance_before = env.get_balance(env.traders[0].address(), env.denoms["base"].clone());
+
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::WithdrawShutdown { vault_id },
+            &[],
+            &env.traders[0],
+        )
+        .unwrap();
+
+        let balance_after = env.get_balance(env.traders[0].address(), env.denoms["base"].clone());
+        assert_eq!(
+            balance_after - balance_before,
+            collateral_amount - debt_value
+        );
+
+        let vault: VaultResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+        assert!(vault.collateral.is_zero());
+        assert!(vault.short_amount.is_zero());
+    }
+}
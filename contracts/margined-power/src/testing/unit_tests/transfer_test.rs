@@ -0,0 +1,165 @@
+use crate::{contract::CONTRACT_NAME, state::Config};
+
+use cosmwasm_std::{coin, Decimal, Uint128};
+use cw721::OwnerOfResponse;
+use margined_protocol::power::{ExecuteMsg, QueryMsg, UserVaultsResponse, VaultResponse};
+use margined_testing::{helpers::parse_event_attribute, power_env::PowerEnv};
+use osmosis_test_tube::{Account, Module, Wasm};
+use std::str::FromStr;
+
+#[test]
+fn test_transfer_vault() {
+    let env = PowerEnv::new();
+
+    let wasm = Wasm::new(&env.app);
+    let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
+
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.base_pool_id,
+        Decimal::from_str("3000.0").unwrap(),
+    );
+    env.set_oracle_price(
+        &wasm,
+        config.query_contract.to_string(),
+        env.power_pool_id,
+        Decimal::from_str("3030.0").unwrap(),
+    );
+
+    let mint_response = wasm
+        .execute(
+            &perp_address,
+            &ExecuteMsg::MintPowerPerp {
+                amount: Uint128::from(10_000_000u128),
+                vault_id: None,
+                rebase: false,
+                market_id: None,
+            },
+            &[coin(45_000_000u128, env.denoms["base"].to_string())],
+            &env.traders[0],
+        )
+        .unwrap();
+
+    let vault_id = u64::from_str(&parse_event_attribute(
+        mint_response.events,
+        "wasm-mint",
+        "vault_id",
+    ))
+    .unwrap();
+
+    // should fail if sender is not the vault operator
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::TransferVault {
+                vault_id,
+                recipient: env.traders[1].address(),
+            },
+            &[],
+            &env.traders[1],
+        )
+        .unwrap_err();
+    }
+
+    // should fail if vault does not exist
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::TransferVault {
+                vault_id: vault_id + 1,
+                recipient: env.traders[1].address(),
+            },
+            &[],
+            &env.traders[0],
+        )
+        .unwrap_err();
+    }
+
+    // should transfer vault to the recipient
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::TransferVault {
+                vault_id,
+                recipient: env.traders[1].address(),
+            },
+            &[],
+            &env.traders[0],
+        )
+        .unwrap();
+
+        let vault: VaultResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+        assert_eq!(vault.operator, env.traders[1].address());
+        assert_eq!(vault.collateral, Uint128::from(45_000_000u128));
+        assert_eq!(vault.short_amount, Uint128::from(10_000_000u128));
+
+        let previous_owner_vaults: UserVaultsResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetUserVaults {
+                    user: env.traders[0].address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert!(previous_owner_vaults.vaults.is_empty());
+
+        let new_owner_vaults: UserVaultsResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::GetUserVaults {
+                    user: env.traders[1].address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(new_owner_vaults.vaults, vec![vault_id]);
+
+        let token_owner: OwnerOfResponse = wasm
+            .query(
+                &perp_address,
+                &QueryMsg::OwnerOf {
+                    token_id: vault_id.to_string(),
+                    include_expired: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(token_owner.owner, env.traders[1].address());
+    }
+
+    // previous owner can no longer operate the vault
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Deposit { vault_id },
+            &[coin(1_000_000u128, env.denoms["base"].to_string())],
+            &env.traders[0],
+        )
+        .unwrap_err();
+    }
+
+    // new owner can operate the vault
+    {
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::Deposit { vault_id },
+            &[coin(1_000_000u128, env.denoms["base"].to_string())],
+            &env.traders[1],
+        )
+        .unwrap();
+
+        let vault: VaultResponse = wasm
+            .query(&perp_address, &QueryMsg::GetVault { vault_id })
+            .unwrap();
+        assert_eq!(vault.collateral, Uint128::from(46_000_000u128));
+    }
+}
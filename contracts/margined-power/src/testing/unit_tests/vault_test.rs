@@ -16,8 +16,12 @@ fn test_check_vault_is_safe() {
     let wasm = Wasm::new(&env.app);
     let (perp_address, _) = env.deploy_power(&wasm, CONTRACT_NAME.to_string(), true);
 
-    let config: Config = wasm.query(&perp_address, &QueryMsg::Config {}).unwrap();
-    let state_initial: State = wasm.query(&perp_address, &QueryMsg::State {}).unwrap();
+    let config: Config = wasm
+        .query(&perp_address, &QueryMsg::Config { market_id: None })
+        .unwrap();
+    let state_initial: State = wasm
+        .query(&perp_address, &QueryMsg::State { market_id: None })
+        .unwrap();
     let mut vault_id = 0u64;
 
     assert_eq!(Decimal::one(), state_initial.normalisation_factor);
@@ -56,6 +60,7 @@ fn test_check_vault_is_safe() {
 
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(
                     collateral_amount.into(),
@@ -103,6 +108,7 @@ fn test_check_vault_is_safe() {
                     amount: mint_amount,
                     vault_id: None,
                     rebase: false,
+                    market_id: None,
                 },
                 &[coin(
                     collateral_amount.into(),
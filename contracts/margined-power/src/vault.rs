@@ -1,22 +1,28 @@
 use crate::{
+    collateral::value_vault,
     helpers::decimal_to_fixed,
-    queries::get_scaled_pool_twap,
-    state::{Config, CONFIG, TWAP_PERIOD},
+    nft::{get_vault_nft_owner, mint_vault_nft, transfer_vault_nft},
+    oracle::get_scaled_base_price,
+    state::{Config, INDEX_SCALE},
 };
 
 use cosmwasm_std::{
-    ensure_eq, Addr, Decimal, Deps, StdError, StdResult, Storage, Timestamp, Uint128,
+    ensure_eq, Addr, BlockInfo, Decimal, Deps, Order, StdError, StdResult, Storage, Timestamp,
+    Uint128,
 };
-use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex};
+use cw721::Expiration;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use margined_common::errors::ContractError;
+use margined_protocol::power::VaultHealthResponse;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-pub const COLLATERAL_RATIO_NUMERATOR: Decimal = Decimal::raw(3_000_000_000_000_000_000u128); // 3
-pub const COLLATERAL_RATIO_DENOMINATOR: Decimal = Decimal::raw(2_000_000_000_000_000_000u128); // 2
-pub const MIN_COLLATERAL: Decimal = Decimal::raw(500_000_000_000_000_000u128); // 0.5
-
 pub const VAULTS: IndexedMap<&u64, Vault, VaultIndexes> = IndexedMap::new("vaults", INDEXES);
 pub const VAULTS_COUNTER: Item<u64> = Item::new("vaults_counter");
+pub const VAULT_APPROVALS: Map<(u64, &Addr), Expiration> = Map::new("vault_approvals");
+pub const LIQUIDATION_AUCTIONS: Map<u64, Timestamp> = Map::new("liquidation_auctions");
+// collateral of each vault other than the base denom, keyed by the vault id and denom
+pub const VAULT_COLLATERAL: Map<(u64, &str), Uint128> = Map::new("vault_collateral");
 
 pub const INDEXES: VaultIndexes<'_> = VaultIndexes {
     owner: MultiIndex::new(vault_operator_idx, "vaults", "vault__owner"),
@@ -24,6 +30,7 @@ pub const INDEXES: VaultIndexes<'_> = VaultIndexes {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct Vault {
+    pub market_id: u64,
     pub operator: Addr,
     pub collateral: Uint128,
     pub short_amount: Uint128,
@@ -45,8 +52,9 @@ pub fn vault_operator_idx(_pk: &[u8], d: &Vault) -> Addr {
 }
 
 impl Vault {
-    pub fn new(operator: Addr) -> Self {
+    pub fn new(operator: Addr, market_id: u64) -> Self {
         Vault {
+            market_id,
             operator,
             collateral: Uint128::zero(),
             short_amount: Uint128::zero(),
@@ -87,29 +95,46 @@ pub fn get_vault_status(
     normalisation_factor: Decimal,
     block_time: Timestamp,
 ) -> StdResult<(bool, bool)> {
-    let start_time = block_time.minus_seconds(TWAP_PERIOD);
+    let vault = VAULTS.may_load(deps.storage, &vault_id)?;
+    if vault.is_none() {
+        return Ok((true, false));
+    }
+
+    let vault = vault.unwrap();
+
+    let start_time = block_time.minus_seconds(config.twap_period);
+
+    let quote_price = get_scaled_base_price(&deps, vault.market_id, start_time, block_time)?;
 
-    let quote_price = get_scaled_pool_twap(
-        &deps,
-        config.base_pool.id,
-        config.base_denom.clone(),
-        config.base_pool.quote_denom,
-        start_time,
-    )
-    .unwrap();
+    let vault = value_vault(&deps, &config, vault_id, vault, start_time, block_time)?;
 
-    get_status(&deps, vault_id, normalisation_factor, quote_price)
+    Ok(calculate_status(
+        &config,
+        vault,
+        normalisation_factor,
+        quote_price,
+    ))
+}
+
+/// Returns the market the vault was opened in.
+pub fn get_vault_market_id(storage: &dyn Storage, vault_id: u64) -> Result<u64, ContractError> {
+    match VAULTS.may_load(storage, &vault_id)? {
+        Some(vault) => Ok(vault.market_id),
+        None => Err(ContractError::VaultDoesNotExist {}),
+    }
 }
 
-pub fn create_vault(storage: &mut dyn Storage, operator: Addr) -> StdResult<u64> {
+pub fn create_vault(storage: &mut dyn Storage, operator: Addr, market_id: u64) -> StdResult<u64> {
     let current_vault_count = VAULTS_COUNTER.load(storage).unwrap_or(0);
     let nonce = current_vault_count + 1;
 
-    let vault = Vault::new(operator);
+    let vault = Vault::new(operator.clone(), market_id);
 
     VAULTS.save(storage, &nonce, &vault)?;
     VAULTS_COUNTER.save(storage, &nonce)?;
 
+    mint_vault_nft(storage, nonce, operator)?;
+
     Ok(nonce)
 }
 
@@ -122,13 +147,10 @@ pub fn update_vault(
 ) -> StdResult<()> {
     let current_vault = VAULTS.load(storage, &vault_id).unwrap();
 
-    ensure_eq!(
-        operator,
-        current_vault.operator,
-        StdError::generic_err("operator does not match")
-    );
+    check_vault_owner(storage, vault_id, &operator)?;
 
     let vault = Vault {
+        market_id: current_vault.market_id,
         operator,
         collateral: current_vault.collateral + collateral,
         short_amount: current_vault.short_amount + short_amount,
@@ -148,11 +170,7 @@ pub fn check_can_burn(
 ) -> StdResult<()> {
     let current_vault = VAULTS.load(storage, &vault_id).unwrap();
 
-    ensure_eq!(
-        operator,
-        current_vault.operator,
-        StdError::generic_err("operator does not match")
-    );
+    check_vault_owner(storage, vault_id, &operator)?;
 
     if collateral_to_withdraw > current_vault.collateral
         || amount_to_burn > current_vault.short_amount
@@ -174,11 +192,7 @@ pub fn burn_vault(
 ) -> StdResult<()> {
     let current_vault = VAULTS.load(storage, &vault_id).unwrap();
 
-    ensure_eq!(
-        operator,
-        current_vault.operator,
-        StdError::generic_err("operator does not match")
-    );
+    check_vault_owner(storage, vault_id, &operator)?;
 
     if collateral > current_vault.collateral || short_amount > current_vault.short_amount {
         return Err(StdError::generic_err(
@@ -187,6 +201,7 @@ pub fn burn_vault(
     }
 
     let vault = Vault {
+        market_id: current_vault.market_id,
         operator,
         collateral: current_vault.collateral - collateral,
         short_amount: current_vault.short_amount - short_amount,
@@ -205,13 +220,10 @@ pub fn add_collateral(
 ) -> StdResult<()> {
     let current_vault = VAULTS.load(storage, &vault_id).unwrap();
 
-    ensure_eq!(
-        operator,
-        current_vault.operator,
-        StdError::generic_err("operator does not match")
-    );
+    check_vault_owner(storage, vault_id, &operator)?;
 
     let vault = Vault {
+        market_id: current_vault.market_id,
         operator,
         collateral: current_vault.collateral + collateral,
         short_amount: current_vault.short_amount,
@@ -230,11 +242,7 @@ pub fn subtract_collateral(
 ) -> StdResult<()> {
     let current_vault = VAULTS.load(storage, &vault_id).unwrap();
 
-    ensure_eq!(
-        operator,
-        current_vault.operator,
-        StdError::generic_err("operator does not match")
-    );
+    check_vault_owner(storage, vault_id, &operator)?;
 
     if collateral > current_vault.collateral {
         return Err(StdError::generic_err(
@@ -243,6 +251,7 @@ pub fn subtract_collateral(
     }
 
     let vault = Vault {
+        market_id: current_vault.market_id,
         operator,
         collateral: current_vault.collateral - collateral,
         short_amount: current_vault.short_amount,
@@ -253,37 +262,240 @@ pub fn subtract_collateral(
     Ok(())
 }
 
-pub fn get_status(
-    deps: &Deps,
+pub fn add_vault_collateral(
+    storage: &mut dyn Storage,
     vault_id: u64,
-    normalisation_factor: Decimal,
-    quote_price: Decimal,
-) -> StdResult<(bool, bool)> {
-    let config = CONFIG.load(deps.storage)?;
+    operator: Addr,
+    denom: &str,
+    amount: Uint128,
+) -> StdResult<()> {
+    check_vault_owner(storage, vault_id, &operator)?;
 
-    let vault = VAULTS.may_load(deps.storage, &vault_id)?;
-    if vault.is_none() {
-        return Ok((true, false));
+    VAULT_COLLATERAL.update(storage, (vault_id, denom), |collateral| -> StdResult<_> {
+        Ok(collateral.unwrap_or_default().checked_add(amount)?)
+    })?;
+
+    Ok(())
+}
+
+pub fn subtract_vault_collateral(
+    storage: &mut dyn Storage,
+    vault_id: u64,
+    operator: Addr,
+    denom: &str,
+    amount: Uint128,
+) -> StdResult<()> {
+    check_vault_owner(storage, vault_id, &operator)?;
+
+    let collateral = VAULT_COLLATERAL
+        .may_load(storage, (vault_id, denom))?
+        .unwrap_or_default();
+
+    if amount > collateral {
+        return Err(StdError::generic_err(
+            "Cannot subtract more collateral than deposited",
+        ));
     }
 
-    let vault = vault.unwrap();
+    if amount == collateral {
+        VAULT_COLLATERAL.remove(storage, (vault_id, denom));
+    } else {
+        VAULT_COLLATERAL.save(storage, (vault_id, denom), &(collateral - amount))?;
+    }
 
-    Ok(calculate_status(
-        config.base_decimals,
-        config.power_decimals,
-        vault,
-        normalisation_factor,
-        quote_price,
-    ))
+    Ok(())
+}
+
+/// Returns the collateral of the vault other than the base denom.
+pub fn get_vault_collateral(
+    storage: &dyn Storage,
+    vault_id: u64,
+) -> StdResult<Vec<(String, Uint128)>> {
+    VAULT_COLLATERAL
+        .prefix(vault_id)
+        .range(storage, None, None, Order::Ascending)
+        .collect()
+}
+
+/// Closes the vault at settlement, returns the vault as it was prior to closing.
+pub fn settle_vault(storage: &mut dyn Storage, vault_id: u64, operator: Addr) -> StdResult<Vault> {
+    let current_vault = VAULTS.load(storage, &vault_id).unwrap();
+
+    check_vault_owner(storage, vault_id, &operator)?;
+
+    VAULTS.save(
+        storage,
+        &vault_id,
+        &Vault::new(operator, current_vault.market_id),
+    )?;
+
+    Ok(current_vault)
+}
+
+pub fn transfer_vault(
+    storage: &mut dyn Storage,
+    vault_id: u64,
+    operator: Addr,
+    recipient: Addr,
+) -> StdResult<()> {
+    let current_vault = VAULTS.load(storage, &vault_id).unwrap();
+
+    check_vault_owner(storage, vault_id, &operator)?;
+
+    let vault = Vault {
+        market_id: current_vault.market_id,
+        operator: recipient.clone(),
+        collateral: current_vault.collateral,
+        short_amount: current_vault.short_amount,
+    };
+
+    // saving through the indexed map replaces the vault__owner index entry
+    VAULTS.save(storage, &vault_id, &vault)?;
+
+    transfer_vault_nft(storage, vault_id, recipient)?;
+
+    clear_vault_approvals(storage, vault_id)?;
+
+    Ok(())
+}
+
+/// Updates the vault operator to the current NFT owner, used after the token
+/// has been moved through the cw721 interface.
+pub fn sync_vault_operator(storage: &mut dyn Storage, vault_id: u64) -> StdResult<()> {
+    let owner = get_vault_nft_owner(storage, vault_id)?;
+
+    VAULTS.update(storage, &vault_id, |vault| -> StdResult<_> {
+        let mut vault = vault.ok_or_else(|| StdError::generic_err("Vault not found"))?;
+        vault.operator = owner;
+        Ok(vault)
+    })?;
+
+    clear_vault_approvals(storage, vault_id)?;
+
+    Ok(())
+}
+
+/// The vault NFT owner is the only account allowed to operate the vault.
+pub fn check_vault_owner(storage: &dyn Storage, vault_id: u64, operator: &Addr) -> StdResult<()> {
+    let owner = get_vault_nft_owner(storage, vault_id)?;
+
+    ensure_eq!(
+        operator,
+        &owner,
+        StdError::generic_err("operator does not match")
+    );
+
+    Ok(())
+}
+
+/// Returns the vault owner if the sender is the owner or holds an unexpired
+/// approval for the vault.
+pub fn get_vault_owner_for_sender(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    vault_id: u64,
+    sender: &Addr,
+) -> StdResult<Addr> {
+    let owner = get_vault_nft_owner(storage, vault_id)?;
+
+    if owner == *sender {
+        return Ok(owner);
+    }
+
+    match VAULT_APPROVALS.may_load(storage, (vault_id, sender))? {
+        Some(expires) if !expires.is_expired(block) => Ok(owner),
+        _ => Err(StdError::generic_err("operator does not match")),
+    }
+}
+
+/// Returns the address that receives what is minted against a vault, which is the
+/// owner when the sender is an approved operator, or the sender for a new vault.
+pub fn get_mint_recipient(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    vault_id: Option<u64>,
+    sender: &Addr,
+) -> StdResult<Addr> {
+    match vault_id {
+        Some(vault_id) => get_vault_owner_for_sender(storage, block, vault_id, sender),
+        None => Ok(sender.clone()),
+    }
+}
+
+pub fn approve_vault_operator(
+    storage: &mut dyn Storage,
+    vault_id: u64,
+    owner: Addr,
+    spender: &Addr,
+    expires: Expiration,
+) -> StdResult<()> {
+    check_vault_owner(storage, vault_id, &owner)?;
+
+    VAULT_APPROVALS.save(storage, (vault_id, spender), &expires)?;
+
+    Ok(())
+}
+
+pub fn revoke_vault_operator(
+    storage: &mut dyn Storage,
+    vault_id: u64,
+    owner: Addr,
+    spender: &Addr,
+) -> StdResult<()> {
+    check_vault_owner(storage, vault_id, &owner)?;
+
+    if !VAULT_APPROVALS.has(storage, (vault_id, spender)) {
+        return Err(StdError::generic_err("Approval not found"));
+    }
+
+    VAULT_APPROVALS.remove(storage, (vault_id, spender));
+
+    Ok(())
+}
+
+/// Approvals are granted by the owner, so they do not carry over to a new owner.
+fn clear_vault_approvals(storage: &mut dyn Storage, vault_id: u64) -> StdResult<()> {
+    let spenders = VAULT_APPROVALS
+        .prefix(vault_id)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<Addr>>>()?;
+
+    for spender in spenders {
+        VAULT_APPROVALS.remove(storage, (vault_id, &spender));
+    }
+
+    Ok(())
+}
+
+/// Returns the time the liquidation auction of the vault started, starting it
+/// at the current block time if this is the first time the vault is unsafe.
+pub fn start_liquidation_auction(
+    storage: &mut dyn Storage,
+    vault_id: u64,
+    block_time: Timestamp,
+) -> StdResult<Timestamp> {
+    match LIQUIDATION_AUCTIONS.may_load(storage, vault_id)? {
+        Some(start_time) => Ok(start_time),
+        None => {
+            LIQUIDATION_AUCTIONS.save(storage, vault_id, &block_time)?;
+            Ok(block_time)
+        }
+    }
+}
+
+pub fn clear_liquidation_auction(storage: &mut dyn Storage, vault_id: u64) {
+    LIQUIDATION_AUCTIONS.remove(storage, vault_id);
 }
 
 fn calculate_status(
-    base_decimals: u32,
-    power_decimals: u32,
+    config: &Config,
     vault: Vault,
     normalisation_factor: Decimal,
     quote_price: Decimal,
 ) -> (bool, bool) {
+    let base_decimals = config.base_decimals;
+    let power_decimals = config.power_decimals;
+
     let decimal_short_amount = Decimal::from_ratio(
         vault.short_amount,
         Uint128::from(10u128.pow(power_decimals)),
@@ -299,15 +511,17 @@ fn calculate_status(
         Decimal::from_ratio(vault.collateral, Uint128::from(10u128.pow(base_decimals)));
 
     let adjusted_collateral = decimal_collateral
-        .checked_mul(COLLATERAL_RATIO_DENOMINATOR)
+        .checked_mul(config.collateral_ratio_denominator)
+        .unwrap();
+    let adjusted_debt = debt_value
+        .checked_mul(config.collateral_ratio_numerator)
         .unwrap();
-    let adjusted_debt = debt_value.checked_mul(COLLATERAL_RATIO_NUMERATOR).unwrap();
 
     // Return to fixed point to remove rounding errors
     let adjusted_collateral = decimal_to_fixed(adjusted_collateral, base_decimals);
     let adjusted_debt = decimal_to_fixed(adjusted_debt, base_decimals);
 
-    let min_collateral = decimal_to_fixed(MIN_COLLATERAL, base_decimals);
+    let min_collateral = decimal_to_fixed(config.min_collateral, base_decimals);
 
     let above_min_collateral = min_collateral <= vault.collateral;
     let is_solvent = adjusted_collateral >= adjusted_debt;
@@ -315,20 +529,166 @@ fn calculate_status(
     (is_solvent, above_min_collateral)
 }
 
+/// Returns the health of the vault using the same thresholds as the status,
+/// values in base and quote are in whole units, amounts in fixed point.
+pub fn calculate_health(
+    config: &Config,
+    vault: Vault,
+    normalisation_factor: Decimal,
+    quote_price: Decimal,
+) -> VaultHealthResponse {
+    let base_decimals = config.base_decimals;
+    let power_decimals = config.power_decimals;
+
+    let decimal_short_amount = Decimal::from_ratio(
+        vault.short_amount,
+        Uint128::from(10u128.pow(power_decimals)),
+    );
+
+    let decimal_collateral =
+        Decimal::from_ratio(vault.collateral, Uint128::from(10u128.pow(base_decimals)));
+
+    // value of one power token in base, the short amount is normalised
+    let power_price = normalisation_factor.checked_mul(quote_price).unwrap();
+
+    let debt_value_in_base = decimal_short_amount.checked_mul(power_price).unwrap();
+    let base_price = quote_price
+        .checked_mul(Decimal::from_atomics(INDEX_SCALE, 0).unwrap())
+        .unwrap();
+    let debt_value_in_quote = debt_value_in_base.checked_mul(base_price).unwrap();
+
+    let (collateral_ratio, liquidation_price) = if debt_value_in_base.is_zero() {
+        (None, None)
+    } else {
+        let collateral_ratio = decimal_collateral.checked_div(debt_value_in_base).unwrap();
+
+        // debt grows linearly with the scaled price of the base
+        let liquidation_price = decimal_collateral
+            .checked_mul(config.collateral_ratio_denominator)
+            .unwrap()
+            .checked_mul(Decimal::from_atomics(INDEX_SCALE, 0).unwrap())
+            .unwrap()
+            .checked_div(
+                decimal_short_amount
+                    .checked_mul(normalisation_factor)
+                    .unwrap()
+                    .checked_mul(config.collateral_ratio_numerator)
+                    .unwrap(),
+            )
+            .unwrap();
+
+        (Some(collateral_ratio), Some(liquidation_price))
+    };
+
+    let (is_safe, above_min_collateral) =
+        calculate_status(config, vault.clone(), normalisation_factor, quote_price);
+
+    let max_mintable = if above_min_collateral && !power_price.is_zero() {
+        let max_short_amount = decimal_collateral
+            .checked_mul(config.collateral_ratio_denominator)
+            .unwrap()
+            .checked_div(
+                power_price
+                    .checked_mul(config.collateral_ratio_numerator)
+                    .unwrap(),
+            )
+            .unwrap();
+
+        decimal_to_fixed(max_short_amount, power_decimals).saturating_sub(vault.short_amount)
+    } else {
+        Uint128::zero()
+    };
+
+    let required_collateral = debt_value_in_base
+        .checked_mul(config.collateral_ratio_numerator)
+        .unwrap()
+        .checked_div(config.collateral_ratio_denominator)
+        .unwrap()
+        .max(config.min_collateral);
+
+    // round up so that withdrawing the maximum leaves the vault safe
+    let mut required_collateral_amount = decimal_to_fixed(required_collateral, base_decimals);
+    if Decimal::from_atomics(required_collateral_amount, base_decimals).unwrap()
+        < required_collateral
+    {
+        required_collateral_amount += Uint128::one();
+    }
+
+    let max_withdrawable = vault.collateral.saturating_sub(required_collateral_amount);
+
+    VaultHealthResponse {
+        is_safe,
+        above_min_collateral,
+        collateral_ratio,
+        debt_value_in_base,
+        debt_value_in_quote,
+        liquidation_price,
+        max_mintable,
+        max_withdrawable,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::vault::{calculate_status, Vault};
+    use crate::{
+        state::{
+            Config, COLLATERAL_RATIO_DENOMINATOR, COLLATERAL_RATIO_NUMERATOR, FUNDING_LOWER_BOUND,
+            FUNDING_UPPER_BOUND, LIQUIDATION_AUCTION_DURATION, LIQUIDATION_BOUNTY, MAX_TWAP_PERIOD,
+            MIN_COLLATERAL, TWAP_PERIOD,
+        },
+        vault::{calculate_health, calculate_status, Vault},
+    };
 
-    use cosmwasm_std::{Addr, Decimal};
+    use cosmwasm_std::{Addr, Decimal, Uint128};
+    use margined_protocol::power::{FeeSchedule, Oracle, Pool, PriceType, FUNDING_PERIOD};
 
     const INDEX_SCALE_FACTOR: Decimal = Decimal::raw(10_000_000_000_000_000_000_000u128); // 10,000.0
 
+    fn test_config(base_decimals: u32, power_decimals: u32) -> Config {
+        Config {
+            query_contract: Addr::unchecked("query"),
+            fee_pool_contract: Addr::unchecked("fee_pool"),
+            fee_rate: Decimal::zero(),
+            power_denom: "power".to_string(),
+            base_denom: "base".to_string(),
+            base_pool: Pool {
+                id: 1,
+                quote_denom: "quote".to_string(),
+            },
+            power_pool: Pool {
+                id: 2,
+                quote_denom: "power".to_string(),
+            },
+            funding_period: FUNDING_PERIOD,
+            base_decimals,
+            power_decimals,
+            min_liquidation_bonus: LIQUIDATION_BOUNTY,
+            max_liquidation_bonus: LIQUIDATION_BOUNTY,
+            liquidation_auction_duration: LIQUIDATION_AUCTION_DURATION,
+            insurance_fee_share: Decimal::zero(),
+            collateral_ratio_numerator: COLLATERAL_RATIO_NUMERATOR,
+            collateral_ratio_denominator: COLLATERAL_RATIO_DENOMINATOR,
+            min_collateral: MIN_COLLATERAL,
+            twap_period: TWAP_PERIOD,
+            max_twap_period: MAX_TWAP_PERIOD,
+            funding_lower_bound: FUNDING_LOWER_BOUND,
+            funding_upper_bound: FUNDING_UPPER_BOUND,
+            oracle: Oracle::default(),
+            max_spot_deviation: Decimal::zero(),
+            power_price_type: PriceType::default(),
+            flash_mint_fee_rate: Decimal::zero(),
+            fee_schedule: FeeSchedule::default(),
+            fee_recipients: vec![],
+        }
+    }
+
     #[test]
     fn test_calculate_status() {
         let base_decimals = 6u32;
         let power_decimals = 6u32;
 
         let vault = Vault {
+            market_id: 1,
             operator: Addr::unchecked(""),
             collateral: 45_000_000u128.into(),    // 45.0
             short_amount: 100_000_000u128.into(), // 100.0
@@ -343,8 +703,7 @@ mod tests {
             .unwrap();
 
         let (solvent, above_min_collateral) = calculate_status(
-            base_decimals,
-            power_decimals,
+            &test_config(base_decimals, power_decimals),
             vault,
             normalization_factor,
             scaled_quote_price,
@@ -360,6 +719,7 @@ mod tests {
         let power_decimals = 6u32;
 
         let vault = Vault {
+            market_id: 1,
             operator: Addr::unchecked(""),
             collateral: 45_000_000u128.into(),    // 45.0
             short_amount: 100_000_000u128.into(), // 100.0
@@ -374,8 +734,7 @@ mod tests {
             .unwrap();
 
         let (solvent, above_min_collateral) = calculate_status(
-            base_decimals,
-            power_decimals,
+            &test_config(base_decimals, power_decimals),
             vault,
             normalization_factor,
             scaled_quote_price,
@@ -391,6 +750,7 @@ mod tests {
         let power_decimals = 6u32;
 
         let vault = Vault {
+            market_id: 1,
             operator: Addr::unchecked(""),
             collateral: 45_000_000u128.into(),    // 45.0
             short_amount: 100_000_000u128.into(), // 100.0
@@ -405,8 +765,7 @@ mod tests {
             .unwrap();
 
         let (solvent, above_min_collateral) = calculate_status(
-            base_decimals,
-            power_decimals,
+            &test_config(base_decimals, power_decimals),
             vault,
             normalization_factor,
             scaled_quote_price,
@@ -422,6 +781,7 @@ mod tests {
         let power_decimals = 6u32;
 
         let vault = Vault {
+            market_id: 1,
             operator: Addr::unchecked(""),
             collateral: 45_000_000u128.into(),    // 45.0
             short_amount: 100_000_000u128.into(), // 100.0
@@ -436,8 +796,7 @@ mod tests {
             .unwrap();
 
         let (solvent, above_min_collateral) = calculate_status(
-            base_decimals,
-            power_decimals,
+            &test_config(base_decimals, power_decimals),
             vault,
             normalization_factor,
             scaled_quote_price,
@@ -453,6 +812,7 @@ mod tests {
         let power_decimals = 6u32;
 
         let vault = Vault {
+            market_id: 1,
             operator: Addr::unchecked(""),
             collateral: 450_000u128.into(),     // 0.45
             short_amount: 1_000_000u128.into(), // 1.0
@@ -467,8 +827,7 @@ mod tests {
             .unwrap();
 
         let (solvent, above_min_collateral) = calculate_status(
-            base_decimals,
-            power_decimals,
+            &test_config(base_decimals, power_decimals),
             vault,
             normalization_factor,
             scaled_quote_price,
@@ -484,6 +843,7 @@ mod tests {
         let power_decimals = 6u32;
 
         let vault = Vault {
+            market_id: 1,
             operator: Addr::unchecked(""),
             collateral: 450_000u128.into(),     // 0.45
             short_amount: 1_000_000u128.into(), // 1.0
@@ -498,8 +858,7 @@ mod tests {
             .unwrap();
 
         let (solvent, above_min_collateral) = calculate_status(
-            base_decimals,
-            power_decimals,
+            &test_config(base_decimals, power_decimals),
             vault,
             normalization_factor,
             scaled_quote_price,
@@ -515,6 +874,7 @@ mod tests {
         let power_decimals = 6u32;
 
         let vault = Vault {
+            market_id: 1,
             operator: Addr::unchecked(""),
             collateral: 20_000_000u128.into(),    // 20.0
             short_amount: 102_561_000u128.into(), // 10.0
@@ -529,8 +889,7 @@ mod tests {
             .unwrap();
 
         let (solvent, above_min_collateral) = calculate_status(
-            base_decimals,
-            power_decimals,
+            &test_config(base_decimals, power_decimals),
             vault,
             normalization_factor,
             scaled_quote_price,
@@ -539,4 +898,88 @@ mod tests {
         assert!(solvent);
         assert!(above_min_collateral);
     }
+
+    #[test]
+    fn test_calculate_health() {
+        let base_decimals = 6u32;
+        let power_decimals = 6u32;
+
+        let vault = Vault {
+            market_id: 1,
+            operator: Addr::unchecked(""),
+            collateral: 60_000_000u128.into(),    // 60.0
+            short_amount: 100_000_000u128.into(), // 100.0
+        };
+
+        let normalization_factor = Decimal::from_atomics(1u128, 0u32).unwrap();
+
+        // price is 3000.0
+        let scaled_quote_price = Decimal::from_atomics(3_000_000_000u128, base_decimals)
+            .unwrap()
+            .checked_div(INDEX_SCALE_FACTOR)
+            .unwrap();
+
+        let health = calculate_health(
+            &test_config(base_decimals, power_decimals),
+            vault,
+            normalization_factor,
+            scaled_quote_price,
+        );
+
+        assert!(health.is_safe);
+        assert!(health.above_min_collateral);
+        assert_eq!(
+            health.collateral_ratio,
+            Some(Decimal::from_ratio(2u128, 1u128))
+        );
+        assert_eq!(
+            health.debt_value_in_base,
+            Decimal::from_ratio(30u128, 1u128)
+        );
+        assert_eq!(
+            health.debt_value_in_quote,
+            Decimal::from_ratio(90_000u128, 1u128)
+        );
+        assert_eq!(
+            health.liquidation_price,
+            Some(Decimal::from_ratio(4_000u128, 1u128))
+        );
+        assert_eq!(health.max_mintable, Uint128::from(33_333_333u128));
+        assert_eq!(health.max_withdrawable, Uint128::from(15_000_000u128));
+    }
+
+    #[test]
+    fn test_calculate_health_without_debt() {
+        let base_decimals = 6u32;
+        let power_decimals = 6u32;
+
+        let vault = Vault {
+            market_id: 1,
+            operator: Addr::unchecked(""),
+            collateral: 45_000_000u128.into(), // 45.0
+            short_amount: Uint128::zero(),
+        };
+
+        let normalization_factor = Decimal::from_atomics(1u128, 0u32).unwrap();
+
+        // price is 3000.0
+        let scaled_quote_price = Decimal::from_atomics(3_000_000_000u128, base_decimals)
+            .unwrap()
+            .checked_div(INDEX_SCALE_FACTOR)
+            .unwrap();
+
+        let health = calculate_health(
+            &test_config(base_decimals, power_decimals),
+            vault,
+            normalization_factor,
+            scaled_quote_price,
+        );
+
+        assert!(health.is_safe);
+        assert_eq!(health.collateral_ratio, None);
+        assert_eq!(health.liquidation_price, None);
+        assert!(health.debt_value_in_base.is_zero());
+        assert_eq!(health.max_mintable, Uint128::from(100_000_000u128));
+        assert_eq!(health.max_withdrawable, Uint128::from(44_500_000u128));
+    }
 }
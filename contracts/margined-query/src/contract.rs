@@ -1,4 +1,7 @@
-use crate::query::{get_arithmetic_twap_now, get_denom_authority};
+use crate::query::{
+    get_arithmetic_twap, get_arithmetic_twap_now, get_denom_authority, get_geometric_twap,
+    get_geometric_twap_now, get_spot_price,
+};
 
 use cosmwasm_std::{
     entry_point, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
@@ -51,6 +54,51 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             quote_asset,
             start_time,
         )?),
+        QueryMsg::GetArithmeticTwap {
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+            end_time,
+        } => to_binary(&get_arithmetic_twap(
+            deps,
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+            end_time,
+        )?),
+        QueryMsg::GetGeometricTwapToNow {
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+        } => to_binary(&get_geometric_twap_now(
+            deps,
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+        )?),
+        QueryMsg::GetGeometricTwap {
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+            end_time,
+        } => to_binary(&get_geometric_twap(
+            deps,
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+            end_time,
+        )?),
+        QueryMsg::GetSpotPrice {
+            pool_id,
+            base_asset,
+            quote_asset,
+        } => to_binary(&get_spot_price(deps, pool_id, base_asset, quote_asset)?),
         QueryMsg::GetDenomAuthority { denom } => to_binary(&get_denom_authority(deps, denom)?),
     }
 }
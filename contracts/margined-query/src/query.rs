@@ -1,6 +1,7 @@
 use cosmwasm_std::{Decimal, Deps, StdResult, Timestamp};
 use osmosis_std::{
     shim::Timestamp as OsmosisTimestamp,
+    types::osmosis::poolmanager::v1beta1::PoolmanagerQuerier,
     types::osmosis::tokenfactory::v1beta1::{DenomAuthorityMetadata, TokenfactoryQuerier},
     types::osmosis::twap::v1beta1::TwapQuerier,
 };
@@ -15,18 +16,100 @@ pub fn get_arithmetic_twap_now(
 ) -> StdResult<Decimal> {
     let querier = TwapQuerier::new(&deps.querier);
 
-    let start_time = OsmosisTimestamp {
-        seconds: start_time.seconds() as i64,
-        nanos: start_time.subsec_nanos() as i32,
-    };
+    let res = querier.arithmetic_twap_to_now(
+        pool_id,
+        base_asset,
+        quote_asset,
+        Some(to_osmosis_timestamp(start_time)),
+    )?;
 
-    let res = querier.arithmetic_twap_to_now(pool_id, base_asset, quote_asset, Some(start_time))?;
+    let price = Decimal::from_str(&res.arithmetic_twap).unwrap();
+
+    Ok(price)
+}
+
+pub fn get_arithmetic_twap(
+    deps: Deps,
+    pool_id: u64,
+    base_asset: String,
+    quote_asset: String,
+    start_time: Timestamp,
+    end_time: Timestamp,
+) -> StdResult<Decimal> {
+    let querier = TwapQuerier::new(&deps.querier);
+
+    let res = querier.arithmetic_twap(
+        pool_id,
+        base_asset,
+        quote_asset,
+        Some(to_osmosis_timestamp(start_time)),
+        Some(to_osmosis_timestamp(end_time)),
+    )?;
 
     let price = Decimal::from_str(&res.arithmetic_twap).unwrap();
 
     Ok(price)
 }
 
+pub fn get_geometric_twap_now(
+    deps: Deps,
+    pool_id: u64,
+    base_asset: String,
+    quote_asset: String,
+    start_time: Timestamp,
+) -> StdResult<Decimal> {
+    let querier = TwapQuerier::new(&deps.querier);
+
+    let res = querier.geometric_twap_to_now(
+        pool_id,
+        base_asset,
+        quote_asset,
+        Some(to_osmosis_timestamp(start_time)),
+    )?;
+
+    let price = Decimal::from_str(&res.geometric_twap).unwrap();
+
+    Ok(price)
+}
+
+pub fn get_geometric_twap(
+    deps: Deps,
+    pool_id: u64,
+    base_asset: String,
+    quote_asset: String,
+    start_time: Timestamp,
+    end_time: Timestamp,
+) -> StdResult<Decimal> {
+    let querier = TwapQuerier::new(&deps.querier);
+
+    let res = querier.geometric_twap(
+        pool_id,
+        base_asset,
+        quote_asset,
+        Some(to_osmosis_timestamp(start_time)),
+        Some(to_osmosis_timestamp(end_time)),
+    )?;
+
+    let price = Decimal::from_str(&res.geometric_twap).unwrap();
+
+    Ok(price)
+}
+
+pub fn get_spot_price(
+    deps: Deps,
+    pool_id: u64,
+    base_asset: String,
+    quote_asset: String,
+) -> StdResult<Decimal> {
+    let querier = PoolmanagerQuerier::new(&deps.querier);
+
+    let res = querier.spot_price(pool_id, base_asset, quote_asset)?;
+
+    let price = Decimal::from_str(&res.spot_price).unwrap();
+
+    Ok(price)
+}
+
 pub fn get_denom_authority(deps: Deps, denom: String) -> StdResult<Option<String>> {
     let querier = TokenfactoryQuerier::new(&deps.querier);
 
@@ -40,3 +123,10 @@ pub fn get_denom_authority(deps: Deps, denom: String) -> StdResult<Option<String
         None => Ok(None),
     }
 }
+
+fn to_osmosis_timestamp(timestamp: Timestamp) -> OsmosisTimestamp {
+    OsmosisTimestamp {
+        seconds: timestamp.seconds() as i64,
+        nanos: timestamp.subsec_nanos() as i32,
+    }
+}
@@ -26,4 +26,67 @@ fn test_instantiation_and_queries() {
         )
         .unwrap();
     assert_eq!(index, Decimal::from_str("3000.0").unwrap());
+
+    let index: Decimal = wasm
+        .query(
+            &contract_address,
+            &QueryMsg::GetGeometricTwapToNow {
+                pool_id: 1,
+                base_asset: env.denoms["base"].clone(),
+                quote_asset: env.denoms["quote"].clone(),
+                start_time: cw_now,
+            },
+        )
+        .unwrap();
+    // geometric mean is calculated through logarithms on chain
+    assert!(
+        index.abs_diff(Decimal::from_str("3000.0").unwrap()) < Decimal::from_str("0.001").unwrap()
+    );
+
+    let spot_price: Decimal = wasm
+        .query(
+            &contract_address,
+            &QueryMsg::GetSpotPrice {
+                pool_id: 1,
+                base_asset: env.denoms["base"].clone(),
+                quote_asset: env.denoms["quote"].clone(),
+            },
+        )
+        .unwrap();
+    assert!(
+        spot_price.abs_diff(Decimal::from_str("3000.0").unwrap())
+            < Decimal::from_str("0.001").unwrap()
+    );
+
+    env.app.increase_time(10u64);
+
+    let index: Decimal = wasm
+        .query(
+            &contract_address,
+            &QueryMsg::GetArithmeticTwap {
+                pool_id: 1,
+                base_asset: env.denoms["base"].clone(),
+                quote_asset: env.denoms["quote"].clone(),
+                start_time: cw_now,
+                end_time: cw_now.plus_seconds(5u64),
+            },
+        )
+        .unwrap();
+    assert_eq!(index, Decimal::from_str("3000.0").unwrap());
+
+    let index: Decimal = wasm
+        .query(
+            &contract_address,
+            &QueryMsg::GetGeometricTwap {
+                pool_id: 1,
+                base_asset: env.denoms["base"].clone(),
+                quote_asset: env.denoms["quote"].clone(),
+                start_time: cw_now,
+                end_time: cw_now.plus_seconds(5u64),
+            },
+        )
+        .unwrap();
+    assert!(
+        index.abs_diff(Decimal::from_str("3000.0").unwrap()) < Decimal::from_str("0.001").unwrap()
+    );
 }
@@ -1,17 +1,25 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::{
-    entry_point, to_binary, Addr, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Response,
-    StdResult,
+    entry_point, to_binary, Addr, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Order,
+    Response, StdError, StdResult, Timestamp,
 };
-use cw_storage_plus::Map;
-use margined_protocol::query::QueryMsg;
+use cw_storage_plus::{Bound, Map};
+use injective_math::FPDecimal;
+use margined_protocol::query::{PriceFeedQueryMsg, PriceFeedResponse, QueryMsg};
+use num::pow::Pow;
 use osmosis_std::types::osmosis::tokenfactory::v1beta1::{
     DenomAuthorityMetadata, TokenfactoryQuerier,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 pub const KEY_PRICES: Map<u64, Decimal> = Map::new("prices");
+pub const SPOT_PRICES: Map<u64, Decimal> = Map::new("spot_prices");
+pub const FEED_PRICES: Map<(&str, &str), (Decimal, Timestamp)> = Map::new("feed_prices");
+
+// price observations keyed by pool id, "base/quote" pair and time in seconds
+pub const OBSERVATIONS: Map<(u64, &str, u64), Decimal> = Map::new("observations");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct InstantiateMsg {}
@@ -19,7 +27,46 @@ pub struct InstantiateMsg {}
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    AppendPrice { pool_id: u64, price: Decimal },
+    AppendPrice {
+        pool_id: u64,
+        price: Decimal,
+    },
+    RecordPrice {
+        pool_id: u64,
+        base_asset: String,
+        quote_asset: String,
+        price: Decimal,
+    },
+    SetPricePath {
+        pool_id: u64,
+        base_asset: String,
+        quote_asset: String,
+        path: Vec<PricePoint>,
+    },
+    SetSpotPrice {
+        pool_id: u64,
+        price: Decimal,
+    },
+    SetFeedPrice {
+        base_asset: String,
+        quote_asset: String,
+        price: Decimal,
+    },
+}
+
+/// price observed a delay in seconds after the path is set
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PricePoint {
+    pub delay: u64,
+    pub price: Decimal,
+}
+
+/// the mock also serves as a price feed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum MockQueryMsg {
+    Query(QueryMsg),
+    PriceFeed(PriceFeedQueryMsg),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -27,6 +74,12 @@ pub struct ConfigResponse {
     pub owner: Addr,
 }
 
+#[derive(Clone, Copy)]
+enum Average {
+    Arithmetic,
+    Geometric,
+}
+
 #[cfg(not(tarpaulin_include))]
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -40,20 +93,43 @@ pub fn instantiate(
 
 #[cfg(not(tarpaulin_include))]
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn execute(
-    deps: DepsMut,
-    _env: Env,
-    info: MessageInfo,
-    msg: ExecuteMsg,
-) -> StdResult<Response> {
+pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
     match msg {
         ExecuteMsg::AppendPrice { pool_id, price } => append_price(deps, info, pool_id, price),
+        ExecuteMsg::RecordPrice {
+            pool_id,
+            base_asset,
+            quote_asset,
+            price,
+        } => set_price_path(
+            deps,
+            env,
+            pool_id,
+            base_asset,
+            quote_asset,
+            vec![PricePoint { delay: 0, price }],
+        ),
+        ExecuteMsg::SetPricePath {
+            pool_id,
+            base_asset,
+            quote_asset,
+            path,
+        } => set_price_path(deps, env, pool_id, base_asset, quote_asset, path),
+        ExecuteMsg::SetSpotPrice { pool_id, price } => set_spot_price(deps, pool_id, price),
+        ExecuteMsg::SetFeedPrice {
+            base_asset,
+            quote_asset,
+            price,
+        } => set_feed_price(deps, env, base_asset, quote_asset, price),
     }
 }
 
 /// this is a mock function that enables storage of data
 /// by the contract owner will be replaced by integration
 /// with on-chain price oracles in the future.
+///
+/// the price is constant over time and in both directions,
+/// it is used for pairs without any recorded observations.
 #[cfg(not(tarpaulin_include))]
 pub fn append_price(
     deps: DepsMut,
@@ -66,21 +142,294 @@ pub fn append_price(
     Ok(Response::default())
 }
 
+/// records observations of the pair at the given delays from now,
+/// each price holds until the next observation like a pool price
+#[cfg(not(tarpaulin_include))]
+pub fn set_price_path(
+    deps: DepsMut,
+    env: Env,
+    pool_id: u64,
+    base_asset: String,
+    quote_asset: String,
+    path: Vec<PricePoint>,
+) -> StdResult<Response> {
+    let pair = pair_key(&base_asset, &quote_asset);
+
+    for point in path {
+        if point.price.is_zero() {
+            return Err(StdError::generic_err("Price cannot be zero"));
+        }
+
+        let time = env.block.time.plus_seconds(point.delay).seconds();
+
+        OBSERVATIONS.save(deps.storage, (pool_id, &pair, time), &point.price)?;
+    }
+
+    Ok(Response::default())
+}
+
+/// spot prices follow the recorded prices unless set
+#[cfg(not(tarpaulin_include))]
+pub fn set_spot_price(deps: DepsMut, pool_id: u64, price: Decimal) -> StdResult<Response> {
+    SPOT_PRICES.save(deps.storage, pool_id, &price)?;
+
+    Ok(Response::default())
+}
+
+#[cfg(not(tarpaulin_include))]
+pub fn set_feed_price(
+    deps: DepsMut,
+    env: Env,
+    base_asset: String,
+    quote_asset: String,
+    price: Decimal,
+) -> StdResult<Response> {
+    FEED_PRICES.save(
+        deps.storage,
+        (&base_asset, &quote_asset),
+        &(price, env.block.time),
+    )?;
+
+    Ok(Response::default())
+}
+
 #[cfg(not(tarpaulin_include))]
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: MockQueryMsg) -> StdResult<Binary> {
     match msg {
-        QueryMsg::GetArithmeticTwapToNow { pool_id, .. } => {
-            to_binary(&get_arithmetic_twap_now(deps, pool_id)?)
+        MockQueryMsg::Query(QueryMsg::GetArithmeticTwapToNow {
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+        }) => to_binary(&get_twap(
+            deps,
+            &env,
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+            env.block.time,
+            Average::Arithmetic,
+        )?),
+        MockQueryMsg::Query(QueryMsg::GetArithmeticTwap {
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+            end_time,
+        }) => to_binary(&get_twap(
+            deps,
+            &env,
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+            end_time,
+            Average::Arithmetic,
+        )?),
+        MockQueryMsg::Query(QueryMsg::GetGeometricTwapToNow {
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+        }) => to_binary(&get_twap(
+            deps,
+            &env,
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+            env.block.time,
+            Average::Geometric,
+        )?),
+        MockQueryMsg::Query(QueryMsg::GetGeometricTwap {
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+            end_time,
+        }) => to_binary(&get_twap(
+            deps,
+            &env,
+            pool_id,
+            base_asset,
+            quote_asset,
+            start_time,
+            end_time,
+            Average::Geometric,
+        )?),
+        MockQueryMsg::Query(QueryMsg::GetSpotPrice {
+            pool_id,
+            base_asset,
+            quote_asset,
+        }) => to_binary(&get_spot_price(
+            deps,
+            &env,
+            pool_id,
+            base_asset,
+            quote_asset,
+        )?),
+        MockQueryMsg::Query(QueryMsg::GetDenomAuthority { denom }) => {
+            to_binary(&get_denom_authority(deps, denom)?)
         }
-        QueryMsg::GetDenomAuthority { denom } => to_binary(&get_denom_authority(deps, denom)?),
+        MockQueryMsg::PriceFeed(PriceFeedQueryMsg::GetPrice {
+            base_asset,
+            quote_asset,
+        }) => to_binary(&get_feed_price(deps, base_asset, quote_asset)?),
     }
 }
 
-/// Queries latest price for pair stored with key
+/// Queries the time weighted average price of the pair over the window,
+/// falling back to the appended price of the pool if none are recorded
 #[cfg(not(tarpaulin_include))]
-pub fn get_arithmetic_twap_now(deps: Deps, pool_id: u64) -> StdResult<Decimal> {
-    KEY_PRICES.load(deps.storage, pool_id)
+#[allow(clippy::too_many_arguments)]
+fn get_twap(
+    deps: Deps,
+    env: &Env,
+    pool_id: u64,
+    base_asset: String,
+    quote_asset: String,
+    start_time: Timestamp,
+    end_time: Timestamp,
+    average: Average,
+) -> StdResult<Decimal> {
+    if start_time > end_time {
+        return Err(StdError::generic_err("Start time is after the end time"));
+    }
+
+    if end_time > env.block.time {
+        return Err(StdError::generic_err("End time is in the future"));
+    }
+
+    let observations = match get_observations(deps, pool_id, &base_asset, &quote_asset, end_time)? {
+        Some(observations) => observations,
+        None => return KEY_PRICES.load(deps.storage, pool_id),
+    };
+
+    let (start, end) = (start_time.seconds(), end_time.seconds());
+
+    if start < observations[0].0 {
+        return Err(StdError::generic_err(
+            "Start time precedes the first price observation",
+        ));
+    }
+
+    // each observation holds from its time until the next one
+    let mut segments: Vec<(Decimal, u64)> = vec![];
+    for (index, (time, price)) in observations.iter().enumerate() {
+        let segment_end = observations
+            .get(index + 1)
+            .map_or(end, |(next_time, _)| *next_time)
+            .min(end);
+        let segment_start = (*time).max(start);
+
+        if segment_end > segment_start {
+            segments.push((*price, segment_end - segment_start));
+        }
+    }
+
+    // an empty window returns the price at the start time
+    if segments.is_empty() {
+        let (_, price) = observations
+            .iter()
+            .rev()
+            .find(|(time, _)| *time <= start)
+            .unwrap();
+
+        return Ok(*price);
+    }
+
+    let duration = end - start;
+
+    let twap = match average {
+        Average::Arithmetic => segments
+            .iter()
+            .fold(Decimal::zero(), |twap, (price, seconds)| {
+                twap + *price * Decimal::from_ratio(*seconds, duration)
+            }),
+        Average::Geometric => segments
+            .iter()
+            .fold(Decimal::one(), |twap, (price, seconds)| {
+                twap * pow(*price, Decimal::from_ratio(*seconds, duration))
+            }),
+    };
+
+    Ok(twap)
+}
+
+/// Queries the price of the pair at the current time,
+/// unless a spot price is set for the pool
+#[cfg(not(tarpaulin_include))]
+pub fn get_spot_price(
+    deps: Deps,
+    env: &Env,
+    pool_id: u64,
+    base_asset: String,
+    quote_asset: String,
+) -> StdResult<Decimal> {
+    if let Some(price) = SPOT_PRICES.may_load(deps.storage, pool_id)? {
+        return Ok(price);
+    }
+
+    match get_observations(deps, pool_id, &base_asset, &quote_asset, env.block.time)? {
+        Some(observations) => Ok(observations.last().unwrap().1),
+        None => KEY_PRICES.load(deps.storage, pool_id),
+    }
+}
+
+/// Returns the observations of the pair up to the end time in ascending
+/// order, inverting the observations recorded in the opposite direction
+fn get_observations(
+    deps: Deps,
+    pool_id: u64,
+    base_asset: &str,
+    quote_asset: &str,
+    end_time: Timestamp,
+) -> StdResult<Option<Vec<(u64, Decimal)>>> {
+    let load = |pair: String| {
+        OBSERVATIONS
+            .prefix((pool_id, pair.as_str()))
+            .range(
+                deps.storage,
+                None,
+                Some(Bound::inclusive(end_time.seconds())),
+                Order::Ascending,
+            )
+            .collect::<StdResult<Vec<(u64, Decimal)>>>()
+    };
+
+    let observations = load(pair_key(base_asset, quote_asset))?;
+    if !observations.is_empty() {
+        return Ok(Some(observations));
+    }
+
+    let inverse = load(pair_key(quote_asset, base_asset))?;
+    if !inverse.is_empty() {
+        return Ok(Some(
+            inverse
+                .into_iter()
+                .map(|(time, price)| (time, Decimal::one() / price))
+                .collect(),
+        ));
+    }
+
+    Ok(None)
+}
+
+/// Queries the price set for the pair with the time it was set
+#[cfg(not(tarpaulin_include))]
+pub fn get_feed_price(
+    deps: Deps,
+    base_asset: String,
+    quote_asset: String,
+) -> StdResult<PriceFeedResponse> {
+    let (price, last_updated) = FEED_PRICES.load(deps.storage, (&base_asset, &quote_asset))?;
+
+    Ok(PriceFeedResponse {
+        price,
+        last_updated,
+    })
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -97,3 +446,14 @@ pub fn get_denom_authority(deps: Deps, denom: String) -> StdResult<Option<String
         None => Ok(None),
     }
 }
+
+fn pair_key(base_asset: &str, quote_asset: &str) -> String {
+    format!("{base_asset}/{quote_asset}")
+}
+
+fn pow(base: Decimal, exponent: Decimal) -> Decimal {
+    let fp_base = FPDecimal::from_str(&base.to_string()).unwrap();
+    let fp_exponent = FPDecimal::from_str(&exponent.to_string()).unwrap();
+
+    Decimal::from_str(&fp_base.pow(fp_exponent).to_string()).unwrap()
+}
@@ -13,9 +13,15 @@ pub enum ContractError {
     #[error("Vault is below minimum collateral amount (0.5 base denom)")]
     BelowMinCollateralAmount {},
 
+    #[error("Circuit breaker tripped: {0}")]
+    CircuitBreaker(String),
+
     #[error("Strategy denom not initialised")]
     DenomNotInitialized {},
 
+    #[error("Transaction deadline has passed")]
+    DeadlineExceeded {},
+
     #[error("Zero Division Error")]
     DivideByZero {},
 
@@ -25,18 +31,36 @@ pub enum ContractError {
     #[error("Event '{0}' not found")]
     EventNotFound(String),
 
+    #[error("Hedge not allowed, neither price nor time threshold has been reached")]
+    HedgeNotAllowed {},
+
+    #[error("Slippage exceeded, input {0} is more than maximum {1}")]
+    ExcessiveInput(Uint128, Uint128),
+
+    #[error("Flash mint already in progress")]
+    FlashMintInProgress {},
+
+    #[error("Flash mint not repaid, {0} power outstanding")]
+    FlashMintNotRepaid(Uint128),
+
     #[error("Invalid funds")]
     InvalidFunds {},
 
     #[error("Invalid liquidation")]
     InvalidLiquidation {},
 
+    #[error("Invalid swap route: {0}")]
+    InvalidSwapRoute(String),
+
     #[error("Invalid denom {0} not found")]
     InvalidDenom(String),
 
     #[error("Contract is already open")]
     IsOpen {},
 
+    #[error("Cannot perform action as contract is shutdown")]
+    IsShutdown {},
+
     #[error("Invalid duration cannot be greater than {0}")]
     InvalidDuration(u64),
 
@@ -49,6 +73,9 @@ pub enum ContractError {
     #[error("Insufficient balance")]
     InsufficientBalance {},
 
+    #[error("Slippage exceeded, output {0} is less than minimum {1}")]
+    InsufficientOutput(Uint128, Uint128),
+
     #[error("Insufficient denom {0}. {1} required")]
     InsufficientPower(String, Uint128),
 
@@ -70,6 +97,9 @@ pub enum ContractError {
     #[error("Contract is not paused")]
     NotPaused {},
 
+    #[error("Cannot perform action as contract is not shutdown")]
+    NotShutdown {},
+
     #[error("Contract is not admin of the power token")]
     NotTokenAdmin {},
 
@@ -103,6 +133,9 @@ pub enum ContractError {
     #[error("Vault does not exist, cannot perform operation")]
     VaultDoesNotExist {},
 
+    #[error("Vault does not belong to market {0}")]
+    VaultMarketMismatch(u64),
+
     #[error("Zero mint not supported")]
     ZeroMint {},
 
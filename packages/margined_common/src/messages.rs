@@ -32,6 +32,7 @@ pub fn create_swap_exact_amount_in_message(
     token_in_denom: String,
     token_out_denom: String,
     amount: String,
+    token_out_min_amount: String,
 ) -> MsgSwapExactAmountIn {
     MsgSwapExactAmountIn {
         sender,
@@ -43,7 +44,7 @@ pub fn create_swap_exact_amount_in_message(
             denom: token_in_denom,
             amount,
         }),
-        token_out_min_amount: "1".to_string(),
+        token_out_min_amount,
     }
 }
 
@@ -1,4 +1,4 @@
-use crate::power::Pool;
+use crate::power::{Pool, VaultResponse};
 use cosmwasm_schema::{cw_serde, QueryResponses};
 use cosmwasm_std::{Addr, Decimal, Timestamp, Uint128};
 
@@ -23,18 +23,29 @@ pub struct InstantiateMsg {
 pub enum ExecuteMsg {
     ClaimOwnership {},
     Deposit {},
-    FlashDeposit {},
-    FlashWithdraw {},
+    FlashDeposit {
+        min_base_out: Option<Uint128>, // minimum base received for the power sold
+    },
+    FlashWithdraw {
+        max_base_in: Option<Uint128>, // maximum base paid to buy back the power burnt
+    },
     HedgeOTC {},
     Pause {},
-    ProposeNewOwner { new_owner: String, duration: u64 },
+    ProposeNewOwner {
+        new_owner: String,
+        duration: u64,
+    },
     RedeemShortShutdown {},
     RejectOwner {},
     SetOpen {},
-    TransferVault {},
+    TransferVault {
+        recipient: String,
+    },
     Withdraw {},
     WithdrawShutdown {},
-    UpdateConfig { new_config: UpdateConfig },
+    UpdateConfig {
+        new_config: UpdateConfig,
+    },
     UnPause {},
 }
 
@@ -45,20 +56,16 @@ pub enum QueryMsg {
     Config {},
     #[returns(StateResponse)]
     State {},
-    #[returns(StubResponse)]
+    #[returns(bool)]
     CheckPriceHedge {},
-    #[returns(StubResponse)]
+    #[returns(bool)]
     CheckTimeHedge {},
-    #[returns(StubResponse)]
-    DomainSeparator {},
-    #[returns(OwnerProposalResponse)]
+    #[returns(VaultResponse)]
     GetVaultDetails {},
-    #[returns(StubResponse)]
+    #[returns(OwnerProposalResponse)]
     GetOwnershipProposal {},
-    #[returns(StubResponse)]
-    GetWsqueethFromCrabAmount {},
-    #[returns(StubResponse)]
-    Nonce {},
+    #[returns(Uint128)]
+    GetPowerFromCrabAmount { crab_amount: Uint128 },
     #[returns(Addr)]
     Owner {},
 }
@@ -78,30 +85,24 @@ pub struct ConfigResponse {
     pub base_decimals: u32,
     pub power_decimals: u32,
     pub fee_rate: Decimal,
-    pub hedge_price_threshold: Uint128,
+    pub hedge_price_threshold: Decimal,
     pub hedge_time_threshold: u64,
     pub hedging_twap_period: u64,
     pub strategy_cap: Uint128,
     pub strategy_denom: String,
+    pub hedger: Addr,
 }
 
 #[derive(Default)]
 #[cw_serde]
 pub struct UpdateConfig {
-    pub power_contract: Option<Addr>,
-    pub query_contract: Option<Addr>,
-    pub fee_pool_contract: Option<Addr>,
-    pub power_denom: Option<String>,
-    pub base_denom: Option<String>,
-    pub base_pool: Option<Pool>,
-    pub power_pool: Option<Pool>,
-    pub base_decimals: Option<u32>,
-    pub power_decimals: Option<u32>,
+    pub fee_pool_contract: Option<String>,
     pub fee_rate: Option<Decimal>,
-    pub hedge_price_threshold: Option<Uint128>,
+    pub hedge_price_threshold: Option<Decimal>,
     pub hedge_time_threshold: Option<u64>,
     pub hedging_twap_period: Option<u64>,
     pub strategy_cap: Option<Uint128>,
+    pub hedger: Option<String>,
 }
 
 #[cw_serde]
@@ -112,17 +113,7 @@ pub struct StateResponse {
     pub time_at_last_hedge: Timestamp,
     pub price_at_last_hedge: Decimal,
     pub strategy_vault_id: u64,
-}
-
-#[derive(Default)]
-#[cw_serde]
-pub struct UpdateStateResponse {
-    pub is_open: Option<bool>,
-    pub is_paused: Option<bool>,
-    pub last_pause: Option<Timestamp>,
-    pub time_at_last_hedge: Option<Decimal>,
-    pub price_at_last_hedge: Option<u64>,
-    pub strategy_vault_id: Option<u64>,
+    pub has_redeemed_in_shutdown: bool,
 }
 
 #[cw_serde]
@@ -130,6 +121,3 @@ pub struct OwnerProposalResponse {
     pub owner: Addr,
     pub expiry: u64,
 }
-
-#[cw_serde]
-pub struct StubResponse {}
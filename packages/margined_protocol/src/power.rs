@@ -1,5 +1,9 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Decimal, Timestamp, Uint128};
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Empty, Timestamp, Uint128};
+use cw721::{
+    Approval, ContractInfoResponse, Expiration, NftInfoResponse, NumTokensResponse,
+    OwnerOfResponse, TokensResponse,
+};
 
 pub const FUNDING_PERIOD: u64 = 420 * 60 * 60; // 420 hours
 
@@ -17,13 +21,35 @@ pub struct InstantiateMsg {
     pub power_decimals: u32,     // decimals of the power perp token
 }
 
+#[cw_serde]
+pub struct NewMarket {
+    pub fee_rate: String,        // rate of fees charge, must be less than 1
+    pub fee_pool: String,        // address of fee pool contract
+    pub query_contract: String,  // query contract that wraps native querier
+    pub base_denom: String,      // denom of the underlying token, e.g. osmo
+    pub power_denom: String,     // denom of the power token, e.g. osmo^2
+    pub base_pool_id: u64,       // id of the pool of the underlying to quote, e.g. osmo:usdc
+    pub base_pool_quote: String, // denom of the base pool quote asset, e.g. usdc
+    pub power_pool_id: u64,      // id of the pool of the underlying to power, e.g. osmo:osmo^2
+    pub base_decimals: u32,      // decimals of the underlying token
+    pub power_decimals: u32,     // decimals of the power perp token
+}
+
+// market_id selects the market of market scoped messages, defaulting to the
+// market created on instantiation, vault scoped messages use the market of the vault
 #[cw_serde]
 pub enum ExecuteMsg {
-    SetOpen {},
+    SetOpen {
+        market_id: Option<u64>,
+    },
+    AddMarket {
+        new_market: NewMarket,
+    },
     MintPowerPerp {
         amount: Uint128,
         vault_id: Option<u64>,
         rebase: bool,
+        market_id: Option<u64>,
     },
     BurnPowerPerp {
         amount_to_withdraw: Option<Uint128>,
@@ -32,11 +58,44 @@ pub enum ExecuteMsg {
     OpenShort {
         amount: Uint128,
         vault_id: Option<u64>,
+        min_base_out: Option<Uint128>, // minimum received from selling the minted power
+        deadline: Option<Timestamp>,
+        market_id: Option<u64>,
+        // up to three hops starting with power, the proceeds and `min_base_out` are in the
+        // final denom, defaults to selling power for base in the power pool
+        route: Option<Vec<SwapHop>>,
     },
     CloseShort {
         amount_to_burn: Uint128,
         amount_to_withdraw: Option<Uint128>,
         vault_id: u64,
+        max_base_in: Option<Uint128>, // maximum spent buying back power, defaults to funds sent
+        deadline: Option<Timestamp>,
+        // up to three hops ending with power, paid and refunded in the first denom,
+        // defaults to buying power with base in the power pool
+        route: Option<Vec<SwapHop>>,
+    },
+    // swaps the base sent for power in the power pool
+    OpenLong {
+        min_power_out: Option<Uint128>,
+        deadline: Option<Timestamp>,
+        market_id: Option<u64>,
+    },
+    // mints power against the collateral amount of the base sent and provides it with the
+    // rest of the base as a full range position in the power pool held for the sender
+    MintAndProvideLiquidity {
+        amount: Uint128,
+        collateral_amount: Uint128,
+        vault_id: Option<u64>,
+        min_power_provided: Option<Uint128>, // minimum power taken by the position
+        min_base_provided: Option<Uint128>,  // minimum base taken by the position
+        deadline: Option<Timestamp>,
+        market_id: Option<u64>,
+    },
+    // withdraws a position provided with `MintAndProvideLiquidity`, sending the tokens and any
+    // rewards collected to its owner
+    RemoveLiquidity {
+        position_id: u64,
     },
     Deposit {
         vault_id: u64,
@@ -45,17 +104,117 @@ pub enum ExecuteMsg {
         amount: Uint128,
         vault_id: u64,
     },
+    WithdrawCollateral {
+        denom: String, // whitelisted collateral denom other than the base denom
+        amount: Uint128,
+        vault_id: u64,
+    },
+    // mints power to the callback contract and calls it, when it returns the power must
+    // have been sent back to be burnt or be covered by the debt of the vault, the fee is
+    // paid in base with the funds sent and any excess refunded
+    FlashMint {
+        amount: Uint128,
+        callback: FlashMintCallback,
+        vault_id: Option<u64>,
+        market_id: Option<u64>,
+    },
+    // applies the changes to the base collateral and debt of the vault, checking
+    // its health once they are all applied, increases are paid in the funds sent
+    AdjustVault {
+        vault_id: u64,
+        collateral_delta: Option<VaultDelta>,
+        debt_delta: Option<VaultDelta>,
+    },
     Liquidate {
         max_debt_amount: Uint128,
         vault_id: u64,
     },
-    ApplyFunding {},
+    // records the time an unsafe vault's liquidation auction starts, callable by anyone
+    StartLiquidationAuction {
+        vault_id: u64,
+    },
+    FundInsurance {
+        market_id: Option<u64>,
+    },
+    TransferVault {
+        vault_id: u64,
+        recipient: String,
+    },
+    ApproveOperator {
+        vault_id: u64,
+        spender: String,
+        expires: Option<Expiration>,
+    },
+    RevokeOperator {
+        vault_id: u64,
+        spender: String,
+    },
+    // cw721 interface for the vault NFTs, token ids are the vault ids
+    TransferNft {
+        recipient: String,
+        token_id: String,
+    },
+    SendNft {
+        contract: String,
+        token_id: String,
+        msg: Binary,
+    },
+    Approve {
+        spender: String,
+        token_id: String,
+        expires: Option<Expiration>,
+    },
+    Revoke {
+        spender: String,
+        token_id: String,
+    },
+    ApproveAll {
+        operator: String,
+        expires: Option<Expiration>,
+    },
+    RevokeAll {
+        operator: String,
+    },
+    ApplyFunding {
+        market_id: Option<u64>,
+    },
     UpdateConfig {
-        fee_rate: Option<String>,
-        fee_pool: Option<String>,
+        market_id: Option<u64>,
+        new_config: UpdateConfig,
+    },
+    // whitelists a collateral denom for the vaults of the market, or updates it
+    SetCollateralAsset {
+        market_id: Option<u64>,
+        asset: CollateralAsset,
+    },
+    // stops new deposits of a collateral denom, vaults holding it keep its pricing
+    DelistCollateralAsset {
+        market_id: Option<u64>,
+        denom: String,
+    },
+    Pause {
+        market_id: Option<u64>,
+    },
+    // migrates the vaults of a single market contract, called until all are migrated
+    MigrateVaults {
+        limit: Option<u32>,
+    },
+    // pauses the market when the recent twap of a pool deviates from its twap, callable by anyone
+    TripCircuitBreaker {
+        market_id: Option<u64>,
+    },
+    UnPause {
+        market_id: Option<u64>,
+    },
+    Shutdown {
+        market_id: Option<u64>,
+    },
+    RedeemShutdown {
+        market_id: Option<u64>,
+    },
+    WithdrawShutdown {
+        vault_id: u64,
     },
-    Pause {},
-    UnPause {},
     ProposeNewOwner {
         new_owner: String,
         duration: u64,
@@ -68,21 +227,26 @@ pub enum ExecuteMsg {
 #[derive(QueryResponses)]
 pub enum QueryMsg {
     #[returns(ConfigResponse)]
-    Config {},
+    Config { market_id: Option<u64> },
     #[returns(StateResponse)]
-    State {},
+    State { market_id: Option<u64> },
+    #[returns(MarketsResponse)]
+    GetMarkets {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
     #[returns(Addr)]
     Owner {},
     #[returns(Decimal)]
-    GetNormalisationFactor {},
+    GetNormalisationFactor { market_id: Option<u64> },
     #[returns(Decimal)]
-    GetIndex { period: u64 },
+    GetIndex { period: u64, market_id: Option<u64> },
     #[returns(Decimal)]
-    GetUnscaledIndex { period: u64 },
+    GetUnscaledIndex { period: u64, market_id: Option<u64> },
     #[returns(Decimal)]
-    GetDenormalisedMark { period: u64 },
+    GetDenormalisedMark { period: u64, market_id: Option<u64> },
     #[returns(Decimal)]
-    GetDenormalisedMarkFunding { period: u64 },
+    GetDenormalisedMarkFunding { period: u64, market_id: Option<u64> },
     #[returns(VaultResponse)]
     GetVault { vault_id: u64 },
     #[returns(UserVaultsResponse)]
@@ -91,12 +255,81 @@ pub enum QueryMsg {
         start_after: Option<u64>,
         limit: Option<u32>,
     },
+    #[returns(UserLiquidityPositionsResponse)]
+    GetUserLiquidityPositions {
+        user: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(VaultsResponse)]
+    ListVaults {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    // checks a page of vaults, continue after the last scanned vault
+    #[returns(UnsafeVaultsResponse)]
+    ListUnsafeVaults {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(VaultOperatorsResponse)]
+    GetVaultOperators { vault_id: u64 },
     #[returns(u64)]
     GetNextVaultId {},
     #[returns(OwnerProposalResponse)]
     GetOwnershipProposal {},
     #[returns(bool)]
     CheckVault { vault_id: u64 },
+    #[returns(LiquidationAuctionResponse)]
+    GetLiquidationAuction { vault_id: u64 },
+    #[returns(VaultHealthResponse)]
+    GetVaultHealth { vault_id: u64 },
+    #[returns(VaultCollateralResponse)]
+    GetVaultCollateral { vault_id: u64 },
+    #[returns(CollateralAssetsResponse)]
+    GetCollateralAssets { market_id: Option<u64> },
+    // fee charged to the user for minting the amount of power
+    #[returns(FeePreviewResponse)]
+    GetFeePreview {
+        user: String,
+        amount: Uint128,
+        market_id: Option<u64>,
+    },
+    #[returns(Uint128)]
+    GetInsuranceBalance { market_id: Option<u64> },
+    #[returns(Uint128)]
+    GetTotalBadDebt { market_id: Option<u64> },
+    #[returns(FundingHistoryResponse)]
+    GetFundingHistory {
+        market_id: Option<u64>,
+        start_after: Option<u64>, // timestamp in seconds of the last snapshot of the previous page
+        limit: Option<u32>,
+    },
+    #[returns(FundingRateResponse)]
+    GetAnnualisedFundingRate { period: u64, market_id: Option<u64> },
+    // cw721 interface for the vault NFTs, token ids are the vault ids
+    #[returns(OwnerOfResponse)]
+    OwnerOf {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    #[returns(NftInfoResponse<Empty>)]
+    NftInfo { token_id: String },
+    #[returns(TokensResponse)]
+    Tokens {
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(TokensResponse)]
+    AllTokens {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(NumTokensResponse)]
+    NumTokens {},
+    #[returns(ContractInfoResponse)]
+    ContractInfo {},
 }
 
 #[cw_serde]
@@ -104,16 +337,89 @@ pub struct MigrateMsg {}
 
 #[cw_serde]
 pub struct VaultResponse {
+    pub market_id: u64,
     pub operator: Addr,
     pub collateral: Uint128,
     pub short_amount: Uint128,
 }
 
+#[cw_serde]
+pub struct VaultCollateralResponse {
+    pub collateral: Vec<Coin>, // collateral of the vault by denom, including the base denom
+    pub collateral_value: Uint128, // value in base after haircuts, as used for the vault status
+}
+
+#[cw_serde]
+pub struct CollateralAssetsResponse {
+    pub assets: Vec<CollateralAsset>,
+    pub delisted: Vec<String>,
+}
+
+#[cw_serde]
+pub struct FeePreviewResponse {
+    pub mint_value: Uint128, // value in base of the power minted
+    pub staked_amount: Uint128,
+    pub fee_rate: Decimal, // rate after the size tier and staking discount
+    pub fee_amount: Uint128,
+}
+
+#[cw_serde]
+pub struct LiquidationAuctionResponse {
+    pub is_safe: bool,
+    pub start_time: Option<Timestamp>, // time the vault was first recorded as unsafe
+    pub liquidation_bonus: Decimal,    // current multiplier applied to the debt value
+    pub auction_price: Decimal,        // base paid per power token repaid, including the bonus
+}
+
+#[cw_serde]
+pub struct VaultHealthResponse {
+    pub is_safe: bool,
+    pub above_min_collateral: bool,
+    pub collateral_ratio: Option<Decimal>, // collateral over the debt value, none without debt
+    pub debt_value_in_base: Decimal,
+    pub debt_value_in_quote: Decimal,
+    pub liquidation_price: Option<Decimal>, // base price in quote at which the vault is unsafe
+    pub max_mintable: Uint128,              // additional power mintable without depositing
+    pub max_withdrawable: Uint128,          // collateral withdrawable without burning
+}
+
+#[cw_serde]
+pub struct VaultOperatorsResponse {
+    pub operators: Vec<Approval>,
+}
+
 #[cw_serde]
 pub struct UserVaultsResponse {
     pub vaults: Vec<u64>,
 }
 
+#[cw_serde]
+pub struct LiquidityPosition {
+    pub position_id: u64, // id of the concentrated liquidity position in the power pool
+    pub market_id: u64,
+}
+
+#[cw_serde]
+pub struct UserLiquidityPositionsResponse {
+    pub positions: Vec<LiquidityPosition>,
+}
+
+#[cw_serde]
+pub struct VaultsResponse {
+    pub vaults: Vec<u64>,
+}
+
+#[cw_serde]
+pub struct UnsafeVaultsResponse {
+    pub vaults: Vec<u64>,
+    pub last_scanned: Option<u64>, // none once there are no vaults left to scan
+}
+
+#[cw_serde]
+pub struct MarketsResponse {
+    pub markets: Vec<u64>,
+}
+
 #[cw_serde]
 pub struct ConfigResponse {
     pub query_contract: Addr,
@@ -126,6 +432,49 @@ pub struct ConfigResponse {
     pub funding_period: u64,
     pub base_decimals: u32,
     pub power_decimals: u32,
+    pub min_liquidation_bonus: Decimal,
+    pub max_liquidation_bonus: Decimal,
+    pub liquidation_auction_duration: u64,
+    pub insurance_fee_share: Decimal,
+    pub collateral_ratio_numerator: Decimal,
+    pub collateral_ratio_denominator: Decimal,
+    pub min_collateral: Decimal,
+    pub twap_period: u64,
+    pub max_twap_period: u64,
+    pub funding_lower_bound: Decimal,
+    pub funding_upper_bound: Decimal,
+    pub oracle: Oracle,
+    pub max_spot_deviation: Decimal,
+    pub power_price_type: PriceType,
+    pub flash_mint_fee_rate: Decimal,
+    pub fee_schedule: FeeSchedule,
+    pub fee_recipients: Vec<FeeRecipient>,
+}
+
+#[derive(Default)]
+#[cw_serde]
+pub struct UpdateConfig {
+    pub fee_rate: Option<String>,
+    pub fee_pool: Option<String>,
+    pub min_liquidation_bonus: Option<Decimal>,
+    pub max_liquidation_bonus: Option<Decimal>,
+    pub liquidation_auction_duration: Option<u64>,
+    pub insurance_fee_share: Option<Decimal>,
+    pub collateral_ratio_numerator: Option<Decimal>,
+    pub collateral_ratio_denominator: Option<Decimal>,
+    pub min_collateral: Option<Decimal>,
+    pub twap_period: Option<u64>,
+    pub max_twap_period: Option<u64>,
+    pub funding_lower_bound: Option<Decimal>,
+    pub funding_upper_bound: Option<Decimal>,
+    pub oracle: Option<Oracle>,
+    // pauses the market when a spot price deviates more from its TWAP, zero disables
+    pub max_spot_deviation: Option<Decimal>,
+    pub power_price_type: Option<PriceType>, // price of the power pool for the mark and debt
+    pub flash_mint_fee_rate: Option<Decimal>, // charged on the value of the power flash minted
+    pub fee_schedule: Option<FeeSchedule>,
+    // recipients of the mint fees after the insurance share, empty sends them to the fee pool
+    pub fee_recipients: Option<Vec<FeeRecipient>>,
 }
 
 #[cw_serde]
@@ -135,6 +484,31 @@ pub struct StateResponse {
     pub last_pause: Timestamp,
     pub normalisation_factor: Decimal,
     pub last_funding_update: Timestamp,
+    pub is_shutdown: bool,
+    pub settlement_price: Option<Decimal>,
+    pub insurance_balance: Uint128,
+    pub total_bad_debt: Uint128,
+}
+
+#[cw_serde]
+pub struct FundingSnapshot {
+    pub timestamp: Timestamp,
+    pub old_normalisation_factor: Decimal,
+    pub new_normalisation_factor: Decimal,
+    pub mark: Decimal, // denormalised mark used for funding, after clamping
+    pub index: Decimal,
+    pub is_clamped: bool, // mark was outside the funding bounds of the index
+}
+
+#[cw_serde]
+pub struct FundingHistoryResponse {
+    pub snapshots: Vec<FundingSnapshot>,
+}
+
+#[cw_serde]
+pub struct FundingRateResponse {
+    pub annualised_rate: Decimal,
+    pub longs_pay: bool, // longs pay shorts while the mark is above the index
 }
 
 #[cw_serde]
@@ -148,3 +522,120 @@ pub struct Pool {
     pub id: u64,
     pub quote_denom: String,
 }
+
+// sources of the price of the base in quote used for the index
+#[cw_serde]
+pub enum OracleSource {
+    ArithmeticTwap {}, // arithmetic TWAP of the base pool through the query contract
+    GeometricTwap {},  // geometric TWAP of the base pool through the query contract
+    PriceFeed {
+        contract: String, // contract implementing the price feed interface
+        max_age: u64,     // seconds after which a price is stale
+    },
+}
+
+#[cw_serde]
+pub struct Oracle {
+    pub primary: OracleSource,
+    pub secondary: Option<OracleSource>, // used when the primary fails
+    pub max_deviation: Decimal,          // max difference relative to the secondary price
+}
+
+impl Default for Oracle {
+    fn default() -> Self {
+        Oracle {
+            primary: OracleSource::ArithmeticTwap {},
+            secondary: None,
+            max_deviation: Decimal::percent(10),
+        }
+    }
+}
+
+// fee rates of mints by the value of the power minted, and discounts for stakers
+#[cw_serde]
+#[derive(Default)]
+pub struct FeeSchedule {
+    pub size_tiers: Vec<FeeTier>, // ascending, the highest tier reached replaces the fee rate
+    pub staking_contract: Option<String>, // staking contract queried for the staked amounts
+    pub staking_discounts: Vec<StakingDiscount>, // ascending, the highest tier reached applies
+}
+
+#[cw_serde]
+pub struct FeeTier {
+    pub min_value: Uint128, // value in base of the power minted
+    pub fee_rate: Decimal,
+}
+
+#[cw_serde]
+pub struct StakingDiscount {
+    pub min_staked: Uint128,
+    pub discount: Decimal, // share of the fee waived
+}
+
+// share of the mint fees sent to an address, the weights of all recipients are positive
+// and sum to 10,000 with each address listed once, shares are rounded down and the
+// remainder is sent to the last recipient
+#[cw_serde]
+pub struct FeeRecipient {
+    pub address: String,
+    pub weight_bps: u16,
+}
+
+// collateral accepted by the vaults of a market in addition to the base denom, it is
+// paid to liquidators before the insurance fund covers a shortfall and is valued at the
+// prices snapshotted at shutdown
+#[cw_serde]
+pub struct CollateralAsset {
+    pub denom: String,
+    pub decimals: u32,
+    pub haircut: Decimal, // share of the value discounted when valuing vaults
+    pub price_source: CollateralPriceSource,
+}
+
+// sources of the price of a collateral asset in the base denom
+#[cw_serde]
+pub enum CollateralPriceSource {
+    Quote {}, // the quote denom of the base pool, priced through the market oracle
+    Pool {
+        pool_id: u64,
+        quote_denom: String, // base denom or the quote denom of the base pool
+    },
+    PriceFeed {
+        contract: String, // contract implementing the price feed interface, priced in base
+        max_age: u64,     // seconds after which a price is stale
+    },
+}
+
+#[cw_serde]
+pub struct FlashMintCallback {
+    pub contract: String,
+    pub msg: Binary, // executed on the contract with the minted power
+}
+
+// change to the collateral or debt of a vault
+#[cw_serde]
+pub enum VaultDelta {
+    Increase { amount: Uint128 },
+    Decrease { amount: Uint128 },
+}
+
+// single pool swap within a route, in the order the tokens are swapped
+#[cw_serde]
+pub struct SwapHop {
+    pub pool_id: u64,
+    pub token_in_denom: String,
+    pub token_out_denom: String,
+}
+
+// average of a pool price used by the controller
+#[cw_serde]
+pub enum PriceType {
+    ArithmeticTwap {},
+    GeometricTwap {}, // less sensitive to short lived price spikes
+}
+
+impl Default for PriceType {
+    fn default() -> Self {
+        PriceType::ArithmeticTwap {}
+    }
+}
@@ -17,6 +17,52 @@ pub enum QueryMsg {
         quote_asset: String,
         start_time: Timestamp,
     },
+    #[returns(Decimal)]
+    GetArithmeticTwap {
+        pool_id: u64,
+        base_asset: String,
+        quote_asset: String,
+        start_time: Timestamp,
+        end_time: Timestamp,
+    },
+    #[returns(Decimal)]
+    GetGeometricTwapToNow {
+        pool_id: u64,
+        base_asset: String,
+        quote_asset: String,
+        start_time: Timestamp,
+    },
+    #[returns(Decimal)]
+    GetGeometricTwap {
+        pool_id: u64,
+        base_asset: String,
+        quote_asset: String,
+        start_time: Timestamp,
+        end_time: Timestamp,
+    },
+    #[returns(Decimal)]
+    GetSpotPrice {
+        pool_id: u64,
+        base_asset: String,
+        quote_asset: String,
+    },
     #[returns(Option<String>)]
     GetDenomAuthority { denom: String },
 }
+
+// interface of push based price feeds used as oracle sources
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum PriceFeedQueryMsg {
+    #[returns(PriceFeedResponse)]
+    GetPrice {
+        base_asset: String,
+        quote_asset: String,
+    },
+}
+
+#[cw_serde]
+pub struct PriceFeedResponse {
+    pub price: Decimal,
+    pub last_updated: Timestamp,
+}
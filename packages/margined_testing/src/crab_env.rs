@@ -16,7 +16,7 @@ pub const MOCK_BASE_POOL_QUOTE_DENOM: &str =
 pub const MOCK_FEE_RATE: Decimal = Decimal::zero();
 pub const MOCK_BASE_DENOM: &str = "uosmo";
 pub const MOCK_HEDGING_TWAP_PERIOD: u64 = 420u64;
-pub const MOCK_HEDGE_PRICE_THRESHOLD: Uint128 = Uint128::new(200_000_000_000_000_000u128);
+pub const MOCK_HEDGE_PRICE_THRESHOLD: Decimal = Decimal::percent(20);
 pub const MOCK_HEDGE_TIME_THRESHOLD: u64 = 1800u64;
 pub const MOCK_STRATEGY_CAP: Uint128 = Uint128::new(10000000000000000000000u128);
 
@@ -6,8 +6,8 @@ use cosmrs::proto::{
 };
 use cosmwasm_std::{coin, Addr, Decimal, Uint128};
 use margined_protocol::{
-    crab::InstantiateMsg as CrabInstantiateMsg,
-    power::{ExecuteMsg, InstantiateMsg},
+    crab::{ExecuteMsg as CrabExecuteMsg, InstantiateMsg as CrabInstantiateMsg},
+    power::{ExecuteMsg, InstantiateMsg, UpdateConfig},
     query::{InstantiateMsg as QueryInstantiateMsg, QueryMsg as QueryQueryMsg},
 };
 use mock_query::contract::ExecuteMsg as MockQueryExecuteMsg;
@@ -291,8 +291,10 @@ impl PowerEnv {
             },
             None,
             Some("margined-crab-contract"),
-            &[coin(300_000_000u128, "ubase")], // 300.00
-            //&[],
+            &[
+                coin(300_000_000u128, "ubase"), // 300.00
+                coin(10_000_000u128, "uosmo"),  // denom creation fee
+            ],
             &self.signer,
         )
         .unwrap()
@@ -371,8 +373,11 @@ impl PowerEnv {
         wasm.execute(
             &power_address,
             &ExecuteMsg::UpdateConfig {
-                fee_rate: Some(power_fee),
-                fee_pool: None,
+                new_config: UpdateConfig {
+                    fee_rate: Some(power_fee),
+                    ..Default::default()
+                },
+                market_id: None,
             },
             &[],
             &self.signer,
@@ -381,7 +386,7 @@ impl PowerEnv {
 
         wasm.execute(
             &power_address,
-            &ExecuteMsg::ApplyFunding {},
+            &ExecuteMsg::ApplyFunding { market_id: None },
             &[],
             &self.signer,
         )
@@ -389,8 +394,13 @@ impl PowerEnv {
 
         let crab_address = self.deploy_crab(wasm, power_address.clone(), query_address.clone());
 
-        wasm.execute(&crab_address, &ExecuteMsg::SetOpen {}, &[], &self.signer)
-            .unwrap();
+        wasm.execute(
+            &crab_address,
+            &CrabExecuteMsg::SetOpen {},
+            &[],
+            &self.signer,
+        )
+        .unwrap();
 
         (power_address, query_address, crab_address)
     }
@@ -507,8 +517,13 @@ impl PowerEnv {
             )
             .unwrap();
 
-        wasm.execute(&perp_address, &ExecuteMsg::SetOpen {}, &[], &self.signer)
-            .unwrap();
+        wasm.execute(
+            &perp_address,
+            &ExecuteMsg::SetOpen { market_id: None },
+            &[],
+            &self.signer,
+        )
+        .unwrap();
 
         (perp_address, query_address)
     }